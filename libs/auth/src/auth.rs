@@ -1,5 +1,11 @@
 use std::collections::HashMap;
 
+use async_trait::async_trait;
+use sha2::{Digest, Sha256};
+
+use crate::context::{AuthContext, AuthDirection};
+
+#[async_trait]
 pub trait Auth: Send + Sync + Clone {
     fn auth(
         &self, app: Option<&str>, stream: Option<&str>, query: Option<&str>,
@@ -10,6 +16,20 @@ pub trait Auth: Send + Sync + Clone {
     ) -> Result<(), AuthError> {
         Ok(())
     }
+
+    /// Check a session against its full context, by default the direction
+    /// is dispatched to `auth` or `auth_pull`.
+    async fn authenticate(&self, ctx: &AuthContext) -> Result<(), AuthError> {
+        let (app, stream, query) = (
+            Some(ctx.app.as_str()),
+            Some(ctx.stream.as_str()),
+            ctx.query.as_deref(),
+        );
+        match ctx.direction {
+            AuthDirection::Publish => self.auth(app, stream, query),
+            AuthDirection::Play => self.auth_pull(app, stream, query),
+        }
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -18,6 +38,16 @@ pub enum AuthError {
     TokenIsNotCorrect,
     #[error("no token found.")]
     NoTokenFound,
+    #[error("stream is not found.")]
+    StreamNotFound,
+    #[error("stream is closed.")]
+    StreamIsClosed,
+    #[error("signature is expired.")]
+    SignatureIsExpired,
+    #[error("signature is not correct.")]
+    SignatureIsNotCorrect,
+    #[error("rejected by auth callback: {0}")]
+    CallbackRejected(String),
 }
 
 #[derive(Debug, Clone)]
@@ -33,14 +63,32 @@ impl SimpleTokenAuthenticator {
     pub fn new_nonauth() -> Self {
         Self { token: None }
     }
+
+    /// Check a token taken from the query or the `Authorization` header.
+    pub fn verify_token(&self, token: Option<&str>) -> Result<(), AuthError> {
+        match (&self.token, token) {
+            (None, _) => Ok(()),
+            (Some(_), None) => Err(AuthError::NoTokenFound),
+            (Some(auth_token), Some(token)) if constant_time_eq(token, auth_token) => Ok(()),
+            _ => Err(AuthError::TokenIsNotCorrect),
+        }
+    }
 }
 
-fn extract_query(q: &str) -> HashMap<String, String> {
+pub(crate) fn extract_query(q: &str) -> HashMap<String, String> {
     url::form_urlencoded::parse(q.as_bytes())
         .into_owned()
         .collect()
 }
 
+/// Compare two secrets in constant time, the fixed-size sha-256 digests are
+/// compared so that the length of the expected secret is not leaked either.
+pub fn constant_time_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (Sha256::digest(a), Sha256::digest(b));
+    a.iter().zip(b.iter()).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[async_trait]
 impl Auth for SimpleTokenAuthenticator {
     fn auth(
         &self, app: Option<&str>, stream: Option<&str>, query: Option<&str>,
@@ -56,7 +104,7 @@ impl Auth for SimpleTokenAuthenticator {
                     let query = extract_query(query);
                     tracing::debug!(?query, "session query");
                     match query.get("token") {
-                        Some(token) if token == auth_token => Ok(()),
+                        Some(token) if constant_time_eq(token, auth_token) => Ok(()),
                         None => Err(AuthError::NoTokenFound),
                         _ => Err(AuthError::TokenIsNotCorrect),
                     }
@@ -66,6 +114,17 @@ impl Auth for SimpleTokenAuthenticator {
             Ok(())
         }
     }
+
+    //the token is checked for playing too
+    fn auth_pull(
+        &self, app: Option<&str>, stream: Option<&str>, query: Option<&str>,
+    ) -> Result<(), AuthError> {
+        self.auth(app, stream, query)
+    }
+
+    async fn authenticate(&self, ctx: &AuthContext) -> Result<(), AuthError> {
+        self.verify_token(ctx.token().as_deref())
+    }
 }
 
 impl Auth for () {
@@ -75,3 +134,16 @@ impl Auth for () {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq("secret", "secret"));
+        assert!(!constant_time_eq("secret", "secreT"));
+        assert!(!constant_time_eq("secret", "secret1"));
+        assert!(!constant_time_eq("", "secret"));
+    }
+}
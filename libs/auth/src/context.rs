@@ -0,0 +1,160 @@
+use std::{collections::HashMap, fmt, net::SocketAddr};
+
+use crate::auth::extract_query;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthDirection {
+    Publish,
+    Play,
+}
+
+impl AuthDirection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Publish => "publish",
+            Self::Play => "play",
+        }
+    }
+}
+
+impl fmt::Display for AuthDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthProtocol {
+    Rtmp,
+    WebRTC,
+    HttpFlv,
+    Hls,
+    Rtsp,
+}
+
+impl AuthProtocol {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Rtmp => "rtmp",
+            Self::WebRTC => "webrtc",
+            Self::HttpFlv => "httpflv",
+            Self::Hls => "hls",
+            Self::Rtsp => "rtsp",
+        }
+    }
+}
+
+impl fmt::Display for AuthProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The properties of the rtmp `connect` command.
+#[derive(Debug, Clone, Default)]
+pub struct RtmpConnectInfo {
+    pub tc_url: Option<String>,
+    pub page_url: Option<String>,
+    pub flash_ver: Option<String>,
+    pub swf_url: Option<String>,
+}
+
+/// Everything known about a session when it asks to publish or play a stream.
+#[derive(Debug, Clone)]
+pub struct AuthContext {
+    pub protocol: AuthProtocol,
+    pub direction: AuthDirection,
+    pub app: String,
+    pub stream: String,
+    /// the raw query string of the request url
+    pub query: Option<String>,
+    pub remote_addr: Option<SocketAddr>,
+    /// the http request headers of http based protocols, names are lowercase
+    pub headers: HashMap<String, String>,
+    pub rtmp_connect: Option<RtmpConnectInfo>,
+}
+
+impl AuthContext {
+    pub fn new(
+        protocol: AuthProtocol, direction: AuthDirection, app: impl Into<String>,
+        stream: impl Into<String>,
+    ) -> Self {
+        Self {
+            protocol,
+            direction,
+            app: app.into(),
+            stream: stream.into(),
+            query: None,
+            remote_addr: None,
+            headers: HashMap::new(),
+            rtmp_connect: None,
+        }
+    }
+
+    pub fn with_query(mut self, query: Option<&str>) -> Self {
+        self.query = query.map(str::to_string);
+        self
+    }
+
+    pub fn with_remote_addr(mut self, remote_addr: Option<SocketAddr>) -> Self {
+        self.remote_addr = remote_addr;
+        self
+    }
+
+    pub fn with_header(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.headers.insert(name.to_ascii_lowercase(), value.into());
+        self
+    }
+
+    pub fn with_rtmp_connect(mut self, rtmp_connect: RtmpConnectInfo) -> Self {
+        self.rtmp_connect = Some(rtmp_connect);
+        self
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .get(&name.to_ascii_lowercase())
+            .map(String::as_str)
+    }
+
+    /// The token of an `Authorization: Bearer {token}` header.
+    pub fn bearer_token(&self) -> Option<&str> {
+        let value = self.header("authorization")?;
+        let (scheme, token) = value.split_once(' ')?;
+        if scheme.eq_ignore_ascii_case("bearer") && !token.trim().is_empty() {
+            Some(token.trim())
+        } else {
+            None
+        }
+    }
+
+    /// The `token` query param, or the bearer token when the query has none.
+    pub fn token(&self) -> Option<String> {
+        self.query
+            .as_deref()
+            .and_then(|query| extract_query(query).remove("token"))
+            .or_else(|| self.bearer_token().map(str::to_string))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context_token() {
+        let ctx = AuthContext::new(AuthProtocol::WebRTC, AuthDirection::Publish, "live", "s");
+        assert_eq!(ctx.token(), None);
+
+        let ctx = ctx.with_header("Authorization", "Bearer abc");
+        assert_eq!(ctx.bearer_token(), Some("abc"));
+        assert_eq!(ctx.token().as_deref(), Some("abc"));
+
+        let ctx = ctx.with_query(Some("app=live&token=xyz"));
+        assert_eq!(ctx.token().as_deref(), Some("xyz"));
+
+        let ctx = AuthContext::new(AuthProtocol::WebRTC, AuthDirection::Play, "live", "s")
+            .with_header("authorization", "Basic abc");
+        assert_eq!(ctx.bearer_token(), None);
+    }
+}
@@ -0,0 +1,106 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::Serialize;
+
+use crate::{
+    auth::{Auth, AuthError},
+    context::AuthContext,
+};
+
+#[derive(Debug, Serialize)]
+struct CallbackRequest<'a> {
+    action: &'a str,
+    app: &'a str,
+    stream: &'a str,
+    query: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    protocol: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    remote_addr: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tc_url: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page_url: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    flash_ver: Option<&'a str>,
+}
+
+/// Ask an http service whether a session is allowed, the request is posted as
+///
+///   {"action": "publish" | "play", "app": "...", "stream": "...", "query": "...",
+///    "protocol": "...", "remote_addr": "...", "token": "...",
+///    "tc_url": "...", "page_url": "...", "flash_ver": "..."}
+///
+/// where the fields after `query` are only present when known,
+/// and any 2xx response allows the session.
+#[derive(Debug, Clone)]
+pub struct HttpCallbackAuthenticator {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl HttpCallbackAuthenticator {
+    pub fn new(url: String, timeout: Duration) -> Result<Self, reqwest::Error> {
+        let client = reqwest::Client::builder().timeout(timeout).build()?;
+        Ok(Self { url, client })
+    }
+
+    //the callback is only asked asynchronously by `authenticate`, the synchronous
+    //checks are rejected instead of blocking a worker of the runtime.
+    fn unsupported() -> Result<(), AuthError> {
+        Err(AuthError::CallbackRejected(
+            "the http callback requires the asynchronous authentication".to_string(),
+        ))
+    }
+
+    async fn request(&self, request: CallbackRequest<'_>) -> Result<(), AuthError> {
+        let response = self
+            .client
+            .post(&self.url)
+            .json(&request)
+            .send()
+            .await
+            .map_err(|err| AuthError::CallbackRejected(err.to_string()))?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(AuthError::CallbackRejected(response.status().to_string()))
+        }
+    }
+}
+
+#[async_trait]
+impl Auth for HttpCallbackAuthenticator {
+    fn auth(
+        &self, _app: Option<&str>, _stream: Option<&str>, _query: Option<&str>,
+    ) -> Result<(), AuthError> {
+        Self::unsupported()
+    }
+
+    fn auth_pull(
+        &self, _app: Option<&str>, _stream: Option<&str>, _query: Option<&str>,
+    ) -> Result<(), AuthError> {
+        Self::unsupported()
+    }
+
+    async fn authenticate(&self, ctx: &AuthContext) -> Result<(), AuthError> {
+        let rtmp_connect = ctx.rtmp_connect.as_ref();
+        let request = CallbackRequest {
+            action: ctx.direction.as_str(),
+            app: &ctx.app,
+            stream: &ctx.stream,
+            query: ctx.query.as_deref().unwrap_or_default(),
+            protocol: Some(ctx.protocol.as_str()),
+            remote_addr: ctx.remote_addr.map(|addr| addr.to_string()),
+            token: ctx.token(),
+            tc_url: rtmp_connect.and_then(|c| c.tc_url.as_deref()),
+            page_url: rtmp_connect.and_then(|c| c.page_url.as_deref()),
+            flash_ver: rtmp_connect.and_then(|c| c.flash_ver.as_deref()),
+        };
+        self.request(request).await
+    }
+}
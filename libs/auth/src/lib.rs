@@ -1,4 +1,11 @@
-
 pub mod auth;
+pub mod context;
+pub mod http_callback;
+pub mod signed_url;
+pub mod table;
 
-pub use auth::{Auth, AuthError, SimpleTokenAuthenticator};
\ No newline at end of file
+pub use auth::{constant_time_eq, Auth, AuthError, SimpleTokenAuthenticator};
+pub use context::{AuthContext, AuthDirection, AuthProtocol, RtmpConnectInfo};
+pub use http_callback::HttpCallbackAuthenticator;
+pub use signed_url::SignedUrlAuthenticator;
+pub use table::TokenTableAuthenticator;
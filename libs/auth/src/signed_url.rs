@@ -0,0 +1,141 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::{
+    auth::{extract_query, Auth, AuthError},
+    context::AuthDirection,
+};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Check the HMAC signed, expiring urls, the action is `publish` or `play`:
+///
+///   ?expires={unix seconds}&sign={hex(hmac_sha256(secret, "{action}:{app}/{stream}:{expires}"))}
+#[derive(Debug, Clone)]
+pub struct SignedUrlAuthenticator {
+    secret: String,
+}
+
+impl SignedUrlAuthenticator {
+    pub fn new(secret: String) -> Self {
+        Self { secret }
+    }
+
+    fn mac(&self, direction: AuthDirection, app: &str, stream: &str, expires: u64) -> HmacSha256 {
+        //a hmac accepts keys of any length
+        let mut mac = HmacSha256::new_from_slice(self.secret.as_bytes())
+            .expect("hmac can take key of any size");
+        let message = format!("{}:{}/{}:{}", direction.as_str(), app, stream, expires);
+        mac.update(message.as_bytes());
+        mac
+    }
+
+    /// Sign the url of a stream for publishing or playing, returns the `expires`
+    /// and `sign` query params.
+    pub fn sign(&self, direction: AuthDirection, app: &str, stream: &str, expires: u64) -> String {
+        let mac = self.mac(direction, app, stream, expires);
+        let sign = hex::encode(mac.finalize().into_bytes());
+        format!("expires={}&sign={}", expires, sign)
+    }
+
+    fn verify(
+        &self, direction: AuthDirection, app: &str, stream: &str, query: &str, now: u64,
+    ) -> Result<(), AuthError> {
+        let query = extract_query(query);
+        let (Some(expires), Some(sign)) = (query.get("expires"), query.get("sign")) else {
+            return Err(AuthError::NoTokenFound);
+        };
+        let expires: u64 = expires
+            .parse()
+            .map_err(|_| AuthError::SignatureIsNotCorrect)?;
+        let sign = hex::decode(sign).map_err(|_| AuthError::SignatureIsNotCorrect)?;
+
+        //constant time comparison
+        self.mac(direction, app, stream, expires)
+            .verify_slice(&sign)
+            .map_err(|_| AuthError::SignatureIsNotCorrect)?;
+
+        if expires < now {
+            return Err(AuthError::SignatureIsExpired);
+        }
+        Ok(())
+    }
+}
+
+impl SignedUrlAuthenticator {
+    fn verify_now(
+        &self, direction: AuthDirection, app: Option<&str>, stream: Option<&str>,
+        query: Option<&str>,
+    ) -> Result<(), AuthError> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        self.verify(
+            direction,
+            app.unwrap_or_default(),
+            stream.unwrap_or_default(),
+            query.unwrap_or_default(),
+            now,
+        )
+    }
+}
+
+impl Auth for SignedUrlAuthenticator {
+    fn auth(
+        &self, app: Option<&str>, stream: Option<&str>, query: Option<&str>,
+    ) -> Result<(), AuthError> {
+        self.verify_now(AuthDirection::Publish, app, stream, query)
+    }
+
+    fn auth_pull(
+        &self, app: Option<&str>, stream: Option<&str>, query: Option<&str>,
+    ) -> Result<(), AuthError> {
+        self.verify_now(AuthDirection::Play, app, stream, query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signed_url() {
+        let authenticator = SignedUrlAuthenticator::new("secret".to_string());
+        let query = authenticator.sign(AuthDirection::Publish, "live", "room1", 1000);
+        let verify = |app, stream, query, now| {
+            authenticator.verify(AuthDirection::Publish, app, stream, query, now)
+        };
+
+        assert!(verify("live", "room1", &query, 999).is_ok());
+        assert!(matches!(
+            verify("live", "room1", &query, 1001),
+            Err(AuthError::SignatureIsExpired)
+        ));
+        assert!(matches!(
+            verify("live", "room2", &query, 999),
+            Err(AuthError::SignatureIsNotCorrect)
+        ));
+        assert!(matches!(
+            verify("live", "room1", "expires=1000", 999),
+            Err(AuthError::NoTokenFound)
+        ));
+    }
+
+    #[test]
+    fn test_signed_url_action() {
+        let authenticator = SignedUrlAuthenticator::new("secret".to_string());
+        let query = authenticator.sign(AuthDirection::Play, "live", "room1", u64::MAX);
+
+        assert!(authenticator
+            .auth_pull(Some("live"), Some("room1"), Some(&query))
+            .is_ok());
+        //a url signed for playing cannot be used for publishing
+        assert!(matches!(
+            authenticator.auth(Some("live"), Some("room1"), Some(&query)),
+            Err(AuthError::SignatureIsNotCorrect)
+        ));
+    }
+}
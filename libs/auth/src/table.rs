@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+
+use crate::{
+    auth::{constant_time_eq, extract_query, Auth, AuthError},
+    context::AuthContext,
+};
+
+/// the stream name matching every stream of an app
+pub const ANY_STREAM: &str = "*";
+
+/// Check the `token` query param against a per app/stream token table,
+/// an entry with the `*` stream name matches every stream of the app.
+#[derive(Debug, Clone, Default)]
+pub struct TokenTableAuthenticator {
+    tokens: HashMap<(String, String), String>,
+}
+
+impl TokenTableAuthenticator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, app: String, stream: String, token: String) {
+        self.tokens.insert((app, stream), token);
+    }
+
+    fn find_token(&self, app: &str, stream: &str) -> Option<&String> {
+        self.tokens
+            .get(&(app.to_string(), stream.to_string()))
+            .or_else(|| self.tokens.get(&(app.to_string(), ANY_STREAM.to_string())))
+    }
+
+    fn verify(&self, app: &str, stream: &str, token: Option<&str>) -> Result<(), AuthError> {
+        let expected = self
+            .find_token(app, stream)
+            .ok_or(AuthError::StreamNotFound)?;
+
+        match token {
+            Some(token) if constant_time_eq(token, expected) => Ok(()),
+            None => Err(AuthError::NoTokenFound),
+            _ => Err(AuthError::TokenIsNotCorrect),
+        }
+    }
+}
+
+#[async_trait]
+impl Auth for TokenTableAuthenticator {
+    fn auth(
+        &self, app: Option<&str>, stream: Option<&str>, query: Option<&str>,
+    ) -> Result<(), AuthError> {
+        let query = extract_query(query.unwrap_or_default());
+        self.verify(
+            app.unwrap_or_default(),
+            stream.unwrap_or_default(),
+            query.get("token").map(String::as_str),
+        )
+    }
+
+    fn auth_pull(
+        &self, app: Option<&str>, stream: Option<&str>, query: Option<&str>,
+    ) -> Result<(), AuthError> {
+        self.auth(app, stream, query)
+    }
+
+    async fn authenticate(&self, ctx: &AuthContext) -> Result<(), AuthError> {
+        self.verify(&ctx.app, &ctx.stream, ctx.token().as_deref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_table() {
+        let mut authenticator = TokenTableAuthenticator::new();
+        authenticator.insert("live".into(), "room1".into(), "abc".into());
+        authenticator.insert("live".into(), ANY_STREAM.into(), "xyz".into());
+
+        assert!(authenticator
+            .auth(Some("live"), Some("room1"), Some("token=abc"))
+            .is_ok());
+        assert!(authenticator
+            .auth(Some("live"), Some("room1"), Some("token=xyz"))
+            .is_err());
+        assert!(authenticator
+            .auth(Some("live"), Some("room2"), Some("token=xyz"))
+            .is_ok());
+        assert!(matches!(
+            authenticator.auth(Some("vod"), Some("room1"), Some("token=abc")),
+            Err(AuthError::StreamNotFound)
+        ));
+    }
+}
@@ -265,3 +265,60 @@ pub enum BitVecErrorValue {
 pub struct BitVecError {
     pub value: BitVecErrorValue,
 }
+
+#[derive(Debug, Fail)]
+pub enum Fmp4MuxerErrorValue {
+    #[fail(display = "bytes write error:{}", _0)]
+    BytesWriteError(#[cause] BytesWriteError),
+    #[fail(display = "mpeg avc error:{}", _0)]
+    Mpeg4AvcHevcError(#[cause] Mpeg4AvcHevcError),
+    #[fail(display = "mpeg aac error:{}", _0)]
+    MpegAacError(#[cause] MpegAacError),
+    #[fail(display = "the track is not configured")]
+    NoTrack,
+}
+
+#[derive(Debug)]
+pub struct Fmp4MuxerError {
+    pub value: Fmp4MuxerErrorValue,
+}
+
+impl From<BytesWriteError> for Fmp4MuxerError {
+    fn from(error: BytesWriteError) -> Self {
+        Fmp4MuxerError {
+            value: Fmp4MuxerErrorValue::BytesWriteError(error),
+        }
+    }
+}
+
+impl From<Mpeg4AvcHevcError> for Fmp4MuxerError {
+    fn from(error: Mpeg4AvcHevcError) -> Self {
+        Fmp4MuxerError {
+            value: Fmp4MuxerErrorValue::Mpeg4AvcHevcError(error),
+        }
+    }
+}
+
+impl From<MpegAacError> for Fmp4MuxerError {
+    fn from(error: MpegAacError) -> Self {
+        Fmp4MuxerError {
+            value: Fmp4MuxerErrorValue::MpegAacError(error),
+        }
+    }
+}
+
+impl fmt::Display for Fmp4MuxerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.value, f)
+    }
+}
+
+impl Fail for Fmp4MuxerError {
+    fn cause(&self) -> Option<&dyn Fail> {
+        self.value.cause()
+    }
+
+    fn backtrace(&self) -> Option<&Backtrace> {
+        self.value.backtrace()
+    }
+}
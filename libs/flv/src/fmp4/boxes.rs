@@ -0,0 +1,309 @@
+use {
+    super::define::{AudioTrackConfig, Fmp4Sample, VideoCodec, VideoTrackConfig},
+    crate::errors::Fmp4MuxerError,
+    byteorder::BigEndian,
+    bytes::BytesMut,
+    bytesio::bytes_writer::BytesWriter,
+};
+
+const UNITY_MATRIX: [u32; 9] = [0x00010000, 0, 0, 0, 0x00010000, 0, 0, 0, 0x40000000];
+
+//trun flags
+pub const TRUN_DATA_OFFSET: u32 = 0x000001;
+pub const TRUN_SAMPLE_DURATION: u32 = 0x000100;
+pub const TRUN_SAMPLE_SIZE: u32 = 0x000200;
+pub const TRUN_SAMPLE_FLAGS: u32 = 0x000400;
+pub const TRUN_SAMPLE_CTO: u32 = 0x000800;
+//tfhd flags
+const TFHD_DEFAULT_BASE_IS_MOOF: u32 = 0x020000;
+
+//sample_depends_on = 2, it does not depend on others
+const SAMPLE_FLAGS_SYNC: u32 = 0x02000000;
+//sample_depends_on = 1 and sample_is_non_sync_sample = 1
+const SAMPLE_FLAGS_NON_SYNC: u32 = 0x01010000;
+
+pub fn mp4_box(box_type: &[u8; 4], payloads: &[&[u8]]) -> Result<BytesMut, Fmp4MuxerError> {
+    let size: usize = payloads.iter().map(|payload| payload.len()).sum();
+
+    let mut writer = BytesWriter::new();
+    writer.write_u32::<BigEndian>(8 + size as u32)?;
+    writer.write(box_type)?;
+    for payload in payloads {
+        writer.write(payload)?;
+    }
+    Ok(writer.extract_current_bytes())
+}
+
+pub fn full_box(
+    box_type: &[u8; 4], version: u8, flags: u32, payloads: &[&[u8]],
+) -> Result<BytesMut, Fmp4MuxerError> {
+    let header = ((version as u32) << 24 | (flags & 0xFFFFFF)).to_be_bytes();
+
+    let mut all = vec![&header[..]];
+    all.extend_from_slice(payloads);
+    mp4_box(box_type, &all)
+}
+
+pub fn ftyp() -> Result<BytesMut, Fmp4MuxerError> {
+    mp4_box(
+        b"ftyp",
+        &[
+            b"iso6", //major brand
+            &0u32.to_be_bytes(),
+            b"iso6",
+            b"cmfc",
+            b"mp41",
+        ],
+    )
+}
+
+pub fn moov(
+    video: Option<&VideoTrackConfig>, audio: Option<&AudioTrackConfig>, video_track_id: u32,
+    audio_track_id: u32,
+) -> Result<BytesMut, Fmp4MuxerError> {
+    let mut traks = Vec::new();
+    let mut trexs = Vec::new();
+
+    if let Some(video) = video {
+        traks.push(video_trak(video, video_track_id)?);
+        trexs.push(trex(video_track_id)?);
+    }
+    if let Some(audio) = audio {
+        traks.push(audio_trak(audio, audio_track_id)?);
+        trexs.push(trex(audio_track_id)?);
+    }
+
+    let mvhd = mvhd(video_track_id.max(audio_track_id) + 1)?;
+    let trexs: Vec<&[u8]> = trexs.iter().map(|trex| &trex[..]).collect();
+    let mvex = mp4_box(b"mvex", &trexs)?;
+
+    let mut payloads: Vec<&[u8]> = vec![&mvhd[..]];
+    payloads.extend(traks.iter().map(|trak| &trak[..]));
+    payloads.push(&mvex[..]);
+    mp4_box(b"moov", &payloads)
+}
+
+fn mvhd(next_track_id: u32) -> Result<BytesMut, Fmp4MuxerError> {
+    let mut writer = BytesWriter::new();
+    writer.write_u32::<BigEndian>(0)?; //creation time
+    writer.write_u32::<BigEndian>(0)?; //modification time
+    writer.write_u32::<BigEndian>(1000)?; //timescale
+    writer.write_u32::<BigEndian>(0)?; //duration
+    writer.write_u32::<BigEndian>(0x00010000)?; //rate 1.0
+    writer.write_u16::<BigEndian>(0x0100)?; //volume 1.0
+    writer.write(&[0; 10])?; //reserved
+    for value in UNITY_MATRIX {
+        writer.write_u32::<BigEndian>(value)?;
+    }
+    writer.write(&[0; 24])?; //pre defined
+    writer.write_u32::<BigEndian>(next_track_id)?;
+
+    full_box(b"mvhd", 0, 0, &[&writer.extract_current_bytes()[..]])
+}
+
+fn tkhd(
+    track_id: u32, is_audio: bool, width: u32, height: u32,
+) -> Result<BytesMut, Fmp4MuxerError> {
+    let mut writer = BytesWriter::new();
+    writer.write_u32::<BigEndian>(0)?; //creation time
+    writer.write_u32::<BigEndian>(0)?; //modification time
+    writer.write_u32::<BigEndian>(track_id)?;
+    writer.write_u32::<BigEndian>(0)?; //reserved
+    writer.write_u32::<BigEndian>(0)?; //duration
+    writer.write(&[0; 8])?; //reserved
+    writer.write_u16::<BigEndian>(0)?; //layer
+    writer.write_u16::<BigEndian>(0)?; //alternate group
+    writer.write_u16::<BigEndian>(if is_audio { 0x0100 } else { 0 })?; //volume
+    writer.write_u16::<BigEndian>(0)?; //reserved
+    for value in UNITY_MATRIX {
+        writer.write_u32::<BigEndian>(value)?;
+    }
+    writer.write_u32::<BigEndian>(width << 16)?;
+    writer.write_u32::<BigEndian>(height << 16)?;
+
+    //track enabled and in movie
+    full_box(b"tkhd", 0, 0x000003, &[&writer.extract_current_bytes()[..]])
+}
+
+fn mdia(timescale: u32, is_audio: bool, stsd: BytesMut) -> Result<BytesMut, Fmp4MuxerError> {
+    let mut writer = BytesWriter::new();
+    writer.write_u32::<BigEndian>(0)?; //creation time
+    writer.write_u32::<BigEndian>(0)?; //modification time
+    writer.write_u32::<BigEndian>(timescale)?;
+    writer.write_u32::<BigEndian>(0)?; //duration
+    writer.write_u16::<BigEndian>(0x55c4)?; //language: und
+    writer.write_u16::<BigEndian>(0)?; //pre defined
+    let mdhd = full_box(b"mdhd", 0, 0, &[&writer.extract_current_bytes()[..]])?;
+
+    let (handler_type, name): (&[u8; 4], &[u8]) = if is_audio {
+        (b"soun", b"SoundHandler\0")
+    } else {
+        (b"vide", b"VideoHandler\0")
+    };
+    let hdlr = full_box(b"hdlr", 0, 0, &[&[0; 4], handler_type, &[0; 12], name])?;
+
+    let media_header = if is_audio {
+        //balance and reserved
+        full_box(b"smhd", 0, 0, &[&[0; 4]])?
+    } else {
+        //graphics mode and op color
+        full_box(b"vmhd", 0, 1, &[&[0; 8]])?
+    };
+
+    //the media data is in the same file
+    let url = full_box(b"url ", 0, 1, &[])?;
+    let dref = full_box(b"dref", 0, 0, &[&1u32.to_be_bytes(), &url[..]])?;
+    let dinf = mp4_box(b"dinf", &[&dref[..]])?;
+
+    //the samples are described in the fragments, the tables are empty
+    let empty_entries = 0u32.to_be_bytes();
+    let stts = full_box(b"stts", 0, 0, &[&empty_entries])?;
+    let stsc = full_box(b"stsc", 0, 0, &[&empty_entries])?;
+    let stsz = full_box(b"stsz", 0, 0, &[&empty_entries, &empty_entries])?;
+    let stco = full_box(b"stco", 0, 0, &[&empty_entries])?;
+    let stbl = mp4_box(
+        b"stbl",
+        &[&stsd[..], &stts[..], &stsc[..], &stsz[..], &stco[..]],
+    )?;
+
+    let minf = mp4_box(b"minf", &[&media_header[..], &dinf[..], &stbl[..]])?;
+    mp4_box(b"mdia", &[&mdhd[..], &hdlr[..], &minf[..]])
+}
+
+fn video_trak(video: &VideoTrackConfig, track_id: u32) -> Result<BytesMut, Fmp4MuxerError> {
+    let mut writer = BytesWriter::new();
+    writer.write(&[0; 6])?; //reserved
+    writer.write_u16::<BigEndian>(1)?; //data reference index
+    writer.write(&[0; 16])?; //pre defined and reserved
+    writer.write_u16::<BigEndian>(video.width as u16)?;
+    writer.write_u16::<BigEndian>(video.height as u16)?;
+    writer.write_u32::<BigEndian>(0x00480000)?; //horizontal resolution 72 dpi
+    writer.write_u32::<BigEndian>(0x00480000)?; //vertical resolution 72 dpi
+    writer.write_u32::<BigEndian>(0)?; //reserved
+    writer.write_u16::<BigEndian>(1)?; //frame count
+    writer.write(&[0; 32])?; //compressor name
+    writer.write_u16::<BigEndian>(0x0018)?; //depth
+    writer.write_u16::<BigEndian>(0xFFFF)?; //pre defined
+
+    let (entry_type, config_type) = match video.codec {
+        VideoCodec::Avc => (b"avc1", b"avcC"),
+        VideoCodec::Hevc => (b"hvc1", b"hvcC"),
+    };
+    let config = mp4_box(config_type, &[&video.record[..]])?;
+    let entry = mp4_box(
+        entry_type,
+        &[&writer.extract_current_bytes()[..], &config[..]],
+    )?;
+    let stsd = full_box(b"stsd", 0, 0, &[&1u32.to_be_bytes(), &entry[..]])?;
+
+    let tkhd = tkhd(track_id, false, video.width, video.height)?;
+    let mdia = mdia(video.timescale, false, stsd)?;
+    mp4_box(b"trak", &[&tkhd[..], &mdia[..]])
+}
+
+fn audio_trak(audio: &AudioTrackConfig, track_id: u32) -> Result<BytesMut, Fmp4MuxerError> {
+    let mut writer = BytesWriter::new();
+    writer.write(&[0; 6])?; //reserved
+    writer.write_u16::<BigEndian>(1)?; //data reference index
+    writer.write(&[0; 8])?; //reserved
+    writer.write_u16::<BigEndian>(audio.channels as u16)?;
+    writer.write_u16::<BigEndian>(16)?; //sample size
+    writer.write_u32::<BigEndian>(0)?; //pre defined and reserved
+    writer.write_u32::<BigEndian>(audio.sample_rate << 16)?;
+
+    let esds = esds(&audio.audio_specific_config, track_id)?;
+    let entry = mp4_box(b"mp4a", &[&writer.extract_current_bytes()[..], &esds[..]])?;
+    let stsd = full_box(b"stsd", 0, 0, &[&1u32.to_be_bytes(), &entry[..]])?;
+
+    let tkhd = tkhd(track_id, true, 0, 0)?;
+    let mdia = mdia(audio.sample_rate, true, stsd)?;
+    mp4_box(b"trak", &[&tkhd[..], &mdia[..]])
+}
+
+//ISO/IEC 14496-1 ES_Descriptor carrying the AudioSpecificConfig
+fn esds(audio_specific_config: &[u8], track_id: u32) -> Result<BytesMut, Fmp4MuxerError> {
+    let config_len = audio_specific_config.len() as u8;
+    let decoder_config_len = 13 + 2 + config_len;
+    let es_len = 3 + 2 + decoder_config_len + 2 + 1;
+
+    let mut writer = BytesWriter::new();
+    writer.write_u8(0x03)?; //ES_DescrTag
+    writer.write_u8(es_len)?;
+    writer.write_u16::<BigEndian>(track_id as u16)?; //ES_ID
+    writer.write_u8(0)?; //flags
+
+    writer.write_u8(0x04)?; //DecoderConfigDescrTag
+    writer.write_u8(decoder_config_len)?;
+    writer.write_u8(0x40)?; //object type: mpeg4 audio
+    writer.write_u8(0x15)?; //stream type: audio
+    writer.write_u24::<BigEndian>(0)?; //buffer size
+    writer.write_u32::<BigEndian>(0)?; //max bitrate
+    writer.write_u32::<BigEndian>(0)?; //avg bitrate
+
+    writer.write_u8(0x05)?; //DecSpecificInfoTag
+    writer.write_u8(config_len)?;
+    writer.write(audio_specific_config)?;
+
+    writer.write_u8(0x06)?; //SLConfigDescrTag
+    writer.write_u8(1)?;
+    writer.write_u8(0x02)?;
+
+    full_box(b"esds", 0, 0, &[&writer.extract_current_bytes()[..]])
+}
+
+fn trex(track_id: u32) -> Result<BytesMut, Fmp4MuxerError> {
+    let mut writer = BytesWriter::new();
+    writer.write_u32::<BigEndian>(track_id)?;
+    writer.write_u32::<BigEndian>(1)?; //default sample description index
+    writer.write_u32::<BigEndian>(0)?; //default sample duration
+    writer.write_u32::<BigEndian>(0)?; //default sample size
+    writer.write_u32::<BigEndian>(0)?; //default sample flags
+    full_box(b"trex", 0, 0, &[&writer.extract_current_bytes()[..]])
+}
+
+pub fn mfhd(sequence_number: u32) -> Result<BytesMut, Fmp4MuxerError> {
+    full_box(b"mfhd", 0, 0, &[&sequence_number.to_be_bytes()])
+}
+
+/// A traf box, the data offset of its trun is written as 0 and the position
+/// of it in the traf is returned, it is patched after the moof size is known.
+pub fn traf(
+    track_id: u32, base_media_decode_time: u64, samples: &[Fmp4Sample], is_video: bool,
+) -> Result<(BytesMut, usize), Fmp4MuxerError> {
+    let tfhd = full_box(
+        b"tfhd",
+        0,
+        TFHD_DEFAULT_BASE_IS_MOOF,
+        &[&track_id.to_be_bytes()],
+    )?;
+    let tfdt = full_box(b"tfdt", 1, 0, &[&base_media_decode_time.to_be_bytes()])?;
+
+    let mut flags = TRUN_DATA_OFFSET | TRUN_SAMPLE_DURATION | TRUN_SAMPLE_SIZE;
+    if is_video {
+        flags |= TRUN_SAMPLE_FLAGS | TRUN_SAMPLE_CTO;
+    }
+
+    let mut writer = BytesWriter::new();
+    writer.write_u32::<BigEndian>(samples.len() as u32)?;
+    writer.write_u32::<BigEndian>(0)?; //data offset
+    for sample in samples {
+        writer.write_u32::<BigEndian>(sample.duration)?;
+        writer.write_u32::<BigEndian>(sample.data.len() as u32)?;
+        if is_video {
+            let sample_flags = if sample.is_key_frame {
+                SAMPLE_FLAGS_SYNC
+            } else {
+                SAMPLE_FLAGS_NON_SYNC
+            };
+            writer.write_u32::<BigEndian>(sample_flags)?;
+            //version 1 allows negative composition offsets
+            writer.write_u32::<BigEndian>(sample.composition_offset as u32)?;
+        }
+    }
+    let trun = full_box(b"trun", 1, flags, &[&writer.extract_current_bytes()[..]])?;
+
+    //traf header + tfhd + tfdt + trun header + sample count
+    let data_offset_pos = 8 + tfhd.len() + tfdt.len() + 12 + 4;
+    let traf = mp4_box(b"traf", &[&tfhd[..], &tfdt[..], &trun[..]])?;
+    Ok((traf, data_offset_pos))
+}
@@ -0,0 +1,86 @@
+use {
+    crate::{errors::Fmp4MuxerError, mpeg4_aac::Mpeg4AacProcessor, mpeg4_avc::Mpeg4AvcProcessor},
+    bytes::BytesMut,
+};
+
+//the flv timestamps are in milliseconds
+pub const VIDEO_TIMESCALE: u32 = 1000;
+pub const AAC_SAMPLES_PER_FRAME: u32 = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VideoCodec {
+    Avc,
+    Hevc,
+}
+
+#[derive(Debug, Clone)]
+pub struct VideoTrackConfig {
+    pub codec: VideoCodec,
+    //AVCDecoderConfigurationRecord or HEVCDecoderConfigurationRecord
+    pub record: BytesMut,
+    pub width: u32,
+    pub height: u32,
+    pub timescale: u32,
+}
+
+impl VideoTrackConfig {
+    pub fn avc(record: BytesMut, width: u32, height: u32) -> Self {
+        Self {
+            codec: VideoCodec::Avc,
+            record,
+            width,
+            height,
+            timescale: VIDEO_TIMESCALE,
+        }
+    }
+
+    pub fn hevc(record: BytesMut, width: u32, height: u32) -> Self {
+        Self {
+            codec: VideoCodec::Hevc,
+            record,
+            width,
+            height,
+            timescale: VIDEO_TIMESCALE,
+        }
+    }
+
+    /// The processor must have loaded the decoder configuration record.
+    pub fn from_avc(processor: &mut Mpeg4AvcProcessor) -> Result<Self, Fmp4MuxerError> {
+        let record = processor.decoder_configuration_record_save()?;
+        Ok(Self::avc(
+            record,
+            processor.mpeg4_avc.width,
+            processor.mpeg4_avc.height,
+        ))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AudioTrackConfig {
+    pub audio_specific_config: BytesMut,
+    pub sample_rate: u32,
+    pub channels: u8,
+}
+
+impl AudioTrackConfig {
+    /// The processor must have loaded the AudioSpecificConfig.
+    pub fn from_aac(processor: &Mpeg4AacProcessor) -> Result<Self, Fmp4MuxerError> {
+        let mpeg4_aac = &processor.mpeg4_aac;
+        Ok(Self {
+            audio_specific_config: mpeg4_aac.gen_audio_specific_config()?,
+            sample_rate: mpeg4_aac.sampling_frequency,
+            channels: mpeg4_aac.channels,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Fmp4Sample {
+    //decode timestamp in the track timescale
+    pub dts: u64,
+    pub duration: u32,
+    //pts - dts in the track timescale
+    pub composition_offset: i32,
+    pub is_key_frame: bool,
+    pub data: BytesMut,
+}
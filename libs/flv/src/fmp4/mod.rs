@@ -0,0 +1,6 @@
+pub mod boxes;
+pub mod define;
+pub mod muxer;
+
+pub use self::define::{AudioTrackConfig, Fmp4Sample, VideoCodec, VideoTrackConfig};
+pub use self::muxer::Fmp4Muxer;
@@ -0,0 +1,329 @@
+use {
+    super::{
+        boxes,
+        define::{AudioTrackConfig, Fmp4Sample, VideoTrackConfig, AAC_SAMPLES_PER_FRAME},
+    },
+    crate::{
+        define::{aac_packet_type, avc_packet_type, frame_type},
+        errors::{Fmp4MuxerError, Fmp4MuxerErrorValue},
+    },
+    bytes::{BufMut, BytesMut},
+};
+
+const VIDEO_TRACK_ID: u32 = 1;
+const AUDIO_TRACK_ID: u32 = 2;
+//used for the last sample of the first fragment, whose next sample is unknown
+const DEFAULT_VIDEO_SAMPLE_DURATION_MS: u32 = 40;
+
+const FLV_SOUND_FORMAT_AAC: u8 = 10;
+
+/// Mux the AVC/HEVC and AAC frames into a fragmented mp4 (CMAF) stream,
+/// an init segment (ftyp + moov) followed by fragments (moof + mdat).
+pub struct Fmp4Muxer {
+    video: Option<VideoTrackConfig>,
+    audio: Option<AudioTrackConfig>,
+
+    sequence_number: u32,
+    video_samples: Vec<Fmp4Sample>,
+    audio_samples: Vec<Fmp4Sample>,
+    //the duration of the last sample in a fragment
+    last_video_duration: u32,
+}
+
+impl Fmp4Muxer {
+    pub fn new(video: Option<VideoTrackConfig>, audio: Option<AudioTrackConfig>) -> Self {
+        let last_video_duration = video
+            .as_ref()
+            .map(|video| DEFAULT_VIDEO_SAMPLE_DURATION_MS * video.timescale / 1000)
+            .unwrap_or_default();
+
+        Self {
+            video,
+            audio,
+            sequence_number: 0,
+            video_samples: Vec::new(),
+            audio_samples: Vec::new(),
+            last_video_duration,
+        }
+    }
+
+    pub fn init_segment(&self) -> Result<BytesMut, Fmp4MuxerError> {
+        let mut segment = boxes::ftyp()?;
+        segment.extend_from_slice(&boxes::moov(
+            self.video.as_ref(),
+            self.audio.as_ref(),
+            VIDEO_TRACK_ID,
+            AUDIO_TRACK_ID,
+        )?);
+        Ok(segment)
+    }
+
+    /// Queue a video frame, the data is the length prefixed nalus.
+    /// The timestamps are in milliseconds, the composition time is pts - dts.
+    pub fn write_video(
+        &mut self, dts: u32, composition_time: i32, is_key_frame: bool, data: BytesMut,
+    ) -> Result<(), Fmp4MuxerError> {
+        let timescale = match &self.video {
+            Some(video) => video.timescale as i64,
+            None => {
+                return Err(Fmp4MuxerError {
+                    value: Fmp4MuxerErrorValue::NoTrack,
+                })
+            }
+        };
+
+        self.video_samples.push(Fmp4Sample {
+            dts: dts as u64 * timescale as u64 / 1000,
+            duration: 0,
+            composition_offset: (composition_time as i64 * timescale / 1000) as i32,
+            is_key_frame,
+            data,
+        });
+        Ok(())
+    }
+
+    /// Queue a raw aac frame, the timestamp is in milliseconds.
+    pub fn write_audio(&mut self, dts: u32, data: BytesMut) -> Result<(), Fmp4MuxerError> {
+        let sample_rate = match &self.audio {
+            Some(audio) => audio.sample_rate,
+            None => {
+                return Err(Fmp4MuxerError {
+                    value: Fmp4MuxerErrorValue::NoTrack,
+                })
+            }
+        };
+
+        self.audio_samples.push(Fmp4Sample {
+            dts: dts as u64 * sample_rate as u64 / 1000,
+            duration: AAC_SAMPLES_PER_FRAME,
+            composition_offset: 0,
+            is_key_frame: true,
+            data,
+        });
+        Ok(())
+    }
+
+    /// Queue the body of a flv video tag, the sequence headers are skipped.
+    pub fn write_flv_video(
+        &mut self, timestamp: u32, mut data: BytesMut,
+    ) -> Result<(), Fmp4MuxerError> {
+        //frame type and codec id, avc packet type, composition time(SI24)
+        if data.len() < 5 || data[1] != avc_packet_type::AVC_NALU {
+            return Ok(());
+        }
+
+        let is_key_frame = data[0] >> 4 == frame_type::KEY_FRAME;
+        let composition_time =
+            ((data[2] as i32) << 24 | (data[3] as i32) << 16 | (data[4] as i32) << 8) >> 8;
+        self.write_video(
+            timestamp,
+            composition_time,
+            is_key_frame,
+            data.split_off(5),
+        )
+    }
+
+    /// Queue the body of a flv audio tag, only the aac raw frames are muxed.
+    pub fn write_flv_audio(
+        &mut self, timestamp: u32, mut data: BytesMut,
+    ) -> Result<(), Fmp4MuxerError> {
+        if data.len() < 2
+            || data[0] >> 4 != FLV_SOUND_FORMAT_AAC
+            || data[1] != aac_packet_type::AAC_RAW
+        {
+            return Ok(());
+        }
+        self.write_audio(timestamp, data.split_off(2))
+    }
+
+    pub fn has_pending_samples(&self) -> bool {
+        !self.video_samples.is_empty() || !self.audio_samples.is_empty()
+    }
+
+    //the pending video duration in milliseconds
+    pub fn pending_video_duration(&self) -> u32 {
+        match (
+            &self.video,
+            self.video_samples.first(),
+            self.video_samples.last(),
+        ) {
+            (Some(video), Some(first), Some(last)) => {
+                ((last.dts - first.dts) * 1000 / video.timescale as u64) as u32
+            }
+            _ => 0,
+        }
+    }
+
+    /// Mux the queued samples into a fragment, none if no sample is queued.
+    pub fn flush_fragment(&mut self) -> Result<Option<BytesMut>, Fmp4MuxerError> {
+        if !self.has_pending_samples() {
+            return Ok(None);
+        }
+
+        let video_samples = std::mem::take(&mut self.video_samples);
+        let video_samples = self.fill_video_durations(video_samples);
+        let audio_samples = std::mem::take(&mut self.audio_samples);
+
+        self.sequence_number += 1;
+        let mfhd = boxes::mfhd(self.sequence_number)?;
+
+        let mut trafs = Vec::new();
+        if let Some(first) = video_samples.first() {
+            trafs.push((
+                boxes::traf(VIDEO_TRACK_ID, first.dts, &video_samples, true)?,
+                &video_samples,
+            ));
+        }
+        if let Some(first) = audio_samples.first() {
+            trafs.push((
+                boxes::traf(AUDIO_TRACK_ID, first.dts, &audio_samples, false)?,
+                &audio_samples,
+            ));
+        }
+
+        let mut payloads: Vec<&[u8]> = vec![&mfhd[..]];
+        payloads.extend(trafs.iter().map(|((traf, _), _)| &traf[..]));
+        let mut moof = boxes::mp4_box(b"moof", &payloads)?;
+
+        //the data offsets are relative to the moof start, the samples follow the mdat header
+        let mut traf_pos = 8 + mfhd.len();
+        let mut data_offset = moof.len() + 8;
+        let mut mdat_payloads: Vec<&[u8]> = Vec::new();
+        for ((traf, offset_pos), samples) in &trafs {
+            let pos = traf_pos + offset_pos;
+            moof[pos..pos + 4].copy_from_slice(&(data_offset as u32).to_be_bytes());
+
+            traf_pos += traf.len();
+            for sample in samples.iter() {
+                data_offset += sample.data.len();
+                mdat_payloads.push(&sample.data[..]);
+            }
+        }
+
+        let mdat = boxes::mp4_box(b"mdat", &mdat_payloads)?;
+        let mut fragment = BytesMut::with_capacity(moof.len() + mdat.len());
+        fragment.put(moof);
+        fragment.put(mdat);
+        Ok(Some(fragment))
+    }
+
+    fn fill_video_durations(&mut self, mut samples: Vec<Fmp4Sample>) -> Vec<Fmp4Sample> {
+        for index in 1..samples.len() {
+            let duration = samples[index].dts.saturating_sub(samples[index - 1].dts) as u32;
+            samples[index - 1].duration = duration;
+            self.last_video_duration = duration;
+        }
+        if let Some(last) = samples.last_mut() {
+            last.duration = self.last_video_duration;
+        }
+        samples
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{fmp4::define::VideoTrackConfig, mpeg4_aac::Mpeg4AacProcessor},
+        std::convert::TryInto,
+    };
+
+    //find the payload of the first box of the type
+    fn find_box<'a>(data: &'a [u8], box_type: &[u8; 4]) -> Option<(usize, &'a [u8])> {
+        let pos = data.windows(4).position(|window| window == box_type)? - 4;
+        let size = u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+        Some((pos, &data[pos + 8..pos + size]))
+    }
+
+    fn top_level_boxes(data: &[u8]) -> Vec<[u8; 4]> {
+        let mut boxes = Vec::new();
+        let mut pos = 0;
+        while pos < data.len() {
+            let size = u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+            boxes.push(data[pos + 4..pos + 8].try_into().unwrap());
+            pos += size;
+        }
+        assert_eq!(pos, data.len());
+        boxes
+    }
+
+    fn muxer() -> Fmp4Muxer {
+        let mut aac_processor = Mpeg4AacProcessor::new();
+        aac_processor
+            .extend_data(BytesMut::from(&[0x12, 0x10][..]))
+            .audio_specific_config_load()
+            .unwrap();
+
+        let record = BytesMut::from(&[0x01, 0x64, 0x00, 0x1f, 0xff, 0xe0, 0x00][..]);
+        Fmp4Muxer::new(
+            Some(VideoTrackConfig::avc(record, 1280, 720)),
+            Some(AudioTrackConfig::from_aac(&aac_processor).unwrap()),
+        )
+    }
+
+    #[test]
+    fn test_init_segment() {
+        let segment = muxer().init_segment().unwrap();
+
+        assert_eq!(top_level_boxes(&segment), vec![*b"ftyp", *b"moov"]);
+        let (_, avcc) = find_box(&segment, b"avcC").unwrap();
+        assert_eq!(avcc, &[0x01, 0x64, 0x00, 0x1f, 0xff, 0xe0, 0x00]);
+        //mpeg4 audio, aac lc 44100hz stereo
+        let (_, esds) = find_box(&segment, b"esds").unwrap();
+        assert!(esds
+            .windows(4)
+            .any(|window| window == [0x05, 0x02, 0x12, 0x10]));
+        //the audio track is the last one, its timescale is the sample rate
+        let mdhd = segment
+            .windows(4)
+            .rposition(|window| window == b"mdhd")
+            .unwrap()
+            + 4;
+        assert_eq!(&segment[mdhd + 12..mdhd + 16], &44100u32.to_be_bytes());
+    }
+
+    #[test]
+    fn test_fragment() {
+        let mut muxer = muxer();
+        //flv video tags: key frame with cts 80ms, then b frames
+        for (timestamp, cts, frame_type) in [(0u32, 80u8, 0x17u8), (40, 0, 0x27), (80, 40, 0x27)] {
+            let tag = [frame_type, 0x01, 0x00, 0x00, cts, timestamp as u8];
+            muxer
+                .write_flv_video(timestamp, BytesMut::from(&tag[..]))
+                .unwrap();
+        }
+        muxer
+            .write_flv_audio(0, BytesMut::from(&[0xaf, 0x01, 0xa1][..]))
+            .unwrap();
+        assert_eq!(muxer.pending_video_duration(), 80);
+
+        let fragment = muxer.flush_fragment().unwrap().unwrap();
+        assert_eq!(top_level_boxes(&fragment), vec![*b"moof", *b"mdat"]);
+        assert!(muxer.flush_fragment().unwrap().is_none());
+
+        let (trun_pos, trun) = find_box(&fragment, b"trun").unwrap();
+        //version 1, data offset, duration, size, flags and composition offset
+        assert_eq!(&trun[..4], &[0x01, 0x00, 0x0f, 0x01]);
+        assert_eq!(u32::from_be_bytes(trun[4..8].try_into().unwrap()), 3);
+        let data_offset = u32::from_be_bytes(trun[8..12].try_into().unwrap()) as usize;
+        let (moof_pos, _) = find_box(&fragment, b"moof").unwrap();
+        assert_eq!(fragment[moof_pos + data_offset], 0);
+
+        let sample = |index: usize, field: usize| {
+            let pos = 12 + index * 16 + field * 4;
+            u32::from_be_bytes(trun[pos..pos + 4].try_into().unwrap())
+        };
+        assert_eq!((sample(0, 0), sample(0, 1), sample(0, 3)), (40, 1, 80));
+        assert_eq!((sample(1, 0), sample(1, 3)), (40, 0));
+        //the last duration repeats the previous one
+        assert_eq!((sample(2, 0), sample(2, 3)), (40, 40));
+        assert_eq!(sample(0, 2), 0x02000000);
+        assert_eq!(sample(1, 2), 0x01010000);
+
+        //the audio trun follows the video one, its sample is after the video samples
+        let (_, audio_trun) = find_box(&fragment[trun_pos + 8..], b"trun").unwrap();
+        let audio_offset = u32::from_be_bytes(audio_trun[8..12].try_into().unwrap()) as usize;
+        assert_eq!(audio_offset, data_offset + 3);
+        assert_eq!(fragment[moof_pos + audio_offset], 0xa1);
+    }
+}
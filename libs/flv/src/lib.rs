@@ -3,6 +3,7 @@ pub mod define;
 pub mod demuxer;
 pub mod errors;
 pub mod flv_tag_header;
+pub mod fmp4;
 pub mod mpeg4_aac;
 pub mod mpeg4_avc;
 pub mod mpeg4_hevc;
@@ -1,7 +1,4 @@
-use {
-    super::errors::FlvMuxerError, byteorder::BigEndian, bytes::BytesMut,
-    bytesio::bytes_writer::BytesWriter,
-};
+use {super::errors::FlvMuxerError, byteorder::BigEndian, bytesio::bytes_writer::BytesWriter};
 
 const FLV_HEADER_AV: [u8; 9] = [
     0x46, // 'F'
@@ -91,8 +88,8 @@ impl FlvMuxer {
         Ok(())
     }
 
-    pub fn write_flv_tag_body(&mut self, body: BytesMut) -> Result<(), FlvMuxerError> {
-        self.writer.write(&body[..])?;
+    pub fn write_flv_tag_body(&mut self, body: &[u8]) -> Result<(), FlvMuxerError> {
+        self.writer.write(body)?;
         Ok(())
     }
 
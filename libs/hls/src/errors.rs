@@ -0,0 +1,100 @@
+use {
+    failure::{Backtrace, Fail},
+    std::fmt,
+    streamhub::errors::StreamHubError,
+    tokio::sync::broadcast::error::RecvError,
+    tokio::sync::oneshot::error::RecvError as OneshotRecvError,
+    xflv::errors::FlvDemuxerError,
+    xmpegts::errors::MpegTsError,
+};
+
+#[derive(Debug, Fail)]
+pub enum HlsErrorValue {
+    #[fail(display = "hls error")]
+    Error,
+    #[fail(display = "flv demuxer error:{}", _0)]
+    FlvDemuxerError(#[cause] FlvDemuxerError),
+    #[fail(display = "mpegts error:{}", _0)]
+    MpegTsError(#[cause] MpegTsError),
+    #[fail(display = "io error:{}", _0)]
+    IOError(#[cause] std::io::Error),
+    #[fail(display = "receive error:{}", _0)]
+    RecvError(#[cause] RecvError),
+    #[fail(display = "stream hub event send error")]
+    StreamHubEventSendErr,
+    #[fail(display = "event execute error: {}", _0)]
+    ChannelError(#[cause] StreamHubError),
+    #[fail(display = "tokio: oneshot receiver err: {}", _0)]
+    OneshotRecvError(#[cause] OneshotRecvError),
+    #[fail(display = "invalid app or stream name: {}", _0)]
+    InvalidStreamName(String),
+}
+
+#[derive(Debug)]
+pub struct HlsError {
+    pub value: HlsErrorValue,
+}
+
+impl From<FlvDemuxerError> for HlsError {
+    fn from(error: FlvDemuxerError) -> Self {
+        HlsError {
+            value: HlsErrorValue::FlvDemuxerError(error),
+        }
+    }
+}
+
+impl From<MpegTsError> for HlsError {
+    fn from(error: MpegTsError) -> Self {
+        HlsError {
+            value: HlsErrorValue::MpegTsError(error),
+        }
+    }
+}
+
+impl From<std::io::Error> for HlsError {
+    fn from(error: std::io::Error) -> Self {
+        HlsError {
+            value: HlsErrorValue::IOError(error),
+        }
+    }
+}
+
+impl From<RecvError> for HlsError {
+    fn from(error: RecvError) -> Self {
+        HlsError {
+            value: HlsErrorValue::RecvError(error),
+        }
+    }
+}
+
+impl From<StreamHubError> for HlsError {
+    fn from(error: StreamHubError) -> Self {
+        HlsError {
+            value: HlsErrorValue::ChannelError(error),
+        }
+    }
+}
+
+impl From<OneshotRecvError> for HlsError {
+    fn from(error: OneshotRecvError) -> Self {
+        HlsError {
+            value: HlsErrorValue::OneshotRecvError(error),
+        }
+    }
+}
+
+impl fmt::Display for HlsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.value, f)
+    }
+}
+
+impl Fail for HlsError {
+    fn cause(&self) -> Option<&dyn Fail> {
+        self.value.cause()
+    }
+
+    fn backtrace(&self) -> Option<&Backtrace> {
+        self.value.backtrace()
+    }
+}
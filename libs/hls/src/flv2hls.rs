@@ -0,0 +1,194 @@
+use {
+    super::{errors::HlsError, m3u8::M3u8},
+    bytes::BytesMut,
+    xflv::{
+        define::{frame_type, AvcCodecId, FlvData, SoundFormat},
+        demuxer::{FlvAudioTagDemuxer, FlvVideoTagDemuxer},
+    },
+    xmpegts::{
+        define::{frame_flags, stream_type, TS_CLOCK_RATE},
+        ts::TsMuxer,
+    },
+};
+
+pub struct Flv2HlsRemuxer {
+    video_demuxer: FlvVideoTagDemuxer,
+    audio_demuxer: FlvAudioTagDemuxer,
+
+    ts_muxer: TsMuxer,
+    video_pid: Option<u16>,
+    audio_pid: Option<u16>,
+
+    /*all the timestamps are in milliseconds*/
+    fragment_duration: i64,
+    segment_start_dts: Option<i64>,
+    last_dts: i64,
+    /*the first segment must begin with a key frame*/
+    key_frame_received: bool,
+
+    m3u8_handler: M3u8,
+}
+
+impl Flv2HlsRemuxer {
+    pub fn new(
+        hls_path: &str,
+        app_name: &str,
+        stream_name: &str,
+        fragment_seconds: i64,
+        fragment_max_count: usize,
+    ) -> Result<Self, HlsError> {
+        Ok(Self {
+            video_demuxer: FlvVideoTagDemuxer::new(),
+            audio_demuxer: FlvAudioTagDemuxer::new(),
+
+            ts_muxer: TsMuxer::new(),
+            video_pid: None,
+            audio_pid: None,
+
+            fragment_duration: fragment_seconds.max(1) * 1000,
+            segment_start_dts: None,
+            last_dts: 0,
+            key_frame_received: false,
+
+            m3u8_handler: M3u8::new(hls_path, app_name, stream_name, fragment_max_count)?,
+        })
+    }
+
+    pub fn process_flv_data(&mut self, data: FlvData) -> Result<(), HlsError> {
+        match data {
+            FlvData::Video { timestamp, data } => self.process_video(timestamp, data),
+            FlvData::Audio { timestamp, data } => self.process_audio(timestamp, data),
+            FlvData::MetaData { .. } => Ok(()),
+        }
+    }
+
+    fn process_video(&mut self, timestamp: u32, data: BytesMut) -> Result<(), HlsError> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        /*only h264 can be demuxed by the flv video tag demuxer now*/
+        if data[0] & 0x0f != AvcCodecId::H264 as u8 {
+            return Ok(());
+        }
+        if self.video_pid.is_none() {
+            self.video_pid = Some(self.ts_muxer.add_stream(stream_type::H264)?);
+        }
+
+        let video_data = match self.video_demuxer.demux(timestamp, data)? {
+            Some(video_data) => video_data,
+            None => return Ok(()),
+        };
+
+        let is_key_frame = video_data.frame_type == frame_type::KEY_FRAME;
+        if !self.key_frame_received {
+            if !is_key_frame {
+                return Ok(());
+            }
+            self.key_frame_received = true;
+        }
+
+        if is_key_frame {
+            self.try_cut_segment(video_data.dts)?;
+        }
+
+        let flags = if is_key_frame {
+            frame_flags::KEY_FRAME
+        } else {
+            0
+        };
+        self.write_ts(
+            self.video_pid,
+            video_data.pts,
+            video_data.dts,
+            flags,
+            video_data.data,
+        )
+    }
+
+    fn process_audio(&mut self, timestamp: u32, data: BytesMut) -> Result<(), HlsError> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        if data[0] >> 4 != SoundFormat::AAC as u8 {
+            return Ok(());
+        }
+        if self.audio_pid.is_none() {
+            self.audio_pid = Some(self.ts_muxer.add_stream(stream_type::AAC)?);
+        }
+
+        let audio_data = self.audio_demuxer.demux(timestamp, data)?;
+        if !audio_data.has_data {
+            return Ok(());
+        }
+
+        if self.video_pid.is_some() {
+            if !self.key_frame_received {
+                return Ok(());
+            }
+        } else {
+            /*pure audio stream, cut the segments by audio timestamps*/
+            self.try_cut_segment(audio_data.dts)?;
+        }
+
+        self.write_ts(
+            self.audio_pid,
+            audio_data.pts,
+            audio_data.dts,
+            0,
+            audio_data.data,
+        )
+    }
+
+    fn try_cut_segment(&mut self, dts: i64) -> Result<(), HlsError> {
+        match self.segment_start_dts {
+            Some(start_dts) => {
+                if dts - start_dts >= self.fragment_duration {
+                    self.flush_segment(dts)?;
+                }
+            }
+            None => {
+                self.segment_start_dts = Some(dts);
+            }
+        }
+        Ok(())
+    }
+
+    fn flush_segment(&mut self, end_dts: i64) -> Result<(), HlsError> {
+        let data = self.ts_muxer.get_data();
+        if let Some(start_dts) = self.segment_start_dts {
+            if !data.is_empty() {
+                self.m3u8_handler
+                    .add_segment((end_dts - start_dts).max(0), data)?;
+            }
+        }
+        self.segment_start_dts = Some(end_dts);
+        Ok(())
+    }
+
+    fn write_ts(
+        &mut self,
+        pid: Option<u16>,
+        pts: i64,
+        dts: i64,
+        flags: u8,
+        data: BytesMut,
+    ) -> Result<(), HlsError> {
+        if let Some(pid) = pid {
+            self.last_dts = self.last_dts.max(dts);
+            self.ts_muxer.write(
+                pid,
+                pts * TS_CLOCK_RATE as i64,
+                dts * TS_CLOCK_RATE as i64,
+                flags,
+                data,
+            )?;
+        }
+        Ok(())
+    }
+
+    /*write the last partial segment and end the playlist*/
+    pub fn flush_remaining_data(&mut self) -> Result<(), HlsError> {
+        self.flush_segment(self.last_dts)?;
+        self.m3u8_handler.finish()
+    }
+}
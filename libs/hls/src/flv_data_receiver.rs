@@ -0,0 +1,146 @@
+use {
+    super::{
+        errors::{HlsError, HlsErrorValue},
+        flv2hls::Flv2HlsRemuxer,
+    },
+    bytes::BytesMut,
+    streamhub::{
+        define::{
+            FrameData, NotifyInfo, StreamHubEvent, StreamHubEventSender, SubDataType,
+            SubFrameDataReceiver, SubscribeType, SubscriberInfo,
+        },
+        stream::StreamIdentifier,
+        utils::Uuid,
+    },
+    tokio::sync::oneshot,
+    xflv::define::FlvData,
+};
+
+#[derive(Debug, Clone)]
+pub struct HlsConfig {
+    pub hls_path: String,
+    pub fragment_seconds: i64,
+    pub fragment_max_count: usize,
+}
+
+//Subscribe the frame data of a rtmp stream from the stream hub,
+//and remux it into hls segments.
+pub struct FlvDataReceiver {
+    app_name: String,
+    stream_name: String,
+
+    event_producer: StreamHubEventSender,
+    subscriber_id: Uuid,
+    media_processor: Flv2HlsRemuxer,
+}
+
+impl FlvDataReceiver {
+    pub fn new(
+        app_name: String,
+        stream_name: String,
+        event_producer: StreamHubEventSender,
+        config: &HlsConfig,
+    ) -> Result<Self, HlsError> {
+        let media_processor = Flv2HlsRemuxer::new(
+            &config.hls_path,
+            &app_name,
+            &stream_name,
+            config.fragment_seconds,
+            config.fragment_max_count,
+        )?;
+
+        Ok(Self {
+            app_name,
+            stream_name,
+            event_producer,
+            subscriber_id: Uuid::new(),
+            media_processor,
+        })
+    }
+
+    pub async fn run(&mut self) -> Result<(), HlsError> {
+        let data_consumer = self.subscribe_from_rtmp_channels().await?;
+        let result = self.receive_flv_data(data_consumer).await;
+        self.unsubscribe_from_rtmp_channels();
+        result
+    }
+
+    fn get_identifier(&self) -> StreamIdentifier {
+        StreamIdentifier::Rtmp {
+            app_name: self.app_name.clone(),
+            stream_name: self.stream_name.clone(),
+        }
+    }
+
+    fn get_subscriber_info(&self) -> SubscriberInfo {
+        SubscriberInfo {
+            id: self.subscriber_id,
+            sub_type: SubscribeType::GenerateHls,
+            sub_data_type: SubDataType::Frame,
+            notify_info: NotifyInfo {
+                request_url: String::from(""),
+                remote_addr: String::from(""),
+            },
+        }
+    }
+
+    async fn subscribe_from_rtmp_channels(&mut self) -> Result<SubFrameDataReceiver, HlsError> {
+        let (event_result_sender, event_result_receiver) = oneshot::channel();
+
+        let subscribe_event = StreamHubEvent::Subscribe {
+            identifier: self.get_identifier(),
+            info: self.get_subscriber_info(),
+            result_sender: event_result_sender,
+        };
+
+        if self.event_producer.send(subscribe_event).is_err() {
+            return Err(HlsError {
+                value: HlsErrorValue::StreamHubEventSendErr,
+            });
+        }
+
+        let receiver = event_result_receiver.await??.0;
+        receiver.frame_receiver.ok_or(HlsError {
+            value: HlsErrorValue::Error,
+        })
+    }
+
+    fn unsubscribe_from_rtmp_channels(&mut self) {
+        let unsubscribe_event = StreamHubEvent::UnSubscribe {
+            identifier: self.get_identifier(),
+            info: self.get_subscriber_info(),
+        };
+        if let Err(err) = self.event_producer.send(unsubscribe_event) {
+            log::error!("hls unsubscribe_from_rtmp_channels err {}", err);
+        }
+    }
+
+    async fn receive_flv_data(
+        &mut self,
+        mut data_consumer: SubFrameDataReceiver,
+    ) -> Result<(), HlsError> {
+        //the frame channel is closed when the stream is unpublished
+        while let Some(data) = data_consumer.recv().await {
+            let flv_data = match data {
+                //the demuxers take the frames apart, a shared frame is copied here
+                FrameData::Video { timestamp, data } => FlvData::Video {
+                    timestamp,
+                    data: BytesMut::from(data),
+                },
+                FrameData::Audio { timestamp, data } => FlvData::Audio {
+                    timestamp,
+                    data: BytesMut::from(data),
+                },
+                _ => continue,
+            };
+            self.media_processor.process_flv_data(flv_data)?;
+        }
+
+        log::info!(
+            "hls stream ended, app_name: {}, stream_name: {}",
+            self.app_name,
+            self.stream_name
+        );
+        self.media_processor.flush_remaining_data()
+    }
+}
@@ -0,0 +1,6 @@
+pub mod errors;
+pub mod flv2hls;
+pub mod flv_data_receiver;
+pub mod m3u8;
+pub mod remuxer;
+pub mod server;
@@ -0,0 +1,162 @@
+use {
+    super::errors::{HlsError, HlsErrorValue},
+    bytes::BytesMut,
+    std::{
+        collections::VecDeque,
+        fmt::Write as _,
+        fs,
+        path::{Path, PathBuf},
+    },
+    streamhub::utils::is_path_component,
+};
+
+pub struct Segment {
+    pub sequence_no: u64,
+    /*in milliseconds*/
+    pub duration: i64,
+    pub name: String,
+}
+
+/*Keep a sliding window live playlist:
+    {hls_path}/{app_name}/{stream_name}.m3u8
+    {hls_path}/{app_name}/{stream_name}/{sequence_no}.ts
+the segment uris inside the playlist are relative to the playlist.*/
+pub struct M3u8 {
+    stream_name: String,
+    app_path: PathBuf,
+    segment_path: PathBuf,
+
+    fragment_max_count: usize,
+    segments: VecDeque<Segment>,
+    next_sequence_no: u64,
+}
+
+impl M3u8 {
+    pub fn new(
+        hls_path: &str,
+        app_name: &str,
+        stream_name: &str,
+        fragment_max_count: usize,
+    ) -> Result<Self, HlsError> {
+        /*the names come from the publishers, and the segment directory is removed below*/
+        for name in [app_name, stream_name] {
+            if !is_path_component(name) {
+                return Err(HlsError {
+                    value: HlsErrorValue::InvalidStreamName(name.to_string()),
+                });
+            }
+        }
+
+        let app_path = Path::new(hls_path).join(app_name);
+        let segment_path = app_path.join(stream_name);
+
+        /*clean up the files left by the last publish of the same stream*/
+        if segment_path.exists() {
+            fs::remove_dir_all(&segment_path)?;
+        }
+        fs::create_dir_all(&segment_path)?;
+
+        Ok(Self {
+            stream_name: stream_name.to_string(),
+            app_path,
+            segment_path,
+            fragment_max_count: fragment_max_count.max(1),
+            segments: VecDeque::new(),
+            next_sequence_no: 0,
+        })
+    }
+
+    pub fn playlist_path(&self) -> PathBuf {
+        self.app_path.join(format!("{}.m3u8", self.stream_name))
+    }
+
+    pub fn add_segment(&mut self, duration: i64, data: BytesMut) -> Result<(), HlsError> {
+        let sequence_no = self.next_sequence_no;
+        self.next_sequence_no += 1;
+
+        let name = format!("{sequence_no}.ts");
+        fs::write(self.segment_path.join(&name), &data[..])?;
+
+        self.segments.push_back(Segment {
+            sequence_no,
+            duration,
+            name,
+        });
+
+        while self.segments.len() > self.fragment_max_count {
+            if let Some(segment) = self.segments.pop_front() {
+                if let Err(err) = fs::remove_file(self.segment_path.join(&segment.name)) {
+                    log::warn!("remove hls segment {} error: {}", segment.name, err);
+                }
+            }
+        }
+
+        self.write_playlist(false)
+    }
+
+    /*mark the playlist as ended when the stream is unpublished*/
+    pub fn finish(&mut self) -> Result<(), HlsError> {
+        self.write_playlist(true)
+    }
+
+    pub fn gen_playlist(&self, ended: bool) -> String {
+        let target_duration = self
+            .segments
+            .iter()
+            .map(|s| (s.duration + 999) / 1000)
+            .max()
+            .unwrap_or(1)
+            .max(1);
+        let media_sequence = self.segments.front().map_or(0, |s| s.sequence_no);
+
+        let mut playlist = String::new();
+        let _ = writeln!(playlist, "#EXTM3U");
+        let _ = writeln!(playlist, "#EXT-X-VERSION:3");
+        let _ = writeln!(playlist, "#EXT-X-TARGETDURATION:{target_duration}");
+        let _ = writeln!(playlist, "#EXT-X-MEDIA-SEQUENCE:{media_sequence}");
+
+        for segment in &self.segments {
+            let _ = writeln!(playlist, "#EXTINF:{:.3},", segment.duration as f64 / 1000.0);
+            let _ = writeln!(playlist, "{}/{}", self.stream_name, segment.name);
+        }
+
+        if ended {
+            let _ = writeln!(playlist, "#EXT-X-ENDLIST");
+        }
+
+        playlist
+    }
+
+    fn write_playlist(&self, ended: bool) -> Result<(), HlsError> {
+        /*write to a temp file and rename it, so that a player never reads a half written playlist*/
+        let playlist_path = self.playlist_path();
+        let tmp_path = playlist_path.with_extension("m3u8.tmp");
+        fs::write(&tmp_path, self.gen_playlist(ended))?;
+        fs::rename(tmp_path, playlist_path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_stream_name() {
+        let root = std::env::temp_dir().join("hls_test_invalid_stream_name");
+        let hls_path = root.join("hls");
+        let outside = root.join("x");
+        fs::create_dir_all(&hls_path).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        fs::write(outside.join("file"), b"data").unwrap();
+
+        let hls_path = hls_path.to_str().unwrap();
+        assert!(M3u8::new(hls_path, "live", "../x", 3).is_err());
+        assert!(M3u8::new(hls_path, "..", "x", 3).is_err());
+        assert!(M3u8::new(hls_path, "live", outside.to_str().unwrap(), 3).is_err());
+        assert!(outside.join("file").exists());
+        assert!(!Path::new(hls_path).join("x").exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
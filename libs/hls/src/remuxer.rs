@@ -0,0 +1,73 @@
+use {
+    super::{
+        errors::HlsError,
+        flv_data_receiver::{FlvDataReceiver, HlsConfig},
+    },
+    streamhub::{
+        define::{BroadcastEvent, BroadcastEventReceiver, StreamHubEventSender},
+        stream::StreamKey,
+        utils::recv_broadcast_event,
+    },
+};
+
+//Receive publish event from stream hub and
+//generate hls segments for every stream, the streams published
+//over other protocols are remuxed to rtmp on demand
+pub struct HlsRemuxer {
+    client_event_consumer: BroadcastEventReceiver,
+    event_producer: StreamHubEventSender,
+    config: HlsConfig,
+}
+
+impl HlsRemuxer {
+    pub fn new(
+        consumer: BroadcastEventReceiver,
+        event_producer: StreamHubEventSender,
+        config: HlsConfig,
+    ) -> Self {
+        Self {
+            client_event_consumer: consumer,
+            event_producer,
+            config,
+        }
+    }
+
+    pub async fn run(&mut self) -> Result<(), HlsError> {
+        log::info!("hls remuxer start...");
+
+        loop {
+            let val = recv_broadcast_event(&mut self.client_event_consumer).await?;
+            if let BroadcastEvent::Publish { identifier, .. } = val {
+                let StreamKey {
+                    app_name,
+                    stream_name,
+                } = identifier.stream_key();
+
+                log::info!(
+                    "hls remuxer receive publish event, app_name: {} stream_name: {}",
+                    app_name,
+                    stream_name
+                );
+
+                let mut receiver = match FlvDataReceiver::new(
+                    app_name,
+                    stream_name,
+                    self.event_producer.clone(),
+                    &self.config,
+                ) {
+                    Ok(receiver) => receiver,
+                    Err(err) => {
+                        log::error!("create hls flv data receiver error: {}", err);
+                        continue;
+                    }
+                };
+
+                tokio::spawn(async move {
+                    if let Err(err) = receiver.run().await {
+                        log::error!("hls flv data receiver run error: {}", err);
+                    }
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use {
+    axum::{
+        extract::{Path, State},
+        http::{header, StatusCode},
+        response::{IntoResponse, Response},
+        routing::get,
+        Router,
+    },
+    std::path::{Component, PathBuf},
+    std::sync::Arc,
+};
+
+struct ServerState {
+    hls_path: PathBuf,
+}
+
+/*resolve the request path under the hls root, only m3u8 and ts files can be served*/
+fn resolve_file(hls_path: &std::path::Path, request_path: &str) -> Option<(PathBuf, &'static str)> {
+    let relative = std::path::Path::new(request_path);
+    if !relative
+        .components()
+        .all(|c| matches!(c, Component::Normal(_)))
+    {
+        return None;
+    }
+
+    let content_type = match relative.extension().and_then(|e| e.to_str()) {
+        Some("m3u8") => "application/vnd.apple.mpegurl",
+        Some("ts") => "video/mp2t",
+        _ => return None,
+    };
+
+    Some((hls_path.join(relative), content_type))
+}
+
+async fn serve_hls_file(
+    State(state): State<Arc<ServerState>>,
+    Path(request_path): Path<String>,
+) -> Response {
+    let (file_path, content_type) = match resolve_file(&state.hls_path, &request_path) {
+        Some(v) => v,
+        None => return StatusCode::NOT_FOUND.into_response(),
+    };
+
+    match tokio::fs::read(&file_path).await {
+        Ok(data) => {
+            /*the live playlist changes with every new segment*/
+            let cache_control = if content_type == "video/mp2t" {
+                "max-age=60"
+            } else {
+                "no-cache"
+            };
+            (
+                [
+                    (header::CONTENT_TYPE, content_type),
+                    (header::CACHE_CONTROL, cache_control),
+                    (header::ACCESS_CONTROL_ALLOW_ORIGIN, "*"),
+                ],
+                data,
+            )
+                .into_response()
+        }
+        Err(err) => {
+            log::debug!("hls file {:?} read error: {}", file_path, err);
+            StatusCode::NOT_FOUND.into_response()
+        }
+    }
+}
+
+//Serve the playlists and segments generated by the hls remuxer:
+//  GET /{app_name}/{stream_name}.m3u8
+//  GET /{app_name}/{stream_name}/{sequence_no}.ts
+pub async fn run(address: String, hls_path: String) -> std::io::Result<()> {
+    let state = Arc::new(ServerState {
+        hls_path: PathBuf::from(hls_path),
+    });
+
+    let app = Router::new()
+        .route("/*path", get(serve_hls_file))
+        .with_state(state);
+
+    let listener = tokio::net::TcpListener::bind(&address).await?;
+    log::info!("hls server listening on http://{}", address);
+
+    axum::serve(listener, app).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::resolve_file;
+    use std::path::Path;
+
+    #[test]
+    fn test_resolve_file() {
+        let root = Path::new("/tmp/hls");
+        assert_eq!(
+            resolve_file(root, "live/test.m3u8"),
+            Some((root.join("live/test.m3u8"), "application/vnd.apple.mpegurl"))
+        );
+        assert_eq!(
+            resolve_file(root, "live/test/3.ts"),
+            Some((root.join("live/test/3.ts"), "video/mp2t"))
+        );
+        assert_eq!(resolve_file(root, "live/../../etc/passwd.ts"), None);
+        assert_eq!(resolve_file(root, "live/test.flv"), None);
+    }
+}
@@ -0,0 +1,68 @@
+use {
+    failure::{Backtrace, Fail},
+    std::fmt,
+    streamhub::errors::StreamHubError,
+    tokio::sync::oneshot::error::RecvError as OneshotRecvError,
+    xflv::errors::FlvMuxerError,
+};
+
+#[derive(Debug, Fail)]
+pub enum HttpFlvErrorValue {
+    #[fail(display = "flv muxer error:{}", _0)]
+    FlvMuxerError(#[cause] FlvMuxerError),
+    #[fail(display = "stream hub event send error")]
+    StreamHubEventSendErr,
+    #[fail(display = "event execute error: {}", _0)]
+    ChannelError(#[cause] StreamHubError),
+    #[fail(display = "tokio: oneshot receiver err: {}", _0)]
+    OneshotRecvError(#[cause] OneshotRecvError),
+    #[fail(display = "the http response data receiver is closed")]
+    ResponseDataSendError,
+    #[fail(display = "no frame data receiver")]
+    NoFrameDataReceiver,
+}
+
+#[derive(Debug)]
+pub struct HttpFlvError {
+    pub value: HttpFlvErrorValue,
+}
+
+impl From<FlvMuxerError> for HttpFlvError {
+    fn from(error: FlvMuxerError) -> Self {
+        HttpFlvError {
+            value: HttpFlvErrorValue::FlvMuxerError(error),
+        }
+    }
+}
+
+impl From<StreamHubError> for HttpFlvError {
+    fn from(error: StreamHubError) -> Self {
+        HttpFlvError {
+            value: HttpFlvErrorValue::ChannelError(error),
+        }
+    }
+}
+
+impl From<OneshotRecvError> for HttpFlvError {
+    fn from(error: OneshotRecvError) -> Self {
+        HttpFlvError {
+            value: HttpFlvErrorValue::OneshotRecvError(error),
+        }
+    }
+}
+
+impl fmt::Display for HttpFlvError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.value, f)
+    }
+}
+
+impl Fail for HttpFlvError {
+    fn cause(&self) -> Option<&dyn Fail> {
+        self.value.cause()
+    }
+
+    fn backtrace(&self) -> Option<&Backtrace> {
+        self.value.backtrace()
+    }
+}
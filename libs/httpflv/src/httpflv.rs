@@ -0,0 +1,174 @@
+use {
+    super::errors::{HttpFlvError, HttpFlvErrorValue},
+    bytes::Bytes,
+    streamhub::{
+        define::{
+            FrameData, NotifyInfo, StreamHubEvent, StreamHubEventSender, SubDataType,
+            SubFrameDataReceiver, SubscribeType, SubscriberInfo,
+        },
+        stream::StreamIdentifier,
+        utils::Uuid,
+    },
+    tokio::sync::{mpsc, oneshot},
+    xflv::{define::tag_type, muxer::FlvMuxer, muxer::HEADER_LENGTH},
+};
+
+//amf0 string marker + length + "@setDataFrame"
+const AMF0_SET_DATA_FRAME: [u8; 16] = [
+    0x02, 0x00, 0x0d, b'@', b's', b'e', b't', b'D', b'a', b't', b'a', b'F', b'r', b'a', b'm', b'e',
+];
+
+//the muxed flv data is sent to the http/websocket response through this channel,
+//it is bounded so a stalled player fills its subscriber queue in the stream hub
+pub type HttpResponseDataProducer = mpsc::Sender<Bytes>;
+pub type HttpResponseDataConsumer = mpsc::Receiver<Bytes>;
+pub const HTTP_RESPONSE_DATA_CAPACITY: usize = 16;
+
+//Subscribe the frame data of a rtmp stream from the stream hub,
+//and mux it into a flv byte stream for a http-flv/websocket-flv player.
+pub struct HttpFlv {
+    app_name: String,
+    stream_name: String,
+
+    event_producer: StreamHubEventSender,
+    data_consumer: Option<SubFrameDataReceiver>,
+    http_response_data_producer: HttpResponseDataProducer,
+
+    muxer: FlvMuxer,
+    subscriber_id: Uuid,
+    request_url: String,
+    remote_addr: String,
+}
+
+impl HttpFlv {
+    pub fn new(
+        app_name: String,
+        stream_name: String,
+        event_producer: StreamHubEventSender,
+        http_response_data_producer: HttpResponseDataProducer,
+        request_url: String,
+        remote_addr: String,
+    ) -> Self {
+        Self {
+            app_name,
+            stream_name,
+            event_producer,
+            data_consumer: None,
+            http_response_data_producer,
+            muxer: FlvMuxer::new(),
+            subscriber_id: Uuid::new(),
+            request_url,
+            remote_addr,
+        }
+    }
+
+    pub async fn run(&mut self) -> Result<(), HttpFlvError> {
+        let result = self.send_media_stream().await;
+        self.unsubscribe_from_rtmp_channels();
+        result
+    }
+
+    fn get_identifier(&self) -> StreamIdentifier {
+        StreamIdentifier::Rtmp {
+            app_name: self.app_name.clone(),
+            stream_name: self.stream_name.clone(),
+        }
+    }
+
+    fn get_subscriber_info(&self) -> SubscriberInfo {
+        SubscriberInfo {
+            id: self.subscriber_id,
+            sub_type: SubscribeType::PlayerHttpFlv,
+            sub_data_type: SubDataType::Frame,
+            notify_info: NotifyInfo {
+                request_url: self.request_url.clone(),
+                remote_addr: self.remote_addr.clone(),
+            },
+        }
+    }
+
+    //the cached metadata, sequence headers and gop are sent
+    //first by the stream handler of the publisher.
+    pub async fn subscribe_from_rtmp_channels(&mut self) -> Result<(), HttpFlvError> {
+        let (event_result_sender, event_result_receiver) = oneshot::channel();
+
+        let subscribe_event = StreamHubEvent::Subscribe {
+            identifier: self.get_identifier(),
+            info: self.get_subscriber_info(),
+            result_sender: event_result_sender,
+        };
+
+        if self.event_producer.send(subscribe_event).is_err() {
+            return Err(HttpFlvError {
+                value: HttpFlvErrorValue::StreamHubEventSendErr,
+            });
+        }
+
+        let receiver = event_result_receiver.await??.0;
+        self.data_consumer = receiver.frame_receiver;
+        Ok(())
+    }
+
+    fn unsubscribe_from_rtmp_channels(&mut self) {
+        let unsubscribe_event = StreamHubEvent::UnSubscribe {
+            identifier: self.get_identifier(),
+            info: self.get_subscriber_info(),
+        };
+        if let Err(err) = self.event_producer.send(unsubscribe_event) {
+            log::error!("httpflv unsubscribe_from_rtmp_channels err {}", err);
+        }
+    }
+
+    async fn send_media_stream(&mut self) -> Result<(), HttpFlvError> {
+        let mut data_consumer = self.data_consumer.take().ok_or(HttpFlvError {
+            value: HttpFlvErrorValue::NoFrameDataReceiver,
+        })?;
+
+        self.muxer.write_flv_header(true, true)?;
+        self.muxer.write_previous_tag_size(0)?;
+        self.flush_response_data().await?;
+
+        //the frame channel is closed when the stream is unpublished
+        while let Some(data) = data_consumer.recv().await {
+            let (tag_type, timestamp, data) = match data {
+                FrameData::Video { timestamp, data } => (tag_type::VIDEO, timestamp, data),
+                FrameData::Audio { timestamp, data } => (tag_type::AUDIO, timestamp, data),
+                FrameData::MetaData { timestamp, mut data } => {
+                    //rtmp publishers send "@setDataFrame", "onMetaData", {...},
+                    //but a flv script tag should begin with "onMetaData".
+                    if data.starts_with(&AMF0_SET_DATA_FRAME) {
+                        let _ = data.split_to(AMF0_SET_DATA_FRAME.len());
+                    }
+                    (tag_type::SCRIPT_DATA_AMF, timestamp, data)
+                }
+                FrameData::MediaInfo { .. } => continue,
+            };
+
+            let data_len = data.len() as u32;
+            self.muxer
+                .write_flv_tag_header(tag_type, data_len, timestamp)?;
+            self.muxer.write_flv_tag_body(&data)?;
+            self.muxer
+                .write_previous_tag_size(data_len + HEADER_LENGTH)?;
+            self.flush_response_data().await?;
+        }
+
+        log::info!(
+            "httpflv stream ended, app_name: {}, stream_name: {}",
+            self.app_name,
+            self.stream_name
+        );
+        Ok(())
+    }
+
+    async fn flush_response_data(&mut self) -> Result<(), HttpFlvError> {
+        let data = self.muxer.writer.extract_current_bytes();
+        //the receiver is dropped when the player disconnects
+        self.http_response_data_producer
+            .send(data.freeze())
+            .await
+            .map_err(|_| HttpFlvError {
+                value: HttpFlvErrorValue::ResponseDataSendError,
+            })
+    }
+}
@@ -0,0 +1,175 @@
+use {
+    super::httpflv::{HttpFlv, HttpResponseDataConsumer, HTTP_RESPONSE_DATA_CAPACITY},
+    auth::{Auth, AuthContext, AuthDirection, AuthProtocol},
+    axum::{
+        body::Body,
+        extract::{
+            ws::{Message, WebSocket, WebSocketUpgrade},
+            ConnectInfo, OriginalUri, Path, State,
+        },
+        http::{header, HeaderMap, StatusCode},
+        response::{IntoResponse, Response},
+        routing::get,
+        Router,
+    },
+    futures::{SinkExt, StreamExt},
+    std::net::SocketAddr,
+    streamhub::define::StreamHubEventSender,
+    tokio::sync::mpsc,
+};
+
+#[derive(Clone)]
+struct ServerState<A: Auth> {
+    auth: Option<A>,
+    event_producer: StreamHubEventSender,
+}
+
+//Serve the rtmp streams as flv:
+//  GET /{app_name}/{stream_name}.flv                   chunked http-flv
+//  GET /{app_name}/{stream_name}.flv (upgrade: websocket) websocket-flv
+pub struct HttpFlvServer<A: Auth + 'static> {
+    address: String,
+    event_producer: StreamHubEventSender,
+    auth: Option<A>,
+}
+
+impl<A: Auth + 'static> HttpFlvServer<A> {
+    pub fn new(address: String, event_producer: StreamHubEventSender, auth: Option<A>) -> Self {
+        Self {
+            address,
+            event_producer,
+            auth,
+        }
+    }
+
+    pub async fn run(&mut self) -> std::io::Result<()> {
+        let state = ServerState {
+            auth: self.auth.clone(),
+            event_producer: self.event_producer.clone(),
+        };
+
+        let app = Router::new()
+            .route("/:app_name/:stream_file", get(flv_handler::<A>))
+            .with_state(state);
+
+        let listener = tokio::net::TcpListener::bind(&self.address).await?;
+        log::info!("httpflv server listening on http://{}", self.address);
+
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+    }
+}
+
+async fn flv_handler<A: Auth>(
+    State(state): State<ServerState<A>>,
+    Path((app_name, stream_file)): Path<(String, String)>,
+    ConnectInfo(remote_addr): ConnectInfo<SocketAddr>,
+    OriginalUri(uri): OriginalUri,
+    headers: HeaderMap,
+    ws: Option<WebSocketUpgrade>,
+) -> Response {
+    let stream_name = match stream_file.strip_suffix(".flv") {
+        Some(stream_name) if !stream_name.is_empty() => stream_name.to_string(),
+        _ => return StatusCode::NOT_FOUND.into_response(),
+    };
+
+    if let Some(auth) = &state.auth {
+        let mut ctx = AuthContext::new(
+            AuthProtocol::HttpFlv,
+            AuthDirection::Play,
+            app_name.clone(),
+            stream_name.clone(),
+        )
+        .with_query(uri.query())
+        .with_remote_addr(Some(remote_addr));
+        for (name, value) in &headers {
+            if let Ok(value) = value.to_str() {
+                ctx = ctx.with_header(name.as_str(), value);
+            }
+        }
+
+        if let Err(err) = auth.authenticate(&ctx).await {
+            log::error!(
+                "httpflv auth error: app={} stream={}: {}",
+                app_name,
+                stream_name,
+                err
+            );
+            return StatusCode::UNAUTHORIZED.into_response();
+        }
+    }
+
+    let (response_producer, response_consumer) = mpsc::channel(HTTP_RESPONSE_DATA_CAPACITY);
+    let mut session = HttpFlv::new(
+        app_name,
+        stream_name,
+        state.event_producer,
+        response_producer,
+        uri.to_string(),
+        remote_addr.to_string(),
+    );
+
+    if let Err(err) = session.subscribe_from_rtmp_channels().await {
+        log::error!("httpflv subscribe error: {}", err);
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    tokio::spawn(async move {
+        if let Err(err) = session.run().await {
+            log::info!("httpflv session closed: {}", err);
+        }
+    });
+
+    match ws {
+        Some(ws) => ws.on_upgrade(move |socket| send_websocket_data(socket, response_consumer)),
+        None => {
+            let stream = futures::stream::unfold(response_consumer, |mut consumer| async move {
+                consumer
+                    .recv()
+                    .await
+                    .map(|data| (Ok::<_, std::io::Error>(data), consumer))
+            });
+
+            (
+                [
+                    (header::CONTENT_TYPE, "video/x-flv"),
+                    (header::CACHE_CONTROL, "no-cache"),
+                    (header::ACCESS_CONTROL_ALLOW_ORIGIN, "*"),
+                ],
+                Body::from_stream(stream),
+            )
+                .into_response()
+        }
+    }
+}
+
+async fn send_websocket_data(socket: WebSocket, mut response_consumer: HttpResponseDataConsumer) {
+    let (mut ws_sender, mut ws_receiver) = socket.split();
+
+    loop {
+        tokio::select! {
+            data = response_consumer.recv() => {
+                match data {
+                    Some(data) => {
+                        if ws_sender.send(Message::Binary(data.to_vec())).await.is_err() {
+                            break;
+                        }
+                    }
+                    None => {
+                        let _ = ws_sender.send(Message::Close(None)).await;
+                        break;
+                    }
+                }
+            }
+            msg = ws_receiver.next() => {
+                match msg {
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    _ => {}
+                }
+            }
+        }
+    }
+}
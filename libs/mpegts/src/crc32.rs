@@ -0,0 +1,47 @@
+/*CRC-32/MPEG-2: poly 0x04c11db7, init 0xffffffff, no reflection, no final xor*/
+const CRC32_POLY: u32 = 0x04c1_1db7;
+
+const fn gen_crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u32) << 24;
+        let mut j = 0;
+        while j < 8 {
+            crc = if crc & 0x8000_0000 != 0 {
+                (crc << 1) ^ CRC32_POLY
+            } else {
+                crc << 1
+            };
+            j += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+const CRC32_TABLE: [u32; 256] = gen_crc32_table();
+
+pub fn gen_crc32(data: &[u8]) -> u32 {
+    let mut crc: u32 = 0xffff_ffff;
+    for byte in data {
+        crc = (crc << 8) ^ CRC32_TABLE[(((crc >> 24) as u8) ^ byte) as usize];
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::gen_crc32;
+
+    #[test]
+    fn test_crc32_mpeg2() {
+        assert_eq!(gen_crc32(b"123456789"), 0x0376_e6e7);
+        /*the PAT section written by ffmpeg(program 1 -> pmt pid 0x1000)*/
+        let pat = [
+            0x00, 0xb0, 0x0d, 0x00, 0x01, 0xc1, 0x00, 0x00, 0x00, 0x01, 0xf0, 0x00,
+        ];
+        assert_eq!(gen_crc32(&pat), 0x2ab1_04b2);
+    }
+}
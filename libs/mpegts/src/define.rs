@@ -0,0 +1,42 @@
+pub const TS_PACKET_SIZE: usize = 188;
+pub const TS_HEADER_SIZE: usize = 4;
+pub const TS_PAYLOAD_SIZE: usize = TS_PACKET_SIZE - TS_HEADER_SIZE;
+pub const TS_SYNC_BYTE: u8 = 0x47;
+
+pub mod pid {
+    pub const PAT: u16 = 0x0000;
+    pub const PMT: u16 = 0x1001;
+    pub const VIDEO: u16 = 0x0100;
+    pub const AUDIO: u16 = 0x0101;
+}
+
+pub mod table_id {
+    pub const PAT: u8 = 0x00;
+    pub const PMT: u8 = 0x02;
+}
+
+/*ISO/IEC 13818-1 Table 2-34 stream_type assignments*/
+pub mod stream_type {
+    pub const AAC: u8 = 0x0f;
+    pub const H264: u8 = 0x1b;
+    pub const H265: u8 = 0x24;
+}
+
+pub mod stream_id {
+    pub const AUDIO: u8 = 0xc0;
+    pub const VIDEO: u8 = 0xe0;
+}
+
+pub const PROGRAM_NUMBER: u16 = 0x0001;
+pub const TRANSPORT_STREAM_ID: u16 = 0x0001;
+
+/*the PES/PCR time base is 90kHz*/
+pub const TS_CLOCK_RATE: u64 = 90;
+pub const PTS_NO_VALUE: i64 = i64::MIN;
+
+/*access unit delimiter, every h264 frame inside a ts stream should start with it*/
+pub const H264_AUD_NALU: [u8; 6] = [0x00, 0x00, 0x00, 0x01, 0x09, 0xf0];
+
+pub mod frame_flags {
+    pub const KEY_FRAME: u8 = 0x01;
+}
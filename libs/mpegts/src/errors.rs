@@ -0,0 +1,44 @@
+use {
+    bytesio::bytes_errors::BytesWriteError,
+    failure::{Backtrace, Fail},
+    std::fmt,
+};
+
+#[derive(Debug, Fail)]
+pub enum MpegTsErrorValue {
+    #[fail(display = "bytes write error:{}", _0)]
+    BytesWriteError(#[cause] BytesWriteError),
+    #[fail(display = "stream not found, pid:{}", _0)]
+    StreamNotFound(u16),
+    #[fail(display = "stream count exceeds the limit")]
+    StreamCountExceeded,
+}
+
+#[derive(Debug)]
+pub struct MpegTsError {
+    pub value: MpegTsErrorValue,
+}
+
+impl From<BytesWriteError> for MpegTsError {
+    fn from(error: BytesWriteError) -> Self {
+        MpegTsError {
+            value: MpegTsErrorValue::BytesWriteError(error),
+        }
+    }
+}
+
+impl fmt::Display for MpegTsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.value, f)
+    }
+}
+
+impl Fail for MpegTsError {
+    fn cause(&self) -> Option<&dyn Fail> {
+        self.value.cause()
+    }
+
+    fn backtrace(&self) -> Option<&Backtrace> {
+        self.value.backtrace()
+    }
+}
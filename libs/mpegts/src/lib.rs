@@ -0,0 +1,6 @@
+pub mod crc32;
+pub mod define;
+pub mod errors;
+pub mod pes;
+pub mod psi;
+pub mod ts;
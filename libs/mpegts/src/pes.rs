@@ -0,0 +1,79 @@
+use {
+    super::{define, errors::MpegTsError},
+    byteorder::BigEndian,
+    bytes::BytesMut,
+    bytesio::bytes_writer::BytesWriter,
+};
+
+#[derive(Debug, Clone)]
+pub struct PesStream {
+    pub pid: u16,
+    pub stream_id: u8,
+    pub stream_type: u8,
+    pub continuity_counter: u8,
+}
+
+impl PesStream {
+    pub fn new(pid: u16, stream_id: u8, stream_type: u8) -> Self {
+        Self {
+            pid,
+            stream_id,
+            stream_type,
+            continuity_counter: 0,
+        }
+    }
+
+    pub fn next_continuity_counter(&mut self) -> u8 {
+        let cc = self.continuity_counter;
+        self.continuity_counter = (self.continuity_counter + 1) & 0x0f;
+        cc
+    }
+}
+
+/*'0010'/'0011'/'0001' + 33 bits timestamp splited by marker bits*/
+fn write_timestamp(writer: &mut BytesWriter, prefix: u8, ts: i64) -> Result<(), MpegTsError> {
+    let ts = ts as u64 & 0x1_ffff_ffff;
+    writer.write_u8((prefix << 4) | (((ts >> 30) as u8 & 0x07) << 1) | 0x01)?;
+    writer.write_u16::<BigEndian>(((((ts >> 15) & 0x7fff) << 1) | 0x01) as u16)?;
+    writer.write_u16::<BigEndian>((((ts & 0x7fff) << 1) | 0x01) as u16)?;
+    Ok(())
+}
+
+/*ISO/IEC 13818-1 2.4.3.6 PES packet, pts and dts are in 90kHz*/
+pub fn gen_pes_header(
+    stream_id: u8,
+    payload_len: usize,
+    pts: i64,
+    dts: i64,
+) -> Result<BytesMut, MpegTsError> {
+    let mut writer = BytesWriter::new();
+
+    let with_dts = dts != define::PTS_NO_VALUE && dts != pts;
+    let header_data_length: u8 = if with_dts { 10 } else { 5 };
+
+    writer.write_u24::<BigEndian>(0x000001)?;
+    writer.write_u8(stream_id)?;
+
+    /*the PES_packet_length can be 0 (unbounded) only for video streams*/
+    let pes_packet_length = 3 + header_data_length as usize + payload_len;
+    if pes_packet_length > 0xffff && stream_id == define::stream_id::VIDEO {
+        writer.write_u16::<BigEndian>(0)?;
+    } else {
+        writer.write_u16::<BigEndian>(pes_packet_length as u16)?;
+    }
+
+    /*'10' scrambling(2) priority(1) data_alignment_indicator(1) copyright(1) original(1)*/
+    writer.write_u8(0x80)?;
+    /*PTS_DTS_flags(2) + 6 other flags*/
+    writer.write_u8(if with_dts { 0xc0 } else { 0x80 })?;
+    writer.write_u8(header_data_length)?;
+
+    if with_dts {
+        write_timestamp(&mut writer, 0x03, pts)?;
+        write_timestamp(&mut writer, 0x01, dts)?;
+    } else {
+        write_timestamp(&mut writer, 0x02, pts)?;
+    }
+
+    Ok(writer.extract_current_bytes())
+}
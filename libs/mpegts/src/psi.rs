@@ -0,0 +1,67 @@
+use {
+    super::{crc32, define, errors::MpegTsError},
+    byteorder::BigEndian,
+    bytes::BytesMut,
+    bytesio::bytes_writer::BytesWriter,
+};
+
+#[derive(Debug, Clone)]
+pub struct PmtStream {
+    pub stream_type: u8,
+    pub pid: u16,
+}
+
+fn write_section_end(writer: &mut BytesWriter) -> Result<BytesMut, MpegTsError> {
+    let crc = crc32::gen_crc32(&writer.get_current_bytes()[..]);
+    writer.write_u32::<BigEndian>(crc)?;
+    Ok(writer.extract_current_bytes())
+}
+
+/*ISO/IEC 13818-1 2.4.4.3 Program association Table*/
+pub fn gen_pat(pmt_pid: u16) -> Result<BytesMut, MpegTsError> {
+    let mut writer = BytesWriter::new();
+
+    writer.write_u8(define::table_id::PAT)?;
+    /*section_syntax_indicator(1) '0'(1) reserved(2) section_length(12):
+    5 bytes header + 4 bytes program + 4 bytes crc*/
+    writer.write_u16::<BigEndian>(0xb000 | 13)?;
+    writer.write_u16::<BigEndian>(define::TRANSPORT_STREAM_ID)?;
+    /*reserved(2) version_number(5) current_next_indicator(1)*/
+    writer.write_u8(0xc1)?;
+    /*section_number*/
+    writer.write_u8(0x00)?;
+    /*last_section_number*/
+    writer.write_u8(0x00)?;
+
+    writer.write_u16::<BigEndian>(define::PROGRAM_NUMBER)?;
+    writer.write_u16::<BigEndian>(0xe000 | (pmt_pid & 0x1fff))?;
+
+    write_section_end(&mut writer)
+}
+
+/*ISO/IEC 13818-1 2.4.4.8 Program Map Table*/
+pub fn gen_pmt(pcr_pid: u16, streams: &[PmtStream]) -> Result<BytesMut, MpegTsError> {
+    let mut writer = BytesWriter::new();
+
+    let section_length = 9 + 5 * streams.len() as u16 + 4;
+
+    writer.write_u8(define::table_id::PMT)?;
+    writer.write_u16::<BigEndian>(0xb000 | section_length)?;
+    writer.write_u16::<BigEndian>(define::PROGRAM_NUMBER)?;
+    writer.write_u8(0xc1)?;
+    writer.write_u8(0x00)?;
+    writer.write_u8(0x00)?;
+    /*reserved(3) PCR_PID(13)*/
+    writer.write_u16::<BigEndian>(0xe000 | (pcr_pid & 0x1fff))?;
+    /*reserved(4) program_info_length(12)*/
+    writer.write_u16::<BigEndian>(0xf000)?;
+
+    for stream in streams {
+        writer.write_u8(stream.stream_type)?;
+        writer.write_u16::<BigEndian>(0xe000 | (stream.pid & 0x1fff))?;
+        /*reserved(4) ES_info_length(12)*/
+        writer.write_u16::<BigEndian>(0xf000)?;
+    }
+
+    write_section_end(&mut writer)
+}
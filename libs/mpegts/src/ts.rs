@@ -0,0 +1,273 @@
+use {
+    super::{
+        define::{self, frame_flags, stream_id, stream_type, TS_PACKET_SIZE, TS_PAYLOAD_SIZE},
+        errors::{MpegTsError, MpegTsErrorValue},
+        pes::{self, PesStream},
+        psi::{self, PmtStream},
+    },
+    bytes::BytesMut,
+    bytesio::bytes_writer::BytesWriter,
+};
+
+const MAX_STREAM_COUNT: usize = 2;
+
+/*Mux elementary stream frames(h264/h265 annexb, aac adts) into 188 bytes ts packets.
+A segment always begins with PAT/PMT, so every extracted segment can be played alone.*/
+pub struct TsMuxer {
+    bytes_writer: BytesWriter,
+    pat_continuity_counter: u8,
+    pmt_continuity_counter: u8,
+    streams: Vec<PesStream>,
+    pcr_pid: u16,
+}
+
+impl Default for TsMuxer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TsMuxer {
+    pub fn new() -> Self {
+        Self {
+            bytes_writer: BytesWriter::new(),
+            pat_continuity_counter: 0,
+            pmt_continuity_counter: 0,
+            streams: Vec::new(),
+            pcr_pid: define::pid::VIDEO,
+        }
+    }
+
+    /*return the pid of the new added stream*/
+    pub fn add_stream(&mut self, stream_type: u8) -> Result<u16, MpegTsError> {
+        let (pid, stream_id) = match stream_type {
+            stream_type::H264 | stream_type::H265 => (define::pid::VIDEO, stream_id::VIDEO),
+            _ => (define::pid::AUDIO, stream_id::AUDIO),
+        };
+
+        if let Some(stream) = self.streams.iter().find(|s| s.pid == pid) {
+            return Ok(stream.pid);
+        }
+        if self.streams.len() >= MAX_STREAM_COUNT {
+            return Err(MpegTsError {
+                value: MpegTsErrorValue::StreamCountExceeded,
+            });
+        }
+
+        self.streams.push(PesStream::new(pid, stream_id, stream_type));
+        /*prefer the video stream to carry the PCR*/
+        self.pcr_pid = if self.streams.iter().any(|s| s.pid == define::pid::VIDEO) {
+            define::pid::VIDEO
+        } else {
+            pid
+        };
+
+        Ok(pid)
+    }
+
+    pub fn has_stream(&self) -> bool {
+        !self.streams.is_empty()
+    }
+
+    /*pts/dts are in 90kHz*/
+    pub fn write(
+        &mut self,
+        pid: u16,
+        pts: i64,
+        dts: i64,
+        flags: u8,
+        payload: BytesMut,
+    ) -> Result<(), MpegTsError> {
+        if self.bytes_writer.is_empty() {
+            self.write_pat_pmt()?;
+        }
+
+        let stream_idx = self
+            .streams
+            .iter()
+            .position(|s| s.pid == pid)
+            .ok_or(MpegTsError {
+                value: MpegTsErrorValue::StreamNotFound(pid),
+            })?;
+
+        let (es_stream_id, es_stream_type) = {
+            let stream = &self.streams[stream_idx];
+            (stream.stream_id, stream.stream_type)
+        };
+
+        let mut es_data = BytesMut::new();
+        if es_stream_type == stream_type::H264 {
+            es_data.extend_from_slice(&define::H264_AUD_NALU);
+        }
+        es_data.extend_from_slice(&payload[..]);
+
+        let mut pes_data = pes::gen_pes_header(es_stream_id, es_data.len(), pts, dts)?;
+        pes_data.extend_from_slice(&es_data[..]);
+
+        let pcr = if pid == self.pcr_pid {
+            Some(if dts == define::PTS_NO_VALUE { pts } else { dts })
+        } else {
+            None
+        };
+        let random_access = flags & frame_flags::KEY_FRAME != 0;
+
+        self.write_pes_packets(stream_idx, &pes_data[..], pcr, random_access)
+    }
+
+    /*extract the muxed ts data, the next write will start a new segment*/
+    pub fn get_data(&mut self) -> BytesMut {
+        self.bytes_writer.extract_current_bytes()
+    }
+
+    pub fn reset(&mut self) {
+        self.bytes_writer.clear();
+    }
+
+    fn write_pat_pmt(&mut self) -> Result<(), MpegTsError> {
+        let pat = psi::gen_pat(define::pid::PMT)?;
+        let cc = self.pat_continuity_counter;
+        self.pat_continuity_counter = (cc + 1) & 0x0f;
+        self.write_psi_packet(define::pid::PAT, cc, &pat[..])?;
+
+        let pmt_streams: Vec<PmtStream> = self
+            .streams
+            .iter()
+            .map(|s| PmtStream {
+                stream_type: s.stream_type,
+                pid: s.pid,
+            })
+            .collect();
+        let pmt = psi::gen_pmt(self.pcr_pid, &pmt_streams)?;
+        let cc = self.pmt_continuity_counter;
+        self.pmt_continuity_counter = (cc + 1) & 0x0f;
+        self.write_psi_packet(define::pid::PMT, cc, &pmt[..])
+    }
+
+    fn write_psi_packet(&mut self, pid: u16, cc: u8, section: &[u8]) -> Result<(), MpegTsError> {
+        let mut packet = Vec::with_capacity(TS_PACKET_SIZE);
+        packet.push(define::TS_SYNC_BYTE);
+        /*payload_unit_start_indicator = 1*/
+        packet.push(0x40 | ((pid >> 8) as u8 & 0x1f));
+        packet.push(pid as u8);
+        packet.push(0x10 | cc);
+        /*pointer_field*/
+        packet.push(0x00);
+        packet.extend_from_slice(section);
+        packet.resize(TS_PACKET_SIZE, 0xff);
+
+        self.bytes_writer.write(&packet[..])?;
+        Ok(())
+    }
+
+    fn write_pes_packets(
+        &mut self,
+        stream_idx: usize,
+        pes_data: &[u8],
+        pcr: Option<i64>,
+        random_access: bool,
+    ) -> Result<(), MpegTsError> {
+        let pid = self.streams[stream_idx].pid;
+        let mut offset = 0;
+        let mut first = true;
+
+        while offset < pes_data.len() {
+            let cc = self.streams[stream_idx].next_continuity_counter();
+
+            /*adaptation field without the adaptation_field_length byte*/
+            let mut adaptation = Vec::new();
+            if first && (pcr.is_some() || random_access) {
+                let mut flags = 0x00;
+                if random_access {
+                    flags |= 0x40;
+                }
+                if pcr.is_some() {
+                    flags |= 0x10;
+                }
+                adaptation.push(flags);
+
+                if let Some(pcr) = pcr {
+                    /*program_clock_reference_base(33) reserved(6) extension(9)*/
+                    let base = pcr as u64 & 0x1_ffff_ffff;
+                    adaptation.push((base >> 25) as u8);
+                    adaptation.push((base >> 17) as u8);
+                    adaptation.push((base >> 9) as u8);
+                    adaptation.push((base >> 1) as u8);
+                    adaptation.push((((base & 0x01) as u8) << 7) | 0x7e);
+                    adaptation.push(0x00);
+                }
+            }
+
+            let mut adaptation_size = if adaptation.is_empty() {
+                0
+            } else {
+                1 + adaptation.len()
+            };
+
+            let remaining = pes_data.len() - offset;
+            let space = TS_PAYLOAD_SIZE - adaptation_size;
+            /*fill the last packet by stuffing bytes in the adaptation field*/
+            if remaining < space {
+                let stuffing = space - remaining;
+                if adaptation.is_empty() {
+                    if stuffing > 1 {
+                        adaptation.push(0x00);
+                        adaptation.resize(stuffing - 1, 0xff);
+                    }
+                } else {
+                    adaptation.resize(adaptation.len() + stuffing, 0xff);
+                }
+                adaptation_size += stuffing;
+            }
+
+            let payload_size = TS_PAYLOAD_SIZE - adaptation_size;
+
+            let mut packet = Vec::with_capacity(TS_PACKET_SIZE);
+            packet.push(define::TS_SYNC_BYTE);
+            packet.push(if first { 0x40 } else { 0x00 } | ((pid >> 8) as u8 & 0x1f));
+            packet.push(pid as u8);
+            if adaptation_size > 0 {
+                packet.push(0x30 | cc);
+                packet.push((adaptation_size - 1) as u8);
+                packet.extend_from_slice(&adaptation[..]);
+            } else {
+                packet.push(0x10 | cc);
+            }
+            packet.extend_from_slice(&pes_data[offset..offset + payload_size]);
+
+            self.bytes_writer.write(&packet[..])?;
+
+            offset += payload_size;
+            first = false;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ts_packets_alignment() {
+        let mut muxer = TsMuxer::new();
+        let video_pid = muxer.add_stream(stream_type::H264).unwrap();
+        let audio_pid = muxer.add_stream(stream_type::AAC).unwrap();
+
+        for (len, pid) in [(1000, video_pid), (183, audio_pid), (2, audio_pid), (184, video_pid)] {
+            let payload = BytesMut::from(&vec![0x11u8; len][..]);
+            muxer
+                .write(pid, 9000, 9000, frame_flags::KEY_FRAME, payload)
+                .unwrap();
+        }
+
+        let data = muxer.get_data();
+        assert_eq!(data.len() % TS_PACKET_SIZE, 0);
+        for packet in data.chunks(TS_PACKET_SIZE) {
+            assert_eq!(packet[0], define::TS_SYNC_BYTE);
+        }
+        /*PAT and PMT*/
+        assert_eq!(data[1] & 0x1f, 0x00);
+        assert_eq!(((data[189] as u16 & 0x1f) << 8) | data[190] as u16, define::pid::PMT);
+    }
+}
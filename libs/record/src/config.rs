@@ -0,0 +1,185 @@
+use {
+    chrono::{DateTime, Local},
+    std::path::{Component, Path, PathBuf},
+    streamhub::{define::PublishMode, utils::is_path_component},
+};
+
+#[derive(Debug, Clone)]
+pub struct RecordAppConfig {
+    pub app_name: String,
+    //record every stream of the app, even if it is published as live
+    pub always: bool,
+    //overrides the default path template
+    pub path: Option<String>,
+    //only these streams are recorded if it is not empty
+    pub streams: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordConfig {
+    //the path template of the recorded files, the placeholders are
+    //{app}, {stream}, {time}(%Y%m%d%H%M%S) and {timestamp}(unix seconds)
+    pub path: String,
+    //split the file at the next key frame once it exceeds the size(bytes), 0 means no limit
+    pub max_file_size: u64,
+    //split the file at the next key frame once it exceeds the duration(ms), 0 means no limit
+    pub max_duration: u32,
+    //every app is allowed to record if it is empty
+    pub apps: Vec<RecordAppConfig>,
+}
+
+impl RecordConfig {
+    /// The path template of a published stream, none if it is not recorded.
+    pub fn path_template(
+        &self, app_name: &str, stream_name: &str, mode: PublishMode,
+    ) -> Option<&str> {
+        if self.apps.is_empty() {
+            return match mode {
+                PublishMode::Live => None,
+                PublishMode::Record | PublishMode::Append => Some(&self.path),
+            };
+        }
+
+        let app = self.apps.iter().find(|app| app.app_name == app_name)?;
+        if !app.streams.is_empty() && !app.streams.iter().any(|stream| stream == stream_name) {
+            return None;
+        }
+        if mode == PublishMode::Live && !app.always {
+            return None;
+        }
+        Some(app.path.as_deref().unwrap_or(&self.path))
+    }
+}
+
+/// Fill the placeholders of the template, none if the names from the publisher
+/// would make the path leave the directory of the template.
+pub fn format_path(
+    template: &str, app_name: &str, stream_name: &str, now: DateTime<Local>,
+) -> Option<PathBuf> {
+    if !is_path_component(app_name) || !is_path_component(stream_name) {
+        return None;
+    }
+
+    let path = PathBuf::from(
+        template
+            .replace("{app}", app_name)
+            .replace("{stream}", stream_name)
+            .replace("{time}", &now.format("%Y%m%d%H%M%S").to_string())
+            .replace("{timestamp}", &now.timestamp().to_string()),
+    );
+    let relative = path.strip_prefix(template_root(template)).ok()?;
+    relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+        .then_some(path)
+}
+
+//the directory of the template before the first placeholder
+fn template_root(template: &str) -> &Path {
+    let prefix = &template[..template.find('{').unwrap_or(template.len())];
+    match prefix.rfind('/') {
+        Some(idx) => Path::new(&prefix[..=idx]),
+        None => Path::new(""),
+    }
+}
+
+/// Add a -N suffix to the file name until it does not exist.
+pub fn unique_path(path: &Path) -> PathBuf {
+    if !path.exists() {
+        return path.to_path_buf();
+    }
+
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+
+    let mut index = 1;
+    loop {
+        let candidate = path.with_file_name(format!("{}-{}{}", stem, index, extension));
+        if !candidate.exists() {
+            return candidate;
+        }
+        index += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, chrono::TimeZone};
+
+    fn config() -> RecordConfig {
+        RecordConfig {
+            path: "/data/{app}/{stream}.flv".to_string(),
+            max_file_size: 0,
+            max_duration: 0,
+            apps: vec![
+                RecordAppConfig {
+                    app_name: "live".to_string(),
+                    always: false,
+                    path: None,
+                    streams: Vec::new(),
+                },
+                RecordAppConfig {
+                    app_name: "class".to_string(),
+                    always: true,
+                    path: Some("/class/{stream}-{time}.flv".to_string()),
+                    streams: vec!["math".to_string()],
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_path_template() {
+        let config = config();
+
+        assert_eq!(config.path_template("live", "a", PublishMode::Live), None);
+        assert_eq!(
+            config.path_template("live", "a", PublishMode::Record),
+            Some("/data/{app}/{stream}.flv")
+        );
+        assert_eq!(
+            config.path_template("class", "math", PublishMode::Live),
+            Some("/class/{stream}-{time}.flv")
+        );
+        assert_eq!(
+            config.path_template("class", "art", PublishMode::Record),
+            None
+        );
+        assert_eq!(
+            config.path_template("other", "a", PublishMode::Append),
+            None
+        );
+    }
+
+    #[test]
+    fn test_format_path() {
+        let now = Local.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap();
+        assert_eq!(
+            format_path("/data/{app}/{stream}-{time}.flv", "live", "test", now),
+            Some(PathBuf::from("/data/live/test-20240102030405.flv"))
+        );
+        assert_eq!(
+            format_path("{stream}-{timestamp}.flv", "live", "test", now),
+            Some(PathBuf::from(format!("test-{}.flv", now.timestamp())))
+        );
+    }
+
+    #[test]
+    fn test_format_invalid_path() {
+        let now = Local::now();
+        let template = "/data/{app}/{stream}.flv";
+        assert_eq!(format_path(template, "live", "../../etc/x", now), None);
+        assert_eq!(format_path(template, "..", "x", now), None);
+        assert_eq!(format_path(template, "live", "/etc/x", now), None);
+        assert_eq!(
+            format_path("/data/{app}/../{stream}.flv", "live", "x", now),
+            None
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use {
+    failure::{Backtrace, Fail},
+    std::fmt,
+    streamhub::errors::StreamHubError,
+    tokio::sync::broadcast::error::RecvError,
+    tokio::sync::oneshot::error::RecvError as OneshotRecvError,
+    tokio::task::JoinError,
+    xflv::{amf0::Amf0WriteError, errors::FlvMuxerError},
+};
+
+#[derive(Debug, Fail)]
+pub enum RecordErrorValue {
+    #[fail(display = "record error")]
+    Error,
+    #[fail(display = "flv muxer error:{}", _0)]
+    FlvMuxerError(#[cause] FlvMuxerError),
+    #[fail(display = "amf0 write error:{}", _0)]
+    Amf0WriteError(#[cause] Amf0WriteError),
+    #[fail(display = "io error:{}", _0)]
+    IOError(#[cause] std::io::Error),
+    #[fail(display = "not a flv file")]
+    NotFlvFile,
+    #[fail(display = "receive error:{}", _0)]
+    RecvError(#[cause] RecvError),
+    #[fail(display = "stream hub event send error")]
+    StreamHubEventSendErr,
+    #[fail(display = "event execute error: {}", _0)]
+    ChannelError(#[cause] StreamHubError),
+    #[fail(display = "tokio: oneshot receiver err: {}", _0)]
+    OneshotRecvError(#[cause] OneshotRecvError),
+    #[fail(display = "record writer join error: {}", _0)]
+    JoinError(#[cause] JoinError),
+    #[fail(display = "invalid record path of the stream: {}/{}", _0, _1)]
+    InvalidPath(String, String),
+}
+
+#[derive(Debug)]
+pub struct RecordError {
+    pub value: RecordErrorValue,
+}
+
+impl From<FlvMuxerError> for RecordError {
+    fn from(error: FlvMuxerError) -> Self {
+        RecordError {
+            value: RecordErrorValue::FlvMuxerError(error),
+        }
+    }
+}
+
+impl From<Amf0WriteError> for RecordError {
+    fn from(error: Amf0WriteError) -> Self {
+        RecordError {
+            value: RecordErrorValue::Amf0WriteError(error),
+        }
+    }
+}
+
+impl From<std::io::Error> for RecordError {
+    fn from(error: std::io::Error) -> Self {
+        RecordError {
+            value: RecordErrorValue::IOError(error),
+        }
+    }
+}
+
+impl From<RecvError> for RecordError {
+    fn from(error: RecvError) -> Self {
+        RecordError {
+            value: RecordErrorValue::RecvError(error),
+        }
+    }
+}
+
+impl From<StreamHubError> for RecordError {
+    fn from(error: StreamHubError) -> Self {
+        RecordError {
+            value: RecordErrorValue::ChannelError(error),
+        }
+    }
+}
+
+impl From<OneshotRecvError> for RecordError {
+    fn from(error: OneshotRecvError) -> Self {
+        RecordError {
+            value: RecordErrorValue::OneshotRecvError(error),
+        }
+    }
+}
+
+impl From<JoinError> for RecordError {
+    fn from(error: JoinError) -> Self {
+        RecordError {
+            value: RecordErrorValue::JoinError(error),
+        }
+    }
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.value, f)
+    }
+}
+
+impl Fail for RecordError {
+    fn cause(&self) -> Option<&dyn Fail> {
+        self.value.cause()
+    }
+
+    fn backtrace(&self) -> Option<&Backtrace> {
+        self.value.backtrace()
+    }
+}
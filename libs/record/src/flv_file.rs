@@ -0,0 +1,267 @@
+use {
+    super::errors::{RecordError, RecordErrorValue},
+    bytes::BytesMut,
+    indexmap::IndexMap,
+    std::{
+        fs::{self, File, OpenOptions},
+        io::{BufWriter, Read, Seek, SeekFrom, Write},
+        path::{Path, PathBuf},
+    },
+    xflv::{
+        amf0::{amf0_writer::Amf0Writer, Amf0ValueType},
+        define::tag_type,
+        muxer::{FlvMuxer, HEADER_LENGTH},
+    },
+};
+
+//the key length, the key and the number marker of the onMetaData properties,
+//the f64 value follows them.
+const DURATION_PROPERTY: &[u8] = b"\x00\x08duration\x00";
+const FILESIZE_PROPERTY: &[u8] = b"\x00\x08filesize\x00";
+//the onMetaData tag is the first tag, it is searched in the file beginning
+const METADATA_SEARCH_SIZE: u64 = 1024;
+//flv header and the first previous tag size
+const FLV_HEADER_SIZE: u64 = 13;
+
+#[derive(Debug, Clone)]
+pub struct RecordFile {
+    pub path: PathBuf,
+    pub size: u64,
+    //milliseconds
+    pub duration: u32,
+}
+
+/// Write the flv tags into a file, the duration and filesize of the
+/// onMetaData tag are updated when the file is closed.
+pub struct FlvFileWriter {
+    path: PathBuf,
+    file: BufWriter<File>,
+    muxer: FlvMuxer,
+    size: u64,
+    //the stream timestamp of the first tag, the tags start from 0 in the file
+    base_timestamp: Option<u32>,
+    //the last tag timestamp of the existing file when appending
+    timestamp_offset: u32,
+    last_timestamp: u32,
+    //the position of the duration/filesize values in the file
+    duration_pos: Option<u64>,
+    filesize_pos: Option<u64>,
+}
+
+impl FlvFileWriter {
+    pub fn create(path: &Path) -> Result<Self, RecordError> {
+        create_parent_dir(path)?;
+
+        let mut writer = Self::new(path, File::create(path)?, 0, 0);
+        writer.muxer.write_flv_header(true, true)?;
+        writer.muxer.write_previous_tag_size(0)?;
+
+        let metadata_pos = writer.size + FLV_HEADER_SIZE + HEADER_LENGTH as u64;
+        let metadata = Self::metadata_body()?;
+        writer.duration_pos = find(&metadata, DURATION_PROPERTY).map(|pos| metadata_pos + pos);
+        writer.filesize_pos = find(&metadata, FILESIZE_PROPERTY).map(|pos| metadata_pos + pos);
+        writer.write_tag(tag_type::SCRIPT_DATA_AMF, 0, &metadata)?;
+        writer.base_timestamp = None;
+
+        Ok(writer)
+    }
+
+    /// Append to an existing flv file, the new tags continue after its last tag.
+    pub fn open_append(path: &Path) -> Result<Self, RecordError> {
+        let size = match fs::metadata(path) {
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        };
+        if size <= FLV_HEADER_SIZE {
+            return Self::create(path);
+        }
+
+        let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+
+        let mut beginning = vec![0; size.min(METADATA_SEARCH_SIZE) as usize];
+        file.read_exact(&mut beginning)?;
+        if !beginning.starts_with(b"FLV") {
+            return Err(RecordError {
+                value: RecordErrorValue::NotFlvFile,
+            });
+        }
+
+        let last_timestamp = Self::read_last_timestamp(&mut file, size)?;
+        file.seek(SeekFrom::End(0))?;
+
+        let mut writer = Self::new(path, file, size, last_timestamp);
+        writer.duration_pos = find(&beginning, DURATION_PROPERTY);
+        writer.filesize_pos = find(&beginning, FILESIZE_PROPERTY);
+        Ok(writer)
+    }
+
+    fn new(path: &Path, file: File, size: u64, last_timestamp: u32) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            file: BufWriter::new(file),
+            muxer: FlvMuxer::new(),
+            size,
+            base_timestamp: None,
+            timestamp_offset: last_timestamp,
+            last_timestamp,
+            duration_pos: None,
+            filesize_pos: None,
+        }
+    }
+
+    fn metadata_body() -> Result<BytesMut, RecordError> {
+        let mut properties = IndexMap::new();
+        properties.insert(String::from("duration"), Amf0ValueType::Number(0.0));
+        properties.insert(String::from("filesize"), Amf0ValueType::Number(0.0));
+
+        let mut amf_writer = Amf0Writer::new();
+        amf_writer.write_string(&String::from("onMetaData"))?;
+        amf_writer.write_eacm_array(&properties)?;
+        Ok(amf_writer.extract_current_bytes())
+    }
+
+    //read the timestamp of the tag before the last previous tag size
+    fn read_last_timestamp(file: &mut File, size: u64) -> Result<u32, RecordError> {
+        let mut buf = [0; 4];
+        file.seek(SeekFrom::Start(size - 4))?;
+        file.read_exact(&mut buf)?;
+        let tag_size = u32::from_be_bytes(buf) as u64;
+        if tag_size < HEADER_LENGTH as u64 || tag_size + 4 > size - FLV_HEADER_SIZE {
+            return Ok(0);
+        }
+
+        //skip the tag type and the data size
+        file.seek(SeekFrom::Start(size - 4 - tag_size + 4))?;
+        file.read_exact(&mut buf)?;
+        Ok(u32::from_be_bytes([buf[3], buf[0], buf[1], buf[2]]))
+    }
+
+    pub fn write_tag(
+        &mut self, tag_type: u8, timestamp: u32, body: &[u8],
+    ) -> Result<(), RecordError> {
+        let base_timestamp = *self.base_timestamp.get_or_insert(timestamp);
+        let timestamp = timestamp
+            .saturating_sub(base_timestamp)
+            .saturating_add(self.timestamp_offset);
+        let data_size = body.len() as u32;
+
+        self.muxer
+            .write_flv_tag_header(tag_type, data_size, timestamp)?;
+        self.muxer.write_flv_tag_body(body)?;
+        self.muxer
+            .write_previous_tag_size(data_size + HEADER_LENGTH)?;
+
+        let data = self.muxer.writer.extract_current_bytes();
+        self.file.write_all(&data[..])?;
+        self.size += data.len() as u64;
+        self.last_timestamp = self.last_timestamp.max(timestamp);
+
+        Ok(())
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    //the duration written by this writer, excluding the appended file
+    pub fn written_duration(&self) -> u32 {
+        self.last_timestamp - self.timestamp_offset
+    }
+
+    pub fn close(mut self) -> Result<RecordFile, RecordError> {
+        if let Some(pos) = self.duration_pos {
+            let duration = self.last_timestamp as f64 / 1000.0;
+            self.file.seek(SeekFrom::Start(pos))?;
+            self.file.write_all(&duration.to_be_bytes())?;
+        }
+        if let Some(pos) = self.filesize_pos {
+            self.file.seek(SeekFrom::Start(pos))?;
+            self.file.write_all(&(self.size as f64).to_be_bytes())?;
+        }
+        self.file.flush()?;
+
+        Ok(RecordFile {
+            path: self.path,
+            size: self.size,
+            duration: self.last_timestamp,
+        })
+    }
+}
+
+fn create_parent_dir(path: &Path) -> Result<(), RecordError> {
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+    Ok(())
+}
+
+//the position right after the pattern
+fn find(data: &[u8], pattern: &[u8]) -> Option<u64> {
+    data.windows(pattern.len())
+        .position(|window| window == pattern)
+        .map(|pos| (pos + pattern.len()) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_f64(data: &[u8], pattern: &[u8]) -> f64 {
+        let pos = find(data, pattern).unwrap() as usize;
+        f64::from_be_bytes(data[pos..pos + 8].try_into().unwrap())
+    }
+
+    fn write_tags(writer: &mut FlvFileWriter, timestamps: &[u32]) {
+        for timestamp in timestamps {
+            writer
+                .write_tag(tag_type::VIDEO, *timestamp, &[0x17, 0x01])
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_metadata() {
+        let path = std::env::temp_dir()
+            .join("record_test_metadata")
+            .join("test.flv");
+        let _ = fs::remove_file(&path);
+
+        let mut writer = FlvFileWriter::create(&path).unwrap();
+        write_tags(&mut writer, &[1000, 1040, 3500]);
+        let file = writer.close().unwrap();
+
+        let data = fs::read(&path).unwrap();
+        assert_eq!(file.size, data.len() as u64);
+        assert_eq!(file.duration, 2500);
+        assert_eq!(read_f64(&data, DURATION_PROPERTY), 2.5);
+        assert_eq!(read_f64(&data, FILESIZE_PROPERTY), data.len() as f64);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_append() {
+        let path = std::env::temp_dir()
+            .join("record_test_append")
+            .join("test.flv");
+        let _ = fs::remove_file(&path);
+
+        let mut writer = FlvFileWriter::open_append(&path).unwrap();
+        write_tags(&mut writer, &[0, 2000]);
+        writer.close().unwrap();
+
+        let mut writer = FlvFileWriter::open_append(&path).unwrap();
+        write_tags(&mut writer, &[500, 1500]);
+        assert_eq!(writer.written_duration(), 1000);
+        let file = writer.close().unwrap();
+
+        let data = fs::read(&path).unwrap();
+        assert_eq!(file.duration, 3000);
+        assert_eq!(read_f64(&data, DURATION_PROPERTY), 3.0);
+        assert_eq!(read_f64(&data, FILESIZE_PROPERTY), data.len() as f64);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
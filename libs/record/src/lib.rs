@@ -0,0 +1,5 @@
+pub mod config;
+pub mod errors;
+pub mod flv_file;
+pub mod manager;
+pub mod recorder;
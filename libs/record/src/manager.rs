@@ -0,0 +1,74 @@
+use {
+    super::{config::RecordConfig, errors::RecordError, recorder::FlvRecorder},
+    streamhub::{
+        define::{BroadcastEvent, BroadcastEventReceiver, StreamHubEventSender},
+        notify::Notifier,
+        stream::StreamKey,
+        utils::recv_broadcast_event,
+    },
+};
+
+//Receive publish event from stream hub and
+//record the streams matching the config
+pub struct RecordManager {
+    client_event_consumer: BroadcastEventReceiver,
+    event_producer: StreamHubEventSender,
+    config: RecordConfig,
+    notifier: Option<Notifier>,
+}
+
+impl RecordManager {
+    pub fn new(
+        consumer: BroadcastEventReceiver, event_producer: StreamHubEventSender,
+        config: RecordConfig, notifier: Option<Notifier>,
+    ) -> Self {
+        Self {
+            client_event_consumer: consumer,
+            event_producer,
+            config,
+            notifier,
+        }
+    }
+
+    pub async fn run(&mut self) -> Result<(), RecordError> {
+        log::info!("record manager start...");
+
+        loop {
+            let val = recv_broadcast_event(&mut self.client_event_consumer).await?;
+            if let BroadcastEvent::Publish { identifier, mode } = val {
+                let StreamKey {
+                    app_name,
+                    stream_name,
+                } = identifier.stream_key();
+
+                let Some(path_template) = self.config.path_template(&app_name, &stream_name, mode)
+                else {
+                    continue;
+                };
+
+                log::info!(
+                    "record manager receive publish event, app_name: {} stream_name: {} mode: {:?}",
+                    app_name,
+                    stream_name,
+                    mode
+                );
+
+                let recorder = FlvRecorder::new(
+                    app_name,
+                    stream_name,
+                    self.event_producer.clone(),
+                    path_template.to_string(),
+                    mode,
+                    &self.config,
+                    self.notifier.clone(),
+                );
+
+                tokio::spawn(async move {
+                    if let Err(err) = recorder.run().await {
+                        log::error!("flv recorder run error: {}", err);
+                    }
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,274 @@
+use {
+    super::{
+        config::{format_path, unique_path, RecordConfig},
+        errors::{RecordError, RecordErrorValue},
+        flv_file::FlvFileWriter,
+    },
+    bytes::Bytes,
+    chrono::Local,
+    serde_json::json,
+    streamhub::{
+        define::{
+            FrameData, NotifyInfo, PublishMode, StreamHubEvent, StreamHubEventSender, SubDataType,
+            SubFrameDataReceiver, SubscribeType, SubscriberInfo,
+        },
+        notify::{Notifier, NotifyEvent},
+        stream::StreamIdentifier,
+        utils::Uuid,
+    },
+    tokio::sync::oneshot,
+    xflv::define::{aac_packet_type, avc_packet_type, frame_type, tag_type},
+};
+
+const SOUND_FORMAT_AAC: u8 = 10;
+
+//Subscribe the frame data of a rtmp stream from the stream hub,
+//and write it into flv files.
+pub struct FlvRecorder {
+    app_name: String,
+    stream_name: String,
+
+    event_producer: StreamHubEventSender,
+    subscriber_id: Uuid,
+    notifier: Option<Notifier>,
+
+    path_template: String,
+    mode: PublishMode,
+    max_file_size: u64,
+    max_duration: u32,
+
+    writer: Option<FlvFileWriter>,
+    file_count: usize,
+    //written at the beginning of every file
+    video_seq_header: Option<Bytes>,
+    audio_seq_header: Option<Bytes>,
+}
+
+impl FlvRecorder {
+    pub fn new(
+        app_name: String, stream_name: String, event_producer: StreamHubEventSender,
+        path_template: String, mode: PublishMode, config: &RecordConfig,
+        notifier: Option<Notifier>,
+    ) -> Self {
+        Self {
+            app_name,
+            stream_name,
+            event_producer,
+            subscriber_id: Uuid::new(),
+            notifier,
+            path_template,
+            mode,
+            max_file_size: config.max_file_size,
+            max_duration: config.max_duration,
+            writer: None,
+            file_count: 0,
+            video_seq_header: None,
+            audio_seq_header: None,
+        }
+    }
+
+    pub async fn run(mut self) -> Result<(), RecordError> {
+        let data_consumer = self.subscribe_from_rtmp_channels().await?;
+
+        //the files are written on a blocking thread of its own,
+        //so a slow disk does not stall the tokio workers of the media sessions
+        tokio::task::spawn_blocking(move || {
+            let result = self.receive_flv_data(data_consumer);
+            self.unsubscribe_from_rtmp_channels();
+
+            //keep the recorded part even if the recording fails
+            let close_result = self.close_file();
+            result.and(close_result)
+        })
+        .await?
+    }
+
+    fn get_identifier(&self) -> StreamIdentifier {
+        StreamIdentifier::Rtmp {
+            app_name: self.app_name.clone(),
+            stream_name: self.stream_name.clone(),
+        }
+    }
+
+    fn get_subscriber_info(&self) -> SubscriberInfo {
+        SubscriberInfo {
+            id: self.subscriber_id,
+            sub_type: SubscribeType::RecordFlv,
+            sub_data_type: SubDataType::Frame,
+            notify_info: NotifyInfo {
+                request_url: String::from(""),
+                remote_addr: String::from(""),
+            },
+        }
+    }
+
+    async fn subscribe_from_rtmp_channels(&mut self) -> Result<SubFrameDataReceiver, RecordError> {
+        let (event_result_sender, event_result_receiver) = oneshot::channel();
+
+        let subscribe_event = StreamHubEvent::Subscribe {
+            identifier: self.get_identifier(),
+            info: self.get_subscriber_info(),
+            result_sender: event_result_sender,
+        };
+
+        if self.event_producer.send(subscribe_event).is_err() {
+            return Err(RecordError {
+                value: RecordErrorValue::StreamHubEventSendErr,
+            });
+        }
+
+        let receiver = event_result_receiver.await??.0;
+        receiver.frame_receiver.ok_or(RecordError {
+            value: RecordErrorValue::Error,
+        })
+    }
+
+    fn unsubscribe_from_rtmp_channels(&mut self) {
+        let unsubscribe_event = StreamHubEvent::UnSubscribe {
+            identifier: self.get_identifier(),
+            info: self.get_subscriber_info(),
+        };
+        if let Err(err) = self.event_producer.send(unsubscribe_event) {
+            log::error!("record unsubscribe_from_rtmp_channels err {}", err);
+        }
+    }
+
+    fn receive_flv_data(
+        &mut self, mut data_consumer: SubFrameDataReceiver,
+    ) -> Result<(), RecordError> {
+        //the frame channel is closed when the stream is unpublished
+        while let Some(data) = data_consumer.blocking_recv() {
+            match data {
+                FrameData::Video { timestamp, data } => {
+                    self.process_frame(tag_type::VIDEO, timestamp, data)?
+                }
+                FrameData::Audio { timestamp, data } => {
+                    self.process_frame(tag_type::AUDIO, timestamp, data)?
+                }
+                _ => continue,
+            }
+        }
+
+        log::info!(
+            "record stream ended, app_name: {}, stream_name: {}",
+            self.app_name,
+            self.stream_name
+        );
+        Ok(())
+    }
+
+    fn process_frame(
+        &mut self, tag_type: u8, timestamp: u32, data: Bytes,
+    ) -> Result<(), RecordError> {
+        if data.len() < 2 {
+            return Ok(());
+        }
+
+        let is_seq_header = match tag_type {
+            tag_type::VIDEO => data[1] == avc_packet_type::AVC_SEQHDR,
+            _ => data[0] >> 4 == SOUND_FORMAT_AAC && data[1] == aac_packet_type::AAC_SEQHDR,
+        };
+        if is_seq_header {
+            match tag_type {
+                tag_type::VIDEO => self.video_seq_header = Some(data.clone()),
+                _ => self.audio_seq_header = Some(data.clone()),
+            }
+        }
+
+        //a file starts from a key frame, or any audio frame of an audio only stream
+        let can_split = !is_seq_header
+            && match tag_type {
+                tag_type::VIDEO => data[0] >> 4 == frame_type::KEY_FRAME,
+                _ => self.video_seq_header.is_none(),
+            };
+        if can_split && self.need_new_file() {
+            self.open_file(timestamp)?;
+        }
+
+        if let Some(writer) = &mut self.writer {
+            writer.write_tag(tag_type, timestamp, &data)?;
+        }
+        Ok(())
+    }
+
+    fn need_new_file(&self) -> bool {
+        match &self.writer {
+            Some(writer) => {
+                (self.max_file_size > 0 && writer.size() >= self.max_file_size)
+                    || (self.max_duration > 0 && writer.written_duration() >= self.max_duration)
+            }
+            None => true,
+        }
+    }
+
+    fn open_file(&mut self, timestamp: u32) -> Result<(), RecordError> {
+        self.close_file()?;
+
+        let Some(path) = format_path(
+            &self.path_template,
+            &self.app_name,
+            &self.stream_name,
+            Local::now(),
+        ) else {
+            return Err(RecordError {
+                value: RecordErrorValue::InvalidPath(
+                    self.app_name.clone(),
+                    self.stream_name.clone(),
+                ),
+            });
+        };
+        //only the first file is appended, the split files are new ones
+        let append = self.mode == PublishMode::Append && self.file_count == 0;
+        let path = if append { path } else { unique_path(&path) };
+        let mut writer = if append {
+            FlvFileWriter::open_append(&path)?
+        } else {
+            FlvFileWriter::create(&path)?
+        };
+        self.file_count += 1;
+
+        if let Some(data) = &self.audio_seq_header {
+            writer.write_tag(tag_type::AUDIO, timestamp, data)?;
+        }
+        if let Some(data) = &self.video_seq_header {
+            writer.write_tag(tag_type::VIDEO, timestamp, data)?;
+        }
+
+        log::info!(
+            "record start, app_name: {}, stream_name: {}, path: {}",
+            self.app_name,
+            self.stream_name,
+            path.display()
+        );
+        self.writer = Some(writer);
+        Ok(())
+    }
+
+    fn close_file(&mut self) -> Result<(), RecordError> {
+        let Some(writer) = self.writer.take() else {
+            return Ok(());
+        };
+        let file = writer.close()?;
+
+        log::info!(
+            "record done, app_name: {}, stream_name: {}, path: {}, size: {}, duration: {}ms",
+            self.app_name,
+            self.stream_name,
+            file.path.display(),
+            file.size,
+            file.duration
+        );
+        if let Some(notifier) = &self.notifier {
+            notifier.notify(
+                NotifyEvent::OnRecordDone,
+                json!({
+                    "identifier": self.get_identifier(),
+                    "path": file.path,
+                    "size": file.size,
+                    "duration": file.duration,
+                }),
+            );
+        }
+        Ok(())
+    }
+}
@@ -1,4 +1,7 @@
-use {std::collections::VecDeque, streamhub::define::FrameData};
+use {
+    std::collections::{vec_deque, VecDeque},
+    streamhub::define::FrameData,
+};
 #[derive(Clone)]
 pub struct Gop {
     datas: Vec<FrameData>,
@@ -19,8 +22,8 @@ impl Gop {
         self.datas.push(data);
     }
 
-    pub fn get_frame_data(self) -> Vec<FrameData> {
-        self.datas
+    pub fn get_frame_data(&self) -> &[FrameData] {
+        &self.datas
     }
 
     pub fn len(&self) -> usize {
@@ -76,7 +79,68 @@ impl Gops {
         self.size != 0
     }
 
-    pub fn get_gops(&self) -> VecDeque<Gop> {
-        self.gops.clone()
+    pub fn get_gops(&self) -> &VecDeque<Gop> {
+        &self.gops
+    }
+
+    /// The newest gops with at most `max_frames` frames in total, the older gops are
+    /// skipped as a whole so that a replay never breaks off in the middle of a gop.
+    pub fn latest_gops(&self, max_frames: usize) -> vec_deque::Iter<'_, Gop> {
+        let mut frames = 0;
+        let count = self
+            .gops
+            .iter()
+            .rev()
+            .take_while(|gop| {
+                frames += gop.len();
+                frames <= max_frames
+            })
+            .count();
+        self.gops.range(self.gops.len() - count..)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+
+    fn save_gop(gops: &mut Gops, timestamp: u32, frame_count: usize) {
+        for idx in 0..frame_count {
+            let data = FrameData::Video {
+                timestamp,
+                data: Bytes::new(),
+            };
+            gops.save_frame_data(data, idx == 0);
+        }
+    }
+
+    fn timestamps<'a>(gops: impl Iterator<Item = &'a Gop>) -> Vec<u32> {
+        gops.map(|gop| match gop.get_frame_data()[0] {
+            FrameData::Video { timestamp, .. } => timestamp,
+            _ => unreachable!(),
+        })
+        .collect()
+    }
+
+    #[test]
+    fn test_latest_gops() {
+        let mut gops = Gops::new(3);
+        save_gop(&mut gops, 0, 4);
+        save_gop(&mut gops, 1, 2);
+        save_gop(&mut gops, 2, 3);
+
+        assert_eq!(timestamps(gops.latest_gops(9)), vec![0, 1, 2]);
+        assert_eq!(timestamps(gops.latest_gops(8)), vec![1, 2]);
+        assert_eq!(timestamps(gops.latest_gops(3)), vec![2]);
+    }
+
+    #[test]
+    fn test_latest_gops_larger_than_max_frames() {
+        let mut gops = Gops::new(1);
+        save_gop(&mut gops, 0, 10);
+
+        assert_eq!(gops.latest_gops(4).count(), 0);
+        assert_eq!(timestamps(gops.latest_gops(10)), vec![0]);
     }
 }
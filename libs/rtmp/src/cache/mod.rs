@@ -4,11 +4,11 @@ pub mod metadata;
 
 use {
     self::gop::Gops,
-    bytes::BytesMut,
+    bytes::{Bytes, BytesMut},
     bytesio::bytes_reader::BytesReader,
     errors::CacheError,
     gop::Gop,
-    std::collections::VecDeque,
+    std::collections::vec_deque,
     streamhub::define::{FrameData, StatisticData, StatisticDataSender},
     xflv::{
         define,
@@ -19,13 +19,28 @@ use {
     },
 };
 
+//the sound format and aac packet type
+const AUDIO_TAG_HEADER_SIZE: usize = 2;
+//the frame type, codec id, avc packet type and composition time
+const VIDEO_TAG_HEADER_SIZE: usize = 5;
+
+//The frames are shared with the subscribers, only the tag header is copied to parse it.
+fn tag_header_reader(chunk_body: &Bytes, header_size: usize) -> BytesReader {
+    let header_size = header_size.min(chunk_body.len());
+    BytesReader::new(BytesMut::from(&chunk_body[..header_size]))
+}
+
+fn tag_body(chunk_body: &Bytes, header_size: usize) -> BytesMut {
+    BytesMut::from(chunk_body.get(header_size..).unwrap_or_default())
+}
+
 // #[derive(Clone)]
 pub struct Cache {
     metadata: metadata::MetaData,
     metadata_timestamp: u32,
-    video_seq: BytesMut,
+    video_seq: Bytes,
     video_timestamp: u32,
-    audio_seq: BytesMut,
+    audio_seq: Bytes,
     audio_timestamp: u32,
     gops: Gops,
     statistic_data_sender: Option<StatisticDataSender>,
@@ -37,9 +52,9 @@ impl Cache {
         Cache {
             metadata: metadata::MetaData::new(),
             metadata_timestamp: 0,
-            video_seq: BytesMut::new(),
+            video_seq: Bytes::new(),
             video_timestamp: 0,
-            audio_seq: BytesMut::new(),
+            audio_seq: Bytes::new(),
             audio_timestamp: 0,
             gops: Gops::new(gop_num),
             statistic_data_sender,
@@ -47,7 +62,7 @@ impl Cache {
     }
 
     //, values: Vec<Amf0ValueType>
-    pub fn save_metadata(&mut self, chunk_body: &BytesMut, timestamp: u32) {
+    pub fn save_metadata(&mut self, chunk_body: &Bytes, timestamp: u32) {
         self.metadata.save(chunk_body);
         self.metadata_timestamp = timestamp;
     }
@@ -66,7 +81,7 @@ impl Cache {
     //save audio gops and sequence header information
     pub async fn save_audio_data(
         &mut self,
-        chunk_body: &BytesMut,
+        chunk_body: &Bytes,
         timestamp: u32,
     ) -> Result<(), CacheError> {
         let channel_data = FrameData::Audio {
@@ -75,7 +90,7 @@ impl Cache {
         };
         self.gops.save_frame_data(channel_data, false);
 
-        let mut reader = BytesReader::new(chunk_body.clone());
+        let mut reader = tag_header_reader(chunk_body, AUDIO_TAG_HEADER_SIZE);
         let tag_header = AudioTagHeader::unmarshal(&mut reader)?;
 
         if tag_header.sound_format == define::SoundFormat::AAC as u8
@@ -88,7 +103,7 @@ impl Cache {
                 let mut aac_processor = Mpeg4AacProcessor::default();
 
                 let aac = aac_processor
-                    .extend_data(reader.extract_remaining_bytes())
+                    .extend_data(tag_body(chunk_body, AUDIO_TAG_HEADER_SIZE))
                     .audio_specific_config_load()?;
 
                 let statistic_audio_codec = StatisticData::AudioCodec {
@@ -140,7 +155,7 @@ impl Cache {
     //save video gops and sequence header information
     pub async fn save_video_data(
         &mut self,
-        chunk_body: &BytesMut,
+        chunk_body: &Bytes,
         timestamp: u32,
     ) -> Result<(), CacheError> {
         let channel_data = FrameData::Video {
@@ -148,7 +163,7 @@ impl Cache {
             data: chunk_body.clone(),
         };
 
-        let mut reader = BytesReader::new(chunk_body.clone());
+        let mut reader = tag_header_reader(chunk_body, VIDEO_TAG_HEADER_SIZE);
         let tag_header = VideoTagHeader::unmarshal(&mut reader)?;
 
         let is_key_frame = tag_header.frame_type == define::frame_type::KEY_FRAME;
@@ -160,6 +175,7 @@ impl Cache {
 
             if let Some(statistic_data_sender) = &self.statistic_data_sender {
                 let mut avc_processor = Mpeg4AvcProcessor::default();
+                let mut reader = BytesReader::new(tag_body(chunk_body, VIDEO_TAG_HEADER_SIZE));
                 avc_processor.decoder_configuration_record_load(&mut reader)?;
 
                 let statistic_video_codec = StatisticData::VideoCodec {
@@ -191,9 +207,10 @@ impl Cache {
         Ok(())
     }
 
-    pub fn get_gops_data(&self) -> Option<VecDeque<Gop>> {
+    //the newest gops fitting in max_frames
+    pub fn get_gops_data(&self, max_frames: usize) -> Option<vec_deque::Iter<'_, Gop>> {
         if self.gops.setted() {
-            Some(self.gops.get_gops())
+            Some(self.gops.latest_gops(max_frames))
         } else {
             None
         }
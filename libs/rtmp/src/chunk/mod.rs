@@ -5,7 +5,7 @@ pub mod unpacketizer;
 
 // pub use chunk::{ChunkBasicHeader, ChunkHeader, ChunkInfo, ChunkMessageHeader};
 
-use bytes::BytesMut;
+use bytes::Bytes;
 use std::fmt;
 
 //5.3.1.1
@@ -98,7 +98,7 @@ impl ChunkHeader {
 pub struct ChunkInfo {
     pub basic_header: ChunkBasicHeader,
     pub message_header: ChunkMessageHeader,
-    pub payload: BytesMut,
+    pub payload: Bytes,
 }
 
 impl fmt::Debug for ChunkInfo {
@@ -122,7 +122,7 @@ impl fmt::Debug for ChunkInfo {
 
 impl Default for ChunkInfo {
     fn default() -> Self {
-        Self::new(0, 0, 0, 0, 0, 0, BytesMut::new())
+        Self::new(0, 0, 0, 0, 0, 0, Bytes::new())
     }
 }
 
@@ -134,7 +134,7 @@ impl ChunkInfo {
         msg_length: u32,
         msg_type_id: u8,
         msg_stream_id: u32,
-        payload: BytesMut,
+        payload: Bytes,
     ) -> Self {
         Self {
             basic_header: ChunkBasicHeader::new(format, csid),
@@ -74,9 +74,11 @@ pub struct ChunkUnpacketizer {
     //                   depends on the format id from basic header.
     //                   Each field can inherit the value from the previous chunk.
     // - payload:        If the message's payload size is longger than the max chunk size,
-    //                   the whole payload will be splitted into several chunks.
+    //                   the whole payload will be splitted into several chunks,
+    //                   they are joined in current_payload.
     //
     pub current_chunk_info: ChunkInfo,
+    current_payload: BytesMut,
     chunk_message_headers: HashMap<u32, ChunkMessageHeader>,
     chunk_read_state: ChunkReadState,
     msg_header_read_state: MessageHeaderReadState,
@@ -97,6 +99,7 @@ impl ChunkUnpacketizer {
         Self {
             reader: BytesReader::new(BytesMut::new()),
             current_chunk_info: ChunkInfo::default(),
+            current_payload: BytesMut::new(),
             chunk_message_headers: HashMap::new(),
             chunk_read_state: ChunkReadState::ReadBasicHeader,
             msg_header_read_state: MessageHeaderReadState::ReadTimeStamp,
@@ -525,7 +528,7 @@ impl ChunkUnpacketizer {
         let cur_format_id = self.current_chunk_info.basic_header.format;
         if cur_format_id == 1
             || cur_format_id == 2
-            || (cur_format_id == 3 && self.current_chunk_info.payload.is_empty())
+            || (cur_format_id == 3 && self.current_payload.is_empty())
         {
             let timestamp = self.current_message_header().timestamp;
             let timestamp_delta = self.current_message_header().timestamp_delta;
@@ -536,7 +539,7 @@ impl ChunkUnpacketizer {
                     "The current timestamp is overflow, current basic header: {:?}, current message header: {:?}, payload len: {}, abs timestamp: {}",
                     self.current_chunk_info.basic_header,
                     self.current_chunk_info.message_header,
-                    self.current_chunk_info.payload.len(),
+                    self.current_payload.len(),
                     cur_abs_timestamp
                 );
             }
@@ -551,7 +554,7 @@ impl ChunkUnpacketizer {
 
     pub fn read_message_payload(&mut self) -> Result<UnpackResult, UnpackError> {
         let whole_msg_length = self.current_message_header().msg_length as usize;
-        let remaining_bytes = whole_msg_length - self.current_chunk_info.payload.len();
+        let remaining_bytes = whole_msg_length - self.current_payload.len();
 
         log::trace!(
             "read_message_payload whole msg length: {} and remaining bytes need to be read: {}",
@@ -564,10 +567,10 @@ impl ChunkUnpacketizer {
             need_read_length = min(remaining_bytes, self.max_chunk_size);
         }
 
-        let remaining_mut = self.current_chunk_info.payload.remaining_mut();
+        let remaining_mut = self.current_payload.remaining_mut();
         if need_read_length > remaining_mut {
             let additional = need_read_length - remaining_mut;
-            self.current_chunk_info.payload.reserve(additional);
+            self.current_payload.reserve(additional);
         }
 
         log::trace!(
@@ -577,20 +580,18 @@ impl ChunkUnpacketizer {
         );
 
         let payload_data = self.reader.read_bytes(need_read_length)?;
-        self.current_chunk_info
-            .payload
-            .extend_from_slice(&payload_data[..]);
+        self.current_payload.extend_from_slice(&payload_data[..]);
 
         log::trace!(
             "read_message_payload current msg payload len:{}",
-            self.current_chunk_info.payload.len()
+            self.current_payload.len()
         );
 
-        if self.current_chunk_info.payload.len() == whole_msg_length {
+        if self.current_payload.len() == whole_msg_length {
             self.chunk_read_state = ChunkReadState::Finish;
-            //get the complete chunk and clear the current chunk payload
-            let chunk_info = self.current_chunk_info.clone();
-            self.current_chunk_info.payload.clear();
+            //get the complete chunk, the payload is moved out without copying
+            let mut chunk_info = self.current_chunk_info.clone();
+            chunk_info.payload = self.current_payload.split().freeze();
 
             let csid = self.current_chunk_info.basic_header.chunk_stream_id;
             self.chunk_message_headers
@@ -634,7 +635,7 @@ mod tests {
         let mut body = BytesMut::new();
         body.extend_from_slice(&[00, 00, 10, 00]);
 
-        let expected = ChunkInfo::new(2, 0, 0, 4, 1, 0, body);
+        let expected = ChunkInfo::new(2, 0, 0, 4, 1, 0, body.freeze());
 
         println!("{:?}, {:?}", expected.basic_header, expected.message_header);
 
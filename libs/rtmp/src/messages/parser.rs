@@ -9,6 +9,7 @@ use {
         user_control_messages::reader::EventMessagesReader,
         // utils,
     },
+    bytes::BytesMut,
     bytesio::bytes_reader::BytesReader,
     xflv::amf0::{amf0_markers, amf0_reader::Amf0Reader},
 };
@@ -22,7 +23,7 @@ impl MessageParser {
         Self { chunk_info }
     }
     pub fn parse(self) -> Result<Option<RtmpMessageData>, MessageError> {
-        let mut reader = BytesReader::new(self.chunk_info.payload);
+        let mut reader = BytesReader::new(BytesMut::from(self.chunk_info.payload));
 
         match self.chunk_info.message_header.msg_type_id {
             msg_type_id::COMMAND_AMF0 | msg_type_id::COMMAND_AMF3 => {
@@ -83,7 +83,7 @@ impl NetConnection {
             data.len() as u32,
             messages_define::msg_type_id::COMMAND_AMF0,
             0,
-            data,
+            data.freeze(),
         );
 
         self.packetizer.write_chunk(&mut chunk_info).await?;
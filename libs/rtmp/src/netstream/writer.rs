@@ -33,7 +33,7 @@ impl NetStreamWriter {
             data.len() as u32,
             messages_define::msg_type_id::COMMAND_AMF0,
             msg_stream_id,
-            data,
+            data.freeze(),
         );
 
         self.packetizer.write_chunk(&mut chunk_info).await?;
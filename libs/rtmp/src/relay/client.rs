@@ -0,0 +1,295 @@
+use {
+    super::{
+        define::{redact_url, Backoff, ReconnectPolicy},
+        errors::{ClientError, PushClientErrorValue},
+        status::{RelayDirection, RelayState, RelayStatus, RelayStatuses},
+    },
+    crate::{
+        session::{
+            client_session::{ClientSession, ClientType},
+            errors::SessionError,
+        },
+        utils::RtmpUrlParser,
+    },
+    async_trait::async_trait,
+    std::{
+        sync::{atomic::AtomicU64, Arc},
+        time::Instant,
+    },
+    streamhub::{define::StreamHubEventSender, errors::StreamHubErrorValue, utils::Uuid},
+    tokio::{net::TcpStream, sync::oneshot},
+};
+
+const DEFAULT_RTMP_PORT: &str = "1935";
+
+/// How a connection of a relay is ended.
+pub enum Disconnect {
+    //the relay stops instead of reconnecting
+    Finished,
+    //the connection is closed, it is reconnected after the backoff
+    Closed(Option<String>),
+}
+
+/// One connection of a relay, e.g. to a rtmp or rtsp server.
+#[async_trait]
+pub trait RelayConnector: Send {
+    /// Relay the stream until the connection is closed, the hub session id is sent
+    /// to `relaying` once the remote stream is played or published.
+    async fn connect(
+        &mut self, bytes_sent: Arc<AtomicU64>, relaying: oneshot::Sender<Uuid>,
+    ) -> Disconnect;
+}
+
+/// Relay a local stream to/from a remote url, the connection is
+/// retried with exponential backoff until the task is aborted.
+/// A push relay stops when the local stream is unpublished.
+pub struct RelayClient {
+    id: String,
+    connector: Box<dyn RelayConnector>,
+    policy: ReconnectPolicy,
+    statuses: RelayStatuses,
+    bytes_sent: Arc<AtomicU64>,
+}
+
+impl RelayClient {
+    pub fn new(
+        direction: RelayDirection, app_name: String, stream_name: String, url: String,
+        producer: StreamHubEventSender, policy: ReconnectPolicy, statuses: RelayStatuses,
+    ) -> Self {
+        let connector = RtmpConnector {
+            direction,
+            app_name: app_name.clone(),
+            stream_name: stream_name.clone(),
+            url: url.clone(),
+            producer,
+        };
+        Self::with_connector(
+            direction,
+            app_name,
+            stream_name,
+            &url,
+            Box::new(connector),
+            policy,
+            statuses,
+        )
+    }
+
+    /// Relay with another protocol, the status is reported with the rtmp relays.
+    pub fn with_connector(
+        direction: RelayDirection, app_name: String, stream_name: String, url: &str,
+        connector: Box<dyn RelayConnector>, policy: ReconnectPolicy, statuses: RelayStatuses,
+    ) -> Self {
+        //the status is registered until the client is dropped
+        let id = Uuid::new().to_string();
+        let bytes_sent = statuses.insert(RelayStatus {
+            id: id.clone(),
+            direction,
+            app_name,
+            stream_name,
+            url: redact_url(url),
+            state: RelayState::Connecting,
+            retries: 0,
+            last_error: None,
+            connected_time: None,
+            session_id: None,
+            bytes_sent: 0,
+        });
+
+        Self {
+            id,
+            connector,
+            policy,
+            statuses,
+            bytes_sent,
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub async fn run(&mut self) {
+        let mut backoff = Backoff::new(self.policy);
+        loop {
+            let start = Instant::now();
+            let (relaying_sender, mut relaying_receiver) = oneshot::channel();
+            let connect = self
+                .connector
+                .connect(self.bytes_sent.clone(), relaying_sender);
+            tokio::pin!(connect);
+
+            let disconnect = tokio::select! {
+                disconnect = &mut connect => disconnect,
+                Ok(session_id) = &mut relaying_receiver => {
+                    self.statuses.update(&self.id, |status| {
+                        status.state = RelayState::Connected;
+                        status.connected_time = Some(chrono::Local::now());
+                        status.session_id = Some(session_id);
+                    });
+                    connect.await
+                }
+            };
+            let err = match disconnect {
+                Disconnect::Finished => {
+                    log::info!("relay {} stopped, the stream is finished", self.id);
+                    break;
+                }
+                Disconnect::Closed(err) => err,
+            };
+
+            //the connection was stable for a while, start the backoff over
+            if start.elapsed() > self.policy.max_delay {
+                backoff.reset();
+                self.statuses.update(&self.id, |status| status.retries = 0);
+            }
+            let delay = backoff.next_delay();
+            log::warn!(
+                "relay {} disconnected, error: {:?}, reconnect in {:?}",
+                self.id,
+                err,
+                delay
+            );
+
+            self.statuses.update(&self.id, |status| {
+                status.state = RelayState::Retrying;
+                status.retries += 1;
+                status.last_error = err;
+                status.connected_time = None;
+                status.session_id = None;
+            });
+            tokio::time::sleep(delay).await;
+            self.statuses.update(&self.id, |status| {
+                status.state = RelayState::Connecting;
+            });
+        }
+    }
+}
+
+struct RtmpConnector {
+    direction: RelayDirection,
+    app_name: String,
+    stream_name: String,
+    url: String,
+    producer: StreamHubEventSender,
+}
+
+#[async_trait]
+impl RelayConnector for RtmpConnector {
+    async fn connect(
+        &mut self, bytes_sent: Arc<AtomicU64>, relaying: oneshot::Sender<Uuid>,
+    ) -> Disconnect {
+        match self.relay(bytes_sent, relaying).await {
+            Ok(()) => Disconnect::Closed(None),
+            Err(err) if self.direction == RelayDirection::Push && is_stream_finished(&err) => {
+                Disconnect::Finished
+            }
+            Err(err) => Disconnect::Closed(Some(err.to_string())),
+        }
+    }
+}
+
+impl RtmpConnector {
+    async fn relay(
+        &self, bytes_sent: Arc<AtomicU64>, relaying: oneshot::Sender<Uuid>,
+    ) -> Result<(), ClientError> {
+        let mut parser = RtmpUrlParser::new(self.url.clone());
+        parser.parse_url()?;
+        parser.append_port(DEFAULT_RTMP_PORT.to_string());
+
+        let stream = TcpStream::connect(parser.host_with_port.clone()).await?;
+        let client_type = match self.direction {
+            RelayDirection::Push => ClientType::Publish,
+            RelayDirection::Pull => ClientType::Play,
+        };
+        let mut session = ClientSession::new(
+            stream,
+            client_type,
+            parser.host_with_port,
+            parser.app_name,
+            parser.stream_name_with_query,
+            self.producer.clone(),
+            1,
+        );
+        match self.direction {
+            RelayDirection::Push => {
+                session.subscribe(self.app_name.clone(), self.stream_name.clone());
+                session.set_bytes_counter(bytes_sent);
+            }
+            RelayDirection::Pull => {
+                session.publish(self.app_name.clone(), self.stream_name.clone())
+            }
+        }
+        session.notify_relaying(relaying);
+
+        session.run().await?;
+        Ok(())
+    }
+}
+
+//the local stream of a push relay is unpublished, or it is not published
+//any more when the relay subscribes it again after the backoff.
+fn is_stream_finished(err: &ClientError) -> bool {
+    match &err.value {
+        PushClientErrorValue::SessionError(SessionError::NoMediaDataReceived) => true,
+        PushClientErrorValue::SessionError(SessionError::ChannelError(err)) => {
+            matches!(err.value, StreamHubErrorValue::NoAppOrStreamName)
+        }
+        _ => false,
+    }
+}
+
+impl Drop for RelayClient {
+    fn drop(&mut self) {
+        //the relay is finished or its task is aborted
+        self.statuses.remove(&self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //checks the status before and after the stream is relayed
+    struct MockConnector {
+        statuses: RelayStatuses,
+    }
+
+    #[async_trait]
+    impl RelayConnector for MockConnector {
+        async fn connect(
+            &mut self, _bytes_sent: Arc<AtomicU64>, relaying: oneshot::Sender<Uuid>,
+        ) -> Disconnect {
+            assert_eq!(self.statuses.list()[0].state, RelayState::Connecting);
+            let session_id = Uuid::new();
+            relaying.send(session_id).unwrap();
+            while self.statuses.list()[0].state != RelayState::Connected {
+                tokio::task::yield_now().await;
+            }
+
+            let status = self.statuses.list().remove(0);
+            assert_eq!(status.state, RelayState::Connected);
+            assert_eq!(status.session_id, Some(session_id));
+            Disconnect::Finished
+        }
+    }
+
+    #[tokio::test]
+    async fn test_connected_after_relaying() {
+        let statuses = RelayStatuses::new();
+        let connector = MockConnector {
+            statuses: statuses.clone(),
+        };
+        let mut client = RelayClient::with_connector(
+            RelayDirection::Pull,
+            "live".to_string(),
+            "a".to_string(),
+            "rtsp://127.0.0.1/a",
+            Box::new(connector),
+            ReconnectPolicy::default(),
+            statuses.clone(),
+        );
+        client.run().await;
+        drop(client);
+        assert!(statuses.list().is_empty());
+    }
+}
@@ -1,4 +1,5 @@
 use {
+    crate::{session::errors::SessionError, utils::errors::RtmpUrlParseError},
     failure::Fail,
     std::{fmt, io::Error},
     tokio::sync::broadcast::error::RecvError,
@@ -22,8 +23,12 @@ pub enum PushClientErrorValue {
 
     #[fail(display = "send error")]
     SendError,
-    #[fail(display = "io error")]
+    #[fail(display = "io error: {}", _0)]
     IOError(Error),
+    #[fail(display = "url parse error: {}", _0)]
+    UrlParseError(RtmpUrlParseError),
+    #[fail(display = "session error: {}", _0)]
+    SessionError(SessionError),
 }
 
 impl From<Error> for ClientError {
@@ -41,3 +46,19 @@ impl From<RecvError> for ClientError {
         }
     }
 }
+
+impl From<RtmpUrlParseError> for ClientError {
+    fn from(error: RtmpUrlParseError) -> Self {
+        ClientError {
+            value: PushClientErrorValue::UrlParseError(error),
+        }
+    }
+}
+
+impl From<SessionError> for ClientError {
+    fn from(error: SessionError) -> Self {
+        ClientError {
+            value: PushClientErrorValue::SessionError(error),
+        }
+    }
+}
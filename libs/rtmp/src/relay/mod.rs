@@ -1,3 +1,7 @@
+pub mod client;
+pub mod define;
+pub mod errors;
 pub mod pull_client;
 pub mod push_client;
-pub mod errors;
\ No newline at end of file
+pub mod restream;
+pub mod status;
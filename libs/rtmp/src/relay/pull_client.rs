@@ -1,67 +1,58 @@
-use streamhub::stream::StreamIdentifier;
-
 use {
-    super::errors::ClientError,
-    crate::session::client_session::{ClientSession, ClientType},
-    streamhub::define::{BroadcastEvent, BroadcastEventReceiver, StreamHubEventSender},
-    tokio::net::TcpStream,
+    super::{
+        client::RelayClient,
+        define::{redact_url, PullSource, ReconnectPolicy},
+        status::{RelayDirection, RelayStatuses},
+    },
+    streamhub::define::StreamHubEventSender,
+    tokio::task::JoinSet,
 };
 
+/// Pull the remote rtmp streams and publish them as local streams,
+/// they are kept pulling while the server is running.
 pub struct PullClient {
-    address: String,
-    client_event_consumer: BroadcastEventReceiver,
+    sources: Vec<PullSource>,
+    policy: ReconnectPolicy,
+    statuses: RelayStatuses,
     channel_event_producer: StreamHubEventSender,
 }
 
 impl PullClient {
     pub fn new(
-        address: String,
-        consumer: BroadcastEventReceiver,
+        sources: Vec<PullSource>, policy: ReconnectPolicy, statuses: RelayStatuses,
         producer: StreamHubEventSender,
     ) -> Self {
         Self {
-            address,
-
-            client_event_consumer: consumer,
+            sources,
+            policy,
+            statuses,
             channel_event_producer: producer,
         }
     }
 
-    pub async fn run(&mut self) -> Result<(), ClientError> {
-        loop {
-            let event = self.client_event_consumer.recv().await?;
-
-            if let BroadcastEvent::Subscribe {
-                identifier:
-                    StreamIdentifier::Rtmp {
-                        app_name,
-                        stream_name,
-                    },
-            } = event
-            {
-                log::info!(
-                    "receive pull event, app_name :{}, stream_name: {}",
-                    app_name,
-                    stream_name
-                );
-                let stream = TcpStream::connect(self.address.clone()).await?;
-
-                let mut client_session = ClientSession::new(
-                    stream,
-                    ClientType::Play,
-                    self.address.clone(),
-                    app_name.clone(),
-                    stream_name.clone(),
-                    self.channel_event_producer.clone(),
-                    0,
-                );
-
-                tokio::spawn(async move {
-                    if let Err(err) = client_session.run().await {
-                        log::error!("client_session as pull client run error: {}", err);
-                    }
-                });
-            }
+    pub async fn run(&mut self) {
+        log::info!("pull client run...");
+
+        let mut relays = JoinSet::new();
+        for source in &self.sources {
+            log::info!(
+                "start pull relay, app_name: {} stream_name: {} url: {}",
+                source.app_name,
+                source.stream_name,
+                redact_url(&source.url)
+            );
+            let mut client = RelayClient::new(
+                RelayDirection::Pull,
+                source.app_name.clone(),
+                source.stream_name.clone(),
+                source.url.clone(),
+                self.channel_event_producer.clone(),
+                self.policy,
+                self.statuses.clone(),
+            );
+            relays.spawn(async move { client.run().await });
         }
+
+        while relays.join_next().await.is_some() {}
     }
 }
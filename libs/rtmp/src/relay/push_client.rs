@@ -1,30 +1,43 @@
 use {
-    super::errors::ClientError,
-    crate::session::client_session::{ClientSession, ClientType},
+    super::{
+        client::RelayClient,
+        define::{redact_url, PushTarget, ReconnectPolicy},
+        errors::ClientError,
+        status::{RelayDirection, RelayStatuses},
+    },
+    std::collections::HashMap,
     streamhub::{
-        define::{StreamHubEventSender, BroadcastEvent, BroadcastEventReceiver},
-        stream::StreamIdentifier,
+        define::{BroadcastEvent, BroadcastEventReceiver, StreamHubEventSender},
+        stream::StreamKey,
+        utils::recv_broadcast_event,
     },
-    tokio::net::TcpStream,
+    tokio::task::JoinHandle,
 };
 
+/// Push the published streams to the matching targets over rtmp,
+/// the relays are stopped when the streams are unpublished.
 pub struct PushClient {
-    address: String,
+    targets: Vec<PushTarget>,
+    policy: ReconnectPolicy,
+    statuses: RelayStatuses,
     client_event_consumer: BroadcastEventReceiver,
     channel_event_producer: StreamHubEventSender,
+    //the relay tasks of every published stream
+    relays: HashMap<StreamKey, Vec<JoinHandle<()>>>,
 }
 
 impl PushClient {
     pub fn new(
-        address: String,
-        consumer: BroadcastEventReceiver,
-        producer: StreamHubEventSender,
+        targets: Vec<PushTarget>, policy: ReconnectPolicy, statuses: RelayStatuses,
+        consumer: BroadcastEventReceiver, producer: StreamHubEventSender,
     ) -> Self {
         Self {
-            address,
-
+            targets,
+            policy,
+            statuses,
             client_event_consumer: consumer,
             channel_event_producer: producer,
+            relays: HashMap::new(),
         }
     }
 
@@ -32,45 +45,66 @@ impl PushClient {
         log::info!("push client run...");
 
         loop {
-            let val = self.client_event_consumer.recv().await?;
+            let val = recv_broadcast_event(&mut self.client_event_consumer).await?;
 
             match val {
-                BroadcastEvent::Publish { identifier } => {
-                    if let StreamIdentifier::Rtmp {
-                        app_name,
-                        stream_name,
-                    } = identifier
-                    {
-                        log::info!(
-                            "publish app_name: {} stream_name: {} address: {}",
-                            app_name.clone(),
-                            stream_name.clone(),
-                            self.address.clone()
-                        );
-                        let stream = TcpStream::connect(self.address.clone()).await?;
-
-                        let mut client_session = ClientSession::new(
-                            stream,
-                            ClientType::Publish,
-                            self.address.clone(),
-                            app_name,
-                            stream_name,
-                            self.channel_event_producer.clone(),
-                            0,
-                        );
-
-                        tokio::spawn(async move {
-                            if let Err(err) = client_session.run().await {
-                                log::error!("client_session as push client run error: {}", err);
-                            }
-                        });
-                    }
+                BroadcastEvent::Publish { identifier, .. } => {
+                    self.start_relays(identifier.stream_key());
+                }
+                BroadcastEvent::UnPublish { identifier } => {
+                    self.stop_relays(&identifier.stream_key());
                 }
-
                 _ => {
-                    log::info!("push client receive other events");
+                    log::trace!("push client receive other events");
                 }
             }
         }
     }
+
+    //the relays subscribe the rtmp representation, which is
+    //remuxed on demand if the stream is published over other protocols
+    fn start_relays(&mut self, key: StreamKey) {
+        let StreamKey {
+            app_name,
+            stream_name,
+        } = &key;
+
+        let relays: Vec<JoinHandle<()>> = self
+            .targets
+            .iter()
+            .filter(|target| target.is_match(app_name, stream_name))
+            .map(|target| {
+                let url = target.format_url(app_name, stream_name);
+                log::info!(
+                    "start push relay, stream: {} url: {}",
+                    key,
+                    redact_url(&url)
+                );
+                let mut client = RelayClient::new(
+                    RelayDirection::Push,
+                    app_name.clone(),
+                    stream_name.clone(),
+                    url,
+                    self.channel_event_producer.clone(),
+                    self.policy,
+                    self.statuses.clone(),
+                );
+                tokio::spawn(async move { client.run().await })
+            })
+            .collect();
+
+        if !relays.is_empty() {
+            self.stop_relays(&key);
+            self.relays.insert(key, relays);
+        }
+    }
+
+    fn stop_relays(&mut self, key: &StreamKey) {
+        if let Some(relays) = self.relays.remove(key) {
+            log::info!("stop push relays, stream: {}", key);
+            for relay in relays {
+                relay.abort();
+            }
+        }
+    }
 }
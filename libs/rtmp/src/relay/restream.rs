@@ -0,0 +1,155 @@
+use {
+    super::{
+        client::RelayClient,
+        define::ReconnectPolicy,
+        errors::ClientError,
+        status::{RelayDirection, RelayStatus, RelayStatuses},
+    },
+    crate::utils::RtmpUrlParser,
+    std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    },
+    streamhub::{
+        define::{StreamHubEvent, StreamHubEventSender},
+        stream::StreamKey,
+    },
+    tokio::task::JoinHandle,
+};
+
+struct Restream {
+    key: StreamKey,
+    handle: JoinHandle<()>,
+}
+
+/// Attach push relays to the published streams at runtime, a relay is stopped
+/// when it is detached or its stream is unpublished. The relays subscribe the
+/// rtmp representation, which is remuxed on demand for the other protocols.
+#[derive(Clone)]
+pub struct RestreamManager {
+    producer: StreamHubEventSender,
+    policy: ReconnectPolicy,
+    statuses: RelayStatuses,
+    restreams: Arc<Mutex<HashMap<String, Restream>>>,
+}
+
+impl RestreamManager {
+    pub fn new(
+        producer: StreamHubEventSender, policy: ReconnectPolicy, statuses: RelayStatuses,
+    ) -> Self {
+        Self {
+            producer,
+            policy,
+            statuses,
+            restreams: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Push the stream to the url, returns the id of the relay.
+    pub fn attach(&self, key: StreamKey, url: String) -> Result<String, ClientError> {
+        RtmpUrlParser::new(url.clone()).parse_url()?;
+
+        let mut client = RelayClient::new(
+            RelayDirection::Push,
+            key.app_name.clone(),
+            key.stream_name.clone(),
+            url,
+            self.producer.clone(),
+            self.policy,
+            self.statuses.clone(),
+        );
+        let id = client.id().to_string();
+        log::info!("attach restream {} to {}", id, key);
+
+        //the lock is held until the relay is saved, since the task removes it when finished
+        let mut restreams = self.restreams.lock().unwrap();
+        let finished_restreams = self.restreams.clone();
+        let finished_id = id.clone();
+        let handle = tokio::spawn(async move {
+            client.run().await;
+            finished_restreams.lock().unwrap().remove(&finished_id);
+        });
+        restreams.insert(id.clone(), Restream { key, handle });
+
+        Ok(id)
+    }
+
+    /// Stop the relay, the other subscribers of the stream are not affected.
+    pub fn detach(&self, key: &StreamKey, id: &str) -> bool {
+        let restream = {
+            let mut restreams = self.restreams.lock().unwrap();
+            match restreams.get(id) {
+                Some(restream) if &restream.key == key => restreams.remove(id),
+                _ => None,
+            }
+        };
+        let Some(restream) = restream else {
+            return false;
+        };
+
+        log::info!("detach restream {} from {}", id, key);
+        let session_id = self.statuses.get(id).and_then(|status| status.session_id);
+        restream.handle.abort();
+
+        //the aborted session cannot unsubscribe itself
+        if let Some(session_id) = session_id {
+            if self
+                .producer
+                .send(StreamHubEvent::ApiKickClient { id: session_id })
+                .is_err()
+            {
+                log::error!("detach restream {}: send kick client event error", id);
+            }
+        }
+        true
+    }
+
+    pub fn list(&self, key: &StreamKey) -> Vec<RelayStatus> {
+        self.restreams
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, restream)| &restream.key == key)
+            .filter_map(|(id, _)| self.statuses.get(id))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::time::Duration, tokio::sync::mpsc};
+
+    fn key(stream_name: &str) -> StreamKey {
+        StreamKey::new("live", stream_name)
+    }
+
+    #[tokio::test]
+    async fn test_attach_detach() {
+        let (producer, _consumer) = mpsc::unbounded_channel();
+        let statuses = RelayStatuses::new();
+        let policy = ReconnectPolicy {
+            initial_delay: Duration::from_secs(10),
+            max_delay: Duration::from_secs(10),
+        };
+        let manager = RestreamManager::new(producer, policy, statuses.clone());
+
+        assert!(manager
+            .attach(key("a"), "http://127.0.0.1/live/a".to_string())
+            .is_err());
+
+        let id = manager
+            .attach(key("a"), "rtmp://127.0.0.1:1/live/a?key=1".to_string())
+            .unwrap();
+        let restreams = manager.list(&key("a"));
+        assert_eq!(restreams.len(), 1);
+        assert_eq!(restreams[0].url, "rtmp://127.0.0.1:1/live/a?***");
+        assert!(manager.list(&key("b")).is_empty());
+
+        assert!(!manager.detach(&key("b"), &id));
+        assert!(manager.detach(&key("a"), &id));
+        assert!(!manager.detach(&key("a"), &id));
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(statuses.list().is_empty());
+    }
+}
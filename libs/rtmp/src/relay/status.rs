@@ -0,0 +1,122 @@
+use {
+    chrono::{DateTime, Local},
+    serde::Serialize,
+    std::{
+        collections::HashMap,
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc, Mutex,
+        },
+    },
+    streamhub::utils::Uuid,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RelayDirection {
+    Push,
+    Pull,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RelayState {
+    Connecting,
+    Connected,
+    //waiting for the next reconnection
+    Retrying,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RelayStatus {
+    pub id: String,
+    pub direction: RelayDirection,
+    //the local stream
+    pub app_name: String,
+    pub stream_name: String,
+    //the remote url without the query
+    pub url: String,
+    pub state: RelayState,
+    //the reconnection times since the last successful connection
+    pub retries: u32,
+    pub last_error: Option<String>,
+    pub connected_time: Option<DateTime<Local>>,
+    //the id of the current session in the stream hub
+    pub session_id: Option<Uuid>,
+    //the media bytes pushed to the remote server
+    pub bytes_sent: u64,
+}
+
+struct RelayEntry {
+    status: RelayStatus,
+    bytes_sent: Arc<AtomicU64>,
+}
+
+/// The status of the running relays, shared by the relay clients and the statistics api.
+#[derive(Clone, Default)]
+pub struct RelayStatuses {
+    entries: Arc<Mutex<HashMap<String, RelayEntry>>>,
+}
+
+impl RelayStatuses {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the counter of the sent bytes of the relay.
+    pub fn insert(&self, status: RelayStatus) -> Arc<AtomicU64> {
+        let bytes_sent = Arc::new(AtomicU64::new(status.bytes_sent));
+        self.entries.lock().unwrap().insert(
+            status.id.clone(),
+            RelayEntry {
+                status,
+                bytes_sent: bytes_sent.clone(),
+            },
+        );
+        bytes_sent
+    }
+
+    pub fn update<F>(&self, id: &str, f: F)
+    where
+        F: FnOnce(&mut RelayStatus),
+    {
+        if let Some(entry) = self.entries.lock().unwrap().get_mut(id) {
+            f(&mut entry.status);
+        }
+    }
+
+    pub fn remove(&self, id: &str) {
+        self.entries.lock().unwrap().remove(id);
+    }
+
+    pub fn get(&self, id: &str) -> Option<RelayStatus> {
+        self.entries
+            .lock()
+            .unwrap()
+            .get(id)
+            .map(RelayEntry::snapshot)
+    }
+
+    pub fn list(&self) -> Vec<RelayStatus> {
+        let mut statuses: Vec<RelayStatus> = self
+            .entries
+            .lock()
+            .unwrap()
+            .values()
+            .map(RelayEntry::snapshot)
+            .collect();
+        statuses.sort_by(|a, b| {
+            (&a.app_name, &a.stream_name, &a.url).cmp(&(&b.app_name, &b.stream_name, &b.url))
+        });
+        statuses
+    }
+}
+
+impl RelayEntry {
+    fn snapshot(&self) -> RelayStatus {
+        RelayStatus {
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+            ..self.status.clone()
+        }
+    }
+}
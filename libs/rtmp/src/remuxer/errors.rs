@@ -43,6 +43,8 @@ pub enum RtmpRemuxerErrorValue {
     OneshotRecvError(#[cause] OneshotRecvError),
     #[fail(display = "Channel receive error")]
     ChannelRecvError,
+    #[fail(display = "cannot get the frame receiver from stream hub")]
+    NoFrameReceiver,
 }
 impl From<RecvError> for RtmpRemuxerError {
     fn from(error: RecvError) -> Self {
@@ -7,14 +7,15 @@ pub mod whip2rtmp;
 use streamhub::{
     define::{BroadcastEvent, BroadcastEventReceiver, StreamHubEventSender},
     stream::StreamIdentifier,
+    utils::recv_broadcast_event,
 };
 
 use crate::remuxer::whip2rtmp::Whip2RtmpRemuxerSession;
 
 use self::{errors::RtmpRemuxerError, rtsp2rtmp::Rtsp2RtmpRemuxerSession};
 
-//Receive publish event from stream hub and
-//remux from other protocols to rtmp
+//Receive remux event from stream hub and
+//remux the rtsp/webrtc streams to rtmp on demand
 pub struct RtmpRemuxer {
     receiver: BroadcastEventReceiver,
     event_producer: StreamHubEventSender,
@@ -31,10 +32,13 @@ impl RtmpRemuxer {
         log::info!("rtmp remuxer start...");
 
         loop {
-            let val = self.receiver.recv().await?;
+            let val = recv_broadcast_event(&mut self.receiver).await?;
             log::info!("{:?}", val);
             match val {
-                BroadcastEvent::Publish { identifier } => match identifier {
+                BroadcastEvent::Remux {
+                    source,
+                    target: StreamIdentifier::Rtmp { .. },
+                } => match source {
                     StreamIdentifier::Rtsp { stream_path } => {
                         let mut session =
                             Rtsp2RtmpRemuxerSession::new(stream_path, self.event_producer.clone());
@@ -97,7 +97,7 @@ impl RtmpCooker {
     //generate audio rtmp frame (including seq header and common frame)
     pub fn gen_audio_frame_data(
         &self,
-        audio_data: &BytesMut,
+        audio_data: &[u8],
     ) -> Result<BytesMut, RtmpRemuxerError> {
         let mut aac_packet_type: u8 = 0;
 
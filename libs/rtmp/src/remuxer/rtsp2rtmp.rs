@@ -14,16 +14,14 @@ use super::{
 
 use {
     crate::session::common::Common,
-    std::time::Duration,
     streamhub::{
         define::{
-            FrameData, FrameDataReceiver, NotifyInfo, StreamHubEvent, StreamHubEventSender,
+            FrameData, NotifyInfo, StreamHubEvent, StreamHubEventSender, SubFrameDataReceiver,
             SubscribeType, SubscriberInfo,
         },
         stream::StreamIdentifier,
         utils::Uuid,
     },
-    tokio::{sync::mpsc, time::sleep},
 };
 pub struct Rtsp2RtmpRemuxerSession {
     event_producer: StreamHubEventSender,
@@ -32,7 +30,6 @@ pub struct Rtsp2RtmpRemuxerSession {
     stream_name: String,
 
     //RTSP
-    data_receiver: FrameDataReceiver,
     stream_path: String,
     subscribe_id: Uuid,
     video_clock_rate: u32,
@@ -51,8 +48,6 @@ pub fn find_start_code(nalus: &[u8]) -> Option<usize> {
 
 impl Rtsp2RtmpRemuxerSession {
     pub fn new(stream_path: String, event_producer: StreamHubEventSender) -> Self {
-        let (_, data_consumer) = mpsc::unbounded_channel();
-
         let eles: Vec<&str> = stream_path.splitn(2, '/').collect();
         let (app_name, stream_name) = if eles.len() < 2 {
             log::warn!(
@@ -68,7 +63,6 @@ impl Rtsp2RtmpRemuxerSession {
             stream_path,
             app_name,
             stream_name,
-            data_receiver: data_consumer,
             event_producer: event_producer.clone(),
 
             subscribe_id: Uuid::new(),
@@ -77,17 +71,26 @@ impl Rtsp2RtmpRemuxerSession {
             audio_clock_rate: 1000,
             base_audio_timestamp: 0,
             base_video_timestamp: 0,
-            rtmp_handler: Common::new(None, event_producer, SessionType::Server, None),
+            rtmp_handler: Common::new(None, event_producer, SessionType::Remuxer, None),
             rtmp_cooker: RtmpCooker::default(),
         }
     }
 
     pub async fn run(&mut self) -> Result<(), RtmpRemuxerError> {
         self.publish_rtmp().await?;
-        self.subscribe_rtsp().await?;
-        self.receive_rtsp_data().await?;
 
-        Ok(())
+        let result = match self.subscribe_rtsp().await {
+            Ok(data_receiver) => {
+                let result = self.receive_rtsp_data(data_receiver).await;
+                self.unsubscribe_rtsp().await?;
+                result
+            }
+            Err(err) => Err(err),
+        };
+
+        //the rtmp stream is removed by the stream hub already if nobody plays it
+        self.unpublish_rtmp().await?;
+        result
     }
 
     pub async fn publish_rtmp(&mut self) -> Result<(), RtmpRemuxerError> {
@@ -104,11 +107,11 @@ impl Rtsp2RtmpRemuxerSession {
         Ok(())
     }
 
-    pub async fn subscribe_rtsp(&mut self) -> Result<(), RtmpRemuxerError> {
+    pub async fn subscribe_rtsp(&mut self) -> Result<SubFrameDataReceiver, RtmpRemuxerError> {
         let (event_result_sender, event_result_receiver) = oneshot::channel();
         let sub_info = SubscriberInfo {
             id: self.subscribe_id,
-            sub_type: SubscribeType::PlayerRtmp,
+            sub_type: SubscribeType::Remux,
             sub_data_type: streamhub::define::SubDataType::Frame,
             notify_info: NotifyInfo {
                 request_url: String::from(""),
@@ -131,14 +134,15 @@ impl Rtsp2RtmpRemuxerSession {
         }
 
         let receiver = event_result_receiver.await??.0;
-        self.data_receiver = receiver.frame_receiver.unwrap();
-        Ok(())
+        receiver.frame_receiver.ok_or(RtmpRemuxerError {
+            value: RtmpRemuxerErrorValue::NoFrameReceiver,
+        })
     }
 
     pub async fn unsubscribe_rtsp(&mut self) -> Result<(), RtmpRemuxerError> {
         let sub_info = SubscriberInfo {
             id: self.subscribe_id,
-            sub_type: SubscribeType::PlayerRtsp,
+            sub_type: SubscribeType::Remux,
             sub_data_type: streamhub::define::SubDataType::Frame,
             notify_info: NotifyInfo {
                 request_url: String::from(""),
@@ -159,67 +163,54 @@ impl Rtsp2RtmpRemuxerSession {
         Ok(())
     }
 
-    pub async fn receive_rtsp_data(&mut self) -> Result<(), RtmpRemuxerError> {
-        let mut retry_count = 0;
-
-        loop {
-            if let Some(data) = self.data_receiver.recv().await {
-                match data {
-                    FrameData::Audio { timestamp, data } => {
-                        self.on_rtsp_audio(&data, timestamp).await?
-                    }
-                    FrameData::Video {
-                        timestamp,
-                        mut data,
-                    } => {
-                        self.on_rtsp_video(&mut data, timestamp).await?;
-                    }
-                    FrameData::MediaInfo { media_info } => {
-                        self.video_clock_rate = media_info.video_clock_rate;
-                        self.audio_clock_rate = media_info.audio_clock_rate;
-                        log::info!(
-                            "audio clock rate: {} video clock rate: {}",
-                            self.audio_clock_rate,
-                            self.video_clock_rate
-                        );
-
-                        if media_info.vcodec == VideoCodecType::H265 {
-                            log::warn!(
-                                "h265 rtsp to rtmp is not supported now!!! will come soon!!"
-                            );
-                            break;
-                        }
+    pub async fn receive_rtsp_data(
+        &mut self, mut data_receiver: SubFrameDataReceiver,
+    ) -> Result<(), RtmpRemuxerError> {
+        //the receiver is closed when the rtsp stream is unpublished
+        while let Some(data) = data_receiver.recv().await {
+            match data {
+                FrameData::Audio { timestamp, data } => {
+                    self.on_rtsp_audio(&data, timestamp).await?
+                }
+                //the nalus are taken apart, a shared frame is copied here
+                FrameData::Video { timestamp, data } => {
+                    self.on_rtsp_video(&mut BytesMut::from(data), timestamp)
+                        .await?;
+                }
+                FrameData::MediaInfo { media_info } => {
+                    self.video_clock_rate = media_info.video_clock_rate;
+                    self.audio_clock_rate = media_info.audio_clock_rate;
+                    log::info!(
+                        "audio clock rate: {} video clock rate: {}",
+                        self.audio_clock_rate,
+                        self.video_clock_rate
+                    );
+
+                    if media_info.vcodec == VideoCodecType::H265 {
+                        log::warn!("h265 rtsp to rtmp is not supported now!!! will come soon!!");
+                        break;
                     }
-                    _ => continue,
-                };
-                retry_count = 0;
-            } else {
-                sleep(Duration::from_millis(100)).await;
-                retry_count += 1;
-            }
-
-            if retry_count > 10 {
-                break;
-            }
+                }
+                _ => continue,
+            };
         }
 
-        self.unsubscribe_rtsp().await?;
-        self.unpublish_rtmp().await
+        Ok(())
     }
 
     async fn on_rtsp_audio(
-        &mut self, audio_data: &BytesMut, timestamp: u32,
+        &mut self, audio_data: &[u8], timestamp: u32,
     ) -> Result<(), RtmpRemuxerError> {
         if self.base_audio_timestamp == 0 {
             self.base_audio_timestamp = timestamp;
         }
 
-        let mut audio_frame = self.rtmp_cooker.gen_audio_frame_data(audio_data)?;
+        let audio_frame = self.rtmp_cooker.gen_audio_frame_data(audio_data)?;
 
         let timestamp_adjust =
             (timestamp - self.base_audio_timestamp) / (self.audio_clock_rate / 1000);
         self.rtmp_handler
-            .on_audio_data(&mut audio_frame, &timestamp_adjust)
+            .on_audio_data(audio_frame.freeze(), &timestamp_adjust)
             .await?;
 
         Ok(())
@@ -288,25 +279,29 @@ impl Rtsp2RtmpRemuxerSession {
         }
 
         if sps.is_some() && pps.is_some() {
-            let mut meta_data = self.rtmp_cooker.gen_meta_data(width, height)?;
-            self.rtmp_handler.on_meta_data(&mut meta_data, &0).await?;
+            let meta_data = self.rtmp_cooker.gen_meta_data(width, height)?;
+            self.rtmp_handler
+                .on_meta_data(meta_data.freeze(), &0)
+                .await?;
 
-            let mut seq_header = self.rtmp_cooker.gen_video_seq_header(
+            let seq_header = self.rtmp_cooker.gen_video_seq_header(
                 sps.unwrap(),
                 pps.unwrap(),
                 profile,
                 level,
             )?;
-            self.rtmp_handler.on_video_data(&mut seq_header, &0).await?;
+            self.rtmp_handler
+                .on_video_data(seq_header.freeze(), &0)
+                .await?;
         } else {
-            let mut frame_data = self
+            let frame_data = self
                 .rtmp_cooker
                 .gen_video_frame_data(nalu_vec, contains_idr)?;
 
             let timestamp_adjust =
                 (timestamp - self.base_video_timestamp) / (self.video_clock_rate / 1000);
             self.rtmp_handler
-                .on_video_data(&mut frame_data, &timestamp_adjust)
+                .on_video_data(frame_data.freeze(), &timestamp_adjust)
                 .await?;
         }
 
@@ -14,32 +14,63 @@ use super::{
 
 use {
     crate::session::common::Common,
-    std::time::Duration,
+    std::time::Instant,
     streamhub::{
         define::{
-            FrameData, FrameDataReceiver, NotifyInfo, StreamHubEvent, StreamHubEventSender,
+            FrameData, NotifyInfo, StreamHubEvent, StreamHubEventSender, SubFrameDataReceiver,
             SubscribeType, SubscriberInfo,
         },
         stream::StreamIdentifier,
         utils::Uuid,
     },
-    tokio::{sync::mpsc, time::sleep},
 };
+
+//Convert the rtp timestamps of a track into rtmp milliseconds. The tracks of a whip
+//stream start from random rtp timestamps, so every track counts from its first frame,
+//shifted by the time that frame arrived after the first frame of the stream.
+struct TimestampRebaser {
+    clock_rate: u32,
+    last_timestamp: Option<u32>,
+    //the clock ticks since the first frame
+    ticks: i64,
+    offset: u32,
+}
+
+impl TimestampRebaser {
+    fn new(clock_rate: u32) -> Self {
+        Self {
+            clock_rate,
+            last_timestamp: None,
+            ticks: 0,
+            offset: 0,
+        }
+    }
+
+    fn rebase(&mut self, timestamp: u32, arrival: u32) -> u32 {
+        match self.last_timestamp {
+            //the signed difference survives the wrap around and the reordered frames
+            Some(last_timestamp) => {
+                self.ticks += timestamp.wrapping_sub(last_timestamp) as i32 as i64
+            }
+            None => self.offset = arrival,
+        }
+        self.last_timestamp = Some(timestamp);
+
+        let milliseconds = self.ticks.max(0) * 1000 / self.clock_rate as i64;
+        self.offset.wrapping_add(milliseconds as u32)
+    }
+}
+
 pub struct Whip2RtmpRemuxerSession {
     event_producer: StreamHubEventSender,
     //RTMP
     app_name: String,
     stream_name: String,
 
-    //WHIP
-    data_receiver: FrameDataReceiver,
-
     subscribe_id: Uuid,
-    video_clock_rate: u32,
-    audio_clock_rate: u32,
-    //because
-    base_video_timestamp: u32,
-    base_audio_timestamp: u32,
+    start_time: Option<Instant>,
+    video_rebaser: TimestampRebaser,
+    audio_rebaser: TimestampRebaser,
 
     rtmp_handler: Common,
     rtmp_cooker: RtmpCooker,
@@ -49,6 +80,9 @@ pub struct Whip2RtmpRemuxerSession {
     video_seq_header_generated: bool,
 }
 
+//the rtmp cooker sends the audio data up to 5 bytes as the AudioSpecificConfig
+const AAC_SEQ_HEADER_MAX_SIZE: usize = 5;
+
 pub fn find_start_code(nalus: &[u8]) -> Option<usize> {
     let pattern = [0x00, 0x00, 0x01];
     nalus.windows(pattern.len()).position(|w| w == pattern)
@@ -72,20 +106,16 @@ impl Whip2RtmpRemuxerSession {
     pub fn new(
         app_name: String, stream_name: String, event_producer: StreamHubEventSender,
     ) -> Self {
-        let (_, data_consumer) = mpsc::unbounded_channel();
-
         Self {
             app_name,
             stream_name,
-            data_receiver: data_consumer,
             event_producer: event_producer.clone(),
 
             subscribe_id: Uuid::new(),
-            video_clock_rate: 1000,
-            audio_clock_rate: 1000,
-            base_audio_timestamp: 0,
-            base_video_timestamp: 0,
-            rtmp_handler: Common::new(None, event_producer, SessionType::Server, None),
+            start_time: None,
+            video_rebaser: TimestampRebaser::new(1000),
+            audio_rebaser: TimestampRebaser::new(1000),
+            rtmp_handler: Common::new(None, event_producer, SessionType::Remuxer, None),
             rtmp_cooker: RtmpCooker::default(),
             sps: None,
             pps: None,
@@ -95,10 +125,19 @@ impl Whip2RtmpRemuxerSession {
 
     pub async fn run(&mut self) -> Result<(), RtmpRemuxerError> {
         self.publish_rtmp().await?;
-        self.subscribe_whip().await?;
-        self.receive_whip_data().await?;
 
-        Ok(())
+        let result = match self.subscribe_whip().await {
+            Ok(data_receiver) => {
+                let result = self.receive_whip_data(data_receiver).await;
+                self.unsubscribe_whip().await?;
+                result
+            }
+            Err(err) => Err(err),
+        };
+
+        //the rtmp stream is removed by the stream hub already if nobody plays it
+        self.unpublish_rtmp().await?;
+        result
     }
 
     pub async fn publish_rtmp(&mut self) -> Result<(), RtmpRemuxerError> {
@@ -115,12 +154,12 @@ impl Whip2RtmpRemuxerSession {
         Ok(())
     }
 
-    pub async fn subscribe_whip(&mut self) -> Result<(), RtmpRemuxerError> {
+    pub async fn subscribe_whip(&mut self) -> Result<SubFrameDataReceiver, RtmpRemuxerError> {
         let (event_result_sender, event_result_receiver) = oneshot::channel();
 
         let sub_info = SubscriberInfo {
             id: self.subscribe_id,
-            sub_type: SubscribeType::PlayerRtmp,
+            sub_type: SubscribeType::Remux,
             sub_data_type: streamhub::define::SubDataType::Frame,
             notify_info: NotifyInfo {
                 request_url: String::from(""),
@@ -144,14 +183,15 @@ impl Whip2RtmpRemuxerSession {
         }
 
         let receiver = event_result_receiver.await??.0;
-        self.data_receiver = receiver.frame_receiver.unwrap();
-        Ok(())
+        receiver.frame_receiver.ok_or(RtmpRemuxerError {
+            value: RtmpRemuxerErrorValue::NoFrameReceiver,
+        })
     }
 
     pub async fn unsubscribe_whip(&mut self) -> Result<(), RtmpRemuxerError> {
         let sub_info = SubscriberInfo {
             id: self.subscribe_id,
-            sub_type: SubscribeType::PlayerRtmp,
+            sub_type: SubscribeType::Remux,
             sub_data_type: streamhub::define::SubDataType::Frame,
             notify_info: NotifyInfo {
                 request_url: String::from(""),
@@ -173,68 +213,65 @@ impl Whip2RtmpRemuxerSession {
         Ok(())
     }
 
-    pub async fn receive_whip_data(&mut self) -> Result<(), RtmpRemuxerError> {
-        let mut retry_count = 0;
+    pub async fn receive_whip_data(
+        &mut self, mut data_receiver: SubFrameDataReceiver,
+    ) -> Result<(), RtmpRemuxerError> {
         log::info!("begin receive whip data...");
-        loop {
-            if let Some(data) = self.data_receiver.recv().await {
-                match data {
-                    FrameData::Audio { timestamp, data } => {
-                        self.on_whip_audio(&data, timestamp).await?
-                    }
-                    FrameData::Video {
-                        timestamp,
-                        mut data,
-                    } => {
-                        self.on_whip_video(&mut data, timestamp).await?;
-                    }
-                    FrameData::MediaInfo { media_info } => {
-                        self.video_clock_rate = media_info.video_clock_rate;
-                        self.audio_clock_rate = media_info.audio_clock_rate;
-                        log::info!(
-                            "audio clock rate: {} video clock rate: {}",
-                            self.audio_clock_rate,
-                            self.video_clock_rate
-                        );
-
-                        if media_info.vcodec == VideoCodecType::H265 {
-                            log::warn!(
-                                "h265 rtsp to rtmp is not supported now!!! will come soon!!"
-                            );
-                            break;
-                        }
+        //the receiver is closed when the whip stream is unpublished
+        while let Some(data) = data_receiver.recv().await {
+            match data {
+                FrameData::Audio { timestamp, data } => {
+                    self.on_whip_audio(&data, timestamp).await?
+                }
+                //the nalus are taken apart, a shared frame is copied here
+                FrameData::Video { timestamp, data } => {
+                    self.on_whip_video(&mut BytesMut::from(data), timestamp)
+                        .await?;
+                }
+                FrameData::MediaInfo { media_info } => {
+                    self.video_rebaser = TimestampRebaser::new(media_info.video_clock_rate);
+                    self.audio_rebaser = TimestampRebaser::new(media_info.audio_clock_rate);
+                    log::info!(
+                        "audio clock rate: {} video clock rate: {}",
+                        media_info.audio_clock_rate,
+                        media_info.video_clock_rate
+                    );
+
+                    if media_info.vcodec == VideoCodecType::H265 {
+                        log::warn!("h265 whip to rtmp is not supported now!!! will come soon!!");
+                        break;
                     }
-                    _ => continue,
-                };
-                retry_count = 0;
-            } else {
-                sleep(Duration::from_millis(100)).await;
-                retry_count += 1;
-            }
-
-            if retry_count > 10 {
-                break;
-            }
+                }
+                _ => continue,
+            };
         }
 
-        self.unsubscribe_whip().await?;
-        self.unpublish_rtmp().await
+        Ok(())
+    }
+
+    //the milliseconds since the first frame of the stream
+    fn arrival_time(&mut self) -> u32 {
+        self.start_time
+            .get_or_insert_with(Instant::now)
+            .elapsed()
+            .as_millis() as u32
     }
 
     async fn on_whip_audio(
-        &mut self, audio_data: &BytesMut, timestamp: u32,
+        &mut self, audio_data: &[u8], timestamp: u32,
     ) -> Result<(), RtmpRemuxerError> {
-        if self.base_audio_timestamp == 0 {
-            self.base_audio_timestamp = timestamp;
-        }
-
-        let mut audio_frame = self.rtmp_cooker.gen_audio_frame_data(audio_data)?;
-
-        let timestamp_adjust =
-            (timestamp - self.base_audio_timestamp) / (self.audio_clock_rate / 1000);
+        let audio_frame = self.rtmp_cooker.gen_audio_frame_data(audio_data)?;
+
+        //the AudioSpecificConfig is sent before the frames with timestamp 0
+        let timestamp_adjust = if audio_data.len() > AAC_SEQ_HEADER_MAX_SIZE {
+            let arrival = self.arrival_time();
+            self.audio_rebaser.rebase(timestamp, arrival)
+        } else {
+            0
+        };
 
         self.rtmp_handler
-            .on_audio_data(&mut audio_frame, &timestamp_adjust)
+            .on_audio_data(audio_frame.freeze(), &timestamp_adjust)
             .await?;
 
         Ok(())
@@ -243,9 +280,6 @@ impl Whip2RtmpRemuxerSession {
     async fn on_whip_video(
         &mut self, nalus: &mut BytesMut, timestamp: u32,
     ) -> Result<(), RtmpRemuxerError> {
-        if self.base_video_timestamp == 0 {
-            self.base_video_timestamp = timestamp;
-        }
         let mut nalu_vec = Vec::new();
         while !nalus.is_empty() {
             if let Some(first_pos) = find_start_code(&nalus[..]) {
@@ -267,31 +301,17 @@ impl Whip2RtmpRemuxerSession {
             }
         }
 
-        let mut width: u32 = 0;
-        let mut height: u32 = 0;
-        let mut level: u8 = 0;
-        let mut profile: u8 = 0;
+        //an empty nalu has no type, it is skipped instead of ending the remux
+        if nalu_vec.iter().any(|nalu| nalu.is_empty()) {
+            log::warn!("whip2rtmp: skip the empty nalus of a video frame");
+            nalu_vec.retain(|nalu| !nalu.is_empty());
+        }
 
         let mut contains_idr = false;
 
         for nalu in &nalu_vec {
-            let mut nalu_reader = BytesReader::new(nalu.clone());
-
-            let nalu_type = nalu_reader.read_u8()?;
-            match nalu_type & 0x1F {
-                H264_NAL_SPS => {
-                    let mut sps_parser = SpsParser::new(nalu_reader);
-                    (width, height) = if let Ok((width, height)) = sps_parser.parse() {
-                        (width, height)
-                    } else {
-                        (0, 0)
-                    };
-
-                    level = sps_parser.sps.level_idc;
-                    profile = sps_parser.sps.profile_idc;
-
-                    self.sps = Some(nalu.clone());
-                }
+            match nalu[0] & 0x1F {
+                H264_NAL_SPS => self.sps = Some(nalu.clone()),
                 H264_NAL_PPS => self.pps = Some(nalu.clone()),
                 H264_NAL_IDR => {
                     contains_idr = true;
@@ -306,32 +326,86 @@ impl Whip2RtmpRemuxerSession {
         });
 
         if !self.video_seq_header_generated {
-            if self.sps.is_some() && self.pps.is_some() {
-                let mut meta_data = self.rtmp_cooker.gen_meta_data(width, height)?;
-                self.rtmp_handler.on_meta_data(&mut meta_data, &0).await?;
-
-                let mut seq_header = self.rtmp_cooker.gen_video_seq_header(
-                    self.sps.clone().unwrap(),
-                    self.pps.clone().unwrap(),
+            //the sps and pps may arrive in different frames
+            if let (Some(sps), Some(pps)) = (&self.sps, &self.pps) {
+                let (width, height, profile, level) = parse_sps(sps)?;
+                let meta_data = self.rtmp_cooker.gen_meta_data(width, height)?;
+                self.rtmp_handler
+                    .on_meta_data(meta_data.freeze(), &0)
+                    .await?;
+
+                let seq_header = self.rtmp_cooker.gen_video_seq_header(
+                    sps.clone(),
+                    pps.clone(),
                     profile,
                     level,
                 )?;
-                self.rtmp_handler.on_video_data(&mut seq_header, &0).await?;
+                self.rtmp_handler
+                    .on_video_data(seq_header.freeze(), &0)
+                    .await?;
                 self.video_seq_header_generated = true;
             }
-        } else if !nalu_vec.is_empty() {
-            let mut frame_data = self
+        }
+
+        //the frame carrying the parameter sets is sent after the sequence header
+        if self.video_seq_header_generated && !nalu_vec.is_empty() {
+            let frame_data = self
                 .rtmp_cooker
                 .gen_video_frame_data(nalu_vec, contains_idr)?;
 
-            let timestamp_adjust =
-                (timestamp - self.base_video_timestamp) / (self.video_clock_rate / 1000);
+            let arrival = self.arrival_time();
+            let timestamp_adjust = self.video_rebaser.rebase(timestamp, arrival);
 
             self.rtmp_handler
-                .on_video_data(&mut frame_data, &timestamp_adjust)
+                .on_video_data(frame_data.freeze(), &timestamp_adjust)
                 .await?;
         }
 
         Ok(())
     }
 }
+
+//The width, height, profile and level of a sps nalu, the resolution is
+//0 if the sps cannot be parsed.
+fn parse_sps(sps: &BytesMut) -> Result<(u32, u32, u8, u8), RtmpRemuxerError> {
+    let mut nalu_reader = BytesReader::new(sps.clone());
+    //the nalu header
+    nalu_reader.read_u8()?;
+
+    let mut sps_parser = SpsParser::new(nalu_reader);
+    let (width, height) = sps_parser.parse().unwrap_or((0, 0));
+    Ok((
+        width,
+        height,
+        sps_parser.sps.profile_idc,
+        sps_parser.sps.level_idc,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamp_rebase() {
+        let mut video_rebaser = TimestampRebaser::new(90000);
+        let mut audio_rebaser = TimestampRebaser::new(48000);
+
+        //the rtp timestamps of the video wrap around
+        assert_eq!(video_rebaser.rebase(u32::MAX - 2999, 0), 0);
+        assert_eq!(video_rebaser.rebase(6000, 40), 100);
+        //the audio starts later than the video
+        assert_eq!(audio_rebaser.rebase(123456, 20), 20);
+        assert_eq!(audio_rebaser.rebase(123456 + 48000, 1015), 1020);
+        //a reordered frame before the first frame is clamped
+        assert_eq!(audio_rebaser.rebase(123456 - 960, 1030), 20);
+        assert_eq!(video_rebaser.rebase(6000 + 90000, 1100), 1100);
+    }
+
+    #[test]
+    fn test_parse_sps() {
+        //a 1280x720 baseline sps
+        let sps = BytesMut::from(&[0x67, 0x42, 0xc0, 0x1f, 0xda, 0x01, 0x40, 0x16, 0xe4][..]);
+        assert!(matches!(parse_sps(&sps), Ok((1280, 720, 0x42, 0x1f))));
+    }
+}
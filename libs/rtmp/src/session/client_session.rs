@@ -21,10 +21,13 @@ use {
         bytesio::{TNetIO, TcpIO},
     },
     indexmap::IndexMap,
-    std::sync::Arc,
+    std::sync::{atomic::AtomicU64, Arc},
     //crate::utils::print::print,
-    streamhub::define::StreamHubEventSender,
-    tokio::{net::TcpStream, sync::Mutex},
+    streamhub::{define::StreamHubEventSender, utils::Uuid},
+    tokio::{
+        net::TcpStream,
+        sync::{oneshot, Mutex},
+    },
     xflv::amf0::Amf0ValueType,
 };
 
@@ -73,8 +76,14 @@ pub struct ClientSession {
     stream_name: String,
     state: ClientSessionState,
     client_type: ClientType,
-    sub_app_name: Option<String>,
-    sub_stream_name: Option<String>,
+    //the local stream subscribed by a push client or published by a pull client,
+    //it is the same as the remote stream if not set
+    local_app_name: Option<String>,
+    local_stream_name: Option<String>,
+    //the local stream is subscribed or published
+    is_relaying: bool,
+    //notified with the session id once the local stream is relayed
+    relaying_sender: Option<oneshot::Sender<Uuid>>,
     /*configure how many gops will be cached.*/
     gop_num: usize,
 }
@@ -114,13 +123,36 @@ impl ClientSession {
             stream_name,
             state: ClientSessionState::Handshake,
             client_type,
-            sub_app_name: None,
-            sub_stream_name: None,
+            local_app_name: None,
+            local_stream_name: None,
+            is_relaying: false,
+            relaying_sender: None,
             gop_num,
         }
     }
 
     pub async fn run(&mut self) -> Result<(), SessionError> {
+        let result = self.run_loop().await;
+        if result.is_err() && self.is_relaying {
+            self.is_relaying = false;
+            let (app_name, stream_name) = self.local_stream();
+            match self.client_type {
+                ClientType::Publish => {
+                    self.common
+                        .unsubscribe_from_channels(app_name, stream_name)
+                        .await?
+                }
+                ClientType::Play => {
+                    self.common
+                        .unpublish_to_channels(app_name, stream_name)
+                        .await?
+                }
+            }
+        }
+        result
+    }
+
+    async fn run_loop(&mut self) -> Result<(), SessionError> {
         loop {
             match self.state {
                 ClientSessionState::Handshake => {
@@ -240,13 +272,19 @@ impl ClientSession {
                 self.on_stream_is_recorded(stream_id)?;
             }
             RtmpMessageData::AudioData { data } => {
-                self.common.on_audio_data(data, timestamp).await?
+                self.common
+                    .on_audio_data(data.split().freeze(), timestamp)
+                    .await?
             }
             RtmpMessageData::VideoData { data } => {
-                self.common.on_video_data(data, timestamp).await?
+                self.common
+                    .on_video_data(data.split().freeze(), timestamp)
+                    .await?
             }
             RtmpMessageData::AmfData { raw_data } => {
-                self.common.on_meta_data(raw_data, timestamp).await?;
+                self.common
+                    .on_meta_data(raw_data.split().freeze(), timestamp)
+                    .await?;
             }
 
             _ => {}
@@ -484,31 +522,22 @@ impl ClientSession {
                 "NetStream.Publish.Start" => {
                     self.state = ClientSessionState::StartPublish;
                     //subscribe from local session and publish to remote rtmp server
-                    if let (Some(app_name), Some(stream_name)) =
-                        (&self.sub_app_name, &self.sub_stream_name)
-                    {
-                        self.common
-                            .subscribe_from_channels(app_name.clone(), stream_name.clone())
-                            .await?;
-                    } else {
-                        self.common
-                            .subscribe_from_channels(
-                                self.app_name.clone(),
-                                self.stream_name.clone(),
-                            )
-                            .await?;
-                    }
+                    let (app_name, stream_name) = self.local_stream();
+                    self.common
+                        .subscribe_from_channels(app_name, stream_name)
+                        .await?;
+                    self.is_relaying = true;
+                    self.notify_relaying_started();
                 }
                 "NetStream.Publish.Reset" => {}
                 "NetStream.Play.Start" => {
                     //pull from remote rtmp server and publish to local session
+                    let (app_name, stream_name) = self.local_stream();
                     self.common
-                        .publish_to_channels(
-                            self.app_name.clone(),
-                            self.stream_name.clone(),
-                            self.gop_num,
-                        )
-                        .await?
+                        .publish_to_channels(app_name, stream_name, self.gop_num)
+                        .await?;
+                    self.is_relaying = true;
+                    self.notify_relaying_started();
                 }
                 _ => {}
             }
@@ -517,8 +546,43 @@ impl ClientSession {
         Ok(())
     }
 
+    /// The sender is notified once the remote stream is played or published.
+    pub fn notify_relaying(&mut self, sender: oneshot::Sender<Uuid>) {
+        self.relaying_sender = Some(sender);
+    }
+
+    fn notify_relaying_started(&mut self) {
+        if let Some(sender) = self.relaying_sender.take() {
+            let _ = sender.send(self.session_id());
+        }
+    }
+
+    /// Push the local stream instead of the stream with the remote name.
     pub fn subscribe(&mut self, app_name: String, stream_name: String) {
-        self.sub_app_name = Some(app_name);
-        self.sub_stream_name = Some(stream_name);
+        self.local_app_name = Some(app_name);
+        self.local_stream_name = Some(stream_name);
+    }
+
+    /// Publish the pulled stream as the local stream instead of the remote name.
+    pub fn publish(&mut self, app_name: String, stream_name: String) {
+        self.local_app_name = Some(app_name);
+        self.local_stream_name = Some(stream_name);
+    }
+
+    /// The id of the session in the stream hub, e.g. the subscriber id of a push client.
+    pub fn session_id(&self) -> Uuid {
+        self.common.session_id()
+    }
+
+    /// Count the media bytes sent to the remote server.
+    pub fn set_bytes_counter(&mut self, counter: Arc<AtomicU64>) {
+        self.common.set_bytes_counter(counter);
+    }
+
+    fn local_stream(&self) -> (String, String) {
+        match (&self.local_app_name, &self.local_stream_name) {
+            (Some(app_name), Some(stream_name)) => (app_name.clone(), stream_name.clone()),
+            _ => (self.app_name.clone(), self.stream_name.clone()),
+        }
     }
 }
@@ -14,16 +14,23 @@ use {
         messages::define::msg_type_id,
     },
     async_trait::async_trait,
-    bytes::BytesMut,
+    bytes::Bytes,
     std::fmt,
-    std::{net::SocketAddr, sync::Arc},
+    std::{
+        net::SocketAddr,
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc,
+        },
+    },
     streamhub::{
         define::{
-            FrameData, FrameDataReceiver, FrameDataSender, InformationSender, NotifyInfo,
-            PublishType, PublisherInfo, StreamHubEvent, StreamHubEventSender, SubscribeType,
-            SubscriberInfo, TStreamHandler,
+            FrameData, FrameDataSender, InformationSender, NotifyInfo, PublishMode, PublishType,
+            PublisherInfo, StreamHubEvent, StreamHubEventSender, SubFrameDataReceiver,
+            SubscribeType, SubscriberInfo, TStreamHandler,
         },
         errors::{StreamHubError, StreamHubErrorValue},
+        queue::try_send_prior_data,
         statistics::StatisticsStream,
         stream::StreamIdentifier,
         utils::Uuid,
@@ -39,7 +46,7 @@ pub struct Common {
     //only Server Subscriber or Client Publisher needs to send out trunck data.
     packetizer: Option<ChunkPacketizer>,
 
-    data_receiver: FrameDataReceiver,
+    data_receiver: SubFrameDataReceiver,
     data_sender: FrameDataSender,
 
     event_producer: StreamHubEventSender,
@@ -52,6 +59,10 @@ pub struct Common {
     pub stream_handler: Arc<RtmpStreamHandler>,
     /* now used for subscriber session */
     statistic_data_sender: Option<StatisticDataSender>,
+    /*the publish type argument of the publish command*/
+    pub publish_mode: PublishMode,
+    /*count the sent media bytes, used by the relay push client*/
+    bytes_counter: Option<Arc<AtomicU64>>,
 }
 
 impl Common {
@@ -60,7 +71,8 @@ impl Common {
         session_type: SessionType, remote_addr: Option<SocketAddr>,
     ) -> Self {
         //only used for init,since I don't found a better way to deal with this.
-        let (init_producer, init_consumer) = mpsc::unbounded_channel();
+        let (init_producer, _) = mpsc::unbounded_channel();
+        let (_, init_consumer) = mpsc::channel(1);
 
         Self {
             session_id: Uuid::new(),
@@ -75,9 +87,29 @@ impl Common {
             request_url: String::default(),
             stream_handler: Arc::new(RtmpStreamHandler::new()),
             statistic_data_sender: None,
+            publish_mode: PublishMode::Live,
+            bytes_counter: None,
             //cache: None,
         }
     }
+
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.remote_addr
+    }
+
+    pub fn session_id(&self) -> Uuid {
+        self.session_id
+    }
+
+    pub fn set_bytes_counter(&mut self, counter: Arc<AtomicU64>) {
+        self.bytes_counter = Some(counter);
+    }
+
+    fn count_bytes(&self, data_size: usize) {
+        if let Some(counter) = &self.bytes_counter {
+            counter.fetch_add(data_size as u64, Ordering::Relaxed);
+        }
+    }
     pub async fn send_channel_data(&mut self) -> Result<(), SessionError> {
         let mut retry_times = 0;
         loop {
@@ -86,6 +118,7 @@ impl Common {
                     FrameData::Audio { timestamp, data } => {
                         let data_size = data.len();
                         self.send_audio(data, timestamp).await?;
+                        self.count_bytes(data_size);
 
                         if let Some(sender) = &self.statistic_data_sender {
                             let statistic_audio_data = StatisticData::Audio {
@@ -102,6 +135,7 @@ impl Common {
                     FrameData::Video { timestamp, data } => {
                         let data_size = data.len();
                         self.send_video(data, timestamp).await?;
+                        self.count_bytes(data_size);
 
                         if let Some(sender) = &self.statistic_data_sender {
                             let statistic_video_data = StatisticData::Video {
@@ -135,7 +169,7 @@ impl Common {
         }
     }
 
-    pub async fn send_audio(&mut self, data: BytesMut, timestamp: u32) -> Result<(), SessionError> {
+    pub async fn send_audio(&mut self, data: Bytes, timestamp: u32) -> Result<(), SessionError> {
         let mut chunk_info = ChunkInfo::new(
             csid_type::AUDIO,
             chunk_type::TYPE_0,
@@ -153,7 +187,7 @@ impl Common {
         Ok(())
     }
 
-    pub async fn send_video(&mut self, data: BytesMut, timestamp: u32) -> Result<(), SessionError> {
+    pub async fn send_video(&mut self, data: Bytes, timestamp: u32) -> Result<(), SessionError> {
         let mut chunk_info = ChunkInfo::new(
             csid_type::VIDEO,
             chunk_type::TYPE_0,
@@ -171,9 +205,7 @@ impl Common {
         Ok(())
     }
 
-    pub async fn send_metadata(
-        &mut self, data: BytesMut, timestamp: u32,
-    ) -> Result<(), SessionError> {
+    pub async fn send_metadata(&mut self, data: Bytes, timestamp: u32) -> Result<(), SessionError> {
         let mut chunk_info = ChunkInfo::new(
             csid_type::DATA_AMF0_AMF3,
             chunk_type::TYPE_0,
@@ -192,7 +224,7 @@ impl Common {
     }
 
     pub async fn on_video_data(
-        &mut self, data: &mut BytesMut, timestamp: &u32,
+        &mut self, data: Bytes, timestamp: &u32,
     ) -> Result<(), SessionError> {
         let channel_data = FrameData::Video {
             timestamp: *timestamp,
@@ -208,14 +240,14 @@ impl Common {
         }
 
         self.stream_handler
-            .save_video_data(data, *timestamp)
+            .save_video_data(&data, *timestamp)
             .await?;
 
         Ok(())
     }
 
     pub async fn on_audio_data(
-        &mut self, data: &mut BytesMut, timestamp: &u32,
+        &mut self, data: Bytes, timestamp: &u32,
     ) -> Result<(), SessionError> {
         let channel_data = FrameData::Audio {
             timestamp: *timestamp,
@@ -231,15 +263,13 @@ impl Common {
         }
 
         self.stream_handler
-            .save_audio_data(data, *timestamp)
+            .save_audio_data(&data, *timestamp)
             .await?;
 
         Ok(())
     }
 
-    pub async fn on_meta_data(
-        &mut self, data: &mut BytesMut, timestamp: &u32,
-    ) -> Result<(), SessionError> {
+    pub async fn on_meta_data(&mut self, data: Bytes, timestamp: &u32) -> Result<(), SessionError> {
         let channel_data = FrameData::MetaData {
             timestamp: *timestamp,
             data: data.clone(),
@@ -250,7 +280,7 @@ impl Common {
             Err(_) => return Err(SessionError::SendFrameDataErr),
         }
 
-        self.stream_handler.save_metadata(data, *timestamp).await;
+        self.stream_handler.save_metadata(&data, *timestamp).await;
 
         Ok(())
     }
@@ -265,6 +295,7 @@ impl Common {
         let sub_type = match self.session_type {
             SessionType::Client => SubscribeType::PublisherRtmp,
             SessionType::Server => SubscribeType::PlayerRtmp,
+            SessionType::Remuxer => SubscribeType::Remux,
         };
 
         SubscriberInfo {
@@ -290,6 +321,7 @@ impl Common {
         let pub_type = match self.session_type {
             SessionType::Client => PublishType::RelayRtmp,
             SessionType::Server => PublishType::PushRtmp,
+            SessionType::Remuxer => PublishType::Remux,
         };
 
         PublisherInfo {
@@ -300,6 +332,7 @@ impl Common {
                 request_url: self.request_url.clone(),
                 remote_addr,
             },
+            mode: self.publish_mode,
         }
     }
 
@@ -342,7 +375,7 @@ impl Common {
                 id: self.session_id,
                 remote_addr: self.remote_addr.unwrap().to_string(),
                 start_time: chrono::Local::now(),
-                sub_type: SubscribeType::PlayerRtmp,
+                sub_type: self.get_subscriber_info().sub_type,
             };
             if let Err(err) = sender.send(statistic_subscriber) {
                 log::error!("send statistic_subscriber err: {}", err);
@@ -476,7 +509,7 @@ impl RtmpStreamHandler {
     }
 
     pub async fn save_video_data(
-        &self, chunk_body: &BytesMut, timestamp: u32,
+        &self, chunk_body: &Bytes, timestamp: u32,
     ) -> Result<(), CacheError> {
         if let Some(cache) = &mut *self.cache.lock().await {
             cache.save_video_data(chunk_body, timestamp).await?;
@@ -485,7 +518,7 @@ impl RtmpStreamHandler {
     }
 
     pub async fn save_audio_data(
-        &self, chunk_body: &BytesMut, timestamp: u32,
+        &self, chunk_body: &Bytes, timestamp: u32,
     ) -> Result<(), CacheError> {
         if let Some(cache) = &mut *self.cache.lock().await {
             cache.save_audio_data(chunk_body, timestamp).await?;
@@ -493,7 +526,7 @@ impl RtmpStreamHandler {
         Ok(())
     }
 
-    pub async fn save_metadata(&self, chunk_body: &BytesMut, timestamp: u32) {
+    pub async fn save_metadata(&self, chunk_body: &Bytes, timestamp: u32) {
         if let Some(cache) = &mut *self.cache.lock().await {
             cache.save_metadata(chunk_body, timestamp);
         }
@@ -516,33 +549,36 @@ impl TStreamHandler for RtmpStreamHandler {
         if let Some(cache) = &mut *self.cache.lock().await {
             if let Some(meta_body_data) = cache.get_metadata() {
                 log::info!("send_prior_data: meta_body_data: ");
-                sender.send(meta_body_data).map_err(|_| StreamHubError {
-                    value: StreamHubErrorValue::SendError,
-                })?;
+                if !try_send_prior_data(&sender, meta_body_data)? {
+                    return Ok(());
+                }
             }
             if let Some(audio_seq_data) = cache.get_audio_seq() {
                 log::info!("send_prior_data: audio_seq_data: ",);
-                sender.send(audio_seq_data).map_err(|_| StreamHubError {
-                    value: StreamHubErrorValue::SendError,
-                })?;
+                if !try_send_prior_data(&sender, audio_seq_data)? {
+                    return Ok(());
+                }
             }
             if let Some(video_seq_data) = cache.get_video_seq() {
                 log::info!("send_prior_data: video_seq_data:");
-                sender.send(video_seq_data).map_err(|_| StreamHubError {
-                    value: StreamHubErrorValue::SendError,
-                })?;
+                if !try_send_prior_data(&sender, video_seq_data)? {
+                    return Ok(());
+                }
             }
             match sub_type {
                 SubscribeType::PlayerRtmp
                 | SubscribeType::PlayerHttpFlv
                 | SubscribeType::PlayerHls
-                | SubscribeType::GenerateHls => {
-                    if let Some(gops_data) = cache.get_gops_data() {
+                | SubscribeType::GenerateHls
+                | SubscribeType::RecordFlv => {
+                    //replay only the newest gops fitting in the queue, a gop cut off
+                    //in the middle would leave the frames after it undecodable
+                    if let Some(gops_data) = cache.get_gops_data(sender.capacity()) {
                         for gop in gops_data {
                             for channel_data in gop.get_frame_data() {
-                                sender.send(channel_data).map_err(|_| StreamHubError {
-                                    value: StreamHubErrorValue::SendError,
-                                })?;
+                                if !try_send_prior_data(&sender, channel_data.clone())? {
+                                    return Ok(());
+                                }
                             }
                         }
                     }
@@ -29,6 +29,8 @@ pub const RTMP_LEVEL_ERROR: &str = "error\n";
 pub enum SessionType {
     Client,
     Server,
+    //publishes the rtmp version of a stream of another protocol
+    Remuxer,
 }
 
 impl fmt::Display for SessionType {
@@ -36,6 +38,7 @@ impl fmt::Display for SessionType {
         let client_type = match self {
             SessionType::Client => String::from("client"),
             SessionType::Server => String::from("server"),
+            SessionType::Remuxer => String::from("remuxer"),
         };
         write!(f, "{client_type}")
     }
@@ -21,6 +21,7 @@ use {
         user_control_messages::writer::EventMessagesWriter,
         utils::RtmpUrlParser,
     },
+    auth::{AuthContext, AuthDirection, AuthProtocol, RtmpConnectInfo},
     bytes::BytesMut,
     bytesio::{
         bytes_writer::AsyncBytesWriter,
@@ -28,7 +29,7 @@ use {
     },
     indexmap::IndexMap,
     std::{sync::Arc, time::Duration},
-    streamhub::define::StreamHubEventSender,
+    streamhub::define::{PublishMode, StreamHubEventSender},
     tokio::{net::TcpStream, sync::Mutex},
     xflv::amf0::Amf0ValueType,
 };
@@ -250,13 +251,19 @@ impl<A: auth::Auth> ServerSession<A> {
                 self.on_set_chunk_size(*chunk_size as usize)?;
             }
             RtmpMessageData::AudioData { data } => {
-                self.common.on_audio_data(data, timestamp).await?;
+                self.common
+                    .on_audio_data(data.split().freeze(), timestamp)
+                    .await?;
             }
             RtmpMessageData::VideoData { data } => {
-                self.common.on_video_data(data, timestamp).await?;
+                self.common
+                    .on_video_data(data.split().freeze(), timestamp)
+                    .await?;
             }
             RtmpMessageData::AmfData { raw_data } => {
-                self.common.on_meta_data(raw_data, timestamp).await?;
+                self.common
+                    .on_meta_data(raw_data.split().freeze(), timestamp)
+                    .await?;
             }
 
             _ => {}
@@ -502,6 +509,24 @@ impl<A: auth::Auth> ServerSession<A> {
         Ok(())
     }
 
+    fn auth_context(&self, direction: AuthDirection) -> AuthContext {
+        let rtmp_connect = RtmpConnectInfo {
+            tc_url: self.connect_properties.tc_url.clone(),
+            page_url: self.connect_properties.page_url.clone(),
+            flash_ver: self.connect_properties.flash_ver.clone(),
+            swf_url: self.connect_properties.swf_url.clone(),
+        };
+        AuthContext::new(
+            AuthProtocol::Rtmp,
+            direction,
+            self.app_name.clone(),
+            self.stream_name.clone(),
+        )
+        .with_query(self.query.as_deref())
+        .with_remote_addr(self.common.remote_addr())
+        .with_rtmp_connect(rtmp_connect)
+    }
+
     fn get_request_url(&mut self, raw_stream_name: String) -> String {
         if let Some(tc_url) = &self.connect_properties.tc_url {
             format!("{tc_url}/{raw_stream_name}")
@@ -564,6 +589,15 @@ impl<A: auth::Auth> ServerSession<A> {
             break;
         }
 
+        let raw_stream_name = stream_name.ok_or(SessionError::Amf0ValueCountNotCorrect)?;
+
+        (self.stream_name, self.query) =
+            RtmpUrlParser::parse_stream_name_with_query(&raw_stream_name);
+        /*reject the session before it is told the play is started*/
+        if let Some(auth) = &self.auth {
+            auth.authenticate(&self.auth_context(AuthDirection::Play)).await?;
+        }
+
         let mut event_messages = EventMessagesWriter::new(AsyncBytesWriter::new(self.io.clone()));
         event_messages.write_stream_begin(*stream_id).await?;
         log::info!(
@@ -612,14 +646,6 @@ impl<A: auth::Auth> ServerSession<A> {
 
         event_messages.write_stream_is_record(*stream_id).await?;
 
-        let raw_stream_name = stream_name.unwrap();
-
-        (self.stream_name, self.query) =
-            RtmpUrlParser::parse_stream_name_with_query(&raw_stream_name);
-        if let Some(auth) = &self.auth {
-            auth.auth_pull(Some(&self.app_name), Some(&self.stream_name), self.query.as_deref());
-        }
-
         let query = if let Some(query_val) = &self.query {
             query_val.clone()
         } else {
@@ -667,18 +693,20 @@ impl<A: auth::Auth> ServerSession<A> {
             RtmpUrlParser::parse_stream_name_with_query(&stream_name_with_query);
 
         if let Some(auth) = &self.auth {
-            auth.auth(Some(&self.app_name), Some(&self.stream_name), self.query.as_deref())?
+            auth.authenticate(&self.auth_context(AuthDirection::Publish)).await?;
         }
 
         /*Now it can update the request url*/
         self.common.request_url = self.get_request_url(stream_name_with_query);
 
-        let _ = match other_values.remove(0) {
+        let publish_type = match other_values.remove(0) {
             Amf0ValueType::UTF8String(val) => val,
             _ => {
                 return Err(SessionError::Amf0ValueCountNotCorrect);
             }
         };
+        /*live, record or append*/
+        self.common.publish_mode = PublishMode::from(publish_type.as_str());
 
         let query = if let Some(query_val) = &self.query {
             query_val.clone()
@@ -687,10 +715,11 @@ impl<A: auth::Auth> ServerSession<A> {
         };
 
         log::info!(
-            "[ S<-C ] [publish]  app_name: {}, stream_name: {}, query: {}",
+            "[ S<-C ] [publish]  app_name: {}, stream_name: {}, query: {}, type: {}",
             self.app_name,
             self.stream_name,
-            query
+            query,
+            publish_type
         );
 
         log::info!(
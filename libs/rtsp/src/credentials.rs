@@ -0,0 +1,164 @@
+use {
+    base64::{engine::general_purpose::STANDARD, Engine},
+    indexmap::IndexMap,
+    streamhub::utils::Uuid,
+};
+
+/// A challenge of the `WWW-Authenticate` header, e.g. `Digest realm="cam", nonce="abc"`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Challenge {
+    //in lower case
+    pub scheme: String,
+    pub params: IndexMap<String, String>,
+}
+
+impl Challenge {
+    /// Parse the challenges of the header, the repeated headers are joined with commas.
+    pub fn parse_all(value: &str) -> Vec<Self> {
+        let mut challenges: Vec<Self> = Vec::new();
+
+        for part in split_quoted(value, ',') {
+            let part = part.trim();
+            //a new challenge starts with the scheme followed by the first parameter
+            let param = match part.split_once(' ') {
+                Some((scheme, param)) if !scheme.contains('=') => {
+                    challenges.push(Self {
+                        scheme: scheme.to_ascii_lowercase(),
+                        params: IndexMap::new(),
+                    });
+                    param
+                }
+                _ => part,
+            };
+
+            let (Some(challenge), Some((name, value))) =
+                (challenges.last_mut(), param.split_once('='))
+            else {
+                continue;
+            };
+            challenge.params.insert(
+                name.trim().to_ascii_lowercase(),
+                value.trim().trim_matches('"').to_string(),
+            );
+        }
+        challenges
+    }
+
+    fn param(&self, name: &str) -> &str {
+        self.params
+            .get(name)
+            .map(String::as_str)
+            .unwrap_or_default()
+    }
+}
+
+//split the value by the separator outside the quotes
+fn split_quoted(value: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut start, mut quoted) = (0, false);
+    for (idx, c) in value.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c == separator && !quoted => {
+                parts.push(&value[start..idx]);
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&value[start..]);
+    parts
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+}
+
+impl Credentials {
+    /// The `Authorization` header answering the strongest supported challenge
+    /// of a 401 response, digest is preferred over basic.
+    pub fn authorization(
+        &self, challenges: &[Challenge], method: &str, uri: &str,
+    ) -> Option<String> {
+        if let Some(challenge) = challenges.iter().find(|c| c.scheme == "digest") {
+            let algorithm = challenge.param("algorithm");
+            if algorithm.is_empty() || algorithm.eq_ignore_ascii_case("MD5") {
+                let cnonce = Uuid::new().to_string().replace('-', "");
+                return Some(self.digest(challenge, method, uri, &cnonce[..16]));
+            }
+        }
+
+        challenges.iter().find(|c| c.scheme == "basic").map(|_| {
+            let user_pass = format!("{}:{}", self.username, self.password);
+            format!("Basic {}", STANDARD.encode(user_pass))
+        })
+    }
+
+    //RFC 2617, the nonce count is always 1 since the nonce is asked for each connection
+    fn digest(&self, challenge: &Challenge, method: &str, uri: &str, cnonce: &str) -> String {
+        let (realm, nonce) = (challenge.param("realm"), challenge.param("nonce"));
+        let ha1 = md5_hex(&format!("{}:{}:{}", self.username, realm, self.password));
+        let ha2 = md5_hex(&format!("{}:{}", method, uri));
+
+        let mut header = format!(
+            "Digest username=\"{}\", realm=\"{}\", nonce=\"{}\", uri=\"{}\"",
+            self.username, realm, nonce, uri
+        );
+        let qop_auth = challenge
+            .param("qop")
+            .split(',')
+            .any(|qop| qop.trim() == "auth");
+        let response = if qop_auth {
+            header.push_str(&format!(", qop=auth, nc=00000001, cnonce=\"{}\"", cnonce));
+            md5_hex(&format!(
+                "{}:{}:00000001:{}:auth:{}",
+                ha1, nonce, cnonce, ha2
+            ))
+        } else {
+            md5_hex(&format!("{}:{}:{}", ha1, nonce, ha2))
+        };
+        header.push_str(&format!(", response=\"{}\"", response));
+
+        if let Some(opaque) = challenge.params.get("opaque") {
+            header.push_str(&format!(", opaque=\"{}\"", opaque));
+        }
+        header
+    }
+}
+
+fn md5_hex(data: &str) -> String {
+    format!("{:x}", md5::compute(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_digest() {
+        //the example of RFC 2617
+        let challenges = Challenge::parse_all(
+            "Digest realm=\"testrealm@host.com\", qop=\"auth,auth-int\", \
+            nonce=\"dcd98b7102dd2f0e8b11d0f600bfb0c093\", \
+            opaque=\"5ccc069c403ebaf9f0171e9517f40e41\", Basic realm=\"testrealm@host.com\"",
+        );
+        assert_eq!(challenges.len(), 2);
+        assert_eq!(challenges[0].scheme, "digest");
+        assert_eq!(challenges[1].scheme, "basic");
+
+        let credentials = Credentials {
+            username: "Mufasa".to_string(),
+            password: "Circle Of Life".to_string(),
+        };
+        let header = credentials.digest(&challenges[0], "GET", "/dir/index.html", "0a4f113b");
+        assert!(header.contains("response=\"6629fae49393a05397450978507c4ef1\""));
+        assert!(header.contains("opaque=\"5ccc069c403ebaf9f0171e9517f40e41\""));
+
+        let header = credentials
+            .authorization(&challenges[1..], "GET", "/dir/index.html")
+            .unwrap();
+        assert_eq!(header, "Basic TXVmYXNhOkNpcmNsZSBPZiBMaWZl");
+    }
+}
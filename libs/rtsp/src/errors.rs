@@ -0,0 +1,88 @@
+use {
+    failure::{Backtrace, Fail},
+    std::{fmt, io::Error},
+    streamhub::errors::StreamHubError,
+    tokio::sync::oneshot::error::RecvError as OneshotRecvError,
+};
+
+#[derive(Debug, Fail)]
+pub enum RtspErrorValue {
+    #[fail(display = "io error: {}", _0)]
+    IOError(#[cause] Error),
+    #[fail(display = "invalid rtsp message: {}", _0)]
+    InvalidMessage(String),
+    #[fail(display = "invalid transport: {}", _0)]
+    InvalidTransport(String),
+    #[fail(display = "invalid rtp packet")]
+    InvalidRtpPacket,
+    //the url is not shown since it may carry the password
+    #[fail(display = "invalid rtsp url")]
+    InvalidUrl,
+    #[fail(display = "the {} request is answered with the status {}", _0, _1)]
+    UnexpectedStatus(String, u16),
+    #[fail(display = "the connection is closed")]
+    ConnectionClosed,
+    #[fail(display = "no response or media is received in time")]
+    Timeout,
+    #[fail(display = "stream hub event send error")]
+    StreamHubEventSendErr,
+    #[fail(display = "event execute error: {}", _0)]
+    ChannelError(#[cause] StreamHubError),
+    #[fail(display = "tokio: oneshot receiver err: {}", _0)]
+    OneshotRecvError(#[cause] OneshotRecvError),
+    #[fail(display = "the stream is closed")]
+    StreamClosed,
+}
+
+#[derive(Debug)]
+pub struct RtspError {
+    pub value: RtspErrorValue,
+}
+
+impl RtspError {
+    pub fn invalid_message(reason: impl Into<String>) -> Self {
+        Self {
+            value: RtspErrorValue::InvalidMessage(reason.into()),
+        }
+    }
+}
+
+impl From<Error> for RtspError {
+    fn from(error: Error) -> Self {
+        RtspError {
+            value: RtspErrorValue::IOError(error),
+        }
+    }
+}
+
+impl From<StreamHubError> for RtspError {
+    fn from(error: StreamHubError) -> Self {
+        RtspError {
+            value: RtspErrorValue::ChannelError(error),
+        }
+    }
+}
+
+impl From<OneshotRecvError> for RtspError {
+    fn from(error: OneshotRecvError) -> Self {
+        RtspError {
+            value: RtspErrorValue::OneshotRecvError(error),
+        }
+    }
+}
+
+impl fmt::Display for RtspError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.value, f)
+    }
+}
+
+impl Fail for RtspError {
+    fn cause(&self) -> Option<&dyn Fail> {
+        self.value.cause()
+    }
+
+    fn backtrace(&self) -> Option<&Backtrace> {
+        self.value.backtrace()
+    }
+}
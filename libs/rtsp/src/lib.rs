@@ -0,0 +1,9 @@
+pub mod credentials;
+pub mod errors;
+pub mod message;
+pub mod rtp;
+pub mod sdp;
+pub mod server;
+pub mod session;
+pub mod transport;
+pub mod utils;
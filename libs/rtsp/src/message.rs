@@ -0,0 +1,384 @@
+use {
+    super::errors::RtspError,
+    bytes::{BufMut, BytesMut},
+    indexmap::IndexMap,
+};
+
+pub const RTSP_VERSION: &str = "RTSP/1.0";
+//the first byte of a rtp/rtcp packet interleaved in the tcp connection
+pub const INTERLEAVED_MAGIC: u8 = b'$';
+//the max size of a message, including the header and the body
+pub const MAX_MESSAGE_SIZE: usize = 128 * 1024;
+
+pub mod method {
+    pub const OPTIONS: &str = "OPTIONS";
+    pub const DESCRIBE: &str = "DESCRIBE";
+    pub const ANNOUNCE: &str = "ANNOUNCE";
+    pub const SETUP: &str = "SETUP";
+    pub const PLAY: &str = "PLAY";
+    pub const RECORD: &str = "RECORD";
+    pub const PAUSE: &str = "PAUSE";
+    pub const TEARDOWN: &str = "TEARDOWN";
+    pub const GET_PARAMETER: &str = "GET_PARAMETER";
+    pub const SET_PARAMETER: &str = "SET_PARAMETER";
+
+    pub const SUPPORTED: &str =
+        "OPTIONS, DESCRIBE, ANNOUNCE, SETUP, PLAY, RECORD, TEARDOWN, GET_PARAMETER, SET_PARAMETER";
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RtspRequest {
+    pub method: String,
+    pub uri: String,
+    pub version: String,
+    pub headers: IndexMap<String, String>,
+    pub body: Option<String>,
+}
+
+impl RtspRequest {
+    pub fn new(method: &str, uri: impl Into<String>) -> Self {
+        Self {
+            method: method.to_string(),
+            uri: uri.into(),
+            version: RTSP_VERSION.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Parse a request from the beginning of the data, returns the request and
+    /// its length, or none if the request is not received completely.
+    pub fn parse(data: &[u8]) -> Result<Option<(Self, usize)>, RtspError> {
+        let Some((message, length)) = parse_message(data)? else {
+            return Ok(None);
+        };
+        let start_line = message.start_line;
+
+        let mut parts = start_line.split_whitespace();
+        let (Some(method), Some(uri), Some(version)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(RtspError::invalid_message(format!(
+                "request line: {}",
+                start_line
+            )));
+        };
+
+        let request = RtspRequest {
+            method: method.to_string(),
+            uri: uri.to_string(),
+            version: version.to_string(),
+            headers: message.headers,
+            body: message.body,
+        };
+        Ok(Some((request, length)))
+    }
+
+    pub fn with_header(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.headers.insert(name.to_string(), value.into());
+        self
+    }
+
+    pub fn marshal(&self) -> BytesMut {
+        let mut data = format!("{} {} {}\r\n", self.method, self.uri, self.version);
+        marshal_headers_and_body(&mut data, &self.headers, &self.body);
+        BytesMut::from(data.as_bytes())
+    }
+
+    pub fn header(&self, name: &str) -> Option<&String> {
+        find_header(&self.headers, name)
+    }
+
+    pub fn cseq(&self) -> Option<&String> {
+        self.header("CSeq")
+    }
+
+    /// The path of the uri without the leading slash and the query,
+    /// e.g. `live/test/trackID=0` of `rtsp://host:554/live/test/trackID=0?token=1`.
+    pub fn path(&self) -> String {
+        split_uri(&self.uri).0
+    }
+
+    pub fn query(&self) -> Option<String> {
+        split_uri(&self.uri).1
+    }
+}
+
+fn split_uri(uri: &str) -> (String, Option<String>) {
+    let path = match uri.split_once("://") {
+        Some((_, rest)) => rest.split_once('/').map(|(_, path)| path).unwrap_or(""),
+        None => uri.trim_start_matches('/'),
+    };
+    let (path, query) = match path.split_once('?') {
+        Some((path, query)) => (path, Some(query.to_string())),
+        None => (path, None),
+    };
+    (path.trim_end_matches('/').to_string(), query)
+}
+
+struct RtspMessage {
+    start_line: String,
+    headers: IndexMap<String, String>,
+    body: Option<String>,
+}
+
+//Parse a request or a response, the values of the repeated headers are joined with commas.
+fn parse_message(data: &[u8]) -> Result<Option<(RtspMessage, usize)>, RtspError> {
+    let Some(header_end) = data.windows(4).position(|w| w == b"\r\n\r\n") else {
+        return Ok(None);
+    };
+
+    let header = std::str::from_utf8(&data[..header_end])
+        .map_err(|_| RtspError::invalid_message("the header is not utf8"))?;
+    let mut lines = header.split("\r\n");
+    let start_line = lines.next().unwrap_or_default().to_string();
+
+    let mut headers: IndexMap<String, String> = IndexMap::new();
+    for line in lines {
+        if let Some((name, value)) = line.split_once(':') {
+            let (name, value) = (name.trim(), value.trim());
+            match headers.get_mut(name) {
+                Some(values) => {
+                    values.push_str(", ");
+                    values.push_str(value);
+                }
+                None => {
+                    headers.insert(name.to_string(), value.to_string());
+                }
+            }
+        }
+    }
+
+    let content_length = match find_header(&headers, "Content-Length") {
+        Some(length) => length
+            .parse::<usize>()
+            .map_err(|_| RtspError::invalid_message("content length"))?,
+        None => 0,
+    };
+    //the length is from the peer, it must not overflow the buffer or the index
+    let length = match (header_end + 4).checked_add(content_length) {
+        Some(length) if length <= MAX_MESSAGE_SIZE => length,
+        _ => return Err(RtspError::invalid_message("the message is too large")),
+    };
+    if data.len() < length {
+        return Ok(None);
+    }
+    let body = (content_length > 0)
+        .then(|| String::from_utf8_lossy(&data[header_end + 4..length]).to_string());
+
+    let message = RtspMessage {
+        start_line,
+        headers,
+        body,
+    };
+    Ok(Some((message, length)))
+}
+
+fn find_header<'a>(headers: &'a IndexMap<String, String>, name: &str) -> Option<&'a String> {
+    headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value)
+}
+
+fn marshal_headers_and_body(
+    data: &mut String, headers: &IndexMap<String, String>, body: &Option<String>,
+) {
+    for (name, value) in headers {
+        data.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    data.push_str("\r\n");
+    if let Some(body) = body {
+        data.push_str(body);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RtspResponse {
+    pub status_code: u16,
+    pub headers: IndexMap<String, String>,
+    pub body: Option<String>,
+}
+
+impl RtspResponse {
+    pub fn new(status_code: u16, request: &RtspRequest) -> Self {
+        let mut headers = IndexMap::new();
+        if let Some(cseq) = request.cseq() {
+            headers.insert(String::from("CSeq"), cseq.clone());
+        }
+        Self {
+            status_code,
+            headers,
+            body: None,
+        }
+    }
+
+    pub fn with_header(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.headers.insert(name.to_string(), value.into());
+        self
+    }
+
+    pub fn with_body(mut self, content_type: &str, body: String) -> Self {
+        self.headers
+            .insert(String::from("Content-Type"), content_type.to_string());
+        self.headers
+            .insert(String::from("Content-Length"), body.len().to_string());
+        self.body = Some(body);
+        self
+    }
+
+    /// Parse a response from the beginning of the data, returns the response and
+    /// its length, or none if the response is not received completely.
+    pub fn parse(data: &[u8]) -> Result<Option<(Self, usize)>, RtspError> {
+        let Some((message, length)) = parse_message(data)? else {
+            return Ok(None);
+        };
+        let start_line = message.start_line;
+
+        //RTSP/1.0 200 OK
+        let status_code = start_line
+            .strip_prefix("RTSP/")
+            .and_then(|line| line.split_whitespace().nth(1))
+            .and_then(|code| code.parse().ok())
+            .ok_or_else(|| RtspError::invalid_message(format!("status line: {}", start_line)))?;
+
+        let response = RtspResponse {
+            status_code,
+            headers: message.headers,
+            body: message.body,
+        };
+        Ok(Some((response, length)))
+    }
+
+    pub fn header(&self, name: &str) -> Option<&String> {
+        find_header(&self.headers, name)
+    }
+
+    pub fn marshal(&self) -> BytesMut {
+        let mut data = format!(
+            "{} {} {}\r\n",
+            RTSP_VERSION,
+            self.status_code,
+            reason_phrase(self.status_code)
+        );
+        marshal_headers_and_body(&mut data, &self.headers, &self.body);
+        BytesMut::from(data.as_bytes())
+    }
+}
+
+fn reason_phrase(status_code: u16) -> &'static str {
+    match status_code {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        406 => "Not Acceptable",
+        454 => "Session Not Found",
+        455 => "Method Not Valid in This State",
+        461 => "Unsupported Transport",
+        500 => "Internal Server Error",
+        501 => "Not Implemented",
+        _ => "Unknown",
+    }
+}
+
+/// Parse a rtp/rtcp packet interleaved in the tcp connection:
+/// `$`, the channel, the 2 bytes length and the packet.
+/// Returns the channel, the packet and the consumed length,
+/// or none if the packet is not received completely.
+pub fn parse_interleaved(data: &[u8]) -> Option<(u8, &[u8], usize)> {
+    if data.len() < 4 || data[0] != INTERLEAVED_MAGIC {
+        return None;
+    }
+    let length = u16::from_be_bytes([data[2], data[3]]) as usize;
+    if data.len() < 4 + length {
+        return None;
+    }
+    Some((data[1], &data[4..4 + length], 4 + length))
+}
+
+pub fn marshal_interleaved(channel: u8, packet: &[u8]) -> BytesMut {
+    let mut data = BytesMut::with_capacity(4 + packet.len());
+    data.put_u8(INTERLEAVED_MAGIC);
+    data.put_u8(channel);
+    data.put_u16(packet.len() as u16);
+    data.put_slice(packet);
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_request() {
+        let sdp = "v=0\r\n";
+        let data = format!(
+            "ANNOUNCE rtsp://127.0.0.1:554/live/test?token=abc RTSP/1.0\r\nCSeq: 2\r\ncontent-length: {}\r\n\r\n{}",
+            sdp.len(),
+            sdp
+        );
+
+        assert!(RtspRequest::parse(&data.as_bytes()[..data.len() - 1])
+            .unwrap()
+            .is_none());
+
+        let (request, length) = RtspRequest::parse(data.as_bytes()).unwrap().unwrap();
+        assert_eq!(length, data.len());
+        assert_eq!(request.method, method::ANNOUNCE);
+        assert_eq!(request.cseq().unwrap(), "2");
+        assert_eq!(request.path(), "live/test");
+        assert_eq!(request.query().unwrap(), "token=abc");
+        assert_eq!(request.body.unwrap(), sdp);
+
+        let response = RtspResponse::new(
+            200,
+            &RtspRequest::parse(data.as_bytes()).unwrap().unwrap().0,
+        )
+        .with_header("Session", "1234");
+        assert_eq!(
+            &response.marshal()[..],
+            b"RTSP/1.0 200 OK\r\nCSeq: 2\r\nSession: 1234\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_content_length() {
+        for content_length in [usize::MAX.to_string(), (MAX_MESSAGE_SIZE + 1).to_string()] {
+            let data = format!(
+                "ANNOUNCE rtsp://127.0.0.1:554/live/test RTSP/1.0\r\nCSeq: 2\r\nContent-Length: {}\r\n\r\nv=0",
+                content_length
+            );
+            assert!(RtspRequest::parse(data.as_bytes()).is_err());
+        }
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let data = "RTSP/1.0 401 Unauthorized\r\nCSeq: 3\r\n\
+            WWW-Authenticate: Digest realm=\"cam\", nonce=\"abc\"\r\n\
+            WWW-Authenticate: Basic realm=\"cam\"\r\n\r\n";
+
+        let (response, length) = RtspResponse::parse(data.as_bytes()).unwrap().unwrap();
+        assert_eq!(length, data.len());
+        assert_eq!(response.status_code, 401);
+        assert_eq!(
+            response.header("www-authenticate").unwrap(),
+            "Digest realm=\"cam\", nonce=\"abc\", Basic realm=\"cam\""
+        );
+
+        let request =
+            RtspRequest::new(method::OPTIONS, "rtsp://127.0.0.1/live").with_header("CSeq", "1");
+        assert_eq!(
+            &request.marshal()[..],
+            b"OPTIONS rtsp://127.0.0.1/live RTSP/1.0\r\nCSeq: 1\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn test_interleaved() {
+        let data = marshal_interleaved(1, &[1, 2, 3]);
+        assert_eq!(parse_interleaved(&data[..5]), None);
+        assert_eq!(parse_interleaved(&data), Some((1, &[1u8, 2, 3][..], 7)));
+    }
+}
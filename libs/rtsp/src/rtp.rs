@@ -0,0 +1,241 @@
+use {
+    super::errors::{RtspError, RtspErrorValue},
+    bytes::{BufMut, BytesMut},
+};
+
+const RTP_VERSION: u8 = 2;
+const RTP_FIXED_HEADER_SIZE: usize = 12;
+
+const H264_NAL_STAP_A: u8 = 24;
+const H264_NAL_FU_A: u8 = 28;
+pub const ANNEXB_START_CODE: [u8; 4] = [0x00, 0x00, 0x00, 0x01];
+
+//the samples of an aac frame
+pub const AAC_FRAME_SAMPLES: u32 = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RtpHeader {
+    pub marker: bool,
+    pub payload_type: u8,
+    pub sequence_number: u16,
+    pub timestamp: u32,
+    pub ssrc: u32,
+}
+
+/// Parse the header of a rtp packet, returns the header and the payload.
+pub fn parse_rtp_packet(data: &[u8]) -> Result<(RtpHeader, &[u8]), RtspError> {
+    let invalid = || RtspError {
+        value: RtspErrorValue::InvalidRtpPacket,
+    };
+    if data.len() < RTP_FIXED_HEADER_SIZE || data[0] >> 6 != RTP_VERSION {
+        return Err(invalid());
+    }
+
+    let has_padding = data[0] & 0x20 != 0;
+    let has_extension = data[0] & 0x10 != 0;
+    let csrc_count = (data[0] & 0x0F) as usize;
+
+    let header = RtpHeader {
+        marker: data[1] & 0x80 != 0,
+        payload_type: data[1] & 0x7F,
+        sequence_number: u16::from_be_bytes([data[2], data[3]]),
+        timestamp: u32::from_be_bytes([data[4], data[5], data[6], data[7]]),
+        ssrc: u32::from_be_bytes([data[8], data[9], data[10], data[11]]),
+    };
+
+    let mut offset = RTP_FIXED_HEADER_SIZE + csrc_count * 4;
+    if has_extension {
+        let extension = data.get(offset..offset + 4).ok_or_else(invalid)?;
+        offset += 4 + u16::from_be_bytes([extension[2], extension[3]]) as usize * 4;
+    }
+    let mut end = data.len();
+    if has_padding {
+        end = end.saturating_sub(*data.last().ok_or_else(invalid)? as usize);
+    }
+    if offset > end {
+        return Err(invalid());
+    }
+
+    Ok((header, &data[offset..end]))
+}
+
+/// Assemble the h264 rtp payloads (RFC 6184) into annex-b access units.
+#[derive(Default)]
+pub struct H264Depacketizer {
+    access_unit: BytesMut,
+    timestamp: u32,
+    //the fu-a nalu being assembled
+    fragment: Option<BytesMut>,
+}
+
+impl H264Depacketizer {
+    /// Returns the finished access unit and its timestamp.
+    pub fn depacketize(&mut self, header: &RtpHeader, payload: &[u8]) -> Option<(u32, BytesMut)> {
+        let mut finished = None;
+        //the marker bit may be lost, a new timestamp starts a new access unit
+        if header.timestamp != self.timestamp && !self.access_unit.is_empty() {
+            finished = Some((self.timestamp, self.access_unit.split()));
+            self.fragment = None;
+        }
+        self.timestamp = header.timestamp;
+
+        if payload.is_empty() {
+            return finished;
+        }
+        match payload[0] & 0x1F {
+            H264_NAL_STAP_A => {
+                let mut nalus = &payload[1..];
+                while nalus.len() > 2 {
+                    let size = u16::from_be_bytes([nalus[0], nalus[1]]) as usize;
+                    if nalus.len() < 2 + size {
+                        break;
+                    }
+                    self.push_nalu(&nalus[2..2 + size]);
+                    nalus = &nalus[2 + size..];
+                }
+            }
+            H264_NAL_FU_A => {
+                if payload.len() < 2 {
+                    return finished;
+                }
+                let (indicator, fu_header) = (payload[0], payload[1]);
+                if fu_header & 0x80 != 0 {
+                    let mut nalu = BytesMut::new();
+                    nalu.put_u8((indicator & 0xE0) | (fu_header & 0x1F));
+                    self.fragment = Some(nalu);
+                }
+                //the fragments are dropped until the next start fragment if one is lost
+                if let Some(nalu) = &mut self.fragment {
+                    nalu.put_slice(&payload[2..]);
+                }
+                if fu_header & 0x40 != 0 {
+                    if let Some(nalu) = self.fragment.take() {
+                        self.push_nalu(&nalu);
+                    }
+                }
+            }
+            _ => self.push_nalu(payload),
+        }
+
+        if header.marker && finished.is_none() && !self.access_unit.is_empty() {
+            finished = Some((self.timestamp, self.access_unit.split()));
+        }
+        finished
+    }
+
+    fn push_nalu(&mut self, nalu: &[u8]) {
+        if nalu.is_empty() {
+            return;
+        }
+        self.access_unit.put_slice(&ANNEXB_START_CODE);
+        self.access_unit.put_slice(nalu);
+    }
+}
+
+/// Split the mpeg4-generic rtp payloads (RFC 3640, AAC-hbr) into raw aac frames.
+pub struct AacDepacketizer {
+    size_length: u32,
+    index_length: u32,
+}
+
+impl AacDepacketizer {
+    pub fn new(size_length: u32, index_length: u32) -> Self {
+        Self {
+            size_length,
+            index_length,
+        }
+    }
+
+    /// Returns the frames and their timestamps, the frames of a packet are consecutive.
+    pub fn depacketize(&self, header: &RtpHeader, payload: &[u8]) -> Vec<(u32, BytesMut)> {
+        let mut frames = Vec::new();
+        if payload.len() < 2 || self.size_length == 0 {
+            return frames;
+        }
+
+        let headers_bits = u16::from_be_bytes([payload[0], payload[1]]) as usize;
+        let headers_size = headers_bits.div_ceil(8);
+        let Some(headers) = payload.get(2..2 + headers_size) else {
+            return frames;
+        };
+        let au_header_bits = (self.size_length + self.index_length) as usize;
+        let mut data = &payload[2 + headers_size..];
+
+        let mut bit_offset = 0;
+        let mut index = 0;
+        while bit_offset + au_header_bits <= headers_bits {
+            let size = read_bits(headers, bit_offset, self.size_length as usize) as usize;
+            bit_offset += au_header_bits;
+            if size > data.len() {
+                break;
+            }
+            let timestamp = header.timestamp.wrapping_add(index * AAC_FRAME_SAMPLES);
+            frames.push((timestamp, BytesMut::from(&data[..size])));
+            data = &data[size..];
+            index += 1;
+        }
+        frames
+    }
+}
+
+fn read_bits(data: &[u8], bit_offset: usize, bit_count: usize) -> u32 {
+    (0..bit_count).fold(0, |value, i| {
+        let bit = bit_offset + i;
+        (value << 1) | ((data[bit / 8] >> (7 - bit % 8)) & 1) as u32
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rtp_packet(marker: bool, timestamp: u32, payload: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0x80, if marker { 0xE0 } else { 0x60 }, 0, 1];
+        packet.extend_from_slice(&timestamp.to_be_bytes());
+        packet.extend_from_slice(&[0, 0, 0, 1]);
+        packet.extend_from_slice(payload);
+        packet
+    }
+
+    #[test]
+    fn test_h264_depacketize() {
+        let mut depacketizer = H264Depacketizer::default();
+
+        //stap-a with sps and pps
+        let packet = rtp_packet(false, 3000, &[0x18, 0, 2, 0x67, 1, 0, 2, 0x68, 2]);
+        let (header, payload) = parse_rtp_packet(&packet).unwrap();
+        assert_eq!(header.payload_type, 96);
+        assert!(depacketizer.depacketize(&header, payload).is_none());
+
+        //fu-a idr in two fragments
+        let packet = rtp_packet(false, 3000, &[0x7C, 0x85, 0xAA]);
+        let (header, payload) = parse_rtp_packet(&packet).unwrap();
+        assert!(depacketizer.depacketize(&header, payload).is_none());
+        let packet = rtp_packet(true, 3000, &[0x7C, 0x45, 0xBB]);
+        let (header, payload) = parse_rtp_packet(&packet).unwrap();
+
+        let (timestamp, access_unit) = depacketizer.depacketize(&header, payload).unwrap();
+        assert_eq!(timestamp, 3000);
+        assert_eq!(
+            &access_unit[..],
+            &[0, 0, 0, 1, 0x67, 1, 0, 0, 0, 1, 0x68, 2, 0, 0, 0, 1, 0x65, 0xAA, 0xBB]
+        );
+    }
+
+    #[test]
+    fn test_aac_depacketize() {
+        let depacketizer = AacDepacketizer::new(13, 3);
+        //two au headers: size 2 and size 1
+        let packet = rtp_packet(
+            true,
+            1000,
+            &[0x00, 0x20, 0x00, 0x10, 0x00, 0x08, 0xA1, 0xA2, 0xB1],
+        );
+        let (header, payload) = parse_rtp_packet(&packet).unwrap();
+
+        let frames = depacketizer.depacketize(&header, payload);
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0], (1000, BytesMut::from(&[0xA1, 0xA2][..])));
+        assert_eq!(frames[1], (2024, BytesMut::from(&[0xB1][..])));
+    }
+}
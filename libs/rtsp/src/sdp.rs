@@ -0,0 +1,263 @@
+use {
+    base64::{engine::general_purpose::STANDARD, Engine},
+    indexmap::IndexMap,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaKind {
+    Video,
+    Audio,
+}
+
+/// A `m=` section of the session description.
+#[derive(Debug, Clone, Default)]
+pub struct SdpMedia {
+    pub media: String,
+    pub payload_type: u8,
+    //the encoding name in upper case, e.g. H264, H265, MPEG4-GENERIC
+    pub codec: String,
+    pub clock_rate: u32,
+    pub channels: Option<u8>,
+    pub fmtp: IndexMap<String, String>,
+    pub control: Option<String>,
+}
+
+impl SdpMedia {
+    pub fn kind(&self) -> Option<MediaKind> {
+        match self.media.as_str() {
+            "video" => Some(MediaKind::Video),
+            "audio" => Some(MediaKind::Audio),
+            _ => None,
+        }
+    }
+
+    /// Whether the path of a SETUP request is the control url of the media.
+    pub fn is_control_of(&self, request_path: &str) -> bool {
+        let Some(control) = &self.control else {
+            return false;
+        };
+        let control = match control.split_once("://") {
+            //an absolute url, only its path is compared
+            Some((_, rest)) => rest.split_once('/').map(|(_, path)| path).unwrap_or(""),
+            None => control,
+        };
+        let control = control.trim_matches('/');
+        !control.is_empty()
+            && (request_path == control || request_path.ends_with(&format!("/{}", control)))
+    }
+
+    /// The url of the SETUP request of the media, the control may be relative to the base url.
+    pub fn control_url(&self, base_url: &str) -> String {
+        match self.control.as_deref() {
+            None | Some("") | Some("*") => base_url.to_string(),
+            Some(control) if control.starts_with("rtsp://") => control.to_string(),
+            Some(control) => format!("{}/{}", base_url.trim_end_matches('/'), control),
+        }
+    }
+
+    /// The h264 sps and pps of the `sprop-parameter-sets` fmtp parameter.
+    pub fn h264_parameter_sets(&self) -> Option<(Vec<u8>, Vec<u8>)> {
+        let mut sets = self.fmtp.get("sprop-parameter-sets")?.split(',');
+        let sps = STANDARD.decode(sets.next()?.trim()).ok()?;
+        let pps = STANDARD.decode(sets.next()?.trim()).ok()?;
+        Some((sps, pps))
+    }
+
+    /// The AudioSpecificConfig of the mpeg4-generic `config` fmtp parameter.
+    pub fn aac_config(&self) -> Option<Vec<u8>> {
+        let config = self.fmtp.get("config")?;
+        (0..config.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(config.get(i..i + 2)?, 16).ok())
+            .collect()
+    }
+
+    //the length in bits of the AU-size and AU-index(-delta) of the mpeg4-generic AU headers
+    pub fn aac_au_header_bits(&self) -> (u32, u32) {
+        let parse = |name: &str, default: u32| {
+            self.fmtp
+                .get(name)
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(default)
+        };
+        (parse("sizelength", 13), parse("indexlength", 3))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Sdp {
+    pub medias: Vec<SdpMedia>,
+}
+
+impl Sdp {
+    pub fn parse(text: &str) -> Self {
+        let mut medias: Vec<SdpMedia> = Vec::new();
+
+        for line in text.lines() {
+            let Some((key, value)) = line.trim().split_once('=') else {
+                continue;
+            };
+
+            if key == "m" {
+                let mut fields = value.split_whitespace();
+                let media = fields.next().unwrap_or_default().to_string();
+                let payload_type = fields.nth(2).and_then(|pt| pt.parse().ok()).unwrap_or(0);
+                medias.push(static_media(media, payload_type));
+                continue;
+            }
+
+            //the session level attributes are ignored
+            let (Some(media), "a") = (medias.last_mut(), key) else {
+                continue;
+            };
+            let (name, value) = value.split_once(':').unwrap_or((value, ""));
+            match name {
+                "rtpmap" => {
+                    //96 H264/90000, 97 MPEG4-GENERIC/44100/2
+                    let Some((_, encoding)) = value.split_once(' ') else {
+                        continue;
+                    };
+                    let mut encoding = encoding.trim().split('/');
+                    media.codec = encoding.next().unwrap_or_default().to_ascii_uppercase();
+                    if let Some(clock_rate) = encoding.next().and_then(|v| v.parse().ok()) {
+                        media.clock_rate = clock_rate;
+                    }
+                    media.channels = encoding.next().and_then(|v| v.parse().ok());
+                }
+                "fmtp" => {
+                    let Some((_, parameters)) = value.split_once(' ') else {
+                        continue;
+                    };
+                    for parameter in parameters.split(';') {
+                        if let Some((name, value)) = parameter.trim().split_once('=') {
+                            media
+                                .fmtp
+                                .insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+                        }
+                    }
+                }
+                "control" => media.control = Some(value.to_string()),
+                _ => {}
+            }
+        }
+
+        Self { medias }
+    }
+
+    pub fn media(&self, kind: MediaKind) -> Option<&SdpMedia> {
+        self.medias.iter().find(|media| media.kind() == Some(kind))
+    }
+}
+
+/// Replace the controls of the medias with `trackID={index}`, the sdp of a pulled
+/// stream is served to the local players whose SETUP urls are the local ones.
+pub fn rewrite_controls(text: &str) -> String {
+    fn add_missing_control(lines: &mut Vec<String>, index: Option<usize>, has_control: bool) {
+        if let (Some(index), false) = (index, has_control) {
+            lines.push(format!("a=control:trackID={}", index));
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut index, mut has_control) = (None, false);
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        if line.starts_with("m=") {
+            add_missing_control(&mut lines, index, has_control);
+            index = Some(index.map_or(0, |index| index + 1));
+            has_control = false;
+        } else if line.starts_with("a=control:") {
+            lines.push(match index {
+                Some(index) => format!("a=control:trackID={}", index),
+                None => String::from("a=control:*"),
+            });
+            has_control = index.is_some();
+            continue;
+        }
+        lines.push(line.to_string());
+    }
+    add_missing_control(&mut lines, index, has_control);
+
+    lines.join("\r\n") + "\r\n"
+}
+
+//the payload types which are defined without rtpmap, see RFC 3551
+fn static_media(media: String, payload_type: u8) -> SdpMedia {
+    let (codec, clock_rate) = match payload_type {
+        0 => ("PCMU", 8000),
+        8 => ("PCMA", 8000),
+        14 => ("MPA", 90000),
+        26 => ("JPEG", 90000),
+        32 => ("MPV", 90000),
+        _ if media == "video" => ("", 90000),
+        _ => ("", 8000),
+    };
+    SdpMedia {
+        media,
+        payload_type,
+        codec: codec.to_string(),
+        clock_rate,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SDP: &str = "v=0\r\n\
+        o=- 0 0 IN IP4 127.0.0.1\r\n\
+        s=test\r\n\
+        a=control:*\r\n\
+        m=video 0 RTP/AVP 96\r\n\
+        a=rtpmap:96 H264/90000\r\n\
+        a=fmtp:96 packetization-mode=1; sprop-parameter-sets=Z0IAKeKQFAe2AtwEBAaQeJEV,aM48gA==; profile-level-id=420029\r\n\
+        a=control:trackID=0\r\n\
+        m=audio 0 RTP/AVP 97\r\n\
+        a=rtpmap:97 MPEG4-GENERIC/44100/2\r\n\
+        a=fmtp:97 profile-level-id=1;mode=AAC-hbr;sizelength=13;indexlength=3;indexdeltalength=3;config=1210\r\n\
+        a=control:rtsp://127.0.0.1/live/test/trackID=1\r\n";
+
+    #[test]
+    fn test_parse_sdp() {
+        let sdp = Sdp::parse(SDP);
+        assert_eq!(sdp.medias.len(), 2);
+
+        let video = sdp.media(MediaKind::Video).unwrap();
+        assert_eq!(video.codec, "H264");
+        assert_eq!(video.clock_rate, 90000);
+        assert!(video.is_control_of("live/test/trackID=0"));
+        assert!(!video.is_control_of("live/test/trackID=1"));
+        let (sps, pps) = video.h264_parameter_sets().unwrap();
+        assert_eq!(sps[0] & 0x1F, 7);
+        assert_eq!(pps[0] & 0x1F, 8);
+
+        let audio = sdp.media(MediaKind::Audio).unwrap();
+        assert_eq!(audio.codec, "MPEG4-GENERIC");
+        assert_eq!(audio.clock_rate, 44100);
+        assert_eq!(audio.channels, Some(2));
+        assert_eq!(audio.aac_config(), Some(vec![0x12, 0x10]));
+        assert_eq!(audio.aac_au_header_bits(), (13, 3));
+        assert!(audio.is_control_of("live/test/trackID=1"));
+        assert_eq!(
+            audio.control_url("rtsp://127.0.0.1/live"),
+            "rtsp://127.0.0.1/live/test/trackID=1"
+        );
+        assert_eq!(
+            video.control_url("rtsp://127.0.0.1/live/test/"),
+            "rtsp://127.0.0.1/live/test/trackID=0"
+        );
+    }
+
+    #[test]
+    fn test_rewrite_controls() {
+        let sdp = Sdp::parse(&rewrite_controls(SDP));
+        assert_eq!(sdp.medias[0].control.as_deref(), Some("trackID=0"));
+        assert_eq!(sdp.medias[1].control.as_deref(), Some("trackID=1"));
+
+        let text = rewrite_controls("v=0\r\nm=video 0 RTP/AVP 96\r\nc=IN IP4 0.0.0.0\r\n");
+        assert_eq!(
+            text,
+            "v=0\r\nm=video 0 RTP/AVP 96\r\nc=IN IP4 0.0.0.0\r\na=control:trackID=0\r\n"
+        );
+    }
+}
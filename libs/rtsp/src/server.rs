@@ -0,0 +1,42 @@
+use {
+    super::session::RtspServerSession, auth::Auth, streamhub::define::StreamHubEventSender,
+    tokio::net::TcpListener,
+};
+
+//Publish with ANNOUNCE/SETUP/RECORD and play with DESCRIBE/SETUP/PLAY,
+//the rtp packets are interleaved in the rtsp connection or sent over udp.
+pub struct RtspServer<A: Auth + 'static> {
+    address: String,
+    event_producer: StreamHubEventSender,
+    auth: Option<A>,
+}
+
+impl<A: Auth + 'static> RtspServer<A> {
+    pub fn new(address: String, event_producer: StreamHubEventSender, auth: Option<A>) -> Self {
+        Self {
+            address,
+            event_producer,
+            auth,
+        }
+    }
+
+    pub async fn run(&mut self) -> std::io::Result<()> {
+        let listener = TcpListener::bind(&self.address).await?;
+        log::info!("rtsp server listening on rtsp://{}", self.address);
+
+        loop {
+            let (tcp_stream, remote_addr) = listener.accept().await?;
+            let mut session = RtspServerSession::new(
+                tcp_stream,
+                remote_addr,
+                self.event_producer.clone(),
+                self.auth.clone(),
+            );
+            tokio::spawn(async move {
+                if let Err(err) = session.run().await {
+                    log::info!("rtsp session {} closed: {}", remote_addr, err);
+                }
+            });
+        }
+    }
+}
@@ -0,0 +1,474 @@
+use {
+    super::{
+        track::{receive_udp_packets, TrackReceiver},
+        RtspStreamHandler,
+    },
+    crate::{
+        credentials::{Challenge, Credentials},
+        errors::{RtspError, RtspErrorValue},
+        message::{
+            method, parse_interleaved, RtspRequest, RtspResponse, INTERLEAVED_MAGIC,
+            MAX_MESSAGE_SIZE,
+        },
+        sdp::{rewrite_controls, Sdp},
+        transport::{tcp_transport, udp_client_transport, Transport, TransportProtocol},
+        utils::RtspUrl,
+    },
+    bytes::{Buf, BytesMut},
+    std::{sync::Arc, time::Duration},
+    streamhub::{
+        define::{
+            FrameDataSender, NotifyInfo, PacketDataSender, PubDataType, PublishMode, PublishType,
+            PublisherInfo, StreamHubEvent, StreamHubEventSender,
+        },
+        stream::StreamIdentifier,
+        utils::Uuid,
+    },
+    tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{
+            tcp::{OwnedReadHalf, OwnedWriteHalf},
+            TcpStream, UdpSocket,
+        },
+        sync::oneshot,
+        task::JoinSet,
+    },
+};
+
+const READ_BUFFER_SIZE: usize = 4096;
+const USER_AGENT: &str = "streamserver";
+const DEFAULT_SESSION_TIMEOUT_SECS: u64 = 60;
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(10);
+//the connection is closed if no interleaved packet is received in time
+const RECEIVE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ClientTransport {
+    #[default]
+    Tcp,
+    Udp,
+}
+
+enum Incoming {
+    Interleaved { channel: u8, packet: BytesMut },
+    Response(RtspResponse),
+}
+
+//The rtsp connection of the client, the requests are answered one by one.
+struct Connection {
+    reader: OwnedReadHalf,
+    writer: OwnedWriteHalf,
+    buffer: BytesMut,
+    cseq: u32,
+    session: Option<String>,
+    credentials: Option<Credentials>,
+    challenges: Vec<Challenge>,
+}
+
+impl Connection {
+    fn new(stream: TcpStream, credentials: Option<Credentials>) -> Self {
+        let (reader, writer) = stream.into_split();
+        Self {
+            reader,
+            writer,
+            buffer: BytesMut::with_capacity(READ_BUFFER_SIZE),
+            cseq: 0,
+            session: None,
+            credentials,
+            challenges: Vec::new(),
+        }
+    }
+
+    /// Send the request and wait for its response, the request is sent again with
+    /// the credentials if it is unauthorized.
+    async fn request(&mut self, request: RtspRequest) -> Result<RtspResponse, RtspError> {
+        let mut authorized = !self.challenges.is_empty();
+        loop {
+            self.send(request.clone()).await?;
+            let response = tokio::time::timeout(RESPONSE_TIMEOUT, self.receive_response())
+                .await
+                .map_err(|_| RtspError {
+                    value: RtspErrorValue::Timeout,
+                })??;
+
+            if response.status_code == 401 && !authorized && self.credentials.is_some() {
+                let challenges = response
+                    .header("WWW-Authenticate")
+                    .map(|value| Challenge::parse_all(value))
+                    .unwrap_or_default();
+                if !challenges.is_empty() {
+                    self.challenges = challenges;
+                    authorized = true;
+                    continue;
+                }
+            }
+
+            if response.status_code != 200 {
+                return Err(RtspError {
+                    value: RtspErrorValue::UnexpectedStatus(request.method, response.status_code),
+                });
+            }
+            return Ok(response);
+        }
+    }
+
+    async fn send(&mut self, mut request: RtspRequest) -> Result<(), RtspError> {
+        self.cseq += 1;
+        request = request
+            .with_header("CSeq", self.cseq.to_string())
+            .with_header("User-Agent", USER_AGENT);
+        if let Some(session) = &self.session {
+            request = request.with_header("Session", session.clone());
+        }
+        if let Some(credentials) = &self.credentials {
+            if let Some(authorization) =
+                credentials.authorization(&self.challenges, &request.method, &request.uri)
+            {
+                request = request.with_header("Authorization", authorization);
+            }
+        }
+
+        self.writer.write_all(&request.marshal()).await?;
+        Ok(())
+    }
+
+    async fn receive_response(&mut self) -> Result<RtspResponse, RtspError> {
+        loop {
+            //the packets interleaved before the response are dropped
+            if let Incoming::Response(response) = self.receive().await? {
+                return Ok(response);
+            }
+        }
+    }
+
+    async fn receive(&mut self) -> Result<Incoming, RtspError> {
+        loop {
+            if let Some(incoming) = self.parse_incoming()? {
+                return Ok(incoming);
+            }
+            if self.buffer.len() > MAX_MESSAGE_SIZE {
+                return Err(RtspError::invalid_message("the message is too large"));
+            }
+            if self.reader.read_buf(&mut self.buffer).await? == 0 {
+                return Err(RtspError {
+                    value: RtspErrorValue::ConnectionClosed,
+                });
+            }
+        }
+    }
+
+    fn parse_incoming(&mut self) -> Result<Option<Incoming>, RtspError> {
+        if self.buffer.first() == Some(&INTERLEAVED_MAGIC) {
+            let Some((channel, _, length)) = parse_interleaved(&self.buffer) else {
+                return Ok(None);
+            };
+            let mut packet = self.buffer.split_to(length);
+            packet.advance(4);
+            return Ok(Some(Incoming::Interleaved { channel, packet }));
+        }
+
+        let Some((response, length)) = RtspResponse::parse(&self.buffer)? else {
+            return Ok(None);
+        };
+        self.buffer.advance(length);
+        Ok(Some(Incoming::Response(response)))
+    }
+}
+
+/// Pull a remote rtsp stream, e.g. an ip camera, and publish it as a local stream.
+/// It returns when the connection is closed, the caller decides whether to reconnect.
+pub struct RtspClientSession {
+    url: String,
+    transport: ClientTransport,
+    stream_path: String,
+    event_producer: StreamHubEventSender,
+    session_id: Uuid,
+    publisher_info: Option<PublisherInfo>,
+    //notified with the session id once the remote stream is played
+    playing_sender: Option<oneshot::Sender<Uuid>>,
+}
+
+impl RtspClientSession {
+    pub fn new(
+        url: String, transport: ClientTransport, app_name: String, stream_name: String,
+        event_producer: StreamHubEventSender,
+    ) -> Self {
+        Self {
+            url,
+            transport,
+            stream_path: format!("{}/{}", app_name, stream_name),
+            event_producer,
+            session_id: Uuid::new(),
+            publisher_info: None,
+            playing_sender: None,
+        }
+    }
+
+    pub fn session_id(&self) -> Uuid {
+        self.session_id
+    }
+
+    /// The sender is notified once the PLAY request succeeds.
+    pub fn notify_playing(&mut self, sender: oneshot::Sender<Uuid>) {
+        self.playing_sender = Some(sender);
+    }
+
+    pub async fn run(&mut self) -> Result<(), RtspError> {
+        let url = RtspUrl::parse(&self.url)?;
+        let stream = TcpStream::connect(&url.host_with_port).await?;
+        let credentials = url.username.clone().map(|username| Credentials {
+            username,
+            password: url.password.clone().unwrap_or_default(),
+        });
+
+        let mut connection = Connection::new(stream, credentials);
+        let result = self.pull(&mut connection, &url).await;
+        self.unpublish();
+
+        //the remote session is released at once if the connection is still alive
+        if !matches!(
+            result,
+            Err(RtspError {
+                value: RtspErrorValue::ConnectionClosed | RtspErrorValue::IOError(_),
+            })
+        ) {
+            let _ = connection
+                .send(RtspRequest::new(method::TEARDOWN, url.url.clone()))
+                .await;
+        }
+        result
+    }
+
+    async fn pull(&mut self, connection: &mut Connection, url: &RtspUrl) -> Result<(), RtspError> {
+        let options = connection
+            .request(RtspRequest::new(method::OPTIONS, url.url.clone()))
+            .await?;
+        let keepalive_method = match options.header("Public") {
+            Some(public) if public.contains(method::GET_PARAMETER) => method::GET_PARAMETER,
+            _ => method::OPTIONS,
+        };
+
+        let describe = connection
+            .request(
+                RtspRequest::new(method::DESCRIBE, url.url.clone())
+                    .with_header("Accept", "application/sdp"),
+            )
+            .await?;
+        let Some(sdp_text) = describe.body.clone() else {
+            return Err(RtspError::invalid_message(
+                "no sdp in the DESCRIBE response",
+            ));
+        };
+        let base_url = describe
+            .header("Content-Base")
+            .or_else(|| describe.header("Content-Location"))
+            .cloned()
+            .unwrap_or_else(|| url.url.clone());
+        let sdp = Sdp::parse(&sdp_text);
+
+        //the tcp tracks keyed by the rtp channel, and the udp rtp/rtcp sockets
+        let mut tcp_tracks = Vec::new();
+        let mut udp_tracks = Vec::new();
+        let mut session_timeout = DEFAULT_SESSION_TIMEOUT_SECS;
+
+        for (index, media) in sdp.medias.iter().enumerate() {
+            if media.kind().is_none() {
+                continue;
+            }
+
+            let setup = RtspRequest::new(method::SETUP, media.control_url(&base_url));
+            let response = match self.transport {
+                ClientTransport::Tcp => {
+                    let rtcp_channel = u8::try_from(2 * index + 1)
+                        .map_err(|_| RtspError::invalid_message("too many medias in the sdp"))?;
+                    let channels = (rtcp_channel - 1, rtcp_channel);
+                    let response = connection
+                        .request(setup.with_header("Transport", tcp_transport(channels)))
+                        .await?;
+                    //the server may choose other channels
+                    let channel = match response.header("Transport").map(|v| Transport::parse(v)) {
+                        Some(Ok(Transport {
+                            protocol:
+                                TransportProtocol::Tcp {
+                                    interleaved: Some((rtp_channel, _)),
+                                },
+                        })) => rtp_channel,
+                        _ => channels.0,
+                    };
+                    tcp_tracks.push((channel, media));
+                    response
+                }
+                ClientTransport::Udp => {
+                    let rtp_socket = UdpSocket::bind("0.0.0.0:0").await?;
+                    let rtcp_socket = UdpSocket::bind("0.0.0.0:0").await?;
+                    let client_port = (
+                        rtp_socket.local_addr()?.port(),
+                        rtcp_socket.local_addr()?.port(),
+                    );
+                    let response = connection
+                        .request(setup.with_header("Transport", udp_client_transport(client_port)))
+                        .await?;
+                    udp_tracks.push((Arc::new(rtp_socket), rtcp_socket, media));
+                    response
+                }
+            };
+
+            //abcdef;timeout=60
+            if let Some(session) = response.header("Session") {
+                let mut fields = session.split(';');
+                connection.session = fields.next().map(|id| id.trim().to_string());
+                if let Some(timeout) = fields
+                    .filter_map(|field| field.trim().strip_prefix("timeout="))
+                    .find_map(|timeout| timeout.parse().ok())
+                {
+                    session_timeout = timeout;
+                }
+            }
+        }
+        if tcp_tracks.is_empty() && udp_tracks.is_empty() {
+            return Err(RtspError::invalid_message("no audio or video in the sdp"));
+        }
+
+        let (frame_sender, packet_sender) = self.publish(rewrite_controls(&sdp_text), &sdp).await?;
+        let mut receivers = Vec::new();
+        for (channel, media) in tcp_tracks {
+            if let Some(receiver) =
+                TrackReceiver::new(media, frame_sender.clone(), packet_sender.clone())
+            {
+                receivers.push((channel, receiver));
+            }
+        }
+        let peer_ip = connection.reader.peer_addr()?.ip();
+        let mut udp_tasks = JoinSet::new();
+        //the rtcp sockets are only kept open until the connection is closed
+        let mut rtcp_sockets = Vec::new();
+        for (rtp_socket, rtcp_socket, media) in udp_tracks {
+            if let Some(receiver) =
+                TrackReceiver::new(media, frame_sender.clone(), packet_sender.clone())
+            {
+                udp_tasks.spawn(receive_udp_packets(rtp_socket, receiver, peer_ip));
+                rtcp_sockets.push(rtcp_socket);
+            }
+        }
+
+        connection
+            .request(
+                RtspRequest::new(method::PLAY, base_url.clone()).with_header("Range", "npt=0.000-"),
+            )
+            .await?;
+        log::info!("rtsp pull {} is published as {}", url.url, self.stream_path);
+        if let Some(sender) = self.playing_sender.take() {
+            let _ = sender.send(self.session_id);
+        }
+
+        let receive_timeout = (self.transport == ClientTransport::Tcp).then_some(RECEIVE_TIMEOUT);
+        let mut keepalive =
+            tokio::time::interval(Duration::from_secs((session_timeout / 2).max(1)));
+        keepalive.tick().await;
+
+        loop {
+            let incoming = tokio::select! {
+                incoming = receive_with_timeout(connection, receive_timeout) => Some(incoming?),
+                _ = keepalive.tick() => None,
+                Some(_) = udp_tasks.join_next(), if !udp_tasks.is_empty() => {
+                    return Err(RtspError {
+                        value: RtspErrorValue::Timeout,
+                    });
+                }
+            };
+
+            match incoming {
+                Some(Incoming::Interleaved { channel, packet }) => {
+                    //the rtcp packets are ignored
+                    let Some((_, receiver)) = receivers.iter_mut().find(|(c, _)| *c == channel)
+                    else {
+                        continue;
+                    };
+                    match receiver.on_rtp_packet(&packet) {
+                        Err(RtspError {
+                            value: RtspErrorValue::InvalidRtpPacket,
+                        }) => log::warn!("rtsp pull invalid rtp packet of channel {}", channel),
+                        result => result?,
+                    }
+                }
+                //the responses of the keepalive requests
+                Some(Incoming::Response(_)) => {}
+                None => {
+                    connection
+                        .send(RtspRequest::new(keepalive_method, base_url.clone()))
+                        .await?
+                }
+            }
+        }
+    }
+
+    async fn publish(
+        &mut self, sdp_text: String, sdp: &Sdp,
+    ) -> Result<(FrameDataSender, PacketDataSender), RtspError> {
+        let info = PublisherInfo {
+            id: self.session_id,
+            pub_type: PublishType::RelayRtsp,
+            pub_data_type: PubDataType::Both,
+            notify_info: NotifyInfo {
+                request_url: self.url.clone(),
+                remote_addr: String::new(),
+            },
+            mode: PublishMode::Live,
+        };
+
+        let (result_sender, result_receiver) = oneshot::channel();
+        let publish_event = StreamHubEvent::Publish {
+            identifier: self.identifier(),
+            info: info.clone(),
+            result_sender,
+            stream_handler: Arc::new(RtspStreamHandler::new(sdp_text, sdp.clone())),
+        };
+        self.event_producer
+            .send(publish_event)
+            .map_err(|_| RtspError {
+                value: RtspErrorValue::StreamHubEventSendErr,
+            })?;
+
+        match result_receiver.await?? {
+            (Some(frame_sender), Some(packet_sender), _) => {
+                self.publisher_info = Some(info);
+                Ok((frame_sender, packet_sender))
+            }
+            _ => Err(RtspError::invalid_message(
+                "the publish result misses the senders",
+            )),
+        }
+    }
+
+    fn unpublish(&mut self) {
+        let Some(info) = self.publisher_info.take() else {
+            return;
+        };
+        let unpublish_event = StreamHubEvent::UnPublish {
+            identifier: self.identifier(),
+            info,
+        };
+        if self.event_producer.send(unpublish_event).is_err() {
+            log::error!("rtsp pull unpublish {} error", self.stream_path);
+        }
+    }
+
+    fn identifier(&self) -> StreamIdentifier {
+        StreamIdentifier::Rtsp {
+            stream_path: self.stream_path.clone(),
+        }
+    }
+}
+
+async fn receive_with_timeout(
+    connection: &mut Connection, timeout: Option<Duration>,
+) -> Result<Incoming, RtspError> {
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, connection.receive())
+            .await
+            .map_err(|_| RtspError {
+                value: RtspErrorValue::Timeout,
+            })?,
+        None => connection.receive().await,
+    }
+}
@@ -0,0 +1,105 @@
+pub mod client_session;
+pub mod server_session;
+mod track;
+
+pub use self::{client_session::RtspClientSession, server_session::RtspServerSession};
+
+use {
+    super::{
+        rtp::ANNEXB_START_CODE,
+        sdp::{MediaKind, Sdp},
+    },
+    async_trait::async_trait,
+    bytes::{Bytes, BytesMut},
+    streamhub::{
+        define::{
+            DataSender, FrameData, Information, InformationSender, MediaInfo, SubscribeType,
+            TStreamHandler, VideoCodecType,
+        },
+        errors::StreamHubError,
+        queue::try_send_prior_data,
+        statistics::StatisticsStream,
+    },
+};
+
+/// The stream handler of a rtsp publisher, it keeps the sdp of the ANNOUNCE request.
+pub struct RtspStreamHandler {
+    sdp_text: String,
+    sdp: Sdp,
+}
+
+impl RtspStreamHandler {
+    pub fn new(sdp_text: String, sdp: Sdp) -> Self {
+        Self { sdp_text, sdp }
+    }
+
+    fn media_info(&self) -> MediaInfo {
+        let video = self.sdp.media(MediaKind::Video);
+        let audio = self.sdp.media(MediaKind::Audio);
+        MediaInfo {
+            audio_clock_rate: audio.map(|media| media.clock_rate).unwrap_or(0),
+            video_clock_rate: video.map(|media| media.clock_rate).unwrap_or(0),
+            vcodec: match video.map(|media| media.codec.as_str()) {
+                Some("H265") => VideoCodecType::H265,
+                _ => VideoCodecType::H264,
+            },
+        }
+    }
+}
+
+#[async_trait]
+impl TStreamHandler for RtspStreamHandler {
+    //the frame subscribers(e.g. the rtmp remuxer) get the media info and the
+    //sequence headers of the sdp before the first frames
+    async fn send_prior_data(
+        &self, data_sender: DataSender, _sub_type: SubscribeType,
+    ) -> Result<(), StreamHubError> {
+        let DataSender::Frame { sender } = data_sender else {
+            return Ok(());
+        };
+        let media_info = FrameData::MediaInfo {
+            media_info: self.media_info(),
+        };
+        if !try_send_prior_data(&sender, media_info)? {
+            return Ok(());
+        }
+
+        let video = self.sdp.media(MediaKind::Video);
+        if let Some((sps, pps)) = video.and_then(|media| media.h264_parameter_sets()) {
+            let mut data = BytesMut::new();
+            for nalu in [sps, pps] {
+                data.extend_from_slice(&ANNEXB_START_CODE);
+                data.extend_from_slice(&nalu);
+            }
+            let video = FrameData::Video {
+                timestamp: 0,
+                data: data.freeze(),
+            };
+            if !try_send_prior_data(&sender, video)? {
+                return Ok(());
+            }
+        }
+
+        let audio = self.sdp.media(MediaKind::Audio);
+        if let Some(config) = audio.and_then(|media| media.aac_config()) {
+            let audio = FrameData::Audio {
+                timestamp: 0,
+                data: Bytes::copy_from_slice(&config),
+            };
+            try_send_prior_data(&sender, audio)?;
+        }
+        Ok(())
+    }
+
+    async fn get_statistic_data(&self) -> Option<StatisticsStream> {
+        None
+    }
+
+    async fn send_information(&self, sender: InformationSender) {
+        if let Err(err) = sender.send(Information::Sdp {
+            data: self.sdp_text.clone(),
+        }) {
+            log::error!("send sdp information err: {}", err);
+        }
+    }
+}
@@ -0,0 +1,577 @@
+use {
+    super::{
+        track::{receive_udp_packets, Track, TrackReceiver, TrackTransport},
+        RtspStreamHandler,
+    },
+    crate::{
+        errors::{RtspError, RtspErrorValue},
+        message::{
+            marshal_interleaved, method, parse_interleaved, RtspRequest, RtspResponse,
+            INTERLEAVED_MAGIC, MAX_MESSAGE_SIZE,
+        },
+        sdp::{MediaKind, Sdp},
+        transport::{tcp_transport, udp_transport, Transport, TransportProtocol},
+    },
+    auth::{Auth, AuthContext, AuthDirection, AuthProtocol},
+    bytes::{Buf, BytesMut},
+    std::{net::SocketAddr, sync::Arc, time::Duration},
+    streamhub::{
+        define::{
+            FrameDataSender, Information, NotifyInfo, PacketData, PacketDataSender, PubDataType,
+            PublishMode, PublishType, PublisherInfo, StatisticData, StreamHubEvent,
+            StreamHubEventSender, SubDataType, SubPacketDataReceiver, SubscribeType,
+            SubscriberInfo,
+        },
+        stream::StreamIdentifier,
+        utils::Uuid,
+    },
+    tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{tcp::OwnedReadHalf, TcpStream, UdpSocket},
+        sync::{mpsc, oneshot},
+        task::JoinHandle,
+    },
+};
+
+const READ_BUFFER_SIZE: usize = 4096;
+//a request or an interleaved packet never exceeds it
+const SESSION_TIMEOUT_SECS: u64 = 60;
+//the time to wait for the sdp of the stream to be described
+const DESCRIBE_TIMEOUT: Duration = Duration::from_secs(3);
+//the messages waiting to be written, a stalled tcp player fills its subscriber queue instead
+const WRITE_QUEUE_SIZE: usize = 64;
+
+enum Role {
+    Publisher {
+        info: PublisherInfo,
+        frame_sender: FrameDataSender,
+        packet_sender: PacketDataSender,
+    },
+    Player {
+        info: SubscriberInfo,
+    },
+}
+
+/// A rtsp connection which publishes(ANNOUNCE/RECORD) or plays(DESCRIBE/PLAY) a stream.
+pub struct RtspServerSession<A: Auth> {
+    reader: OwnedReadHalf,
+    //the responses and the interleaved packets written into the connection
+    writer: mpsc::Sender<BytesMut>,
+    remote_addr: SocketAddr,
+    event_producer: StreamHubEventSender,
+    auth: Option<A>,
+
+    session_id: Uuid,
+    stream_path: String,
+    sdp: Option<Sdp>,
+    tracks: Vec<Track>,
+    role: Option<Role>,
+    //the receivers of the tcp interleaved tracks, keyed by the rtp channel
+    track_receivers: Vec<(u8, TrackReceiver)>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl<A: Auth> RtspServerSession<A> {
+    pub fn new(
+        stream: TcpStream, remote_addr: SocketAddr, event_producer: StreamHubEventSender,
+        auth: Option<A>,
+    ) -> Self {
+        let (reader, mut write_half) = stream.into_split();
+        let (writer, mut receiver) = mpsc::channel::<BytesMut>(WRITE_QUEUE_SIZE);
+        tokio::spawn(async move {
+            while let Some(data) = receiver.recv().await {
+                if let Err(err) = write_half.write_all(&data).await {
+                    log::error!("rtsp write error: {}", err);
+                    break;
+                }
+            }
+        });
+
+        Self {
+            reader,
+            writer,
+            remote_addr,
+            event_producer,
+            auth,
+            session_id: Uuid::new(),
+            stream_path: String::new(),
+            sdp: None,
+            tracks: Vec::new(),
+            role: None,
+            track_receivers: Vec::new(),
+            tasks: Vec::new(),
+        }
+    }
+
+    pub async fn run(&mut self) -> Result<(), RtspError> {
+        let result = self.run_loop().await;
+        self.close();
+        result
+    }
+
+    async fn run_loop(&mut self) -> Result<(), RtspError> {
+        let mut buffer = BytesMut::with_capacity(READ_BUFFER_SIZE);
+
+        loop {
+            if self.reader.read_buf(&mut buffer).await? == 0 {
+                return Ok(());
+            }
+
+            loop {
+                if buffer.first() == Some(&INTERLEAVED_MAGIC) {
+                    let Some((channel, packet, length)) = parse_interleaved(&buffer) else {
+                        break;
+                    };
+                    self.on_interleaved_packet(channel, packet)?;
+                    buffer.advance(length);
+                    continue;
+                }
+
+                let Some((request, length)) = RtspRequest::parse(&buffer)? else {
+                    break;
+                };
+                buffer.advance(length);
+                self.handle_request(&request).await?;
+            }
+
+            if buffer.len() > MAX_MESSAGE_SIZE {
+                return Err(RtspError::invalid_message("the message is too large"));
+            }
+        }
+    }
+
+    fn on_interleaved_packet(&mut self, channel: u8, packet: &[u8]) -> Result<(), RtspError> {
+        //the rtcp packets and the packets of unknown channels are ignored
+        let Some((_, receiver)) = self
+            .track_receivers
+            .iter_mut()
+            .find(|(rtp_channel, _)| *rtp_channel == channel)
+        else {
+            return Ok(());
+        };
+
+        match receiver.on_rtp_packet(packet) {
+            Err(RtspError {
+                value: RtspErrorValue::InvalidRtpPacket,
+            }) => {
+                log::warn!("rtsp invalid rtp packet of channel {}", channel);
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    async fn handle_request(&mut self, request: &RtspRequest) -> Result<(), RtspError> {
+        log::debug!("rtsp request: {} {}", request.method, request.uri);
+
+        match request.method.as_str() {
+            method::OPTIONS => {
+                self.respond(
+                    RtspResponse::new(200, request).with_header("Public", method::SUPPORTED),
+                )
+                .await
+            }
+            method::ANNOUNCE => self.on_announce(request).await,
+            method::DESCRIBE => self.on_describe(request).await,
+            method::SETUP => self.on_setup(request).await,
+            method::RECORD => self.on_record(request).await,
+            method::PLAY => self.on_play(request).await,
+            method::TEARDOWN => {
+                self.respond(RtspResponse::new(200, request)).await?;
+                self.close();
+                Ok(())
+            }
+            //the keepalive of the clients
+            method::GET_PARAMETER | method::SET_PARAMETER => {
+                self.respond(RtspResponse::new(200, request)).await
+            }
+            _ => self.respond(RtspResponse::new(501, request)).await,
+        }
+    }
+
+    async fn on_announce(&mut self, request: &RtspRequest) -> Result<(), RtspError> {
+        if self.role.is_some() {
+            return self.respond(RtspResponse::new(455, request)).await;
+        }
+        let Some(sdp_text) = &request.body else {
+            return self.respond(RtspResponse::new(400, request)).await;
+        };
+        let sdp = Sdp::parse(sdp_text);
+        if sdp.medias.is_empty() {
+            return self.respond(RtspResponse::new(400, request)).await;
+        }
+        if !self.authenticate(request, AuthDirection::Publish).await {
+            return self.respond(RtspResponse::new(401, request)).await;
+        }
+
+        self.stream_path = request.path();
+        let info = PublisherInfo {
+            id: self.session_id,
+            pub_type: PublishType::PushRtsp,
+            pub_data_type: PubDataType::Both,
+            notify_info: self.notify_info(request),
+            mode: PublishMode::Live,
+        };
+
+        let (result_sender, result_receiver) = oneshot::channel();
+        let publish_event = StreamHubEvent::Publish {
+            identifier: self.identifier(),
+            info: info.clone(),
+            result_sender,
+            stream_handler: Arc::new(RtspStreamHandler::new(sdp_text.clone(), sdp.clone())),
+        };
+        self.send_event(publish_event)?;
+
+        let (frame_sender, packet_sender, statistic_sender) = match result_receiver.await? {
+            Ok((Some(frame_sender), Some(packet_sender), statistic_sender)) => {
+                (frame_sender, packet_sender, statistic_sender)
+            }
+            Ok(_) => return self.respond(RtspResponse::new(500, request)).await,
+            Err(err) => {
+                log::error!("rtsp publish {} error: {}", self.stream_path, err);
+                return self.respond(RtspResponse::new(403, request)).await;
+            }
+        };
+
+        if let Some(sender) = statistic_sender {
+            let statistic_publisher = StatisticData::Publisher {
+                id: self.session_id,
+                remote_addr: self.remote_addr.to_string(),
+                start_time: chrono::Local::now(),
+            };
+            if let Err(err) = sender.send(statistic_publisher) {
+                log::error!("send statistic_publisher err: {}", err);
+            }
+        }
+
+        self.sdp = Some(sdp);
+        self.role = Some(Role::Publisher {
+            info,
+            frame_sender,
+            packet_sender,
+        });
+        self.respond(RtspResponse::new(200, request)).await
+    }
+
+    async fn on_describe(&mut self, request: &RtspRequest) -> Result<(), RtspError> {
+        if !self.authenticate(request, AuthDirection::Play).await {
+            return self.respond(RtspResponse::new(401, request)).await;
+        }
+
+        let stream_path = request.path();
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        self.send_event(StreamHubEvent::Request {
+            identifier: StreamIdentifier::Rtsp {
+                stream_path: stream_path.clone(),
+            },
+            sender,
+        })?;
+
+        //the sender is dropped at once if the stream does not exist
+        let sdp_text = match tokio::time::timeout(DESCRIBE_TIMEOUT, receiver.recv()).await {
+            Ok(Some(Information::Sdp { data })) => data,
+            _ => return self.respond(RtspResponse::new(404, request)).await,
+        };
+
+        self.stream_path = stream_path;
+        self.sdp = Some(Sdp::parse(&sdp_text));
+
+        let content_base = request.uri.split('?').next().unwrap_or_default();
+        let response = RtspResponse::new(200, request)
+            .with_header(
+                "Content-Base",
+                format!("{}/", content_base.trim_end_matches('/')),
+            )
+            .with_body("application/sdp", sdp_text);
+        self.respond(response).await
+    }
+
+    async fn on_setup(&mut self, request: &RtspRequest) -> Result<(), RtspError> {
+        let Some(sdp) = &self.sdp else {
+            return self.respond(RtspResponse::new(455, request)).await;
+        };
+        let transport = match request
+            .header("Transport")
+            .map(|value| Transport::parse(value))
+        {
+            Some(Ok(transport)) => transport,
+            _ => return self.respond(RtspResponse::new(461, request)).await,
+        };
+
+        //the tracks without control urls are set up in order
+        let path = request.path();
+        let index = sdp
+            .medias
+            .iter()
+            .position(|media| media.is_control_of(&path))
+            .unwrap_or(self.tracks.len());
+        let Some(media) = sdp.medias.get(index).cloned() else {
+            return self.respond(RtspResponse::new(404, request)).await;
+        };
+
+        let (transport, transport_header) = match transport.protocol {
+            TransportProtocol::Tcp { interleaved } => {
+                //the channels of the track index must fit into the interleaved header
+                let channels = match interleaved {
+                    Some(channels) => channels,
+                    None => match u8::try_from(2 * index + 1) {
+                        Ok(rtcp_channel) => (rtcp_channel - 1, rtcp_channel),
+                        Err(_) => return self.respond(RtspResponse::new(461, request)).await,
+                    },
+                };
+                (
+                    TrackTransport::Tcp {
+                        rtp_channel: channels.0,
+                    },
+                    tcp_transport(channels),
+                )
+            }
+            TransportProtocol::Udp { client_port } => {
+                let rtp_socket = UdpSocket::bind("0.0.0.0:0").await?;
+                let rtcp_socket = UdpSocket::bind("0.0.0.0:0").await?;
+                let server_port = (
+                    rtp_socket.local_addr()?.port(),
+                    rtcp_socket.local_addr()?.port(),
+                );
+                (
+                    TrackTransport::Udp {
+                        rtp_socket: Arc::new(rtp_socket),
+                        _rtcp_socket: Arc::new(rtcp_socket),
+                        client_rtp_addr: SocketAddr::new(self.remote_addr.ip(), client_port.0),
+                    },
+                    udp_transport(client_port, server_port),
+                )
+            }
+        };
+
+        self.tracks.retain(|track| track.index != index);
+        self.tracks.push(Track {
+            index,
+            media,
+            transport,
+        });
+
+        let response = RtspResponse::new(200, request)
+            .with_header("Transport", transport_header)
+            .with_header("Session", self.session_header());
+        self.respond(response).await
+    }
+
+    async fn on_record(&mut self, request: &RtspRequest) -> Result<(), RtspError> {
+        let Some(Role::Publisher {
+            frame_sender,
+            packet_sender,
+            ..
+        }) = &self.role
+        else {
+            return self.respond(RtspResponse::new(455, request)).await;
+        };
+        if self.tracks.is_empty() || !self.track_receivers.is_empty() {
+            return self.respond(RtspResponse::new(455, request)).await;
+        }
+
+        for track in &self.tracks {
+            let Some(receiver) =
+                TrackReceiver::new(&track.media, frame_sender.clone(), packet_sender.clone())
+            else {
+                continue;
+            };
+            match &track.transport {
+                TrackTransport::Tcp { rtp_channel } => {
+                    self.track_receivers.push((*rtp_channel, receiver));
+                }
+                TrackTransport::Udp { rtp_socket, .. } => {
+                    let socket = rtp_socket.clone();
+                    self.tasks.push(tokio::spawn(receive_udp_packets(
+                        socket,
+                        receiver,
+                        self.remote_addr.ip(),
+                    )));
+                }
+            }
+        }
+
+        let response =
+            RtspResponse::new(200, request).with_header("Session", self.session_header());
+        self.respond(response).await
+    }
+
+    async fn on_play(&mut self, request: &RtspRequest) -> Result<(), RtspError> {
+        if self.role.is_some() || self.tracks.is_empty() {
+            return self.respond(RtspResponse::new(455, request)).await;
+        }
+
+        let info = SubscriberInfo {
+            id: self.session_id,
+            sub_type: SubscribeType::PlayerRtsp,
+            sub_data_type: SubDataType::Packet,
+            notify_info: self.notify_info(request),
+        };
+
+        let (result_sender, result_receiver) = oneshot::channel();
+        self.send_event(StreamHubEvent::Subscribe {
+            identifier: self.identifier(),
+            info: info.clone(),
+            result_sender,
+        })?;
+
+        let (packet_receiver, statistic_sender) = match result_receiver.await? {
+            Ok((receiver, statistic_sender)) => (receiver.packet_receiver, statistic_sender),
+            Err(err) => {
+                log::error!("rtsp play {} error: {}", self.stream_path, err);
+                return self.respond(RtspResponse::new(404, request)).await;
+            }
+        };
+        //the subscriber is registered, it is unsubscribed when the session is closed
+        self.role = Some(Role::Player { info });
+        let Some(packet_receiver) = packet_receiver else {
+            return self.respond(RtspResponse::new(500, request)).await;
+        };
+
+        if let Some(sender) = statistic_sender {
+            let statistic_subscriber = StatisticData::Subscriber {
+                id: self.session_id,
+                remote_addr: self.remote_addr.to_string(),
+                sub_type: SubscribeType::PlayerRtsp,
+                start_time: chrono::Local::now(),
+            };
+            if let Err(err) = sender.send(statistic_subscriber) {
+                log::error!("send statistic_subscriber err: {}", err);
+            }
+        }
+
+        let response = RtspResponse::new(200, request)
+            .with_header("Session", self.session_header())
+            .with_header("Range", "npt=0.000-");
+        //the packets are sent after the response
+        self.respond(response).await?;
+
+        let tracks = self
+            .tracks
+            .iter()
+            .filter_map(|track| Some((track.media.kind()?, track.transport.clone())))
+            .collect();
+        self.tasks.push(tokio::spawn(send_packets(
+            packet_receiver,
+            tracks,
+            self.writer.clone(),
+        )));
+        Ok(())
+    }
+
+    async fn authenticate(&self, request: &RtspRequest, direction: AuthDirection) -> bool {
+        let Some(auth) = &self.auth else {
+            return true;
+        };
+
+        let path = request.path();
+        let (app_name, stream_name) = path.split_once('/').unwrap_or(("", &path));
+        let mut ctx = AuthContext::new(AuthProtocol::Rtsp, direction, app_name, stream_name)
+            .with_query(request.query().as_deref())
+            .with_remote_addr(Some(self.remote_addr));
+        for (name, value) in &request.headers {
+            ctx = ctx.with_header(name, value.as_str());
+        }
+
+        match auth.authenticate(&ctx).await {
+            Ok(()) => true,
+            Err(err) => {
+                log::error!("rtsp auth error: path={} {}: {}", path, direction, err);
+                false
+            }
+        }
+    }
+
+    fn close(&mut self) {
+        for task in self.tasks.drain(..) {
+            task.abort();
+        }
+        self.track_receivers.clear();
+        self.tracks.clear();
+
+        let event = match self.role.take() {
+            Some(Role::Publisher { info, .. }) => StreamHubEvent::UnPublish {
+                identifier: self.identifier(),
+                info,
+            },
+            Some(Role::Player { info }) => StreamHubEvent::UnSubscribe {
+                identifier: self.identifier(),
+                info,
+            },
+            None => return,
+        };
+        if let Err(err) = self.send_event(event) {
+            log::error!("rtsp session close error: {}", err);
+        }
+    }
+
+    async fn respond(&self, response: RtspResponse) -> Result<(), RtspError> {
+        self.writer
+            .send(response.marshal())
+            .await
+            .map_err(|_| RtspError {
+                value: RtspErrorValue::IOError(std::io::ErrorKind::BrokenPipe.into()),
+            })
+    }
+
+    fn send_event(&self, event: StreamHubEvent) -> Result<(), RtspError> {
+        self.event_producer.send(event).map_err(|_| RtspError {
+            value: RtspErrorValue::StreamHubEventSendErr,
+        })
+    }
+
+    fn identifier(&self) -> StreamIdentifier {
+        StreamIdentifier::Rtsp {
+            stream_path: self.stream_path.clone(),
+        }
+    }
+
+    fn session_header(&self) -> String {
+        format!("{};timeout={}", self.session_id, SESSION_TIMEOUT_SECS)
+    }
+
+    fn notify_info(&self, request: &RtspRequest) -> NotifyInfo {
+        NotifyInfo {
+            request_url: request.uri.clone(),
+            remote_addr: self.remote_addr.to_string(),
+        }
+    }
+}
+
+async fn send_packets(
+    mut receiver: SubPacketDataReceiver, tracks: Vec<(MediaKind, TrackTransport)>,
+    writer: mpsc::Sender<BytesMut>,
+) {
+    while let Some(packet) = receiver.recv().await {
+        let (kind, data) = match packet {
+            PacketData::Video { data, .. } => (MediaKind::Video, data),
+            PacketData::Audio { data, .. } => (MediaKind::Audio, data),
+        };
+        let Some((_, transport)) = tracks.iter().find(|(track_kind, _)| *track_kind == kind) else {
+            continue;
+        };
+
+        match transport {
+            TrackTransport::Tcp { rtp_channel } => {
+                if writer
+                    .send(marshal_interleaved(*rtp_channel, &data))
+                    .await
+                    .is_err()
+                {
+                    return;
+                }
+            }
+            TrackTransport::Udp {
+                rtp_socket,
+                client_rtp_addr,
+                ..
+            } => {
+                if let Err(err) = rtp_socket.send_to(&data, client_rtp_addr).await {
+                    log::error!("rtsp udp send error: {}", err);
+                    return;
+                }
+            }
+        }
+    }
+    log::info!("rtsp play stopped, the stream is unpublished");
+}
@@ -0,0 +1,153 @@
+use {
+    crate::{
+        errors::{RtspError, RtspErrorValue},
+        rtp::{parse_rtp_packet, AacDepacketizer, H264Depacketizer},
+        sdp::{MediaKind, SdpMedia},
+    },
+    bytes::Bytes,
+    std::{
+        net::{IpAddr, SocketAddr},
+        sync::Arc,
+        time::Duration,
+    },
+    streamhub::define::{FrameData, FrameDataSender, PacketData, PacketDataSender},
+    tokio::net::UdpSocket,
+};
+
+const UDP_BUFFER_SIZE: usize = 2048;
+//the udp receiving stops if no packet is received in time
+const UDP_RECEIVE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone)]
+pub enum TrackTransport {
+    Tcp {
+        rtp_channel: u8,
+    },
+    Udp {
+        rtp_socket: Arc<UdpSocket>,
+        //the rtcp socket is only kept open, the rtcp packets are ignored
+        _rtcp_socket: Arc<UdpSocket>,
+        client_rtp_addr: SocketAddr,
+    },
+}
+
+/// A set up media of the sdp.
+pub struct Track {
+    pub index: usize,
+    pub media: SdpMedia,
+    pub transport: TrackTransport,
+}
+
+enum Depacketizer {
+    H264(H264Depacketizer),
+    Aac(AacDepacketizer),
+    None,
+}
+
+/// Turn the received rtp packets of a track into packet data and frame data.
+pub struct TrackReceiver {
+    kind: MediaKind,
+    depacketizer: Depacketizer,
+    frame_sender: FrameDataSender,
+    packet_sender: PacketDataSender,
+}
+
+impl TrackReceiver {
+    pub fn new(
+        media: &SdpMedia, frame_sender: FrameDataSender, packet_sender: PacketDataSender,
+    ) -> Option<Self> {
+        //only the codecs which can be remuxed are depacketized into frames
+        let depacketizer = match media.codec.as_str() {
+            "H264" => Depacketizer::H264(H264Depacketizer::default()),
+            "MPEG4-GENERIC" => {
+                let (size_length, index_length) = media.aac_au_header_bits();
+                Depacketizer::Aac(AacDepacketizer::new(size_length, index_length))
+            }
+            _ => Depacketizer::None,
+        };
+        Some(Self {
+            kind: media.kind()?,
+            depacketizer,
+            frame_sender,
+            packet_sender,
+        })
+    }
+
+    pub fn on_rtp_packet(&mut self, packet: &[u8]) -> Result<(), RtspError> {
+        let (header, payload) = parse_rtp_packet(packet)?;
+        let closed = || RtspError {
+            value: RtspErrorValue::StreamClosed,
+        };
+
+        let (timestamp, data) = (header.timestamp, Bytes::copy_from_slice(packet));
+        let packet_data = match self.kind {
+            MediaKind::Video => PacketData::Video { timestamp, data },
+            MediaKind::Audio => PacketData::Audio { timestamp, data },
+        };
+        self.packet_sender.send(packet_data).map_err(|_| closed())?;
+
+        match &mut self.depacketizer {
+            Depacketizer::H264(depacketizer) => {
+                if let Some((timestamp, data)) = depacketizer.depacketize(&header, payload) {
+                    self.frame_sender
+                        .send(FrameData::Video {
+                            timestamp,
+                            data: data.freeze(),
+                        })
+                        .map_err(|_| closed())?;
+                }
+            }
+            Depacketizer::Aac(depacketizer) => {
+                for (timestamp, data) in depacketizer.depacketize(&header, payload) {
+                    self.frame_sender
+                        .send(FrameData::Audio {
+                            timestamp,
+                            data: data.freeze(),
+                        })
+                        .map_err(|_| closed())?;
+                }
+            }
+            Depacketizer::None => {}
+        }
+        Ok(())
+    }
+}
+
+/// Receive the rtp packets of a track, the packets not from the ip of the peer are dropped.
+pub async fn receive_udp_packets(
+    socket: Arc<UdpSocket>, mut receiver: TrackReceiver, peer_ip: IpAddr,
+) {
+    let mut buffer = vec![0u8; UDP_BUFFER_SIZE];
+    loop {
+        let received =
+            tokio::time::timeout(UDP_RECEIVE_TIMEOUT, socket.recv_from(&mut buffer)).await;
+        let length = match received {
+            Ok(Ok((_, addr))) if addr.ip() != peer_ip => {
+                log::debug!("rtsp udp packet from an unknown address: {}", addr);
+                continue;
+            }
+            Ok(Ok((length, _))) => length,
+            Ok(Err(err)) => {
+                log::error!("rtsp udp receive error: {}", err);
+                return;
+            }
+            Err(_) => {
+                log::warn!(
+                    "rtsp udp receive timeout, no rtp packet in {:?}",
+                    UDP_RECEIVE_TIMEOUT
+                );
+                return;
+            }
+        };
+        match receiver.on_rtp_packet(&buffer[..length]) {
+            Ok(())
+            | Err(RtspError {
+                value: RtspErrorValue::InvalidRtpPacket,
+            }) => {}
+            Err(err) => {
+                log::info!("rtsp udp receiving stopped: {}", err);
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use super::errors::{RtspError, RtspErrorValue};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransportProtocol {
+    //rtp/rtcp interleaved in the rtsp tcp connection with the channels
+    Tcp { interleaved: Option<(u8, u8)> },
+    //rtp/rtcp sent over udp to/from the client ports
+    Udp { client_port: (u16, u16) },
+}
+
+/// The `Transport` header of the SETUP request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transport {
+    pub protocol: TransportProtocol,
+}
+
+impl Transport {
+    /// Parse the first supported transport of the header.
+    pub fn parse(value: &str) -> Result<Self, RtspError> {
+        for transport in value.split(',') {
+            let mut fields = transport.trim().split(';');
+            let protocol = fields.next().unwrap_or_default().to_ascii_uppercase();
+
+            let mut interleaved = None;
+            let mut client_port = None;
+            for field in fields {
+                match field.trim().split_once('=') {
+                    Some(("interleaved", value)) => {
+                        interleaved = parse_pair(value).and_then(|(rtp, rtcp)| {
+                            Some((u8::try_from(rtp).ok()?, u8::try_from(rtcp).ok()?))
+                        })
+                    }
+                    Some(("client_port", value)) => client_port = parse_pair(value),
+                    _ => {}
+                }
+            }
+
+            match protocol.as_str() {
+                "RTP/AVP/TCP" => {
+                    return Ok(Self {
+                        protocol: TransportProtocol::Tcp { interleaved },
+                    })
+                }
+                "RTP/AVP" | "RTP/AVP/UDP" => {
+                    if let Some(client_port) = client_port {
+                        return Ok(Self {
+                            protocol: TransportProtocol::Udp { client_port },
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Err(RtspError {
+            value: RtspErrorValue::InvalidTransport(value.to_string()),
+        })
+    }
+}
+
+//`first-second` or a single port which the next port follows
+fn parse_pair(value: &str) -> Option<(u16, u16)> {
+    match value.split_once('-') {
+        Some((first, second)) => Some((first.parse().ok()?, second.parse().ok()?)),
+        None => {
+            let first: u16 = value.parse().ok()?;
+            Some((first, first.checked_add(1)?))
+        }
+    }
+}
+
+pub fn tcp_transport(interleaved: (u8, u8)) -> String {
+    format!(
+        "RTP/AVP/TCP;unicast;interleaved={}-{}",
+        interleaved.0, interleaved.1
+    )
+}
+
+pub fn udp_transport(client_port: (u16, u16), server_port: (u16, u16)) -> String {
+    format!(
+        "RTP/AVP;unicast;client_port={}-{};server_port={}-{}",
+        client_port.0, client_port.1, server_port.0, server_port.1
+    )
+}
+
+pub fn udp_client_transport(client_port: (u16, u16)) -> String {
+    format!(
+        "RTP/AVP;unicast;client_port={}-{}",
+        client_port.0, client_port.1
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_transport() {
+        assert_eq!(
+            Transport::parse("RTP/AVP/TCP;unicast;interleaved=2-3;mode=record")
+                .unwrap()
+                .protocol,
+            TransportProtocol::Tcp {
+                interleaved: Some((2, 3))
+            }
+        );
+        assert_eq!(
+            Transport::parse("RTP/AVP;unicast;client_port=5000-5001")
+                .unwrap()
+                .protocol,
+            TransportProtocol::Udp {
+                client_port: (5000, 5001)
+            }
+        );
+        assert_eq!(
+            Transport::parse("RTP/AVP/UDP;multicast, RTP/AVP;unicast;client_port=6000")
+                .unwrap()
+                .protocol,
+            TransportProtocol::Udp {
+                client_port: (6000, 6001)
+            }
+        );
+        assert!(Transport::parse("RTP/AVP;multicast").is_err());
+    }
+}
@@ -0,0 +1,145 @@
+//Compare the allocations of fanning out video frames to N subscriber queues with
+//copied payloads (BytesMut, the former FrameData) and shared payloads (Bytes).
+//
+//  cargo bench -p streamhub --bench fanout
+
+use {
+    bytes::{Bytes, BytesMut},
+    std::{
+        alloc::{GlobalAlloc, Layout, System},
+        sync::atomic::{AtomicUsize, Ordering},
+        time::{Duration, Instant},
+    },
+    streamhub::{
+        define::FrameData,
+        queue::{MediaKind, SubscriberQueue, SubscriberQueueConfig},
+    },
+    tokio::sync::mpsc,
+};
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const FRAME_SIZE: usize = 64 * 1024;
+const FRAME_COUNT: usize = 250;
+
+struct Report {
+    allocations: usize,
+    allocated_bytes: usize,
+    elapsed: Duration,
+}
+
+fn measure(run: impl FnOnce()) -> Report {
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let allocated_bytes = ALLOCATED_BYTES.load(Ordering::Relaxed);
+    let start = Instant::now();
+    run();
+    Report {
+        allocations: ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+        allocated_bytes: ALLOCATED_BYTES.load(Ordering::Relaxed) - allocated_bytes,
+        elapsed: start.elapsed(),
+    }
+}
+
+fn frame_payload() -> Vec<u8> {
+    //an flv avc key frame nalu
+    let mut payload = vec![0x17, 0x01, 0x00, 0x00, 0x00];
+    payload.resize(FRAME_SIZE, 0xAB);
+    payload
+}
+
+//Push each frame to N subscriber queues the way the transceiver does, every
+//subscriber gets a clone of the frame.
+fn fanout<T: Clone>(subscribers: usize, make_frame: impl Fn(u32) -> T) -> Report {
+    let config = SubscriberQueueConfig {
+        capacity: FRAME_COUNT,
+        drop_threshold: FRAME_COUNT,
+        ..Default::default()
+    };
+    let (mut queues, mut receivers): (Vec<_>, Vec<_>) = (0..subscribers)
+        .map(|_| {
+            let (sender, receiver) = mpsc::channel::<T>(config.capacity);
+            (SubscriberQueue::new(sender, config), receiver)
+        })
+        .unzip();
+    let kind = MediaKind::of_frame(
+        &FrameData::Video {
+            timestamp: 0,
+            data: Bytes::from(frame_payload()),
+        },
+        true,
+    );
+
+    let report = measure(|| {
+        for idx in 0..FRAME_COUNT {
+            let frame = make_frame(idx as u32 * 40);
+            for queue in &mut queues {
+                queue.push(frame.clone(), kind).unwrap();
+            }
+        }
+        for receiver in &mut receivers {
+            while receiver.try_recv().is_ok() {}
+        }
+    });
+    for queue in &queues {
+        assert_eq!(queue.dropped_video, 0);
+    }
+    report
+}
+
+//Each subscriber gets its own copy of the payload (BytesMut, the former FrameData).
+fn fanout_copied(subscribers: usize) -> Report {
+    let payload = BytesMut::from(&frame_payload()[..]);
+    fanout(subscribers, |_| payload.clone())
+}
+
+//The subscribers share the payload.
+fn fanout_shared(subscribers: usize) -> Report {
+    let payload = Bytes::from(frame_payload());
+    fanout(subscribers, |timestamp| FrameData::Video {
+        timestamp,
+        data: payload.clone(),
+    })
+}
+
+fn print_report(name: &str, subscribers: usize, report: &Report) {
+    println!(
+        "{:<8} subscribers: {:>4} allocations: {:>8} allocated: {:>10} KiB elapsed: {:?}",
+        name,
+        subscribers,
+        report.allocations,
+        report.allocated_bytes / 1024,
+        report.elapsed
+    );
+}
+
+fn main() {
+    println!(
+        "fan out {} video frames of {} KiB",
+        FRAME_COUNT,
+        FRAME_SIZE / 1024
+    );
+    for subscribers in [1, 10, 100] {
+        let copied = fanout_copied(subscribers);
+        let shared = fanout_shared(subscribers);
+        print_report("copied", subscribers, &copied);
+        print_report("shared", subscribers, &shared);
+    }
+}
@@ -6,10 +6,10 @@ use crate::utils;
 
 use {
     super::errors::StreamHubError,
-    crate::statistics::StatisticsStream,
-    crate::stream::StreamIdentifier,
+    crate::statistics::{RtpStatistics, StatisticsStream},
+    crate::stream::{StreamIdentifier, StreamKey},
     async_trait::async_trait,
-    bytes::BytesMut,
+    bytes::Bytes,
     serde::ser::SerializeStruct,
     serde::Serialize,
     serde::Serializer,
@@ -34,9 +34,23 @@ pub enum SubscribeType {
     /* Remote client request playing rtsp or webrtc(whep) raw rtp stream.*/
     PlayerRtp,
     GenerateHls,
+    /* Local recorder subscribes the stream and writes it into flv files.*/
+    RecordFlv,
     /* Local client *subscribe* from local rtmp session
     and *publish* (relay push) the stream to remote server.*/
     PublisherRtmp,
+    /* Local remuxer subscribes the stream and publishes it as another protocol.*/
+    Remux,
+}
+
+impl SubscribeType {
+    /// Whether the subscriber is a remote player, not a local session such as a remuxer.
+    pub fn is_remote_player(&self) -> bool {
+        matches!(
+            self,
+            Self::PlayerRtmp | Self::PlayerHttpFlv | Self::PlayerRtsp | Self::PlayerWebrtc
+        )
+    }
 }
 
 //session publish type
@@ -54,6 +68,37 @@ pub enum PublishType {
     PushWebRTC,
     /* It used for publishing raw rtp data of rtsp/whbrtc(whip) */
     PushRtp,
+    /* Local remuxer publishes the stream of another protocol.*/
+    Remux,
+}
+
+impl PublishType {
+    /// Whether the publisher is a remote client, not a local relay or remuxer.
+    pub fn is_remote_publisher(&self) -> bool {
+        matches!(self, Self::PushRtmp | Self::PushRtsp | Self::PushWebRTC)
+    }
+}
+
+//the publish type argument of the rtmp publish command
+#[derive(Debug, Serialize, Clone, Copy, Default, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PublishMode {
+    #[default]
+    Live,
+    /* Record the stream into a new file.*/
+    Record,
+    /* Record the stream and append it to the existing file.*/
+    Append,
+}
+
+impl From<&str> for PublishMode {
+    fn from(value: &str) -> Self {
+        match value {
+            "record" => Self::Record,
+            "append" => Self::Append,
+            _ => Self::Live,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -91,6 +136,7 @@ pub struct PublisherInfo {
     pub pub_type: PublishType,
     pub pub_data_type: PubDataType,
     pub notify_info: NotifyInfo,
+    pub mode: PublishMode,
 }
 
 impl Serialize for PublisherInfo {
@@ -98,12 +144,13 @@ impl Serialize for PublisherInfo {
     where
         S: Serializer,
     {
-        // 3 is the number of fields in the struct.
-        let mut state = serializer.serialize_struct("PublisherInfo", 3)?;
+        // 4 is the number of fields in the struct.
+        let mut state = serializer.serialize_struct("PublisherInfo", 4)?;
 
         state.serialize_field("id", &self.id.to_string())?;
         state.serialize_field("pub_type", &self.pub_type)?;
         state.serialize_field("notify_info", &self.notify_info)?;
+        state.serialize_field("mode", &self.mode)?;
         state.end()
     }
 }
@@ -122,19 +169,21 @@ pub struct MediaInfo {
     pub vcodec: VideoCodecType,
 }
 
+//The payloads are immutable and shared, cloning a frame for each subscriber
+//does not copy the media data.
 #[derive(Clone)]
 pub enum FrameData {
-    Video { timestamp: u32, data: BytesMut },
-    Audio { timestamp: u32, data: BytesMut },
-    MetaData { timestamp: u32, data: BytesMut },
+    Video { timestamp: u32, data: Bytes },
+    Audio { timestamp: u32, data: Bytes },
+    MetaData { timestamp: u32, data: Bytes },
     MediaInfo { media_info: MediaInfo },
 }
 
 //Used to pass rtp raw data.
 #[derive(Clone)]
 pub enum PacketData {
-    Video { timestamp: u32, data: BytesMut },
-    Audio { timestamp: u32, data: BytesMut },
+    Video { timestamp: u32, data: Bytes },
+    Audio { timestamp: u32, data: Bytes },
 }
 
 //used to save data which needs to be transferred between client/server sessions
@@ -154,6 +203,13 @@ pub type FrameDataReceiver = mpsc::UnboundedReceiver<FrameData>;
 pub type PacketDataSender = mpsc::UnboundedSender<PacketData>;
 pub type PacketDataReceiver = mpsc::UnboundedReceiver<PacketData>;
 
+//used to send a/v frames or rtp packets from the stream hub to a subscriber, the
+//queues of the subscribers are bounded to not pile up data for slow viewers.
+pub type SubFrameDataSender = mpsc::Sender<FrameData>;
+pub type SubFrameDataReceiver = mpsc::Receiver<FrameData>;
+pub type SubPacketDataSender = mpsc::Sender<PacketData>;
+pub type SubPacketDataReceiver = mpsc::Receiver<PacketData>;
+
 pub type InformationSender = mpsc::UnboundedSender<Information>;
 pub type InformationReceiver = mpsc::UnboundedReceiver<Information>;
 
@@ -176,7 +232,7 @@ pub type StatisticApiResultSender = oneshot::Sender<Value>;
 pub type StatisticApiResultReceiver = oneshot::Receiver<Value>;
 
 pub type SubEventExecuteResultSender =
-    oneshot::Sender<Result<(DataReceiver, Option<StatisticDataSender>), StreamHubError>>;
+    oneshot::Sender<Result<(SubDataReceiver, Option<StatisticDataSender>), StreamHubError>>;
 pub type PubEventExecuteResultSender = oneshot::Sender<
     Result<
         (
@@ -198,6 +254,9 @@ pub trait TStreamHandler: Send + Sync {
     ) -> Result<(), StreamHubError>;
     async fn get_statistic_data(&self) -> Option<StatisticsStream>;
     async fn send_information(&self, sender: InformationSender);
+    //a subscriber lost the picture, only the publishers able to produce
+    //a key frame on demand handle it
+    async fn request_key_frame(&self) {}
 }
 
 //A publisher can publish one or two kinds of av stream at a time.
@@ -206,11 +265,17 @@ pub struct DataReceiver {
     pub packet_receiver: Option<PacketDataReceiver>,
 }
 
+//A subscriber receives one kind of av stream from its bounded queue.
+pub struct SubDataReceiver {
+    pub frame_receiver: Option<SubFrameDataReceiver>,
+    pub packet_receiver: Option<SubPacketDataReceiver>,
+}
+
 //A subscriber only needs to subscribe to one type of stream at a time
 #[derive(Debug, Clone)]
 pub enum DataSender {
-    Frame { sender: FrameDataSender },
-    Packet { sender: PacketDataSender },
+    Frame { sender: SubFrameDataSender },
+    Packet { sender: SubPacketDataSender },
 }
 //we can only sub one kind of stream.
 #[derive(Debug, Clone, Serialize)]
@@ -226,6 +291,17 @@ pub enum PubDataType {
     Both,
 }
 
+impl PubDataType {
+    pub fn provides(&self, sub_data_type: &SubDataType) -> bool {
+        matches!(
+            (self, sub_data_type),
+            (PubDataType::Both, _)
+                | (PubDataType::Frame, SubDataType::Frame)
+                | (PubDataType::Packet, SubDataType::Packet)
+        )
+    }
+}
+
 #[derive(Serialize)]
 pub enum StreamHubEvent {
     Subscribe {
@@ -259,12 +335,27 @@ pub enum StreamHubEvent {
     },
     #[serde(skip_serializing)]
     ApiKickClient { id: Uuid },
+    //kick off the publisher and all the subscribers of a stream
+    #[serde(skip_serializing)]
+    ApiKickStream { key: StreamKey },
 
     #[serde(skip_serializing)]
     Request {
         identifier: StreamIdentifier,
         sender: InformationSender,
     },
+    //a Publish/Subscribe event allowed by the blocking notify hook
+    #[serde(skip_serializing)]
+    HookAllowed { event: Box<StreamHubEvent> },
+    //the remuxer requested for the pending subscribers does not publish in time
+    #[serde(skip_serializing)]
+    RemuxTimeout {
+        identifier: StreamIdentifier,
+        remux_id: Uuid,
+    },
+    //a subscriber asks the publisher for a key frame (webrtc PLI/FIR)
+    #[serde(skip_serializing)]
+    RequestKeyFrame { identifier: StreamIdentifier },
 }
 
 #[derive(Debug)]
@@ -286,6 +377,7 @@ pub enum TransceiverEvent {
     Request {
         sender: InformationSender,
     },
+    RequestKeyFrame,
 }
 
 impl fmt::Display for TransceiverEvent {
@@ -297,11 +389,20 @@ impl fmt::Display for TransceiverEvent {
 #[derive(Debug, Clone)]
 pub enum BroadcastEvent {
     /*Need publish(push) a stream to other rtmp server*/
-    Publish { identifier: StreamIdentifier },
+    Publish {
+        identifier: StreamIdentifier,
+        mode: PublishMode,
+    },
     UnPublish { identifier: StreamIdentifier },
     /*Need subscribe(pull) a stream from other rtmp server*/
     Subscribe { identifier: StreamIdentifier },
     UnSubscribe { identifier: StreamIdentifier },
+    /*Need remux the published source stream into the target protocol,
+    the remuxer publishes the target identifier to the stream hub.*/
+    Remux {
+        source: StreamIdentifier,
+        target: StreamIdentifier,
+    },
 }
 
 pub enum StatisticData {
@@ -342,4 +443,14 @@ pub enum StatisticData {
         sub_type: SubscribeType,
         start_time: DateTime<Local>,
     },
+    //the bytes of all the tracks sent to a subscriber
+    SubscriberBytes {
+        uuid: Uuid,
+        data_size: u64,
+    },
+    //the rtp statistics of a webrtc publisher or, with an uuid, of a player
+    Rtp {
+        uuid: Option<Uuid>,
+        statistics: RtpStatistics,
+    },
 }
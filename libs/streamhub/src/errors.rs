@@ -31,6 +31,12 @@ pub enum StreamHubErrorValue {
     RecvError(RecvError),
     #[fail(display = "Serde json error")]
     SerdeError(Error),
+    #[fail(display = "rejected by the notify hook: {}", _0)]
+    NotifyRejected(String),
+    #[fail(display = "the remuxer does not publish in time")]
+    RemuxTimeout,
+    #[fail(display = "the subscriber lags behind the stream")]
+    SubscriberLagging,
 }
 #[derive(Debug)]
 pub struct StreamHubError {
@@ -1,6 +1,7 @@
 use define::{
-    FrameDataReceiver, PacketDataReceiver, PacketDataSender, StatisticData, StatisticDataReceiver,
-    StatisticDataSender,
+    FrameDataReceiver, PacketDataReceiver, PubDataType, PublisherInfo, StatisticData,
+    StatisticDataReceiver, StatisticDataSender, SubDataReceiver, SubDataType,
+    SubEventExecuteResultSender,
 };
 use serde_json::{json, Value};
 use statistics::{StatisticSubscriber, StatisticsStream};
@@ -12,26 +13,32 @@ use crate::define::PacketData;
 pub mod define;
 pub mod errors;
 pub mod notify;
+pub mod queue;
 pub mod statistics;
 pub mod stream;
 pub mod utils;
 
 use {
-    crate::notify::Notifier,
+    crate::notify::{Notifier, NotifyEvent, StallWatcher},
     define::{
         BroadcastEvent, BroadcastEventReceiver, BroadcastEventSender, DataReceiver, DataSender,
-        FrameData, FrameDataSender, Information, StreamHubEvent, StreamHubEventReceiver,
-        StreamHubEventSender, SubscribeType, SubscriberInfo, TStreamHandler, TransceiverEvent,
-        TransceiverEventReceiver, TransceiverEventSender,
+        FrameData, Information, StreamHubEvent, StreamHubEventReceiver, StreamHubEventSender,
+        SubscriberInfo, TStreamHandler, TransceiverEvent, TransceiverEventReceiver,
+        TransceiverEventSender,
     },
     errors::{StreamHubError, StreamHubErrorValue},
+    queue::{MediaKind, SubscriberQueue, SubscriberQueueConfig},
     std::collections::HashMap,
     std::sync::Arc,
-    stream::StreamIdentifier,
+    std::time::Duration,
+    stream::{StreamIdentifier, StreamKey},
     tokio::sync::{broadcast, mpsc, mpsc::UnboundedReceiver, Mutex},
     utils::Uuid,
 };
 
+type FrameQueues = Arc<Mutex<HashMap<Uuid, SubscriberQueue<FrameData>>>>;
+type PacketQueues = Arc<Mutex<HashMap<Uuid, SubscriberQueue<PacketData>>>>;
+
 //Receive audio data/video data/meta data/media info from a publisher and send to players/subscribers
 //Receive statistic information from a publisher and send to api callers.
 pub struct StreamDataTransceiver {
@@ -40,9 +47,13 @@ pub struct StreamDataTransceiver {
     //used for receiving event
     event_receiver: TransceiverEventReceiver,
     //used for sending audio/video frame data to players/subscribers
-    id_to_frame_sender: Arc<Mutex<HashMap<Uuid, FrameDataSender>>>,
+    id_to_frame_sender: FrameQueues,
     //used for sending audio/video packet data to players/subscribers
-    id_to_packet_sender: Arc<Mutex<HashMap<Uuid, PacketDataSender>>>,
+    id_to_packet_sender: PacketQueues,
+    //the frames of rtmp publishers are flv tags
+    is_flv: bool,
+    //the bounded queue settings of the subscribers
+    queue_config: SubscriberQueueConfig,
     //publisher and subscribers use this sender to submit statistical data
     statistic_data_sender: StatisticDataSender,
     //used for receiving statistical data from publishers and subscribers
@@ -51,6 +62,8 @@ pub struct StreamDataTransceiver {
     statistic_data: Arc<Mutex<StatisticsStream>>,
     //a hander implement by protocols, such as rtmp, webrtc, http-flv, hls
     stream_handler: Arc<dyn TStreamHandler>,
+    //notify if the publisher stops sending data
+    stall_watcher: Option<StallWatcher>,
 }
 
 impl StreamDataTransceiver {
@@ -58,86 +71,77 @@ impl StreamDataTransceiver {
         data_receiver: DataReceiver,
         event_receiver: UnboundedReceiver<TransceiverEvent>,
         identifier: StreamIdentifier,
+        remuxed: bool,
         h: Arc<dyn TStreamHandler>,
+        notifier: Option<&Notifier>,
+        queue_config: SubscriberQueueConfig,
     ) -> Self {
         let (statistic_data_sender, statistic_data_receiver) = mpsc::unbounded_channel();
+        let stall_watcher = notifier.and_then(|notifier| {
+            StallWatcher::new(notifier, json!({ "identifier": identifier.clone() }))
+        });
+        let is_flv = matches!(identifier, StreamIdentifier::Rtmp { .. });
         Self {
+            stall_watcher,
             data_receiver,
             event_receiver,
             statistic_data_sender,
             statistic_data_receiver,
             id_to_frame_sender: Arc::new(Mutex::new(HashMap::new())),
             id_to_packet_sender: Arc::new(Mutex::new(HashMap::new())),
+            is_flv,
+            queue_config,
             stream_handler: h,
-            statistic_data: Arc::new(Mutex::new(StatisticsStream::new(identifier))),
+            statistic_data: Arc::new(Mutex::new(StatisticsStream::new(identifier, remuxed))),
         }
     }
 
     async fn receive_frame_data(
         data: Option<FrameData>,
-        frame_senders: &Arc<Mutex<HashMap<Uuid, FrameDataSender>>>,
+        frame_senders: &FrameQueues,
+        is_flv: bool,
     ) {
-        if let Some(val) = data {
-            match val {
-                FrameData::MetaData {
-                    timestamp: _,
-                    data: _,
-                } => {}
-                FrameData::Audio { timestamp, data } => {
-                    let data = FrameData::Audio {
-                        timestamp,
-                        data: data.clone(),
-                    };
+        let Some(data) = data else {
+            return;
+        };
+        if let FrameData::MetaData { .. } = data {
+            return;
+        }
 
-                    for (_, v) in frame_senders.lock().await.iter() {
-                        if let Err(audio_err) = v.send(data.clone()).map_err(|_| StreamHubError {
-                            value: StreamHubErrorValue::SendAudioError,
-                        }) {
-                            log::error!("Transmiter send error: {}", audio_err);
-                        }
-                    }
+        let kind = MediaKind::of_frame(&data, is_flv);
+        frame_senders
+            .lock()
+            .await
+            .retain(|id, queue| match queue.push(data.clone(), kind) {
+                Ok(()) => true,
+                Err(err) => {
+                    log::warn!("Transmiter remove frame subscriber {}: {}", id, err);
+                    false
                 }
-                FrameData::Video { timestamp, data } => {
-                    let data = FrameData::Video {
-                        timestamp,
-                        data: data.clone(),
-                    };
-                    for (_, v) in frame_senders.lock().await.iter() {
-                        if let Err(video_err) = v.send(data.clone()).map_err(|_| StreamHubError {
-                            value: StreamHubErrorValue::SendVideoError,
-                        }) {
-                            log::error!("Transmiter send error: {}", video_err);
-                        }
-                    }
-                }
-                FrameData::MediaInfo {
-                    media_info: info_value,
-                } => {
-                    let data = FrameData::MediaInfo {
-                        media_info: info_value,
-                    };
-                    for (_, v) in frame_senders.lock().await.iter() {
-                        if let Err(media_err) = v.send(data.clone()).map_err(|_| StreamHubError {
-                            value: StreamHubErrorValue::SendVideoError,
-                        }) {
-                            log::error!("Transmiter send error: {}", media_err);
-                        }
-                    }
-                }
-            }
-        }
+            });
     }
 
     async fn receive_frame_data_loop(
         mut exit: broadcast::Receiver<()>,
         mut receiver: FrameDataReceiver,
-        frame_senders: Arc<Mutex<HashMap<Uuid, FrameDataSender>>>,
+        frame_senders: FrameQueues,
+        is_flv: bool,
+        mut stall_watcher: Option<StallWatcher>,
     ) {
         tokio::spawn(async move {
             loop {
+                let stall_timeout = stall_watcher.as_ref().and_then(|w| w.wait_timeout());
                 tokio::select! {
                     data = receiver.recv() => {
-                       Self::receive_frame_data(data, &frame_senders).await;
+                        if let Some(watcher) = &mut stall_watcher {
+                            watcher.on_data();
+                        }
+                        Self::receive_frame_data(data, &frame_senders, is_flv).await;
+                    }
+                    _ = tokio::time::sleep(stall_timeout.unwrap_or_default()), if stall_timeout.is_some() => {
+                        if let Some(watcher) = &mut stall_watcher {
+                            watcher.on_stalled();
+                        }
                     }
                     _ = exit.recv()=>{
                         break;
@@ -147,53 +151,44 @@ impl StreamDataTransceiver {
         });
     }
 
-    async fn receive_packet_data(
-        data: Option<PacketData>,
-        packet_senders: &Arc<Mutex<HashMap<Uuid, PacketDataSender>>>,
-    ) {
-        if let Some(val) = data {
-            match val {
-                PacketData::Audio { timestamp, data } => {
-                    let data = PacketData::Audio {
-                        timestamp,
-                        data: data.clone(),
-                    };
+    async fn receive_packet_data(data: Option<PacketData>, packet_senders: &PacketQueues) {
+        let Some(data) = data else {
+            return;
+        };
 
-                    for (_, v) in packet_senders.lock().await.iter() {
-                        if let Err(audio_err) = v.send(data.clone()).map_err(|_| StreamHubError {
-                            value: StreamHubErrorValue::SendAudioError,
-                        }) {
-                            log::error!("Transmiter send error: {}", audio_err);
-                        }
-                    }
+        let kind = MediaKind::of_packet(&data);
+        packet_senders
+            .lock()
+            .await
+            .retain(|id, queue| match queue.push(data.clone(), kind) {
+                Ok(()) => true,
+                Err(err) => {
+                    log::warn!("Transmiter remove packet subscriber {}: {}", id, err);
+                    false
                 }
-                PacketData::Video { timestamp, data } => {
-                    let data = PacketData::Video {
-                        timestamp,
-                        data: data.clone(),
-                    };
-                    for (_, v) in packet_senders.lock().await.iter() {
-                        if let Err(video_err) = v.send(data.clone()).map_err(|_| StreamHubError {
-                            value: StreamHubErrorValue::SendVideoError,
-                        }) {
-                            log::error!("Transmiter send error: {}", video_err);
-                        }
-                    }
-                }
-            }
-        }
+            });
     }
 
     async fn receive_packet_data_loop(
         mut exit: broadcast::Receiver<()>,
         mut receiver: PacketDataReceiver,
-        packet_senders: Arc<Mutex<HashMap<Uuid, PacketDataSender>>>,
+        packet_senders: PacketQueues,
+        mut stall_watcher: Option<StallWatcher>,
     ) {
         tokio::spawn(async move {
             loop {
+                let stall_timeout = stall_watcher.as_ref().and_then(|w| w.wait_timeout());
                 tokio::select! {
                     data = receiver.recv() => {
-                       Self::receive_packet_data(data, &packet_senders).await;
+                        if let Some(watcher) = &mut stall_watcher {
+                            watcher.on_data();
+                        }
+                        Self::receive_packet_data(data, &packet_senders).await;
+                    }
+                    _ = tokio::time::sleep(stall_timeout.unwrap_or_default()), if stall_timeout.is_some() => {
+                        if let Some(watcher) = &mut stall_watcher {
+                            watcher.on_stalled();
+                        }
                     }
                     _ = exit.recv()=>{
                         break;
@@ -320,14 +315,36 @@ impl StreamDataTransceiver {
                     let sub = StatisticSubscriber {
                         id,
                         remote_address: remote_addr,
+                        player: sub_type.is_remote_player(),
                         sub_type,
                         start_time,
                         send_bitrate: 0,
                         send_bytes: 0,
                         total_send_bytes: 0,
+                        dropped_video: 0,
+                        dropped_audio: 0,
+                        rtp: None,
                     };
                     subscriber.insert(id, sub);
                 }
+                StatisticData::SubscriberBytes { uuid, data_size } => {
+                    let stat_data = &mut statistics_data.lock().await;
+                    if let Some(sub) = stat_data.subscribers.get_mut(&uuid) {
+                        sub.send_bytes += data_size;
+                        sub.total_send_bytes += data_size;
+                    }
+                    stat_data.total_send_bytes += data_size;
+                }
+                StatisticData::Rtp { uuid, statistics } => {
+                    let stat_data = &mut statistics_data.lock().await;
+                    if let Some(uid) = uuid {
+                        if let Some(sub) = stat_data.subscribers.get_mut(&uid) {
+                            sub.rtp = Some(statistics);
+                        }
+                    } else {
+                        stat_data.publisher.rtp = Some(statistics);
+                    }
+                }
             }
         }
     }
@@ -357,12 +374,14 @@ impl StreamDataTransceiver {
         });
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn receive_event_loop(
         stream_handler: Arc<dyn TStreamHandler>,
         exit: broadcast::Sender<()>,
         mut receiver: TransceiverEventReceiver,
-        packet_senders: Arc<Mutex<HashMap<Uuid, PacketDataSender>>>,
-        frame_senders: Arc<Mutex<HashMap<Uuid, FrameDataSender>>>,
+        packet_senders: PacketQueues,
+        frame_senders: FrameQueues,
+        queue_config: SubscriberQueueConfig,
         statistic_sender: StatisticDataSender,
         statistics_data: Arc<Mutex<StatisticsStream>>,
     ) {
@@ -379,19 +398,27 @@ impl StreamDataTransceiver {
                                 .send_prior_data(sender.clone(), info.sub_type)
                                 .await
                             {
+                                //only this subscriber is rejected, its subscribe fails
+                                //once the result sender is dropped
                                 log::error!("receive_event_loop send_prior_data err: {}", err);
-                                break;
+                                continue;
                             }
                             match sender {
                                 DataSender::Frame {
                                     sender: frame_sender,
                                 } => {
-                                    frame_senders.lock().await.insert(info.id, frame_sender);
+                                    frame_senders.lock().await.insert(
+                                        info.id,
+                                        SubscriberQueue::new(frame_sender, queue_config),
+                                    );
                                 }
                                 DataSender::Packet {
                                     sender: packet_sender,
                                 } => {
-                                    packet_senders.lock().await.insert(info.id, packet_sender);
+                                    packet_senders.lock().await.insert(
+                                        info.id,
+                                        SubscriberQueue::new(packet_sender, queue_config),
+                                    );
                                 }
                             }
 
@@ -406,11 +433,11 @@ impl StreamDataTransceiver {
                             statistics_data.subscriber_count += 1;
                         }
                         TransceiverEvent::UnSubscribe { info } => {
-                            match info.sub_type {
-                                SubscribeType::PlayerRtp | SubscribeType::PlayerWebrtc => {
+                            match info.sub_data_type {
+                                SubDataType::Packet => {
                                     packet_senders.lock().await.remove(&info.id);
                                 }
-                                _ => {
+                                SubDataType::Frame => {
                                     frame_senders.lock().await.remove(&info.id);
                                 }
                             }
@@ -428,6 +455,12 @@ impl StreamDataTransceiver {
                         }
                         TransceiverEvent::Api { sender, uuid } => {
                             log::info!("api:  stream identifier: {:?}", uuid);
+                            Self::update_dropped_statistics(
+                                &frame_senders,
+                                &packet_senders,
+                                &statistics_data,
+                            )
+                            .await;
                             let statistic_data = if let Some(uid) = uuid {
                                 statistics_data.lock().await.query_by_uuid(uid)
                             } else {
@@ -442,29 +475,61 @@ impl StreamDataTransceiver {
                         TransceiverEvent::Request { sender } => {
                             stream_handler.send_information(sender).await;
                         }
+                        TransceiverEvent::RequestKeyFrame => {
+                            stream_handler.request_key_frame().await;
+                        }
                     }
                 }
             }
         });
     }
 
+    //the drop counters are kept by the subscriber queues and copied when queried
+    async fn update_dropped_statistics(
+        frame_senders: &FrameQueues,
+        packet_senders: &PacketQueues,
+        statistics_data: &Arc<Mutex<StatisticsStream>>,
+    ) {
+        let mut statistics_data = statistics_data.lock().await;
+        let frame_senders = frame_senders.lock().await;
+        let packet_senders = packet_senders.lock().await;
+
+        let frame_counters = frame_senders
+            .iter()
+            .map(|(id, queue)| (id, queue.dropped_video, queue.dropped_audio));
+        let packet_counters = packet_senders
+            .iter()
+            .map(|(id, queue)| (id, queue.dropped_video, queue.dropped_audio));
+        for (id, dropped_video, dropped_audio) in frame_counters.chain(packet_counters) {
+            if let Some(sub) = statistics_data.subscribers.get_mut(id) {
+                sub.dropped_video = dropped_video;
+                sub.dropped_audio = dropped_audio;
+            }
+        }
+    }
+
     pub async fn run(self) -> Result<(), StreamHubError> {
         let (tx, _) = broadcast::channel::<()>(1);
+        let mut stall_watcher = self.stall_watcher;
 
         if let Some(receiver) = self.data_receiver.frame_receiver {
             Self::receive_frame_data_loop(
                 tx.subscribe(),
                 receiver,
                 self.id_to_frame_sender.clone(),
+                self.is_flv,
+                stall_watcher.take(),
             )
             .await;
         }
 
+        //a publisher of both frames and packets is watched on the frames
         if let Some(receiver) = self.data_receiver.packet_receiver {
             Self::receive_packet_data_loop(
                 tx.subscribe(),
                 receiver,
                 self.id_to_packet_sender.clone(),
+                stall_watcher.take(),
             )
             .await;
         }
@@ -483,6 +548,7 @@ impl StreamDataTransceiver {
             self.event_receiver,
             self.id_to_packet_sender,
             self.id_to_frame_sender,
+            self.queue_config,
             self.statistic_data_sender,
             self.statistic_data.clone(),
         )
@@ -496,9 +562,51 @@ impl StreamDataTransceiver {
     }
 }
 
+//the time to wait for a remuxer to publish the requested representation
+const REMUX_TIMEOUT: Duration = Duration::from_secs(5);
+
+//a protocol representation of a stream, published by a client or a remuxer
+struct Representation {
+    transceiver: TransceiverEventSender,
+    pub_data_type: PubDataType,
+    //published by a remuxer on demand, it is stopped when the last subscriber leaves
+    remuxed: bool,
+}
+
+//a subscribe event waiting for its result
+struct Subscription {
+    identifier: StreamIdentifier,
+    info: SubscriberInfo,
+    sender: DataSender,
+    receiver: SubDataReceiver,
+    result_sender: SubEventExecuteResultSender,
+    event_data: Value,
+}
+
+//the subscriptions waiting for a remuxer to publish the requested representation
+struct PendingRemux {
+    remux_id: Uuid,
+    subscriptions: Vec<Subscription>,
+}
+
+//All the protocol representations of a stream key, the origin is published by a
+//client and the other representations are remuxed from it when they are subscribed.
+#[derive(Default)]
+struct HubStream {
+    origin: Option<StreamIdentifier>,
+    representations: HashMap<StreamIdentifier, Representation>,
+    pending_remuxes: HashMap<StreamIdentifier, PendingRemux>,
+}
+
+impl HubStream {
+    fn is_empty(&self) -> bool {
+        self.representations.is_empty() && self.pending_remuxes.is_empty()
+    }
+}
+
 pub struct StreamsHub {
-    //stream identifier to transceiver event sender
-    streams: HashMap<StreamIdentifier, TransceiverEventSender>,
+    //protocol independent stream key to the representations of the stream
+    streams: HashMap<StreamKey, HubStream>,
     //construct UnSubscribe and UnPublish event from Subscribe and Publish event to kick off client
     un_pub_sub_events: HashMap<Uuid, StreamHubEvent>,
     //event is consumed in Stream hub, produced from other protocol sessions
@@ -510,13 +618,22 @@ pub struct StreamsHub {
     //The rtmp static push/pull and the hls transfer is triggered actively,
     //add a control switches separately.
     rtmp_push_enabled: bool,
-    rtmp_remuxer_enabled: bool,
+    //enable remuxing webrtc(whip) streams to rtmp on demand
+    whip_remuxer_enabled: bool,
+    //enable remuxing rtsp streams to rtmp on demand
+    rtsp_remuxer_enabled: bool,
+    //enable remuxing rtmp streams to webrtc on demand
+    webrtc_remuxer_enabled: bool,
     //enable rtmp pull
     rtmp_pull_enabled: bool,
     //enable hls
     hls_enabled: bool,
+    //enable flv recording
+    record_enabled: bool,
     //http notifier on sub/pub event
     notifier: Option<Notifier>,
+    //the bounded queue settings of the subscribers
+    subscriber_queue_config: SubscriberQueueConfig,
 }
 
 impl StreamsHub {
@@ -532,9 +649,13 @@ impl StreamsHub {
             client_event_sender: client_producer,
             rtmp_push_enabled: false,
             rtmp_pull_enabled: false,
-            rtmp_remuxer_enabled: false,
+            whip_remuxer_enabled: false,
+            rtsp_remuxer_enabled: false,
+            webrtc_remuxer_enabled: false,
             hls_enabled: false,
+            record_enabled: false,
             notifier,
+            subscriber_queue_config: SubscriberQueueConfig::default(),
         }
     }
     pub async fn run(&mut self) {
@@ -549,14 +670,30 @@ impl StreamsHub {
         self.rtmp_pull_enabled = enabled;
     }
 
-    pub fn set_rtmp_remuxer_enabled(&mut self, enabled: bool) {
-        self.rtmp_remuxer_enabled = enabled;
+    pub fn set_whip_remuxer_enabled(&mut self, enabled: bool) {
+        self.whip_remuxer_enabled = enabled;
+    }
+
+    pub fn set_rtsp_remuxer_enabled(&mut self, enabled: bool) {
+        self.rtsp_remuxer_enabled = enabled;
+    }
+
+    pub fn set_webrtc_remuxer_enabled(&mut self, enabled: bool) {
+        self.webrtc_remuxer_enabled = enabled;
     }
 
     pub fn set_hls_enabled(&mut self, enabled: bool) {
         self.hls_enabled = enabled;
     }
 
+    pub fn set_record_enabled(&mut self, enabled: bool) {
+        self.record_enabled = enabled;
+    }
+
+    pub fn set_subscriber_queue_config(&mut self, config: SubscriberQueueConfig) {
+        self.subscriber_queue_config = config;
+    }
+
     pub fn get_hub_event_sender(&mut self) -> StreamHubEventSender {
         self.hub_event_sender.clone()
     }
@@ -567,11 +704,19 @@ impl StreamsHub {
 
     pub async fn event_loop(&mut self) {
         while let Some(message) = self.hub_event_receiver.recv().await {
-            let event_serialize_str = if let Ok(data) = serde_json::to_string(&message) {
+            let message = match message {
+                StreamHubEvent::HookAllowed { event } => *event,
+                message => match self.check_notify_hook(message) {
+                    Some(message) => message,
+                    None => continue,
+                },
+            };
+
+            let event_data = if let Ok(data) = serde_json::to_value(&message) {
                 log::info!("event data: {}", data);
                 data
             } else {
-                String::from("empty body")
+                Value::Null
             };
 
             match message {
@@ -622,15 +767,18 @@ impl StreamsHub {
                     };
 
                     let result = match self
-                        .publish(identifier.clone(), receiver, stream_handler)
+                        .publish(identifier.clone(), &info, receiver, stream_handler)
                         .await
                     {
                         Ok(statistic_data_sender) => {
-                            if let Some(notifier) = &self.notifier {
-                                notifier.on_publish_notify(event_serialize_str).await;
-                            }
-                            self.un_pub_sub_events
-                                .insert(info.id, StreamHubEvent::UnPublish { identifier, info });
+                            self.notify(NotifyEvent::OnPublish, event_data);
+                            self.un_pub_sub_events.insert(
+                                info.id,
+                                StreamHubEvent::UnPublish {
+                                    identifier: identifier.clone(),
+                                    info,
+                                },
+                            );
 
                             Ok((frame_sender, packet_sender, Some(statistic_data_sender)))
                         }
@@ -643,12 +791,21 @@ impl StreamsHub {
                     if result_sender.send(result).is_err() {
                         log::error!("event_loop Subscribe error: The receiver dropped.")
                     }
+
+                    self.complete_remux(&identifier).await;
                 }
 
-                StreamHubEvent::UnPublish {
-                    identifier,
-                    info: _,
-                } => {
+                StreamHubEvent::UnPublish { identifier, info } => {
+                    //the publisher may be kicked off already, do not remove
+                    //a stream published again by another session.
+                    if self.un_pub_sub_events.remove(&info.id).is_none() {
+                        log::warn!(
+                            "event_loop Unpublish: publisher {} of {} is not found",
+                            info.id,
+                            identifier
+                        );
+                        continue;
+                    }
                     if let Err(err) = self.unpublish(&identifier) {
                         log::error!(
                             "event_loop Unpublish err: {} with identifier: {}",
@@ -657,39 +814,36 @@ impl StreamsHub {
                         );
                     }
 
-                    if let Some(notifier) = &self.notifier {
-                        notifier.on_unpublish_notify(event_serialize_str).await;
-                    }
+                    self.notify(NotifyEvent::OnUnpublish, event_data);
                 }
                 StreamHubEvent::Subscribe {
                     identifier,
                     info,
                     result_sender,
                 } => {
-                    let sub_id = info.id;
-                    let info_clone = info.clone();
-
                     //new chan for Frame/Packet sender and receiver
                     let (sender, receiver) = match info.sub_data_type {
                         define::SubDataType::Frame => {
-                            let (sender_chan, receiver_chan) = mpsc::unbounded_channel();
+                            let (sender_chan, receiver_chan) =
+                                mpsc::channel(self.subscriber_queue_config.capacity);
                             (
                                 DataSender::Frame {
                                     sender: sender_chan,
                                 },
-                                DataReceiver {
+                                SubDataReceiver {
                                     frame_receiver: Some(receiver_chan),
                                     packet_receiver: None,
                                 },
                             )
                         }
                         define::SubDataType::Packet => {
-                            let (sender_chan, receiver_chan) = mpsc::unbounded_channel();
+                            let (sender_chan, receiver_chan) =
+                                mpsc::channel(self.subscriber_queue_config.capacity);
                             (
                                 DataSender::Packet {
                                     sender: sender_chan,
                                 },
-                                DataReceiver {
+                                SubDataReceiver {
                                     frame_receiver: None,
                                     packet_receiver: Some(receiver_chan),
                                 },
@@ -697,31 +851,24 @@ impl StreamsHub {
                         }
                     };
 
-                    let rv = match self.subscribe(&identifier, info_clone, sender).await {
-                        Ok(statistic_data_sender) => {
-                            if let Some(notifier) = &self.notifier {
-                                notifier.on_play_notify(event_serialize_str).await;
-                            }
-
-                            self.un_pub_sub_events
-                                .insert(sub_id, StreamHubEvent::UnSubscribe { identifier, info });
-                            Ok((receiver, Some(statistic_data_sender)))
-                        }
-                        Err(err) => {
-                            log::error!("event_loop Subscribe error: {}", err);
-                            Err(err)
-                        }
+                    let subscription = Subscription {
+                        identifier,
+                        info,
+                        sender,
+                        receiver,
+                        result_sender,
+                        event_data,
                     };
-
-                    if result_sender.send(rv).is_err() {
-                        log::error!("event_loop Subscribe error: The receiver dropped.")
+                    match self.remux_source(&subscription.identifier) {
+                        Some(source) => self.request_remux(source, subscription),
+                        None => self.complete_subscribe(subscription).await,
                     }
                 }
                 StreamHubEvent::UnSubscribe { identifier, info } => {
+                    self.un_pub_sub_events.remove(&info.id);
                     if self.unsubscribe(&identifier, info).is_ok() {
-                        if let Some(notifier) = &self.notifier {
-                            notifier.on_stop_notify(event_serialize_str).await;
-                        }
+                        self.notify(NotifyEvent::OnStop, event_data);
+                        self.stop_idle_remuxer(&identifier);
                     }
                 }
 
@@ -749,26 +896,126 @@ impl StreamsHub {
                         log::error!("api_kick_off_client api error: {}", err);
                     }
                 }
+                StreamHubEvent::ApiKickStream { key } => {
+                    if let Err(err) = self.api_kick_off_stream(&key) {
+                        log::error!("api_kick_off_stream api error: {}", err);
+                    }
+                }
                 StreamHubEvent::Request { identifier, sender } => {
                     if let Err(err) = self.request(&identifier, sender) {
                         log::error!("event_loop request error: {}", err);
                     }
                 }
+                StreamHubEvent::HookAllowed { .. } => {
+                    log::error!("event_loop: nested HookAllowed event is ignored");
+                }
+                StreamHubEvent::RemuxTimeout {
+                    identifier,
+                    remux_id,
+                } => {
+                    self.remux_timeout(&identifier, remux_id);
+                }
+                StreamHubEvent::RequestKeyFrame { identifier } => {
+                    if let Err(err) = self.request_key_frame(&identifier) {
+                        log::error!("event_loop request_key_frame error: {}", err);
+                    }
+                }
             }
         }
     }
 
+    fn notify(&self, event: NotifyEvent, data: Value) {
+        if let Some(notifier) = &self.notifier {
+            //a blocking hook has been asked before the session is registered
+            if !notifier.is_blocking(event) {
+                notifier.notify(event, data);
+            }
+        }
+    }
+
+    //Ask the blocking hook before a publisher/subscriber is registered, the hook is
+    //waited in a task and the allowed event is sent back as HookAllowed, so the hub
+    //keeps handling other events in the meantime.
+    fn check_notify_hook(&self, message: StreamHubEvent) -> Option<StreamHubEvent> {
+        let Some(notifier) = &self.notifier else {
+            return Some(message);
+        };
+        let Some(event) = hook_event(&message) else {
+            return Some(message);
+        };
+        if !notifier.is_blocking(event) {
+            return Some(message);
+        }
+
+        let data = serde_json::to_value(&message).unwrap_or(Value::Null);
+        let notifier = notifier.clone();
+        let hub_event_sender = self.hub_event_sender.clone();
+
+        tokio::spawn(async move {
+            let err = match notifier.authorize(event, data).await {
+                Ok(()) => {
+                    let allowed = StreamHubEvent::HookAllowed {
+                        event: Box::new(message),
+                    };
+                    if hub_event_sender.send(allowed).is_err() {
+                        log::error!("check_notify_hook: the stream hub is closed");
+                    }
+                    return;
+                }
+                Err(err) => err,
+            };
+
+            log::warn!("{} is rejected by the hook: {}", event.as_str(), err);
+            let value = StreamHubErrorValue::NotifyRejected(err.to_string());
+            let sent = match message {
+                StreamHubEvent::Publish { result_sender, .. } => {
+                    result_sender.send(Err(StreamHubError { value })).is_ok()
+                }
+                StreamHubEvent::Subscribe { result_sender, .. } => {
+                    result_sender.send(Err(StreamHubError { value })).is_ok()
+                }
+                _ => true,
+            };
+            if !sent {
+                log::error!("check_notify_hook: The receiver dropped.");
+            }
+        });
+
+        None
+    }
+
+    fn representation(&self, identifier: &StreamIdentifier) -> Option<&Representation> {
+        self.streams
+            .get(&identifier.stream_key())
+            .and_then(|stream| stream.representations.get(identifier))
+    }
+
     fn request(
         &mut self,
         identifier: &StreamIdentifier,
         sender: mpsc::UnboundedSender<Information>,
     ) -> Result<(), StreamHubError> {
-        if let Some(producer) = self.streams.get_mut(identifier) {
+        if let Some(representation) = self.representation(identifier) {
             let event = TransceiverEvent::Request { sender };
             log::info!("Request:  stream identifier: {}", identifier);
-            producer.send(event).map_err(|_| StreamHubError {
-                value: StreamHubErrorValue::SendError,
-            })?;
+            representation
+                .transceiver
+                .send(event)
+                .map_err(|_| StreamHubError {
+                    value: StreamHubErrorValue::SendError,
+                })?;
+        }
+        Ok(())
+    }
+
+    fn request_key_frame(&self, identifier: &StreamIdentifier) -> Result<(), StreamHubError> {
+        if let Some(representation) = self.representation(identifier) {
+            representation
+                .transceiver
+                .send(TransceiverEvent::RequestKeyFrame)
+                .map_err(|_| StreamHubError {
+                    value: StreamHubErrorValue::SendError,
+                })?;
         }
         Ok(())
     }
@@ -779,29 +1026,50 @@ impl StreamsHub {
         identifier: Option<StreamIdentifier>,
         uuid: Option<Uuid>,
     ) -> Result<Value, StreamHubError> {
-        if self.streams.is_empty() {
-            return Ok(json!({}));
-        }
         log::info!("api_statistic:  stream identifier: {:?}", identifier);
         let (stream_sender, mut stream_receiver) = mpsc::unbounded_channel();
 
         let mut stream_count: usize = 1;
 
         if let Some(identifier) = identifier {
-            if let Some(event_sender) = self.streams.get_mut(&identifier) {
+            //a protocol which is not published is answered by the origin
+            let representation = self
+                .streams
+                .get(&identifier.stream_key())
+                .and_then(|stream| {
+                    stream.representations.get(&identifier).or_else(|| {
+                        stream
+                            .origin
+                            .as_ref()
+                            .and_then(|origin| stream.representations.get(origin))
+                    })
+                });
+
+            if let Some(representation) = representation {
                 let event = TransceiverEvent::Api {
                     sender: stream_sender.clone(),
                     uuid,
                 };
                 log::info!("api_statistic:  stream identifier: {}", identifier);
-                event_sender.send(event).map_err(|_| StreamHubError {
-                    value: StreamHubErrorValue::SendError,
-                })?;
+                representation
+                    .transceiver
+                    .send(event)
+                    .map_err(|_| StreamHubError {
+                        value: StreamHubErrorValue::SendError,
+                    })?;
+            } else {
+                //nothing would be received for a stream that does not exist
+                return Ok(json!([]));
             }
         } else {
-            stream_count = self.streams.len();
-            for v in self.streams.values() {
-                if let Err(err) = v.send(TransceiverEvent::Api {
+            let representations: Vec<&Representation> = self
+                .streams
+                .values()
+                .flat_map(|stream| stream.representations.values())
+                .collect();
+            stream_count = representations.len();
+            for v in representations {
+                if let Err(err) = v.transceiver.send(TransceiverEvent::Api {
                     sender: stream_sender.clone(),
                     uuid,
                 }) {
@@ -813,15 +1081,15 @@ impl StreamsHub {
             }
         }
 
+        //the answers end early if a transceiver exits before answering
+        drop(stream_sender);
         let mut data = Vec::new();
 
-        loop {
-            log::info!("api_statistic:  stream count: {}", stream_count);
-            if let Some(stream_statistics) = stream_receiver.recv().await {
-                data.push(stream_statistics);
-            }
-            if data.len() == stream_count {
-                break;
+        log::info!("api_statistic:  stream count: {}", stream_count);
+        while data.len() < stream_count {
+            match stream_receiver.recv().await {
+                Some(stream_statistics) => data.push(stream_statistics),
+                None => break,
             }
         }
 
@@ -836,6 +1104,12 @@ impl StreamsHub {
 
     fn api_kick_off_client(&mut self, uid: Uuid) -> Result<(), StreamHubError> {
         if let Some(event) = self.un_pub_sub_events.get(&uid) {
+            if let Some(notifier) = &self.notifier {
+                notifier.notify(
+                    NotifyEvent::OnKick,
+                    serde_json::to_value(event).unwrap_or(Value::Null),
+                );
+            }
             match event {
                 StreamHubEvent::UnPublish { identifier, info } => {
                     if self
@@ -874,6 +1148,227 @@ impl StreamsHub {
         Ok(())
     }
 
+    fn api_kick_off_stream(&mut self, key: &StreamKey) -> Result<(), StreamHubError> {
+        //kick the sessions of all the protocols, the subscribers are kicked before
+        //the publishers, the unsubscribe events would fail if the stream is removed first.
+        let mut uids: Vec<(bool, Uuid)> = self
+            .un_pub_sub_events
+            .iter()
+            .filter_map(|(uid, event)| match event {
+                StreamHubEvent::UnSubscribe {
+                    identifier: sub_identifier,
+                    ..
+                } if &sub_identifier.stream_key() == key => Some((false, *uid)),
+                StreamHubEvent::UnPublish {
+                    identifier: pub_identifier,
+                    ..
+                } if &pub_identifier.stream_key() == key => Some((true, *uid)),
+                _ => None,
+            })
+            .collect();
+        uids.sort_by_key(|(is_publisher, _)| *is_publisher);
+
+        log::info!("kick off stream: {}, session count: {}", key, uids.len());
+        for (_, uid) in uids {
+            self.api_kick_off_client(uid)?;
+        }
+
+        Ok(())
+    }
+
+    //The origin to remux from if the requested representation is not published,
+    //a webrtc representation is remuxed from the rtmp one which may be remuxed too.
+    fn remux_source(&self, identifier: &StreamIdentifier) -> Option<StreamIdentifier> {
+        let stream = self.streams.get(&identifier.stream_key())?;
+        if stream.representations.contains_key(identifier) {
+            return None;
+        }
+        let origin = stream.origin.as_ref()?;
+
+        let enabled = match identifier {
+            StreamIdentifier::Rtmp { .. } => self.rtmp_remuxer_enabled(origin),
+            StreamIdentifier::WebRTC { .. } => {
+                self.webrtc_remuxer_enabled
+                    && (matches!(origin, StreamIdentifier::Rtmp { .. })
+                        || self.rtmp_remuxer_enabled(origin))
+            }
+            _ => false,
+        };
+
+        enabled.then(|| origin.clone())
+    }
+
+    //whether the rtmp representation can be remuxed from the origin
+    fn rtmp_remuxer_enabled(&self, origin: &StreamIdentifier) -> bool {
+        match origin {
+            StreamIdentifier::WebRTC { .. } => self.whip_remuxer_enabled,
+            StreamIdentifier::Rtsp { .. } => self.rtsp_remuxer_enabled,
+            _ => false,
+        }
+    }
+
+    //Park the subscription until a remuxer publishes the requested representation,
+    //the remuxer is requested once and the subscriptions are rejected after a timeout.
+    fn request_remux(&mut self, source: StreamIdentifier, subscription: Subscription) {
+        let target = subscription.identifier.clone();
+        let stream = self.streams.entry(target.stream_key()).or_default();
+        if let Some(pending) = stream.pending_remuxes.get_mut(&target) {
+            pending.subscriptions.push(subscription);
+            return;
+        }
+
+        let remux_id = Uuid::new();
+        stream.pending_remuxes.insert(
+            target.clone(),
+            PendingRemux {
+                remux_id,
+                subscriptions: vec![subscription],
+            },
+        );
+
+        log::info!("request remuxer: {} to {}", source, target);
+        let client_event = BroadcastEvent::Remux {
+            source,
+            target: target.clone(),
+        };
+        if self.client_event_sender.send(client_event).is_err() {
+            if let Some(pending) = stream.pending_remuxes.remove(&target) {
+                Self::reject_subscriptions(pending.subscriptions, || {
+                    StreamHubErrorValue::SendError
+                });
+            }
+            return;
+        }
+
+        let hub_event_sender = self.hub_event_sender.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(REMUX_TIMEOUT).await;
+            let event = StreamHubEvent::RemuxTimeout {
+                identifier: target,
+                remux_id,
+            };
+            if hub_event_sender.send(event).is_err() {
+                log::error!("request_remux: the stream hub is closed");
+            }
+        });
+    }
+
+    //serve the subscriptions waiting for the representation published by a remuxer
+    async fn complete_remux(&mut self, identifier: &StreamIdentifier) {
+        let Some(stream) = self.streams.get_mut(&identifier.stream_key()) else {
+            return;
+        };
+        if !stream.representations.contains_key(identifier) {
+            return;
+        }
+
+        if let Some(pending) = stream.pending_remuxes.remove(identifier) {
+            for subscription in pending.subscriptions {
+                self.complete_subscribe(subscription).await;
+            }
+        }
+    }
+
+    fn remux_timeout(&mut self, identifier: &StreamIdentifier, remux_id: Uuid) {
+        let Some(stream) = self.streams.get_mut(&identifier.stream_key()) else {
+            return;
+        };
+        //the remuxer may publish in time and be requested again
+        if !stream
+            .pending_remuxes
+            .get(identifier)
+            .is_some_and(|pending| pending.remux_id == remux_id)
+        {
+            return;
+        }
+
+        if let Some(pending) = stream.pending_remuxes.remove(identifier) {
+            log::warn!("remux timeout, identifier: {}", identifier);
+            Self::reject_subscriptions(pending.subscriptions, || StreamHubErrorValue::RemuxTimeout);
+        }
+    }
+
+    fn reject_subscriptions(
+        subscriptions: Vec<Subscription>,
+        value: impl Fn() -> StreamHubErrorValue,
+    ) {
+        for subscription in subscriptions {
+            log::error!(
+                "event_loop Subscribe error: {}, identifier: {}",
+                value(),
+                subscription.identifier
+            );
+            let result = Err(StreamHubError { value: value() });
+            if subscription.result_sender.send(result).is_err() {
+                log::error!("event_loop Subscribe error: The receiver dropped.")
+            }
+        }
+    }
+
+    //stop the remuxer of a representation when its last subscriber leaves
+    fn stop_idle_remuxer(&mut self, identifier: &StreamIdentifier) {
+        if !self
+            .representation(identifier)
+            .is_some_and(|representation| representation.remuxed)
+        {
+            return;
+        }
+
+        let mut publisher_id = None;
+        for (uid, event) in &self.un_pub_sub_events {
+            match event {
+                StreamHubEvent::UnSubscribe {
+                    identifier: sub_identifier,
+                    ..
+                } if sub_identifier == identifier => return,
+                StreamHubEvent::UnPublish {
+                    identifier: pub_identifier,
+                    ..
+                } if pub_identifier == identifier => publisher_id = Some(*uid),
+                _ => {}
+            }
+        }
+
+        log::info!("stop the idle remuxer, identifier: {}", identifier);
+        //the remuxer exits once it fails to send data to the removed stream
+        let event = publisher_id.and_then(|uid| self.un_pub_sub_events.remove(&uid));
+        if let Err(err) = self.unpublish(identifier) {
+            log::error!("stop_idle_remuxer unpublish err: {}", err);
+        }
+        if let Some(event) = event {
+            let event_data = serde_json::to_value(&event).unwrap_or(Value::Null);
+            self.notify(NotifyEvent::OnUnpublish, event_data);
+        }
+    }
+
+    async fn complete_subscribe(&mut self, subscription: Subscription) {
+        let Subscription {
+            identifier,
+            info,
+            sender,
+            receiver,
+            result_sender,
+            event_data,
+        } = subscription;
+
+        let rv = match self.subscribe(&identifier, info.clone(), sender).await {
+            Ok(statistic_data_sender) => {
+                self.notify(NotifyEvent::OnPlay, event_data);
+                self.un_pub_sub_events
+                    .insert(info.id, StreamHubEvent::UnSubscribe { identifier, info });
+                Ok((receiver, Some(statistic_data_sender)))
+            }
+            Err(err) => {
+                log::error!("event_loop Subscribe error: {}", err);
+                Err(err)
+            }
+        };
+
+        if result_sender.send(rv).is_err() {
+            log::error!("event_loop Subscribe error: The receiver dropped.")
+        }
+    }
+
     //player subscribe a stream
     pub async fn subscribe(
         &mut self,
@@ -881,7 +1376,16 @@ impl StreamsHub {
         sub_info: SubscriberInfo,
         sender: DataSender,
     ) -> Result<StatisticDataSender, StreamHubError> {
-        if let Some(event_sender) = self.streams.get_mut(identifer) {
+        if let Some(representation) = self.representation(identifer) {
+            if !representation
+                .pub_data_type
+                .provides(&sub_info.sub_data_type)
+            {
+                return Err(StreamHubError {
+                    value: StreamHubErrorValue::NotCorrectDataSenderType,
+                });
+            }
+
             let (result_sender, result_receiver) = oneshot::channel();
             let event = TransceiverEvent::Subscribe {
                 sender,
@@ -889,9 +1393,12 @@ impl StreamsHub {
                 result_sender,
             };
             log::info!("subscribe:  stream identifier: {}", identifer);
-            event_sender.send(event).map_err(|_| StreamHubError {
-                value: StreamHubErrorValue::SendError,
-            })?;
+            representation
+                .transceiver
+                .send(event)
+                .map_err(|_| StreamHubError {
+                    value: StreamHubErrorValue::SendError,
+                })?;
 
             return Ok(result_receiver.await?);
         }
@@ -921,13 +1428,16 @@ impl StreamsHub {
         identifer: &StreamIdentifier,
         sub_info: SubscriberInfo,
     ) -> Result<(), StreamHubError> {
-        match self.streams.get_mut(identifer) {
-            Some(producer) => {
+        match self.representation(identifer) {
+            Some(representation) => {
                 log::info!("unsubscribe....:{}", identifer);
                 let event = TransceiverEvent::UnSubscribe { info: sub_info };
-                producer.send(event).map_err(|_| StreamHubError {
-                    value: StreamHubErrorValue::SendError,
-                })?;
+                representation
+                    .transceiver
+                    .send(event)
+                    .map_err(|_| StreamHubError {
+                        value: StreamHubErrorValue::SendError,
+                    })?;
             }
             None => {
                 log::info!("unsubscribe None....:{}", identifer);
@@ -940,22 +1450,48 @@ impl StreamsHub {
         Ok(())
     }
 
-    //publish a stream
+    //Publish a stream, the first representation of a stream key is the origin,
+    //the other protocols are only published by the remuxers requested by the hub.
     pub async fn publish(
         &mut self,
         identifier: StreamIdentifier,
+        info: &PublisherInfo,
         receiver: DataReceiver,
         handler: Arc<dyn TStreamHandler>,
     ) -> Result<StatisticDataSender, StreamHubError> {
-        if self.streams.get(&identifier).is_some() {
-            return Err(StreamHubError {
-                value: StreamHubErrorValue::Exists,
-            });
-        }
+        let key = identifier.stream_key();
+        let remuxed = match self.streams.get(&key) {
+            Some(stream) if stream.representations.contains_key(&identifier) => {
+                return Err(StreamHubError {
+                    value: StreamHubErrorValue::Exists,
+                });
+            }
+            Some(HubStream {
+                origin: Some(origin),
+                pending_remuxes,
+                ..
+            }) => {
+                if !pending_remuxes.contains_key(&identifier) {
+                    log::warn!("publish: {} is published by {} already", key, origin);
+                    return Err(StreamHubError {
+                        value: StreamHubErrorValue::Exists,
+                    });
+                }
+                true
+            }
+            _ => false,
+        };
 
         let (event_sender, event_receiver) = mpsc::unbounded_channel();
-        let transceiver =
-            StreamDataTransceiver::new(receiver, event_receiver, identifier.clone(), handler);
+        let transceiver = StreamDataTransceiver::new(
+            receiver,
+            event_receiver,
+            identifier.clone(),
+            remuxed,
+            handler,
+            self.notifier.as_ref(),
+            self.subscriber_queue_config,
+        );
 
         let statistic_data_sender = transceiver.get_statistics_data_sender();
         let identifier_clone = identifier.clone();
@@ -970,10 +1506,26 @@ impl StreamsHub {
             log::info!("transceiver run success, idetifier: {}", identifier_clone);
         }
 
-        self.streams.insert(identifier.clone(), event_sender);
-
-        if self.rtmp_push_enabled || self.hls_enabled || self.rtmp_remuxer_enabled {
-            let client_event = BroadcastEvent::Publish { identifier };
+        let stream = self.streams.entry(key).or_default();
+        if !remuxed {
+            stream.origin = Some(identifier.clone());
+        }
+        stream.representations.insert(
+            identifier.clone(),
+            Representation {
+                transceiver: event_sender,
+                pub_data_type: info.pub_data_type.clone(),
+                remuxed,
+            },
+        );
+
+        //the push relays, hls and recorder follow the origin and subscribe the
+        //rtmp representation, which is remuxed on demand if needed.
+        if !remuxed && (self.rtmp_push_enabled || self.hls_enabled || self.record_enabled) {
+            let client_event = BroadcastEvent::Publish {
+                identifier,
+                mode: info.mode,
+            };
 
             //send publish info to push clients
             self.client_event_sender
@@ -987,21 +1539,114 @@ impl StreamsHub {
     }
 
     fn unpublish(&mut self, identifier: &StreamIdentifier) -> Result<(), StreamHubError> {
-        match self.streams.remove(identifier) {
-            Some(producer) => {
-                let event = TransceiverEvent::UnPublish {};
-                producer.send(event).map_err(|_| StreamHubError {
+        let key = identifier.stream_key();
+        let Some(stream) = self.streams.get_mut(&key) else {
+            return Err(StreamHubError {
+                value: StreamHubErrorValue::NoAppName,
+            });
+        };
+        let Some(representation) = stream.representations.remove(identifier) else {
+            return Err(StreamHubError {
+                value: StreamHubErrorValue::NoAppName,
+            });
+        };
+
+        let is_origin = stream.origin.as_ref() == Some(identifier);
+        let mut pending_remuxes = HashMap::new();
+        if is_origin {
+            //nothing can be remuxed without the origin, the remuxed representations
+            //are unpublished by the remuxers when their subscriptions end.
+            stream.origin = None;
+            pending_remuxes = std::mem::take(&mut stream.pending_remuxes);
+        }
+        if stream.is_empty() {
+            self.streams.remove(&key);
+        }
+
+        for pending in pending_remuxes.into_values() {
+            Self::reject_subscriptions(pending.subscriptions, || {
+                StreamHubErrorValue::NoAppOrStreamName
+            });
+        }
+
+        representation
+            .transceiver
+            .send(TransceiverEvent::UnPublish {})
+            .map_err(|_| StreamHubError {
+                value: StreamHubErrorValue::SendError,
+            })?;
+        log::info!("unpublish remove stream, stream identifier: {}", identifier);
+
+        if is_origin && self.rtmp_push_enabled {
+            //stop the push relays of the stream
+            let client_event = BroadcastEvent::UnPublish {
+                identifier: identifier.clone(),
+            };
+            self.client_event_sender
+                .send(client_event)
+                .map_err(|_| StreamHubError {
                     value: StreamHubErrorValue::SendError,
                 })?;
-                log::info!("unpublish remove stream, stream identifier: {}", identifier);
-            }
-            None => {
-                return Err(StreamHubError {
-                    value: StreamHubErrorValue::NoAppName,
-                })
-            }
         }
 
         Ok(())
     }
 }
+
+//the hook event of a publisher/subscriber, the local sessions such as
+//the remuxers, the hls generator and the recorder are not asked
+fn hook_event(message: &StreamHubEvent) -> Option<NotifyEvent> {
+    match message {
+        StreamHubEvent::Publish { info, .. } if info.pub_type.is_remote_publisher() => {
+            Some(NotifyEvent::OnPublish)
+        }
+        StreamHubEvent::Subscribe { info, .. } if info.sub_type.is_remote_player() => {
+            Some(NotifyEvent::OnPlay)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        define::{NotifyInfo, SubscribeType},
+    };
+
+    fn subscribe(sub_type: SubscribeType) -> StreamHubEvent {
+        let (result_sender, _) = oneshot::channel();
+        StreamHubEvent::Subscribe {
+            identifier: StreamIdentifier::Rtmp {
+                app_name: "live".to_string(),
+                stream_name: "test".to_string(),
+            },
+            info: SubscriberInfo {
+                id: Uuid::new(),
+                sub_type,
+                notify_info: NotifyInfo {
+                    request_url: String::new(),
+                    remote_addr: String::new(),
+                },
+                sub_data_type: SubDataType::Frame,
+            },
+            result_sender,
+        }
+    }
+
+    #[test]
+    fn test_hook_event() {
+        for sub_type in [
+            SubscribeType::GenerateHls,
+            SubscribeType::RecordFlv,
+            SubscribeType::PublisherRtmp,
+            SubscribeType::Remux,
+        ] {
+            assert!(hook_event(&subscribe(sub_type)).is_none());
+        }
+        assert!(matches!(
+            hook_event(&subscribe(SubscribeType::PlayerRtmp)),
+            Some(NotifyEvent::OnPlay)
+        ));
+    }
+}
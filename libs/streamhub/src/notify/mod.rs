@@ -1,100 +1,360 @@
-use reqwest::Client;
+use {
+    chrono::Local,
+    hmac::{Hmac, Mac},
+    reqwest::{Client, StatusCode},
+    serde::Serialize,
+    serde_json::{json, Value},
+    sha2::Sha256,
+    std::{sync::Arc, time::Duration},
+    tokio::sync::mpsc,
+};
+
+type HmacSha256 = Hmac<Sha256>;
+
+//the header carrying hex(hmac_sha256(secret, body)) when a secret is configured
+pub const SIGNATURE_HEADER: &str = "X-Hub-Signature-256";
+
+#[derive(Debug, Serialize, Clone, Copy, Eq, PartialEq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum NotifyEvent {
+    OnPublish,
+    OnUnpublish,
+    OnPlay,
+    OnStop,
+    /* The publisher is connected but no a/v data is received for a while.*/
+    OnStreamStalled,
+    /* A recording file is closed.*/
+    OnRecordDone,
+    /* A publisher or subscriber is kicked off by the api.*/
+    OnKick,
+}
+
+impl NotifyEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::OnPublish => "on_publish",
+            Self::OnUnpublish => "on_unpublish",
+            Self::OnPlay => "on_play",
+            Self::OnStop => "on_stop",
+            Self::OnStreamStalled => "on_stream_stalled",
+            Self::OnRecordDone => "on_record_done",
+            Self::OnKick => "on_kick",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NotifierConfig {
+    pub on_publish_url: Option<String>,
+    pub on_unpublish_url: Option<String>,
+    pub on_play_url: Option<String>,
+    pub on_stop_url: Option<String>,
+    pub on_stream_stalled_url: Option<String>,
+    pub on_record_done_url: Option<String>,
+    pub on_kick_url: Option<String>,
+    //sign the payloads if it is set
+    pub secret: Option<String>,
+    pub timeout: Duration,
+    //the retry times of a failed notification, the delay doubles every time
+    pub max_retries: u32,
+    pub retry_backoff: Duration,
+    //the notifications waiting to be sent, new ones are dropped when it is full
+    pub queue_size: usize,
+    //wait for the on_publish/on_play response, a non 2xx response denies the session
+    pub blocking_publish: bool,
+    pub blocking_play: bool,
+    //send on_stream_stalled if no data is received in this duration
+    pub stall_timeout: Option<Duration>,
+}
+
+impl Default for NotifierConfig {
+    fn default() -> Self {
+        Self {
+            on_publish_url: None,
+            on_unpublish_url: None,
+            on_play_url: None,
+            on_stop_url: None,
+            on_stream_stalled_url: None,
+            on_record_done_url: None,
+            on_kick_url: None,
+            secret: None,
+            timeout: Duration::from_secs(3),
+            max_retries: 3,
+            retry_backoff: Duration::from_millis(500),
+            queue_size: 1024,
+            blocking_publish: false,
+            blocking_play: false,
+            stall_timeout: None,
+        }
+    }
+}
+
+impl NotifierConfig {
+    fn url(&self, event: NotifyEvent) -> Option<&str> {
+        let url = match event {
+            NotifyEvent::OnPublish => &self.on_publish_url,
+            NotifyEvent::OnUnpublish => &self.on_unpublish_url,
+            NotifyEvent::OnPlay => &self.on_play_url,
+            NotifyEvent::OnStop => &self.on_stop_url,
+            NotifyEvent::OnStreamStalled => &self.on_stream_stalled_url,
+            NotifyEvent::OnRecordDone => &self.on_record_done_url,
+            NotifyEvent::OnKick => &self.on_kick_url,
+        };
+        url.as_deref().filter(|url| !url.is_empty())
+    }
+}
+
+struct NotifyMessage {
+    event: NotifyEvent,
+    url: String,
+    body: String,
+}
+
+#[derive(Debug)]
+pub enum NotifyError {
+    Request(reqwest::Error),
+    Status(StatusCode),
+}
+
+impl std::fmt::Display for NotifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Request(err) => write!(f, "request error: {}", err),
+            Self::Status(status) => write!(f, "response status: {}", status),
+        }
+    }
+}
+
+/// Post the hub events to http hooks, the request body is
+///
+///   {"event": "on_publish", "timestamp": 1700000000000, "data": {...}}
+///
+/// The notifications are queued and retried in the background, except the
+/// on_publish/on_play of the blocking mode, whose response decides if the
+/// session is allowed.
+#[derive(Clone)]
 pub struct Notifier {
     request_client: Client,
-    on_publish_url: Option<String>,
-    on_unpublish_url: Option<String>,
-    on_play_url: Option<String>,
-    on_stop_url: Option<String>,
+    config: Arc<NotifierConfig>,
+    queue_sender: mpsc::Sender<NotifyMessage>,
 }
 
 impl Notifier {
-    pub fn new(
-        on_publish_url: Option<String>,
-        on_unpublish_url: Option<String>,
-        on_play_url: Option<String>,
-        on_stop_url: Option<String>,
-    ) -> Self {
-        Self {
-            request_client: reqwest::Client::new(),
-            on_publish_url,
-            on_unpublish_url,
-            on_play_url,
-            on_stop_url,
+    /// It must be called in a tokio runtime, the sending task is spawned here.
+    pub fn new(config: NotifierConfig) -> Result<Self, reqwest::Error> {
+        let request_client = Client::builder().timeout(config.timeout).build()?;
+        let (queue_sender, queue_receiver) = mpsc::channel(config.queue_size.max(1));
+
+        let notifier = Self {
+            request_client,
+            config: Arc::new(config),
+            queue_sender,
+        };
+
+        //the sending task must not hold the queue sender, or it never stops
+        let (request_client, config) = (notifier.request_client.clone(), notifier.config.clone());
+        tokio::spawn(async move { Self::send_loop(request_client, config, queue_receiver).await });
+
+        Ok(notifier)
+    }
+
+    pub fn is_blocking(&self, event: NotifyEvent) -> bool {
+        if self.config.url(event).is_none() {
+            return false;
+        }
+        match event {
+            NotifyEvent::OnPublish => self.config.blocking_publish,
+            NotifyEvent::OnPlay => self.config.blocking_play,
+            _ => false,
         }
     }
-    pub async fn on_publish_notify(&self, body: String) {
-        if let Some(on_publish_url) = &self.on_publish_url {
-            match self
-                .request_client
-                .post(on_publish_url)
-                .body(body)
-                .send()
-                .await
-            {
-                Err(err) => {
-                    log::error!("on_publish error: {}", err);
-                }
-                Ok(response) => {
-                    log::info!("on_publish success: {:?}", response);
-                }
-            }
+
+    pub fn stall_timeout(&self) -> Option<Duration> {
+        self.config
+            .stall_timeout
+            .filter(|_| self.config.url(NotifyEvent::OnStreamStalled).is_some())
+    }
+
+    fn make_body(event: NotifyEvent, data: Value) -> String {
+        json!({
+            "event": event,
+            "timestamp": Local::now().timestamp_millis(),
+            "data": data,
+        })
+        .to_string()
+    }
+
+    pub fn sign(secret: &str, body: &str) -> String {
+        //a hmac accepts keys of any length
+        let mut mac =
+            HmacSha256::new_from_slice(secret.as_bytes()).expect("hmac can take key of any size");
+        mac.update(body.as_bytes());
+        format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+    }
+
+    async fn post(
+        request_client: &Client, config: &NotifierConfig, url: &str, body: String,
+    ) -> Result<(), NotifyError> {
+        let mut request = request_client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json");
+        if let Some(secret) = &config.secret {
+            request = request.header(SIGNATURE_HEADER, Self::sign(secret, &body));
+        }
+
+        let response = request.body(body).send().await.map_err(NotifyError::Request)?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(NotifyError::Status(response.status()))
         }
     }
 
-    pub async fn on_unpublish_notify(&self, body: String) {
-        if let Some(on_unpublish_url) = &self.on_unpublish_url {
-            match self
-                .request_client
-                .post(on_unpublish_url)
-                .body(body)
-                .send()
-                .await
-            {
-                Err(err) => {
-                    log::error!("on_unpublish error: {}", err);
-                }
-                Ok(response) => {
-                    log::info!("on_unpublish success: {:?}", response);
-                }
-            }
+    /// Queue a notification, it is dropped if no url is configured for the event
+    /// or the queue is full.
+    pub fn notify(&self, event: NotifyEvent, data: Value) {
+        let Some(url) = self.config.url(event) else {
+            return;
+        };
+
+        let message = NotifyMessage {
+            event,
+            url: url.to_string(),
+            body: Self::make_body(event, data),
+        };
+        if let Err(err) = self.queue_sender.try_send(message) {
+            log::error!("{} notify dropped: {}", event.as_str(), err);
         }
     }
 
-    pub async fn on_play_notify(&self, body: String) {
-        if let Some(on_play_url) = &self.on_play_url {
-            match self
-                .request_client
-                .post(on_play_url)
-                .body(body)
-                .send()
-                .await
-            {
-                Err(err) => {
-                    log::error!("on_play error: {}", err);
-                }
-                Ok(response) => {
-                    log::info!("on_play success: {:?}", response);
-                }
-            }
+    /// Ask the hook of a blocking event if the session is allowed,
+    /// it is allowed if the event is not blocking.
+    pub async fn authorize(&self, event: NotifyEvent, data: Value) -> Result<(), NotifyError> {
+        if !self.is_blocking(event) {
+            return Ok(());
         }
+        let Some(url) = self.config.url(event) else {
+            return Ok(());
+        };
+
+        Self::post(
+            &self.request_client,
+            &self.config,
+            url,
+            Self::make_body(event, data),
+        )
+        .await
     }
 
-    pub async fn on_stop_notify(&self, body: String) {
-        if let Some(on_stop_url) = &self.on_stop_url {
-            match self
-                .request_client
-                .post(on_stop_url)
-                .body(body)
-                .send()
-                .await
-            {
-                Err(err) => {
-                    log::error!("on_stop error: {}", err);
-                }
-                Ok(response) => {
-                    log::info!("on_stop success: {:?}", response);
+    async fn send_loop(
+        request_client: Client, config: Arc<NotifierConfig>,
+        mut receiver: mpsc::Receiver<NotifyMessage>,
+    ) {
+        while let Some(message) = receiver.recv().await {
+            let mut retry_times = 0;
+            loop {
+                let result =
+                    Self::post(&request_client, &config, &message.url, message.body.clone()).await;
+                match result {
+                    Ok(()) => {
+                        log::info!("{} notify success", message.event.as_str());
+                        break;
+                    }
+                    //the hook does not accept the request, retrying would not help
+                    Err(NotifyError::Status(status)) if status.is_client_error() => {
+                        log::error!("{} notify error: {}", message.event.as_str(), status);
+                        break;
+                    }
+                    Err(err) if retry_times < config.max_retries => {
+                        let delay = config.retry_backoff * 2u32.saturating_pow(retry_times);
+                        log::warn!(
+                            "{} notify error: {}, retry in {:?}",
+                            message.event.as_str(),
+                            err,
+                            delay
+                        );
+                        retry_times += 1;
+                        tokio::time::sleep(delay).await;
+                    }
+                    Err(err) => {
+                        log::error!(
+                            "{} notify error: {}, give up after {} retries",
+                            message.event.as_str(),
+                            err,
+                            retry_times
+                        );
+                        break;
+                    }
                 }
             }
         }
     }
 }
+
+/// Notify once when a published stream receives no data for the stall timeout,
+/// it is armed again by the next data.
+pub struct StallWatcher {
+    notifier: Notifier,
+    timeout: Duration,
+    data: Value,
+    stalled: bool,
+}
+
+impl StallWatcher {
+    pub fn new(notifier: &Notifier, data: Value) -> Option<Self> {
+        Some(Self {
+            notifier: notifier.clone(),
+            timeout: notifier.stall_timeout()?,
+            data,
+            stalled: false,
+        })
+    }
+
+    pub fn on_data(&mut self) {
+        self.stalled = false;
+    }
+
+    //the time to wait for the next data, none after the stall is notified
+    pub fn wait_timeout(&self) -> Option<Duration> {
+        if self.stalled {
+            None
+        } else {
+            Some(self.timeout)
+        }
+    }
+
+    pub fn on_stalled(&mut self) {
+        self.stalled = true;
+        self.notifier
+            .notify(NotifyEvent::OnStreamStalled, self.data.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign() {
+        //echo -n '{"event":"on_publish"}' | openssl dgst -sha256 -hmac secret
+        assert_eq!(
+            Notifier::sign("secret", r#"{"event":"on_publish"}"#),
+            "sha256=62ce79de5121b44d19698babe07e3cbe7eec757ea3c755623645d0450bd50804"
+        );
+    }
+
+    #[test]
+    fn test_config_url() {
+        let config = NotifierConfig {
+            on_publish_url: Some("http://127.0.0.1/hook".to_string()),
+            on_play_url: Some("".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.url(NotifyEvent::OnPublish),
+            Some("http://127.0.0.1/hook")
+        );
+        assert_eq!(config.url(NotifyEvent::OnPlay), None);
+        assert_eq!(config.url(NotifyEvent::OnStop), None);
+    }
+}
@@ -0,0 +1,418 @@
+use {
+    crate::{
+        define::{FrameData, PacketData},
+        errors::{StreamHubError, StreamHubErrorValue},
+    },
+    std::time::{Duration, Instant},
+    tokio::sync::mpsc::{self, error::TrySendError},
+    xflv::define::{aac_packet_type, avc_packet_type, frame_type, h264_nal_type},
+};
+
+const FLV_SOUND_FORMAT_AAC: u8 = 10;
+const FLV_CODEC_ID_H264: u8 = 7;
+const FLV_CODEC_ID_HEVC: u8 = 12;
+const FLV_DISPOSABLE_INTER_FRAME: u8 = 3;
+//frame type/codec id, avc packet type and composition time
+const FLV_VIDEO_HEADER_SIZE: usize = 5;
+const RTP_HEADER_SIZE: usize = 12;
+const H264_NAL_STAP_A: u8 = 24;
+const H264_NAL_FU_A: u8 = 28;
+
+/// The bounded queue settings of the subscribers.
+#[derive(Debug, Clone, Copy)]
+pub struct SubscriberQueueConfig {
+    /// the max number of frames/packets queued for a subscriber
+    pub capacity: usize,
+    /// the non-reference video frames are dropped above this queue length
+    pub drop_threshold: usize,
+    /// a subscriber staying above the drop threshold this long is disconnected
+    pub max_lag: Duration,
+}
+
+impl Default for SubscriberQueueConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 1024,
+            drop_threshold: 512,
+            max_lag: Duration::from_secs(10),
+        }
+    }
+}
+
+//how the a/v data is treated by a congested subscriber queue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaKind {
+    //the sequence headers, metadata and media info
+    Config,
+    Audio,
+    KeyFrame,
+    //a video frame referenced by the following frames
+    RefFrame,
+    NonRefFrame,
+}
+
+impl MediaKind {
+    /// The rtmp frames are flv tag bodies, the other frames are annexb h264.
+    pub fn of_frame(frame: &FrameData, is_flv: bool) -> Self {
+        match frame {
+            FrameData::Video { data, .. } if is_flv => Self::of_flv_video(data),
+            FrameData::Video { data, .. } => Self::of_annexb_h264(data),
+            FrameData::Audio { data, .. } if is_flv => Self::of_flv_audio(data),
+            FrameData::Audio { .. } => Self::Audio,
+            FrameData::MetaData { .. } | FrameData::MediaInfo { .. } => Self::Config,
+        }
+    }
+
+    /// The video rtp packets are expected to carry h264.
+    pub fn of_packet(packet: &PacketData) -> Self {
+        match packet {
+            PacketData::Video { data, .. } => Self::of_rtp_h264(data),
+            PacketData::Audio { .. } => Self::Audio,
+        }
+    }
+
+    fn of_flv_audio(data: &[u8]) -> Self {
+        match data {
+            [header, aac_packet_type::AAC_SEQHDR, ..] if header >> 4 == FLV_SOUND_FORMAT_AAC => {
+                Self::Config
+            }
+            _ => Self::Audio,
+        }
+    }
+
+    fn of_flv_video(data: &[u8]) -> Self {
+        let [header, packet_type, ..] = data else {
+            return Self::RefFrame;
+        };
+        let codec_id = header & 0x0F;
+        if matches!(codec_id, FLV_CODEC_ID_H264 | FLV_CODEC_ID_HEVC)
+            && *packet_type == avc_packet_type::AVC_SEQHDR
+        {
+            return Self::Config;
+        }
+
+        match header >> 4 {
+            frame_type::KEY_FRAME => Self::KeyFrame,
+            FLV_DISPOSABLE_INTER_FRAME => Self::NonRefFrame,
+            _ if codec_id == FLV_CODEC_ID_H264 && data.len() > FLV_VIDEO_HEADER_SIZE => {
+                Self::of_avcc_h264(&data[FLV_VIDEO_HEADER_SIZE..])
+            }
+            _ => Self::RefFrame,
+        }
+    }
+
+    //the nalus are prefixed with 4 bytes lengths
+    fn of_avcc_h264(mut data: &[u8]) -> Self {
+        let mut nal_headers = Vec::new();
+        while let [a, b, c, d, rest @ ..] = data {
+            let size = u32::from_be_bytes([*a, *b, *c, *d]) as usize;
+            if size == 0 || size > rest.len() {
+                break;
+            }
+            nal_headers.push(rest[0]);
+            data = &rest[size..];
+        }
+        Self::of_h264_nal_headers(nal_headers)
+    }
+
+    fn of_annexb_h264(data: &[u8]) -> Self {
+        let nal_headers = data
+            .windows(4)
+            .filter(|window| window[..3] == [0x00, 0x00, 0x01])
+            .map(|window| window[3]);
+        Self::of_h264_nal_headers(nal_headers)
+    }
+
+    fn of_rtp_h264(packet: &[u8]) -> Self {
+        let Some(&first) = packet.first() else {
+            return Self::RefFrame;
+        };
+        let mut offset = RTP_HEADER_SIZE + (first & 0x0F) as usize * 4;
+        //the header extension
+        if first & 0x10 != 0 {
+            let Some(words) = packet.get(offset + 2..offset + 4) else {
+                return Self::RefFrame;
+            };
+            offset += 4 + u16::from_be_bytes([words[0], words[1]]) as usize * 4;
+        }
+
+        match packet.get(offset..).unwrap_or_default() {
+            [indicator, fu_header, ..] if indicator & 0x1F == H264_NAL_FU_A => {
+                let nal_header = (indicator & 0xE0) | (fu_header & 0x1F);
+                match Self::of_h264_nal_headers([nal_header]) {
+                    //only the first fragment starts a key frame
+                    Self::KeyFrame if fu_header & 0x80 == 0 => Self::RefFrame,
+                    kind => kind,
+                }
+            }
+            [indicator, rest @ ..] if indicator & 0x1F == H264_NAL_STAP_A => {
+                let mut rest = rest;
+                let mut nal_headers = Vec::new();
+                while let [a, b, nalu @ ..] = rest {
+                    let size = u16::from_be_bytes([*a, *b]) as usize;
+                    if size == 0 || size > nalu.len() {
+                        break;
+                    }
+                    nal_headers.push(nalu[0]);
+                    rest = &nalu[size..];
+                }
+                Self::of_h264_nal_headers(nal_headers)
+            }
+            [nal_header, ..] => Self::of_h264_nal_headers([*nal_header]),
+            [] => Self::RefFrame,
+        }
+    }
+
+    //a frame with a sps or an idr slice is a key frame, otherwise it is decided
+    //by the nal_ref_idc of its first slice.
+    fn of_h264_nal_headers(nal_headers: impl IntoIterator<Item = u8>) -> Self {
+        let mut kind = Self::Config;
+        for nal_header in nal_headers {
+            match nal_header & 0x1F {
+                h264_nal_type::H264_NAL_IDR | h264_nal_type::H264_NAL_SPS => return Self::KeyFrame,
+                1..=4 if kind == Self::Config => {
+                    kind = if nal_header & 0x60 == 0 {
+                        Self::NonRefFrame
+                    } else {
+                        Self::RefFrame
+                    };
+                }
+                _ => {}
+            }
+        }
+        kind
+    }
+}
+
+/// Send the prior data (e.g. the sequence headers and the cached gops) to a new subscriber,
+/// false is returned if the queue is full and the rest of the prior data should be skipped.
+pub fn try_send_prior_data<T>(sender: &mpsc::Sender<T>, data: T) -> Result<bool, StreamHubError> {
+    match sender.try_send(data) {
+        Ok(()) => Ok(true),
+        Err(TrySendError::Full(_)) => {
+            log::warn!("send_prior_data: the prior data is truncated");
+            Ok(false)
+        }
+        Err(TrySendError::Closed(_)) => Err(StreamHubError {
+            value: StreamHubErrorValue::SendError,
+        }),
+    }
+}
+
+//The bounded queue of a subscriber. A congested queue drops the non-reference video
+//frames, a full queue drops the video until the next key frame while the audio is kept,
+//and a subscriber staying congested longer than the max lag is disconnected.
+pub struct SubscriberQueue<T> {
+    sender: mpsc::Sender<T>,
+    config: SubscriberQueueConfig,
+    //the dropped video is referenced by the following frames
+    skip_to_key_frame: bool,
+    //when the queue length reaches the drop threshold
+    lag_start: Option<Instant>,
+    //the dropped frames (or rtp packets)
+    pub dropped_video: u64,
+    pub dropped_audio: u64,
+}
+
+impl<T> SubscriberQueue<T> {
+    pub fn new(sender: mpsc::Sender<T>, config: SubscriberQueueConfig) -> Self {
+        Self {
+            sender,
+            config,
+            skip_to_key_frame: false,
+            lag_start: None,
+            dropped_video: 0,
+            dropped_audio: 0,
+        }
+    }
+
+    /// Queue the data or drop it by the policy, an error means the
+    /// subscriber is closed or lags behind and should be removed.
+    pub fn push(&mut self, data: T, kind: MediaKind) -> Result<(), StreamHubError> {
+        let queued = self.sender.max_capacity() - self.sender.capacity();
+        if queued < self.config.drop_threshold {
+            self.lag_start = None;
+        } else if self.lag_start.get_or_insert_with(Instant::now).elapsed() >= self.config.max_lag {
+            return Err(StreamHubError {
+                value: StreamHubErrorValue::SubscriberLagging,
+            });
+        }
+        let congested = self.lag_start.is_some();
+
+        let dropped = match kind {
+            //resume when the queue is not congested any more
+            MediaKind::KeyFrame => self.skip_to_key_frame && congested,
+            MediaKind::RefFrame => self.skip_to_key_frame,
+            MediaKind::NonRefFrame => self.skip_to_key_frame || congested,
+            MediaKind::Config | MediaKind::Audio => false,
+        };
+        if dropped {
+            self.count_dropped(kind);
+            return Ok(());
+        }
+        if kind == MediaKind::KeyFrame {
+            self.skip_to_key_frame = false;
+        }
+
+        match self.sender.try_send(data) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => {
+                self.count_dropped(kind);
+                if matches!(kind, MediaKind::KeyFrame | MediaKind::RefFrame) {
+                    self.skip_to_key_frame = true;
+                }
+                Ok(())
+            }
+            Err(TrySendError::Closed(_)) => Err(StreamHubError {
+                value: StreamHubErrorValue::SendError,
+            }),
+        }
+    }
+
+    fn count_dropped(&mut self, kind: MediaKind) {
+        match kind {
+            MediaKind::Audio => self.dropped_audio += 1,
+            MediaKind::KeyFrame | MediaKind::RefFrame | MediaKind::NonRefFrame => {
+                self.dropped_video += 1
+            }
+            MediaKind::Config => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+
+    fn config(max_lag: Duration) -> SubscriberQueueConfig {
+        SubscriberQueueConfig {
+            capacity: 4,
+            drop_threshold: 2,
+            max_lag,
+        }
+    }
+
+    fn push_all(queue: &mut SubscriberQueue<MediaKind>, kinds: &[MediaKind]) {
+        for kind in kinds {
+            queue.push(*kind, *kind).unwrap();
+        }
+    }
+
+    fn drain(receiver: &mut mpsc::Receiver<MediaKind>) -> Vec<MediaKind> {
+        let mut kinds = Vec::new();
+        while let Ok(kind) = receiver.try_recv() {
+            kinds.push(kind);
+        }
+        kinds
+    }
+
+    #[test]
+    fn test_try_send_prior_data() {
+        let (sender, mut receiver) = mpsc::channel(1);
+        assert!(try_send_prior_data(&sender, MediaKind::Config).unwrap());
+        assert!(!try_send_prior_data(&sender, MediaKind::Config).unwrap());
+
+        drop(receiver.try_recv());
+        drop(receiver);
+        assert!(try_send_prior_data(&sender, MediaKind::Config).is_err());
+    }
+
+    #[test]
+    fn test_media_kind() {
+        let video = |data: &[u8]| FrameData::Video {
+            timestamp: 0,
+            data: Bytes::copy_from_slice(data),
+        };
+        let flv_inter_frame =
+            |nal_header: u8| video(&[0x27, 0x01, 0, 0, 0, 0, 0, 0, 0x02, nal_header, 0x88]);
+        assert_eq!(
+            MediaKind::of_frame(&video(&[0x17, 0x00]), true),
+            MediaKind::Config
+        );
+        assert_eq!(
+            MediaKind::of_frame(&video(&[0x17, 0x01]), true),
+            MediaKind::KeyFrame
+        );
+        assert_eq!(
+            MediaKind::of_frame(&flv_inter_frame(0x41), true),
+            MediaKind::RefFrame
+        );
+        assert_eq!(
+            MediaKind::of_frame(&flv_inter_frame(0x01), true),
+            MediaKind::NonRefFrame
+        );
+
+        let annexb = video(&[
+            0, 0, 0, 1, 0x67, 0x42, 0, 0, 0, 1, 0x68, 0xce, 0, 0, 1, 0x65,
+        ]);
+        assert_eq!(MediaKind::of_frame(&annexb, false), MediaKind::KeyFrame);
+        assert_eq!(
+            MediaKind::of_frame(&video(&[0, 0, 1, 0x01, 0x9a]), false),
+            MediaKind::NonRefFrame
+        );
+
+        let rtp = |payload: &[u8]| {
+            let mut packet = vec![0x80, 0x60, 0, 1, 0, 0, 0, 0, 0, 0, 0, 1];
+            packet.extend_from_slice(payload);
+            PacketData::Video {
+                timestamp: 0,
+                data: Bytes::from(packet),
+            }
+        };
+        //stap-a with sps and pps
+        let stap_a = rtp(&[0x78, 0, 2, 0x67, 0x42, 0, 2, 0x68, 0xce]);
+        assert_eq!(MediaKind::of_packet(&stap_a), MediaKind::KeyFrame);
+        //the first and the second fragments of an idr slice
+        assert_eq!(
+            MediaKind::of_packet(&rtp(&[0x7c, 0x85, 0x88])),
+            MediaKind::KeyFrame
+        );
+        assert_eq!(
+            MediaKind::of_packet(&rtp(&[0x7c, 0x05, 0x88])),
+            MediaKind::RefFrame
+        );
+        assert_eq!(
+            MediaKind::of_packet(&rtp(&[0x1c, 0x81, 0x9a])),
+            MediaKind::NonRefFrame
+        );
+    }
+
+    #[test]
+    fn test_drop_non_ref_frames() {
+        let (sender, mut receiver) = mpsc::channel(4);
+        let mut queue = SubscriberQueue::new(sender, config(Duration::from_secs(10)));
+
+        use MediaKind::*;
+        push_all(
+            &mut queue,
+            &[KeyFrame, RefFrame, NonRefFrame, Audio, RefFrame],
+        );
+        assert_eq!(drain(&mut receiver), [KeyFrame, RefFrame, Audio, RefFrame]);
+        assert_eq!((queue.dropped_video, queue.dropped_audio), (1, 0));
+    }
+
+    #[test]
+    fn test_skip_to_key_frame() {
+        let (sender, mut receiver) = mpsc::channel(4);
+        let mut queue = SubscriberQueue::new(sender, config(Duration::from_secs(10)));
+
+        use MediaKind::*;
+        //the ref frame does not fit, the audio is still queued when there is room
+        push_all(&mut queue, &[KeyFrame, RefFrame, Audio, Audio, RefFrame]);
+        assert_eq!(drain(&mut receiver), [KeyFrame, RefFrame, Audio, Audio]);
+        push_all(&mut queue, &[RefFrame, Audio, KeyFrame, RefFrame]);
+        assert_eq!(drain(&mut receiver), [Audio, KeyFrame, RefFrame]);
+        assert_eq!((queue.dropped_video, queue.dropped_audio), (2, 0));
+    }
+
+    #[test]
+    fn test_disconnect_lagging() {
+        let (sender, _receiver) = mpsc::channel(4);
+        let mut queue = SubscriberQueue::new(sender, config(Duration::ZERO));
+
+        queue.push(0, MediaKind::Audio).unwrap();
+        queue.push(1, MediaKind::Audio).unwrap();
+        assert!(queue.push(2, MediaKind::Audio).is_err());
+    }
+}
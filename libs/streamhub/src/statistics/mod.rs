@@ -44,10 +44,23 @@ pub struct AudioInfo {
     #[serde(rename = "bitrate(kbits/s)")]
     pub bitrate: u64,
 }
+/*the rtp reception of a webrtc publisher, or the one reported back by a webrtc player*/
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct RtpStatistics {
+    pub packets_lost: i64,
+    #[serde(rename = "fraction_lost(%)")]
+    pub fraction_lost: f64,
+    #[serde(rename = "jitter(ms)")]
+    pub jitter: f64,
+    #[serde(rename = "rtt(ms)")]
+    pub rtt: Option<f64>,
+}
 #[derive(Debug, Clone, Serialize, Default)]
 pub struct StatisticsStream {
     /*publisher infomation */
     pub publisher: StatisticPublisher,
+    /*published by a remuxer of this server, not by a client*/
+    pub remuxed: bool,
     /*subscriber infomation */
     pub subscribers: HashMap<Uuid, StatisticSubscriber>,
     /*How many clients are subscribing to this stream.*/
@@ -71,6 +84,8 @@ pub struct StatisticPublisher {
     /*the bitrate at which the server receives streaming data*/
     #[serde(rename = "recv_bitrate(kbits/s)")]
     pub recv_bitrate: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rtp: Option<RtpStatistics>,
 }
 
 impl StatisticPublisher {
@@ -87,6 +102,8 @@ pub struct StatisticSubscriber {
     pub start_time: DateTime<Local>,
     pub remote_address: String,
     pub sub_type: SubscribeType,
+    /*a remote player, not a remuxer, hls or recorder of this server*/
+    pub player: bool,
     /*used for caculate the send_bitrate*/
     #[serde(skip_serializing)]
     pub send_bytes: u64,
@@ -95,12 +112,18 @@ pub struct StatisticSubscriber {
     pub send_bitrate: u64,
     #[serde(rename = "total_send_bytes(kbits/s)")]
     pub total_send_bytes: u64,
+    /*the frames(or rtp packets) dropped by the subscriber queue*/
+    pub dropped_video: u64,
+    pub dropped_audio: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rtp: Option<RtpStatistics>,
 }
 
 impl StatisticsStream {
-    pub fn new(identifier: StreamIdentifier) -> Self {
+    pub fn new(identifier: StreamIdentifier, remuxed: bool) -> Self {
         Self {
             publisher: StatisticPublisher::new(identifier),
+            remuxed,
             ..Default::default()
         }
     }
@@ -47,3 +47,92 @@ impl fmt::Display for StreamIdentifier {
         }
     }
 }
+
+impl StreamIdentifier {
+    /// The protocol independent key of the stream, the rtsp path is split into
+    /// the app name and the stream name at the first slash.
+    pub fn stream_key(&self) -> StreamKey {
+        match self {
+            StreamIdentifier::Rtmp {
+                app_name,
+                stream_name,
+            }
+            | StreamIdentifier::WebRTC {
+                app_name,
+                stream_name,
+            } => StreamKey::new(app_name, stream_name),
+            StreamIdentifier::Rtsp { stream_path } => match stream_path.split_once('/') {
+                Some((app_name, stream_name)) => StreamKey::new(app_name, stream_name),
+                None => StreamKey::new("rtsp", stream_path),
+            },
+            StreamIdentifier::Unkonwn => StreamKey::default(),
+        }
+    }
+}
+
+//The same key is shared by all the protocols, a stream published as `live/foo`
+//over one protocol is played as `live/foo` over the others.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize, Default)]
+pub struct StreamKey {
+    pub app_name: String,
+    pub stream_name: String,
+}
+
+impl StreamKey {
+    pub fn new(app_name: &str, stream_name: &str) -> Self {
+        Self {
+            app_name: app_name.to_string(),
+            stream_name: stream_name.to_string(),
+        }
+    }
+
+    pub fn rtmp_identifier(&self) -> StreamIdentifier {
+        StreamIdentifier::Rtmp {
+            app_name: self.app_name.clone(),
+            stream_name: self.stream_name.clone(),
+        }
+    }
+
+    pub fn rtsp_identifier(&self) -> StreamIdentifier {
+        StreamIdentifier::Rtsp {
+            stream_path: self.to_string(),
+        }
+    }
+
+    pub fn webrtc_identifier(&self) -> StreamIdentifier {
+        StreamIdentifier::WebRTC {
+            app_name: self.app_name.clone(),
+            stream_name: self.stream_name.clone(),
+        }
+    }
+}
+
+impl fmt::Display for StreamKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.app_name, self.stream_name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_key() {
+        let key = StreamKey::new("live", "foo");
+        let rtsp = |stream_path: &str| StreamIdentifier::Rtsp {
+            stream_path: stream_path.to_string(),
+        };
+
+        assert_eq!(key.rtmp_identifier().stream_key(), key);
+        assert_eq!(key.webrtc_identifier().stream_key(), key);
+        assert_eq!(key.rtsp_identifier().stream_key(), key);
+        assert_eq!(rtsp("live/foo").stream_key(), key);
+        assert_eq!(rtsp("foo").stream_key(), StreamKey::new("rtsp", "foo"));
+        assert_eq!(
+            rtsp("live/foo/bar").stream_key(),
+            StreamKey::new("live", "foo/bar")
+        );
+        assert_eq!(key.to_string(), "live/foo");
+    }
+}
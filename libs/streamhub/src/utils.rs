@@ -1,6 +1,9 @@
+use crate::define::{BroadcastEvent, BroadcastEventReceiver};
 use serde::{Serialize, Serializer};
 use std::fmt;
+use std::path::{Component, Path};
 use std::str::FromStr;
+use tokio::sync::broadcast::error::RecvError;
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Default, Copy)]
 pub struct Uuid {
@@ -36,10 +39,38 @@ impl Serialize for Uuid {
     }
 }
 
+/// Whether a name from a client, e.g. an app or stream name, is exactly one normal
+/// path component, so that it can not point outside the directory it is joined to.
+pub fn is_path_component(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+    !name.contains(['/', '\\'])
+        && matches!(
+            (components.next(), components.next()),
+            (Some(Component::Normal(_)), None)
+        )
+}
+
+/// Receive the next hub event, the events missed by a receiver lagging behind
+/// are skipped and only an error for the closed hub is returned.
+pub async fn recv_broadcast_event(
+    receiver: &mut BroadcastEventReceiver,
+) -> Result<BroadcastEvent, RecvError> {
+    loop {
+        match receiver.recv().await {
+            Err(RecvError::Lagged(count)) => {
+                log::warn!("lagged behind the stream hub, {} events are skipped", count);
+            }
+            result => return result,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
-    use super::Uuid;
+    use super::{is_path_component, recv_broadcast_event, Uuid};
+    use crate::{define::BroadcastEvent, stream::StreamIdentifier};
+    use tokio::sync::broadcast::{self, error::RecvError};
 
     #[test]
     fn test_uuid() {
@@ -57,4 +88,39 @@ mod tests {
             println!("{:?}", u.to_string());
         }
     }
+
+    #[tokio::test]
+    async fn test_recv_broadcast_event() {
+        let (sender, mut receiver) = broadcast::channel(1);
+        for stream_name in ["a", "b"] {
+            let identifier = StreamIdentifier::Rtmp {
+                app_name: String::from("live"),
+                stream_name: String::from(stream_name),
+            };
+            let event = BroadcastEvent::UnPublish { identifier };
+            assert!(sender.send(event).is_ok());
+        }
+        drop(sender);
+
+        //the first event is missed, the second one is received
+        assert!(matches!(
+            recv_broadcast_event(&mut receiver).await,
+            Ok(BroadcastEvent::UnPublish {
+                identifier: StreamIdentifier::Rtmp { stream_name, .. }
+            }) if stream_name == "b"
+        ));
+        assert!(matches!(
+            recv_broadcast_event(&mut receiver).await,
+            Err(RecvError::Closed)
+        ));
+    }
+
+    #[test]
+    fn test_is_path_component() {
+        assert!(is_path_component("live"));
+        assert!(is_path_component("test.stream"));
+        for name in ["", ".", "..", "../x", "a/b", "a\\b", "/x", "x/"] {
+            assert!(!is_path_component(name), "{}", name);
+        }
+    }
 }
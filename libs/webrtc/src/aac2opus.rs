@@ -0,0 +1,258 @@
+use crate::errors::Aac2OpusError;
+use audiopus::coder::Encoder as OpusEncoder;
+use fdk_aac::dec::{Decoder as AacDecoder, DecoderError, Transport};
+
+pub const OPUS_SAMPLE_RATE: u32 = 48000;
+//20ms frames, the samples of one channel
+const OPUS_FRAME_SAMPLES: usize = OPUS_SAMPLE_RATE as usize / 50;
+const OPUS_CHANNELS: usize = 2;
+//large enough for a HE-AAC frame (2048 samples) of the downmixed stereo
+const AAC_PCM_BUFFER_SIZE: usize = 2048 * 8;
+const OPUS_PACKET_MAX_SIZE: usize = 1500;
+
+//Linear interpolation of the interleaved stereo samples, the last input
+//sample of every channel is kept to interpolate across the frames.
+struct Resampler {
+    step: f64,
+    position: f64,
+    previous: [i16; OPUS_CHANNELS],
+}
+
+impl Resampler {
+    fn new(input_sample_rate: u32) -> Self {
+        Self {
+            step: input_sample_rate as f64 / OPUS_SAMPLE_RATE as f64,
+            position: 0.0,
+            previous: [0; OPUS_CHANNELS],
+        }
+    }
+
+    fn process(&mut self, input: &[i16], output: &mut Vec<i16>) {
+        let frames = input.len() / OPUS_CHANNELS;
+        if frames == 0 {
+            return;
+        }
+        //the position -1 is the previous sample
+        let sample = |idx: isize, channel: usize| {
+            if idx < 0 {
+                self.previous[channel] as f64
+            } else {
+                input[idx as usize * OPUS_CHANNELS + channel] as f64
+            }
+        };
+
+        while self.position < (frames - 1) as f64 {
+            let idx = self.position.floor();
+            let frac = self.position - idx;
+            for channel in 0..OPUS_CHANNELS {
+                let (current, next) = (
+                    sample(idx as isize, channel),
+                    sample(idx as isize + 1, channel),
+                );
+                output.push((current + (next - current) * frac).round() as i16);
+            }
+            self.position += self.step;
+        }
+
+        self.position -= frames as f64;
+        self.previous
+            .copy_from_slice(&input[(frames - 1) * OPUS_CHANNELS..frames * OPUS_CHANNELS]);
+    }
+}
+
+//Decode the AAC-LC/HE-AAC adts frames, resample the pcm to 48kHz stereo
+//and encode it into 20ms opus packets.
+pub struct Aac2OpusTranscoder {
+    decoder: AacDecoder,
+    encoder: OpusEncoder,
+    decoder_sample_rate: u32,
+    resampler: Resampler,
+    pcm_data: Vec<i16>,
+    //the rtp timestamp of the first sample in pcm_data
+    timestamp: Option<u32>,
+}
+
+impl Aac2OpusTranscoder {
+    pub fn new() -> Result<Self, Aac2OpusError> {
+        let mut decoder = AacDecoder::new(Transport::Adts);
+        //multichannel and parametric stereo streams are downmixed to stereo
+        decoder.set_max_output_channels(OPUS_CHANNELS)?;
+        let encoder = OpusEncoder::new(
+            audiopus::SampleRate::Hz48000,
+            audiopus::Channels::Stereo,
+            audiopus::Application::Audio,
+        )?;
+
+        Ok(Aac2OpusTranscoder {
+            decoder,
+            encoder,
+            decoder_sample_rate: OPUS_SAMPLE_RATE,
+            resampler: Resampler::new(OPUS_SAMPLE_RATE),
+            pcm_data: Vec::new(),
+            timestamp: None,
+        })
+    }
+
+    fn resample(&mut self, pcm_output: &[i16]) {
+        let stream_info = self.decoder.stream_info();
+        let (sample_rate, channels) = (stream_info.sampleRate as u32, stream_info.numChannels);
+        let pcm_output = &pcm_output[..self.decoder.decoded_frame_size()];
+
+        if sample_rate != self.decoder_sample_rate {
+            self.decoder_sample_rate = sample_rate;
+            self.resampler = Resampler::new(sample_rate);
+        }
+        if channels == 1 {
+            let stereo: Vec<i16> = pcm_output.iter().flat_map(|&s| [s, s]).collect();
+            self.resampler.process(&stereo, &mut self.pcm_data);
+        } else {
+            self.resampler.process(pcm_output, &mut self.pcm_data);
+        }
+    }
+
+    /// Transcode an adts frame, the timestamp (in milliseconds) of the first frame
+    /// is the base of the rtp timestamps, which then advance by the encoded samples.
+    pub fn transcode(
+        &mut self, input: &[u8], timestamp: u32,
+    ) -> Result<Vec<(u32, Vec<u8>)>, Aac2OpusError> {
+        let mut rtp_timestamp = *self
+            .timestamp
+            .get_or_insert(timestamp.wrapping_mul(OPUS_SAMPLE_RATE / 1000));
+
+        let mut pcm_output: Vec<i16> = vec![0; AAC_PCM_BUFFER_SIZE];
+        self.decoder.fill(input)?;
+        //the decoder may keep the frames until it gets enough bits
+        loop {
+            match self.decoder.decode_frame(&mut pcm_output) {
+                Ok(()) => self.resample(&pcm_output),
+                Err(err) if err == DecoderError::NOT_ENOUGH_BITS => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        let mut result = Vec::new();
+        let mut opus_output: Vec<u8> = vec![0; OPUS_PACKET_MAX_SIZE];
+        while self.pcm_data.len() >= OPUS_FRAME_SAMPLES * OPUS_CHANNELS {
+            let pcm = self.pcm_data.split_off(OPUS_FRAME_SAMPLES * OPUS_CHANNELS);
+            let size = self.encoder.encode(&self.pcm_data, &mut opus_output)?;
+            self.pcm_data = pcm;
+
+            result.push((rtp_timestamp, opus_output[..size].to_vec()));
+            rtp_timestamp = rtp_timestamp.wrapping_add(OPUS_FRAME_SAMPLES as u32);
+        }
+        self.timestamp = Some(rtp_timestamp);
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use audiopus::{coder::Decoder as OpusDecoder, MutSignals};
+    use fdk_aac::enc::{
+        AudioObjectType, BitRate, ChannelMode, Encoder as AacEncoder, EncoderParams,
+    };
+
+    //one second of a 1kHz stereo sine wave encoded to adts frames
+    fn encode_sine(sample_rate: u32, audio_object_type: AudioObjectType) -> Vec<Vec<u8>> {
+        let encoder = AacEncoder::new(EncoderParams {
+            bit_rate: BitRate::Cbr(64000),
+            sample_rate,
+            transport: fdk_aac::enc::Transport::Adts,
+            channels: ChannelMode::Stereo,
+            audio_object_type,
+        })
+        .unwrap();
+        let frame_length = encoder.info().unwrap().frameLength as usize;
+
+        let pcm: Vec<i16> = (0..sample_rate as usize)
+            .flat_map(|idx| {
+                let phase = 2.0 * std::f64::consts::PI * 1000.0 * idx as f64 / sample_rate as f64;
+                let sample = (phase.sin() * 10000.0) as i16;
+                [sample, sample]
+            })
+            .collect();
+
+        let mut frames = Vec::new();
+        let mut output = vec![0; 4096];
+        for chunk in pcm.chunks(frame_length * 2) {
+            let info = encoder.encode(chunk, &mut output).unwrap();
+            if info.output_size > 0 {
+                frames.push(output[..info.output_size].to_vec());
+            }
+        }
+        frames
+    }
+
+    //transcode the adts frames and decode the opus packets back to pcm
+    fn round_trip(frames: Vec<Vec<u8>>) -> (Vec<u32>, Vec<i16>) {
+        let mut transcoder = Aac2OpusTranscoder::new().unwrap();
+        let mut opus_decoder =
+            OpusDecoder::new(audiopus::SampleRate::Hz48000, audiopus::Channels::Stereo).unwrap();
+
+        let (mut timestamps, mut pcm) = (Vec::new(), Vec::new());
+        for (idx, frame) in frames.iter().enumerate() {
+            for (timestamp, packet) in transcoder.transcode(frame, 100 + idx as u32).unwrap() {
+                let mut output = vec![0_i16; OPUS_FRAME_SAMPLES * OPUS_CHANNELS];
+                let packet = audiopus::packet::Packet::try_from(&packet[..]).unwrap();
+                let signals = MutSignals::try_from(&mut output).unwrap();
+                let samples = opus_decoder.decode(Some(packet), signals, false).unwrap();
+                assert_eq!(samples, OPUS_FRAME_SAMPLES);
+
+                timestamps.push(timestamp);
+                pcm.extend_from_slice(&output);
+            }
+        }
+        (timestamps, pcm)
+    }
+
+    //count the rising zero crossings of the left channel in the middle half second,
+    //so the codec delays and the ramp up are skipped
+    fn frequency(pcm: &[i16]) -> usize {
+        let left: Vec<i16> = pcm.iter().step_by(OPUS_CHANNELS).copied().collect();
+        let middle = &left[left.len() / 2 - 12000..left.len() / 2 + 12000];
+        middle.windows(2).filter(|w| w[0] < 0 && w[1] >= 0).count() * 2
+    }
+
+    fn assert_round_trip(sample_rate: u32, audio_object_type: AudioObjectType) {
+        let (timestamps, pcm) = round_trip(encode_sine(sample_rate, audio_object_type));
+
+        //about one second of 20ms packets, less the frames buffered in the codecs
+        assert!(
+            timestamps.len() > 40 && timestamps.len() <= 50,
+            "{}",
+            timestamps.len()
+        );
+        assert_eq!(timestamps[0], 100 * 48);
+        for w in timestamps.windows(2) {
+            assert_eq!(w[1] - w[0], OPUS_FRAME_SAMPLES as u32);
+        }
+        let hz = frequency(&pcm);
+        assert!((990..=1010).contains(&hz), "{}", hz);
+    }
+
+    #[test]
+    fn test_aac_lc_2_opus() {
+        assert_round_trip(44100, AudioObjectType::Mpeg4LowComplexity);
+        assert_round_trip(48000, AudioObjectType::Mpeg4LowComplexity);
+    }
+
+    #[test]
+    fn test_he_aac_2_opus() {
+        assert_round_trip(44100, AudioObjectType::Mpeg4HeAac);
+    }
+
+    #[test]
+    fn test_resampler() {
+        let mut resampler = Resampler::new(24000);
+        let mut output = Vec::new();
+        resampler.process(&[0, 0, 100, -100], &mut output);
+        resampler.process(&[200, -200, 300, -300], &mut output);
+        //the output is continuous across the input frames
+        assert_eq!(
+            output,
+            [0, 0, 50, -50, 100, -100, 150, -150, 200, -200, 250, -250]
+        );
+    }
+}
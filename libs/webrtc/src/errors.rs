@@ -1,7 +1,7 @@
 use {
     audiopus::error::Error as OpusError,
     failure::{Backtrace, Fail},
-    fdk_aac::enc::EncoderError as AacEncoderError,
+    fdk_aac::{dec::DecoderError as AacDecoderError, enc::EncoderError as AacEncoderError},
     std::fmt,
     std::num::ParseIntError,
     webrtc::error::Error as RTCError,
@@ -98,6 +98,42 @@ impl From<AacEncoderError> for Opus2AacError {
     }
 }
 
+#[derive(Debug)]
+pub struct Aac2OpusError {
+    pub value: Aac2OpusErrorValue,
+}
+
+#[derive(Debug)]
+pub enum Aac2OpusErrorValue {
+    OpusError(OpusError),
+    AacDecoderError(AacDecoderError),
+}
+
+impl From<OpusError> for Aac2OpusError {
+    fn from(error: OpusError) -> Self {
+        Aac2OpusError {
+            value: Aac2OpusErrorValue::OpusError(error),
+        }
+    }
+}
+
+impl From<AacDecoderError> for Aac2OpusError {
+    fn from(error: AacDecoderError) -> Self {
+        Aac2OpusError {
+            value: Aac2OpusErrorValue::AacDecoderError(error),
+        }
+    }
+}
+
+impl fmt::Display for Aac2OpusError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.value {
+            Aac2OpusErrorValue::OpusError(err) => write!(f, "opus error: {}", err),
+            Aac2OpusErrorValue::AacDecoderError(err) => write!(f, "aac decoder error: {}", err),
+        }
+    }
+}
+
 // impl fmt::Display for Opus2AacError {
 //     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 //         fmt::Display::fmt(&self.value, f)
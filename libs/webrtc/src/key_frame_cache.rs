@@ -0,0 +1,229 @@
+use {
+    bytes::Bytes,
+    webrtc::{
+        rtp::packet::Packet as RtpPacket,
+        util::{Marshal, Unmarshal},
+    },
+    xflv::define::h264_nal_type::{H264_NAL_IDR, H264_NAL_PPS, H264_NAL_SPS},
+};
+
+const H264_NAL_STAP_A: u8 = 24;
+const H264_NAL_FU_A: u8 = 28;
+
+//The h264 rtp packets of the latest key frame and parameter sets of a stream. They are
+//sent to the new whep subscribers before the live packets, so the picture starts at once
+//instead of at the next key frame of the publisher.
+//The live P-frames following the replay may reference the frames between the cached key
+//frame and the subscription, which the subscriber never received. Its decoder shows
+//artifacts until the next key frame, which it asks for with a PLI forwarded to the publisher.
+#[derive(Default)]
+pub struct KeyFrameCache {
+    parameter_sets: Vec<Bytes>,
+    parameter_sets_timestamp: u32,
+    key_frame: Vec<Bytes>,
+    //the key frame being received, it is dropped if a packet is lost
+    pending: Vec<Bytes>,
+    pending_timestamp: Option<u32>,
+    pending_lost: bool,
+    next_sequence_number: Option<u16>,
+    //the last packet of the latest completed frame
+    last_sequence_number: u16,
+    last_timestamp: u32,
+}
+
+impl KeyFrameCache {
+    pub fn save(&mut self, data: &Bytes) {
+        let packet = match RtpPacket::unmarshal(&mut data.clone()) {
+            Ok(packet) => packet,
+            Err(err) => {
+                log::warn!("key frame cache unmarshal rtp packet error: {}", err);
+                return;
+            }
+        };
+        let header = &packet.header;
+
+        let lost = self
+            .next_sequence_number
+            .is_some_and(|next| next != header.sequence_number);
+        self.next_sequence_number = Some(header.sequence_number.wrapping_add(1));
+
+        let nal_types = h264_nal_types(&packet.payload);
+        if !nal_types.is_empty()
+            && nal_types
+                .iter()
+                .all(|nal_type| matches!(*nal_type, H264_NAL_SPS | H264_NAL_PPS))
+        {
+            if self.parameter_sets_timestamp != header.timestamp {
+                self.parameter_sets.clear();
+                self.parameter_sets_timestamp = header.timestamp;
+            }
+            self.parameter_sets.push(data.clone());
+        } else if nal_types.contains(&H264_NAL_IDR) {
+            if self.pending_timestamp != Some(header.timestamp) {
+                self.pending.clear();
+                self.pending_timestamp = Some(header.timestamp);
+                self.pending_lost = false;
+            }
+            self.pending_lost |= lost;
+            self.pending.push(data.clone());
+
+            if header.marker {
+                if !self.pending_lost {
+                    self.key_frame = std::mem::take(&mut self.pending);
+                }
+                self.pending.clear();
+                self.pending_timestamp = None;
+            }
+        }
+
+        if header.marker {
+            self.last_sequence_number = header.sequence_number;
+            self.last_timestamp = header.timestamp;
+        }
+    }
+
+    /// The cached packets renumbered to end at the latest completed frame, so the
+    /// live packets following them stay contiguous for a new subscriber.
+    pub fn packets(&self) -> Vec<(u32, Bytes)> {
+        if self.key_frame.is_empty() {
+            return Vec::new();
+        }
+
+        let cached: Vec<&Bytes> = self
+            .parameter_sets
+            .iter()
+            .chain(self.key_frame.iter())
+            .collect();
+        let count = cached.len() as u16;
+
+        cached
+            .into_iter()
+            .enumerate()
+            .filter_map(|(idx, data)| {
+                let mut packet = RtpPacket::unmarshal(&mut data.clone()).ok()?;
+                packet.header.sequence_number = self
+                    .last_sequence_number
+                    .wrapping_sub(count - 1 - idx as u16);
+                packet.header.timestamp = self.last_timestamp;
+                match packet.marshal() {
+                    Ok(data) => Some((self.last_timestamp, data)),
+                    Err(err) => {
+                        log::warn!("key frame cache marshal rtp packet error: {}", err);
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+//the types of the nalus carried by a h264 rtp payload, a fragmented
+//nalu has the type in the fu header of every fragment.
+fn h264_nal_types(payload: &[u8]) -> Vec<u8> {
+    match payload {
+        [indicator, fu_header, ..] if indicator & 0x1F == H264_NAL_FU_A => {
+            vec![fu_header & 0x1F]
+        }
+        [indicator, rest @ ..] if indicator & 0x1F == H264_NAL_STAP_A => {
+            let mut rest = rest;
+            let mut nal_types = Vec::new();
+            while let [a, b, nalu @ ..] = rest {
+                let size = u16::from_be_bytes([*a, *b]) as usize;
+                if size == 0 || size > nalu.len() {
+                    break;
+                }
+                nal_types.push(nalu[0] & 0x1F);
+                rest = &nalu[size..];
+            }
+            nal_types
+        }
+        [nal_header, ..] => vec![nal_header & 0x1F],
+        [] => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::remuxer::rtmp2webrtc::RtpPacketizer;
+    use webrtc::rtp::codecs::h264::H264Payloader;
+
+    fn frame(nal_header: u8, size: usize) -> Bytes {
+        let mut frame = Vec::new();
+        if nal_header & 0x1F == H264_NAL_IDR {
+            for nalu in [[0x67, 0x42, 0xc0, 0x1f], [0x68, 0xce, 0x3c, 0x80]] {
+                frame.extend_from_slice(&[0, 0, 0, 1]);
+                frame.extend_from_slice(&nalu);
+            }
+        }
+        frame.extend_from_slice(&[0, 0, 0, 1, nal_header]);
+        frame.extend((0..size).map(|idx| (idx % 250 + 1) as u8));
+        Bytes::from(frame)
+    }
+
+    fn header(data: &Bytes) -> webrtc::rtp::header::Header {
+        RtpPacket::unmarshal(&mut data.clone()).unwrap().header
+    }
+
+    #[test]
+    fn test_key_frame_cache() {
+        let mut packetizer = RtpPacketizer::new(Box::<H264Payloader>::default(), 96);
+        let mut cache = KeyFrameCache::default();
+
+        let key_frame = packetizer.packetize(&frame(0x65, 3000), 3000).unwrap();
+        let mut last = None;
+        for data in &key_frame {
+            cache.save(data);
+        }
+        for timestamp in [6000, 9000] {
+            for data in packetizer.packetize(&frame(0x41, 2000), timestamp).unwrap() {
+                cache.save(&data);
+                last = Some(header(&data));
+            }
+        }
+        let last = last.unwrap();
+
+        let packets = cache.packets();
+        assert_eq!(packets.len(), key_frame.len());
+        for (idx, (timestamp, data)) in packets.iter().enumerate() {
+            let packet = RtpPacket::unmarshal(&mut data.clone()).unwrap();
+            let original = RtpPacket::unmarshal(&mut key_frame[idx].clone()).unwrap();
+            assert_eq!(*timestamp, last.timestamp);
+            assert_eq!(packet.header.timestamp, last.timestamp);
+            assert_eq!(
+                packet.header.sequence_number,
+                last.sequence_number
+                    .wrapping_sub((packets.len() - 1 - idx) as u16)
+            );
+            assert_eq!(packet.payload, original.payload);
+        }
+    }
+
+    #[test]
+    fn test_key_frame_cache_packet_lost() {
+        let mut packetizer = RtpPacketizer::new(Box::<H264Payloader>::default(), 96);
+        let mut cache = KeyFrameCache::default();
+        assert!(cache.packets().is_empty());
+
+        let first = packetizer.packetize(&frame(0x65, 3000), 3000).unwrap();
+        for data in &first {
+            cache.save(data);
+        }
+        //a fragment of the next key frame is lost, the first one is kept
+        let mut second = packetizer.packetize(&frame(0x65, 4000), 6000).unwrap();
+        second.remove(2);
+        for data in &second {
+            cache.save(data);
+        }
+
+        let packets = cache.packets();
+        assert_eq!(packets.len(), first.len());
+        let payload = RtpPacket::unmarshal(&mut packets[0].1.clone())
+            .unwrap()
+            .payload;
+        assert_eq!(
+            payload,
+            RtpPacket::unmarshal(&mut first[0].clone()).unwrap().payload
+        );
+    }
+}
@@ -1,10 +1,16 @@
 pub mod errors;
+pub mod metrics;
 // pub mod http;
 pub mod session;
 pub mod webrtc;
 pub mod whep;
 pub mod whip;
+pub mod aac2opus;
 pub mod opus2aac;
+pub mod remuxer;
 pub mod rtp_queue;
+pub mod rtp2frame;
+pub mod key_frame_cache;
+pub mod statistics;
 
 pub mod webrtc_interceptors;
\ No newline at end of file
@@ -0,0 +1,8 @@
+//names of the metrics recorded through the `metrics` facade,
+//they are exported by the recorder installed by the application.
+
+//gauge, labels: kind = whip | whep
+pub const PEER_CONNECTIONS: &str = "webrtc_peer_connections";
+//counter, the peer connections that failed (ICE / DTLS) before they were connected,
+//labels: kind = whip | whep
+pub const HANDSHAKE_FAILURES: &str = "webrtc_handshake_failures_total";
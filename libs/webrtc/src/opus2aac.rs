@@ -2,6 +2,14 @@ use crate::errors::Opus2AacError;
 use audiopus::coder::Decoder as OpusDecoder;
 use audiopus::MutSignals;
 use fdk_aac::enc::{AudioObjectType, Encoder as AacEncoder, EncoderParams};
+
+//the samples of one channel in the longest (120ms) opus packet
+const OPUS_MAX_FRAME_SAMPLES: usize = 5760;
+//the samples of one channel in an AAC-LC frame
+const AAC_FRAME_SAMPLES: usize = 1024;
+
+//Decode the opus packets and encode the pcm into raw AAC-LC frames,
+//which can be muxed into rtmp/flv and hls.
 pub struct Opus2AacTranscoder {
     decoder_channels_size: usize,
     decoder: OpusDecoder,
@@ -11,9 +19,7 @@ pub struct Opus2AacTranscoder {
 
 impl Opus2AacTranscoder {
     pub fn new(
-        decoder_sample_rate: i32,
-        decoder_channels: audiopus::Channels,
-        encoder_sample_rate: u32,
+        decoder_sample_rate: i32, decoder_channels: audiopus::Channels, encoder_sample_rate: u32,
         encoder_channels: fdk_aac::enc::ChannelMode,
     ) -> Result<Self, Opus2AacError> {
         let decoder = OpusDecoder::new(
@@ -25,7 +31,7 @@ impl Opus2AacTranscoder {
             transport: fdk_aac::enc::Transport::Raw,
             channels: encoder_channels,
             sample_rate: encoder_sample_rate,
-            audio_object_type: AudioObjectType::Mpeg4LowDelay,
+            audio_object_type: AudioObjectType::Mpeg4LowComplexity,
         })?;
 
         let decoder_channels_size = match decoder_channels {
@@ -43,7 +49,7 @@ impl Opus2AacTranscoder {
 
     pub fn transcode(&mut self, input: &[u8]) -> Result<Vec<Vec<u8>>, Opus2AacError> {
         //https://opus-codec.org/docs/opus_api-1.1.2/group__opus__decoder.html#ga7d1111f64c36027ddcb81799df9b3fc9
-        let mut pcm_output: Vec<i16> = vec![0; 1024 * 2];
+        let mut pcm_output: Vec<i16> = vec![0; OPUS_MAX_FRAME_SAMPLES * self.decoder_channels_size];
         let input_packet = audiopus::packet::Packet::try_from(input)?;
         let mut_signals = MutSignals::try_from(&mut pcm_output)?;
         let pcm_output_len = self
@@ -54,8 +60,10 @@ impl Opus2AacTranscoder {
 
         let mut aac_output: Vec<u8> = vec![0; 1024 * 2];
         let mut result = Vec::new();
-        while self.pcm_data.len() >= 1024 * 2 {
-            let pcm = self.pcm_data.split_off(2048);
+        //the encoder expects a whole frame of interleaved samples
+        let frame_size = AAC_FRAME_SAMPLES * self.decoder_channels_size;
+        while self.pcm_data.len() >= frame_size {
+            let pcm = self.pcm_data.split_off(frame_size);
             let encoder_info = self.encoder.encode(&self.pcm_data, &mut aac_output)?;
             self.pcm_data = pcm;
             if encoder_info.output_size > 0 {
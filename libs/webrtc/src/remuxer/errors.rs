@@ -0,0 +1,72 @@
+use {
+    crate::errors::Aac2OpusError,
+    std::fmt,
+    streamhub::errors::StreamHubError,
+    thiserror::Error,
+    tokio::sync::{broadcast::error::RecvError, oneshot::error::RecvError as OneshotRecvError},
+    webrtc::{rtp::Error as RtpError, util::Error as RTCUtilError},
+    xflv::errors::FlvDemuxerError,
+};
+
+#[derive(Debug, Error)]
+pub struct WebRTCRemuxerError {
+    pub value: WebRTCRemuxerErrorValue,
+}
+
+#[derive(Debug, Error)]
+pub enum WebRTCRemuxerErrorValue {
+    #[error("receive error: {0}")]
+    RecvError(#[from] RecvError),
+    #[error("tokio: oneshot receiver err: {0}")]
+    OneshotRecvError(#[from] OneshotRecvError),
+    #[error("event execute error: {0}")]
+    ChannelError(StreamHubError),
+    #[error("flv demuxer error: {0}")]
+    FlvDemuxerError(FlvDemuxerError),
+    #[error("aac2opus error: {0}")]
+    Aac2OpusError(Aac2OpusError),
+    #[error("rtp error: {0}")]
+    RtpError(#[from] RtpError),
+    #[error("webrtc util error: {0}")]
+    RTCUtilError(#[from] RTCUtilError),
+    #[error("stream hub event send error")]
+    StreamHubEventSendErr,
+    #[error("cannot get the packet sender from stream hub")]
+    NoPacketSender,
+    #[error("cannot get the frame receiver from stream hub")]
+    NoFrameReceiver,
+    #[error("unsupported video codec id {0}, only avc can be remuxed to webrtc")]
+    UnsupportedVideoCodec(u8),
+}
+
+impl From<StreamHubError> for WebRTCRemuxerErrorValue {
+    fn from(value: StreamHubError) -> Self {
+        WebRTCRemuxerErrorValue::ChannelError(value)
+    }
+}
+
+impl From<FlvDemuxerError> for WebRTCRemuxerErrorValue {
+    fn from(value: FlvDemuxerError) -> Self {
+        WebRTCRemuxerErrorValue::FlvDemuxerError(value)
+    }
+}
+
+impl From<Aac2OpusError> for WebRTCRemuxerErrorValue {
+    fn from(value: Aac2OpusError) -> Self {
+        WebRTCRemuxerErrorValue::Aac2OpusError(value)
+    }
+}
+
+impl fmt::Display for WebRTCRemuxerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.value, f)
+    }
+}
+
+impl<E: Into<WebRTCRemuxerErrorValue>> From<E> for WebRTCRemuxerError {
+    fn from(value: E) -> Self {
+        WebRTCRemuxerError {
+            value: value.into(),
+        }
+    }
+}
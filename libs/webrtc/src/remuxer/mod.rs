@@ -0,0 +1,62 @@
+pub mod errors;
+pub mod rtmp2webrtc;
+
+use streamhub::{
+    define::{BroadcastEvent, BroadcastEventReceiver, StreamHubEventSender},
+    stream::StreamIdentifier,
+    utils::recv_broadcast_event,
+};
+
+use self::{errors::WebRTCRemuxerError, rtmp2webrtc::Rtmp2WebRTCRemuxerSession};
+
+//Receive remux event from stream hub and remux the rtmp
+//streams to rtp packets for whep players on demand
+pub struct WebRTCRemuxer {
+    receiver: BroadcastEventReceiver,
+    event_producer: StreamHubEventSender,
+}
+
+impl WebRTCRemuxer {
+    pub fn new(receiver: BroadcastEventReceiver, event_producer: StreamHubEventSender) -> Self {
+        Self {
+            receiver,
+            event_producer,
+        }
+    }
+
+    pub async fn run(&mut self) -> Result<(), WebRTCRemuxerError> {
+        log::info!("webrtc remuxer start...");
+
+        loop {
+            let val = recv_broadcast_event(&mut self.receiver).await?;
+            //the rtmp representation is subscribed, which is remuxed
+            //on demand too if the source is not published over rtmp
+            if let BroadcastEvent::Remux {
+                target:
+                    StreamIdentifier::WebRTC {
+                        app_name,
+                        stream_name,
+                    },
+                ..
+            } = val
+            {
+                let mut session = match Rtmp2WebRTCRemuxerSession::new(
+                    app_name,
+                    stream_name,
+                    self.event_producer.clone(),
+                ) {
+                    Ok(session) => session,
+                    Err(err) => {
+                        log::error!("create rtmp2webrtc session error: {}", err);
+                        continue;
+                    }
+                };
+                tokio::spawn(async move {
+                    if let Err(err) = session.run().await {
+                        log::error!("rtmp2webrtc session error: {}", err);
+                    }
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,402 @@
+use {
+    super::errors::{WebRTCRemuxerError, WebRTCRemuxerErrorValue},
+    crate::{aac2opus::Aac2OpusTranscoder, session::WebRTCStreamHandler},
+    bytes::{Bytes, BytesMut},
+    std::sync::Arc,
+    streamhub::{
+        define::{
+            FrameData, NotifyInfo, PacketData, PacketDataSender, PubDataType, PublishMode,
+            PublishType, PublisherInfo, StreamHubEvent, StreamHubEventSender, SubDataType,
+            SubFrameDataReceiver, SubscribeType, SubscriberInfo,
+        },
+        stream::StreamIdentifier,
+        utils::Uuid,
+    },
+    tokio::sync::oneshot,
+    webrtc::{
+        rtp::{
+            codecs::{h264::H264Payloader, opus::OpusPayloader},
+            header::Header,
+            packet::Packet,
+            packetizer::Payloader,
+        },
+        util::Marshal,
+    },
+    xflv::{
+        define::AvcCodecId,
+        demuxer::{FlvAudioTagDemuxer, FlvVideoTagDemuxer},
+    },
+};
+
+//the payload of the rtp packets is kept under the usual webrtc mtu
+const RTP_MTU: usize = 1200;
+const RTP_HEADER_SIZE: usize = 12;
+const H264_PAYLOAD_TYPE: u8 = 96;
+const H264_CLOCK_RATE: u32 = 90000;
+const OPUS_PAYLOAD_TYPE: u8 = 111;
+
+//The whep tracks are h264, the other codecs (e.g. hevc) cannot be remuxed.
+fn check_video_codec(flv_tag: &[u8]) -> Result<(), WebRTCRemuxerError> {
+    match flv_tag.first().map(|header| header & 0x0f) {
+        Some(codec_id) if codec_id != AvcCodecId::H264 as u8 => Err(WebRTCRemuxerError {
+            value: WebRTCRemuxerErrorValue::UnsupportedVideoCodec(codec_id),
+        }),
+        _ => Ok(()),
+    }
+}
+
+//Splits the frames into rtp packets, the payload type and the ssrc
+//are rewritten by the whep tracks when the packets are written.
+pub struct RtpPacketizer {
+    payloader: Box<dyn Payloader + Send + Sync>,
+    payload_type: u8,
+    sequence_number: u16,
+}
+
+impl RtpPacketizer {
+    pub fn new(payloader: Box<dyn Payloader + Send + Sync>, payload_type: u8) -> Self {
+        Self {
+            payloader,
+            payload_type,
+            sequence_number: 0,
+        }
+    }
+
+    pub fn packetize(
+        &mut self, frame: &Bytes, timestamp: u32,
+    ) -> Result<Vec<Bytes>, WebRTCRemuxerError> {
+        let payloads = self.payloader.payload(RTP_MTU - RTP_HEADER_SIZE, frame)?;
+        let payloads_len = payloads.len();
+
+        let mut packets = Vec::with_capacity(payloads_len);
+        for (idx, payload) in payloads.into_iter().enumerate() {
+            let packet = Packet {
+                header: Header {
+                    version: 2,
+                    marker: idx == payloads_len - 1,
+                    payload_type: self.payload_type,
+                    sequence_number: self.sequence_number,
+                    timestamp,
+                    ..Default::default()
+                },
+                payload,
+            };
+            self.sequence_number = self.sequence_number.wrapping_add(1);
+            packets.push(packet.marshal()?);
+        }
+        Ok(packets)
+    }
+}
+
+//Subscribe the frames of a rtmp stream and republish them
+//as a webrtc stream of rtp packets, so whep players can watch it.
+pub struct Rtmp2WebRTCRemuxerSession {
+    event_producer: StreamHubEventSender,
+    app_name: String,
+    stream_name: String,
+
+    subscribe_id: Uuid,
+    publish_id: Uuid,
+
+    video_demuxer: FlvVideoTagDemuxer,
+    video_packetizer: RtpPacketizer,
+    audio_demuxer: FlvAudioTagDemuxer,
+    audio_transcoder: Aac2OpusTranscoder,
+    audio_packetizer: RtpPacketizer,
+    stream_handler: Arc<WebRTCStreamHandler>,
+}
+
+impl Rtmp2WebRTCRemuxerSession {
+    pub fn new(
+        app_name: String, stream_name: String, event_producer: StreamHubEventSender,
+    ) -> Result<Self, WebRTCRemuxerError> {
+        Ok(Self {
+            event_producer,
+            app_name,
+            stream_name,
+            subscribe_id: Uuid::new(),
+            publish_id: Uuid::new(),
+            video_demuxer: FlvVideoTagDemuxer::new(),
+            video_packetizer: RtpPacketizer::new(
+                Box::<H264Payloader>::default(),
+                H264_PAYLOAD_TYPE,
+            ),
+            audio_demuxer: FlvAudioTagDemuxer::new(),
+            audio_transcoder: Aac2OpusTranscoder::new()?,
+            audio_packetizer: RtpPacketizer::new(
+                Box::<OpusPayloader>::default(),
+                OPUS_PAYLOAD_TYPE,
+            ),
+            stream_handler: Arc::new(WebRTCStreamHandler::default()),
+        })
+    }
+
+    pub async fn run(&mut self) -> Result<(), WebRTCRemuxerError> {
+        let packet_sender = self.publish_webrtc().await?;
+
+        let result = match self.subscribe_rtmp().await {
+            Ok(data_receiver) => {
+                let result = self.receive_rtmp_data(data_receiver, packet_sender).await;
+                self.unsubscribe_rtmp();
+                result
+            }
+            Err(err) => Err(err),
+        };
+
+        self.unpublish_webrtc();
+        result
+    }
+
+    fn get_publisher_info(&self) -> PublisherInfo {
+        PublisherInfo {
+            id: self.publish_id,
+            pub_type: PublishType::Remux,
+            pub_data_type: PubDataType::Packet,
+            notify_info: NotifyInfo {
+                request_url: String::from(""),
+                remote_addr: String::from(""),
+            },
+            mode: PublishMode::Live,
+        }
+    }
+
+    fn get_subscriber_info(&self) -> SubscriberInfo {
+        SubscriberInfo {
+            id: self.subscribe_id,
+            sub_type: SubscribeType::Remux,
+            sub_data_type: SubDataType::Frame,
+            notify_info: NotifyInfo {
+                request_url: String::from(""),
+                remote_addr: String::from(""),
+            },
+        }
+    }
+
+    async fn publish_webrtc(&mut self) -> Result<PacketDataSender, WebRTCRemuxerError> {
+        let (event_result_sender, event_result_receiver) = oneshot::channel();
+
+        let publish_event = StreamHubEvent::Publish {
+            identifier: StreamIdentifier::WebRTC {
+                app_name: self.app_name.clone(),
+                stream_name: self.stream_name.clone(),
+            },
+            info: self.get_publisher_info(),
+            result_sender: event_result_sender,
+            stream_handler: self.stream_handler.clone(),
+        };
+
+        if self.event_producer.send(publish_event).is_err() {
+            return Err(WebRTCRemuxerError {
+                value: WebRTCRemuxerErrorValue::StreamHubEventSendErr,
+            });
+        }
+
+        event_result_receiver.await??.1.ok_or(WebRTCRemuxerError {
+            value: WebRTCRemuxerErrorValue::NoPacketSender,
+        })
+    }
+
+    fn unpublish_webrtc(&mut self) {
+        let unpublish_event = StreamHubEvent::UnPublish {
+            identifier: StreamIdentifier::WebRTC {
+                app_name: self.app_name.clone(),
+                stream_name: self.stream_name.clone(),
+            },
+            info: self.get_publisher_info(),
+        };
+        if let Err(err) = self.event_producer.send(unpublish_event) {
+            log::error!("rtmp2webrtc unpublish_webrtc err {}", err);
+        }
+    }
+
+    async fn subscribe_rtmp(&mut self) -> Result<SubFrameDataReceiver, WebRTCRemuxerError> {
+        let (event_result_sender, event_result_receiver) = oneshot::channel();
+
+        let subscribe_event = StreamHubEvent::Subscribe {
+            identifier: StreamIdentifier::Rtmp {
+                app_name: self.app_name.clone(),
+                stream_name: self.stream_name.clone(),
+            },
+            info: self.get_subscriber_info(),
+            result_sender: event_result_sender,
+        };
+
+        if self.event_producer.send(subscribe_event).is_err() {
+            return Err(WebRTCRemuxerError {
+                value: WebRTCRemuxerErrorValue::StreamHubEventSendErr,
+            });
+        }
+
+        event_result_receiver
+            .await??
+            .0
+            .frame_receiver
+            .ok_or(WebRTCRemuxerError {
+                value: WebRTCRemuxerErrorValue::NoFrameReceiver,
+            })
+    }
+
+    fn unsubscribe_rtmp(&mut self) {
+        let unsubscribe_event = StreamHubEvent::UnSubscribe {
+            identifier: StreamIdentifier::Rtmp {
+                app_name: self.app_name.clone(),
+                stream_name: self.stream_name.clone(),
+            },
+            info: self.get_subscriber_info(),
+        };
+        if let Err(err) = self.event_producer.send(unsubscribe_event) {
+            log::error!("rtmp2webrtc unsubscribe_rtmp err {}", err);
+        }
+    }
+
+    async fn receive_rtmp_data(
+        &mut self, mut data_receiver: SubFrameDataReceiver, packet_sender: PacketDataSender,
+    ) -> Result<(), WebRTCRemuxerError> {
+        log::info!(
+            "begin remux rtmp stream {}/{} to webrtc...",
+            self.app_name,
+            self.stream_name
+        );
+
+        //the frame channel is closed when the rtmp stream is unpublished
+        while let Some(data) = data_receiver.recv().await {
+            let result = match data {
+                //the demuxers take the frames apart, a shared frame is copied here
+                FrameData::Video { timestamp, data } => {
+                    self.on_rtmp_video(timestamp, BytesMut::from(data))
+                }
+                FrameData::Audio { timestamp, data } => {
+                    self.on_rtmp_audio(timestamp, BytesMut::from(data))
+                }
+                _ => continue,
+            };
+            //a corrupt frame is skipped instead of ending the remuxing for all the players
+            let packets = match result {
+                Ok(packets) => packets,
+                Err(
+                    err @ WebRTCRemuxerError {
+                        value: WebRTCRemuxerErrorValue::UnsupportedVideoCodec(_),
+                    },
+                ) => return Err(err),
+                Err(err) => {
+                    log::warn!("rtmp2webrtc skip the frame, remux error: {}", err);
+                    continue;
+                }
+            };
+
+            for packet in packets {
+                if let PacketData::Video { data, .. } = &packet {
+                    self.stream_handler.save_video_packet(data).await;
+                }
+                //the packet receiver is dropped when the webrtc stream is kicked off
+                if packet_sender.send(packet).is_err() {
+                    return Ok(());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn on_rtmp_video(
+        &mut self, timestamp: u32, data: BytesMut,
+    ) -> Result<Vec<PacketData>, WebRTCRemuxerError> {
+        check_video_codec(&data)?;
+        //the avcc nalus are converted to annex-b, and the sps/pps are
+        //inserted before the idr frames
+        let Some(video_data) = self.video_demuxer.demux(timestamp, data)? else {
+            return Ok(Vec::new());
+        };
+
+        let rtp_timestamp = (video_data.pts as u32).wrapping_mul(H264_CLOCK_RATE / 1000);
+        let packets = self
+            .video_packetizer
+            .packetize(&video_data.data.freeze(), rtp_timestamp)?
+            .into_iter()
+            .map(|data| PacketData::Video {
+                timestamp: rtp_timestamp,
+                data,
+            })
+            .collect();
+
+        Ok(packets)
+    }
+
+    fn on_rtmp_audio(
+        &mut self, timestamp: u32, data: BytesMut,
+    ) -> Result<Vec<PacketData>, WebRTCRemuxerError> {
+        //only the aac frames are returned (as adts), the others are ignored
+        let audio_data = self.audio_demuxer.demux(timestamp, data)?;
+        if !audio_data.has_data {
+            return Ok(Vec::new());
+        }
+
+        let mut packets = Vec::new();
+        for (rtp_timestamp, opus_packet) in self
+            .audio_transcoder
+            .transcode(&audio_data.data, audio_data.pts as u32)?
+        {
+            for data in self
+                .audio_packetizer
+                .packetize(&Bytes::from(opus_packet), rtp_timestamp)?
+            {
+                packets.push(PacketData::Audio {
+                    timestamp: rtp_timestamp,
+                    data,
+                });
+            }
+        }
+        Ok(packets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use webrtc::util::Unmarshal;
+
+    #[test]
+    fn test_h264_packetize() {
+        let mut frame = BytesMut::new();
+        frame.extend_from_slice(&[0, 0, 0, 1, 0x67, 0x42, 0xc0, 0x1f]);
+        frame.extend_from_slice(&[0, 0, 0, 1, 0x68, 0xce, 0x3c, 0x80]);
+        frame.extend_from_slice(&[0, 0, 0, 1, 0x65]);
+        frame.extend_from_slice(&[0xab; 3000]);
+
+        let mut packetizer = RtpPacketizer::new(Box::<H264Payloader>::default(), 96);
+        let packets: Vec<Packet> = packetizer
+            .packetize(&frame.freeze(), 9000)
+            .unwrap()
+            .iter()
+            .map(|data| Packet::unmarshal(&mut &data[..]).unwrap())
+            .collect();
+
+        //a STAP-A of the sps/pps and the FU-A fragments of the idr
+        assert_eq!(packets.len(), 4);
+        assert_eq!(packets[0].payload[0] & 0x1f, 24);
+        for (idx, packet) in packets.iter().enumerate() {
+            assert_eq!(packet.header.sequence_number, idx as u16);
+            assert_eq!(packet.header.timestamp, 9000);
+            assert_eq!(packet.header.marker, idx == packets.len() - 1);
+            assert!(packet.payload.len() <= RTP_MTU - RTP_HEADER_SIZE);
+        }
+        assert_eq!(packets[1].payload[0] & 0x1f, 28);
+        //the start bit of the first fragment and the end bit of the last one
+        assert_eq!(packets[1].payload[1], 0x80 | 5);
+        assert_eq!(packets[3].payload[1], 0x40 | 5);
+    }
+
+    #[test]
+    fn test_check_video_codec() {
+        //avc key frame and inter frame
+        assert!(check_video_codec(&[0x17, 0x01]).is_ok());
+        assert!(check_video_codec(&[0x27, 0x01]).is_ok());
+        //hevc key frame
+        assert!(matches!(
+            check_video_codec(&[0x1c, 0x01]),
+            Err(WebRTCRemuxerError {
+                value: WebRTCRemuxerErrorValue::UnsupportedVideoCodec(12)
+            })
+        ));
+    }
+}
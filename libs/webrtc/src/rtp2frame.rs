@@ -0,0 +1,270 @@
+use {
+    crate::{errors::Opus2AacError, opus2aac::Opus2AacTranscoder, rtp_queue::RtpQueue},
+    bytes::BytesMut,
+    webrtc::rtp::{
+        codecs::{h264::H264Packet, opus::OpusPacket},
+        packet::Packet as RtpPacket,
+        packetizer::Depacketizer,
+    },
+};
+
+pub const H264_CLOCK_RATE: u32 = 90000;
+pub const OPUS_CLOCK_RATE: u32 = 48000;
+//the samples of one channel in an AAC-LC frame
+const AAC_FRAME_SAMPLES: u32 = 1024;
+const ANNEXB_START_CODE: [u8; 4] = [0x00, 0x00, 0x00, 0x01];
+const H264_NAL_FILLER: u8 = 12;
+const RTP_QUEUE_SIZE: usize = 100;
+
+/// Split an annexb frame with 4 bytes start codes into nalus.
+pub fn split_nalus(frame: &[u8]) -> Vec<&[u8]> {
+    let starts: Vec<usize> = frame
+        .windows(ANNEXB_START_CODE.len())
+        .enumerate()
+        .filter(|(_, window)| *window == ANNEXB_START_CODE)
+        .map(|(pos, _)| pos)
+        .collect();
+
+    let ends = starts.iter().skip(1).copied().chain([frame.len()]);
+    starts
+        .iter()
+        .zip(ends)
+        .map(|(&start, end)| &frame[start + ANNEXB_START_CODE.len()..end])
+        .collect()
+}
+
+//Reorder the h264 rtp packets of a whip track and join the nalus of
+//one access unit into an annexb frame, the frames with lost packets are dropped.
+pub struct H264FrameAssembler {
+    rtp_queue: RtpQueue,
+    depacketizer: H264Packet,
+    next_sequence_number: Option<u16>,
+    timestamp: u32,
+    frame: BytesMut,
+    broken: bool,
+}
+
+impl Default for H264FrameAssembler {
+    fn default() -> Self {
+        Self {
+            rtp_queue: RtpQueue::new(RTP_QUEUE_SIZE),
+            depacketizer: H264Packet::default(),
+            next_sequence_number: None,
+            timestamp: 0,
+            frame: BytesMut::new(),
+            broken: false,
+        }
+    }
+}
+
+impl H264FrameAssembler {
+    /// Returns the (rtp timestamp, annexb frame) pairs completed by the packet.
+    pub fn push(&mut self, packet: RtpPacket) -> Vec<(u32, BytesMut)> {
+        let mut frames = Vec::new();
+        self.rtp_queue.write_queue(packet);
+
+        while let Some(packet) = self.rtp_queue.read_queue() {
+            let sequence_number = packet.header.sequence_number;
+            if self
+                .next_sequence_number
+                .is_some_and(|next| next != sequence_number)
+            {
+                self.broken = true;
+                self.frame.clear();
+                self.depacketizer = H264Packet::default();
+            }
+            self.next_sequence_number = Some(sequence_number.wrapping_add(1));
+
+            if packet.header.timestamp != self.timestamp {
+                self.flush(&mut frames);
+                self.timestamp = packet.header.timestamp;
+            }
+
+            match self.depacketizer.depacketize(&packet.payload) {
+                Ok(nalus) => {
+                    let is_filler = nalus.len() > ANNEXB_START_CODE.len()
+                        && nalus[ANNEXB_START_CODE.len()] & 0x1F == H264_NAL_FILLER;
+                    if !is_filler {
+                        self.frame.extend_from_slice(&nalus);
+                    }
+                }
+                Err(err) => log::warn!("h264 depacketize error: {}", err),
+            }
+
+            if packet.header.marker {
+                self.flush(&mut frames);
+            }
+        }
+
+        frames
+    }
+
+    fn flush(&mut self, frames: &mut Vec<(u32, BytesMut)>) {
+        let frame = self.frame.split();
+        if !self.broken && !frame.is_empty() {
+            frames.push((self.timestamp, frame));
+        }
+        self.broken = false;
+    }
+}
+
+//Transcode the opus rtp packets of a whip track into raw AAC-LC frames, the
+//frames are timestamped on the 48kHz clock from the first opus packet.
+pub struct OpusFrameTranscoder {
+    depacketizer: OpusPacket,
+    transcoder: Opus2AacTranscoder,
+    timestamp: Option<u32>,
+}
+
+impl OpusFrameTranscoder {
+    pub fn new() -> Result<Self, Opus2AacError> {
+        Ok(Self {
+            depacketizer: OpusPacket,
+            transcoder: Opus2AacTranscoder::new(
+                OPUS_CLOCK_RATE as i32,
+                audiopus::Channels::Stereo,
+                OPUS_CLOCK_RATE,
+                fdk_aac::enc::ChannelMode::Stereo,
+            )?,
+            timestamp: None,
+        })
+    }
+
+    /// Returns the (rtp timestamp, aac frame) pairs encoded from the packet.
+    pub fn push(&mut self, packet: &RtpPacket) -> Result<Vec<(u32, BytesMut)>, Opus2AacError> {
+        let payload = match self.depacketizer.depacketize(&packet.payload) {
+            Ok(payload) => payload,
+            Err(err) => {
+                log::warn!("opus depacketize error: {}", err);
+                return Ok(Vec::new());
+            }
+        };
+
+        let mut timestamp = *self.timestamp.get_or_insert(packet.header.timestamp);
+        let frames = self
+            .transcoder
+            .transcode(&payload)?
+            .into_iter()
+            .map(|frame| {
+                let frame_timestamp = timestamp;
+                timestamp = timestamp.wrapping_add(AAC_FRAME_SAMPLES);
+                (frame_timestamp, BytesMut::from(&frame[..]))
+            })
+            .collect();
+        self.timestamp = Some(timestamp);
+
+        Ok(frames)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::remuxer::rtmp2webrtc::RtpPacketizer;
+    use audiopus::coder::Encoder as OpusEncoder;
+    use bytes::Bytes;
+    use webrtc::{rtp::codecs::h264::H264Payloader, util::Unmarshal};
+
+    fn h264_frame() -> Vec<u8> {
+        let mut frame = Vec::new();
+        //sps, pps and an idr slice large enough to be fragmented
+        for nalu in [vec![0x67, 0x42, 0xc0, 0x1f], vec![0x68, 0xce, 0x3c, 0x80]] {
+            frame.extend_from_slice(&ANNEXB_START_CODE);
+            frame.extend_from_slice(&nalu);
+        }
+        frame.extend_from_slice(&ANNEXB_START_CODE);
+        frame.push(0x65);
+        frame.extend((0..3000).map(|idx| (idx % 250 + 1) as u8));
+        frame
+    }
+
+    fn packetize(frames: &[(u32, Vec<u8>)]) -> Vec<RtpPacket> {
+        let mut packetizer = RtpPacketizer::new(Box::<H264Payloader>::default(), 96);
+        frames
+            .iter()
+            .flat_map(|(timestamp, frame)| {
+                packetizer
+                    .packetize(&Bytes::from(frame.clone()), *timestamp)
+                    .unwrap()
+            })
+            .map(|packet| RtpPacket::unmarshal(&mut &packet[..]).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_split_nalus() {
+        let nalus = split_nalus(&h264_frame()[..])
+            .iter()
+            .map(|nalu| nalu[0])
+            .collect::<Vec<_>>();
+        assert_eq!(nalus, [0x67, 0x68, 0x65]);
+    }
+
+    #[test]
+    fn test_h264_assemble() {
+        let frame = h264_frame();
+        let mut assembler = H264FrameAssembler::default();
+
+        let mut frames = Vec::new();
+        for packet in packetize(&[(3000, frame.clone()), (6000, frame.clone())]) {
+            frames.extend(assembler.push(packet));
+        }
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0], (3000, BytesMut::from(&frame[..])));
+        assert_eq!(frames[1].0, 6000);
+    }
+
+    #[test]
+    fn test_h264_assemble_packet_lost() {
+        let frame = h264_frame();
+        //enough frames to fill the rtp queue, which then skips the lost packet
+        let frames: Vec<(u32, Vec<u8>)> = (1..=50).map(|idx| (idx * 3000, frame.clone())).collect();
+        let mut packets = packetize(&frames);
+        //lose a fragment of the second frame
+        let lost = packets
+            .iter()
+            .rposition(|packet| packet.header.timestamp == 6000)
+            .unwrap();
+        packets.remove(lost - 1);
+
+        let mut assembler = H264FrameAssembler::default();
+        let mut timestamps = Vec::new();
+        for packet in packets {
+            timestamps.extend(assembler.push(packet).into_iter().map(|(ts, _)| ts));
+        }
+        assert_eq!(timestamps[..3], [3000, 9000, 12000]);
+    }
+
+    #[test]
+    fn test_opus_transcode() {
+        let encoder = OpusEncoder::new(
+            audiopus::SampleRate::Hz48000,
+            audiopus::Channels::Stereo,
+            audiopus::Application::Audio,
+        )
+        .unwrap();
+        let mut transcoder = OpusFrameTranscoder::new().unwrap();
+
+        let mut timestamps = Vec::new();
+        let mut output = vec![0; 1500];
+        //one second of 20ms packets
+        for idx in 0..50_u32 {
+            let pcm: Vec<i16> = (0..960 * 2).map(|sample| (sample % 100) as i16).collect();
+            let size = encoder.encode(&pcm, &mut output).unwrap();
+
+            let mut packet = RtpPacket::default();
+            packet.header.timestamp = 1000 + idx * 960;
+            packet.payload = Bytes::copy_from_slice(&output[..size]);
+            for (timestamp, frame) in transcoder.push(&packet).unwrap() {
+                assert!(!frame.is_empty());
+                timestamps.push(timestamp);
+            }
+        }
+
+        assert!(timestamps.len() > 40, "{}", timestamps.len());
+        assert_eq!(timestamps[0], 1000);
+        for window in timestamps.windows(2) {
+            assert_eq!(window[1] - window[0], AAC_FRAME_SAMPLES);
+        }
+    }
+}
@@ -7,19 +7,46 @@ pub use self::wish_entrypoint::WishEntrypointServer;
 
 use streamhub::{
     define::{
-        DataSender, InformationSender, 
-         SubscribeType, TStreamHandler,
+        DataSender, FrameData, InformationSender, MediaInfo, PacketData, SubscribeType,
+        TStreamHandler, VideoCodecType,
     },
     errors::StreamHubError,
+    queue::try_send_prior_data,
     statistics::StatisticsStream,
 };
 use tokio::sync::Mutex;
 use async_trait::async_trait;
+use bytes::{Bytes, BytesMut};
+use std::sync::Weak;
+use std::time::{Duration, Instant};
+use webrtc::peer_connection::RTCPeerConnection;
+use webrtc::rtcp::payload_feedbacks::picture_loss_indication::PictureLossIndication;
+use xflv::mpeg4_aac::Mpeg4Aac;
 
+use crate::key_frame_cache::KeyFrameCache;
+use crate::rtp2frame::{H264_CLOCK_RATE, OPUS_CLOCK_RATE};
+
+const ANNEXB_START_CODE: [u8; 4] = [0x00, 0x00, 0x00, 0x01];
+//the key frame requests of all the subscribers are merged into one PLI per interval
+const KEY_FRAME_REQUEST_INTERVAL: Duration = Duration::from_millis(500);
+
+//The whip video track which the key frame requests are sent to.
+#[derive(Default)]
+struct KeyFrameRequester {
+    peer_connection: Weak<RTCPeerConnection>,
+    media_ssrc: u32,
+    last_request: Option<Instant>,
+}
+
+//The whip tracks are sent to the frame subscribers as h264 annexb frames and
+//48kHz stereo AAC-LC frames, the parameter sets are cached for late subscribers.
+//The packet subscribers start with the cached rtp packets of the latest key frame.
 #[derive(Default)]
 pub struct WebRTCStreamHandler {
     sps: Mutex<Vec<u8>>,
     pps: Mutex<Vec<u8>>,
+    key_frame_cache: Mutex<KeyFrameCache>,
+    key_frame_requester: Mutex<KeyFrameRequester>,
 }
 
 impl WebRTCStreamHandler {
@@ -29,13 +56,80 @@ impl WebRTCStreamHandler {
     pub async fn set_pps(&self, pps: Vec<u8>) {
         *self.pps.lock().await = pps;
     }
+    pub async fn save_video_packet(&self, data: &Bytes) {
+        self.key_frame_cache.lock().await.save(data);
+    }
+    pub async fn set_video_track(&self, peer_connection: Weak<RTCPeerConnection>, media_ssrc: u32) {
+        let mut requester = self.key_frame_requester.lock().await;
+        requester.peer_connection = peer_connection;
+        requester.media_ssrc = media_ssrc;
+    }
 }
 
 #[async_trait]
 impl TStreamHandler for WebRTCStreamHandler {
     async fn send_prior_data(
-        &self, _data_sender: DataSender, _sub_type: SubscribeType,
+        &self, data_sender: DataSender, _sub_type: SubscribeType,
     ) -> Result<(), StreamHubError> {
+        let sender = match data_sender {
+            DataSender::Frame { sender } => sender,
+            DataSender::Packet { sender } => {
+                //a truncated key frame cannot be decoded, it is not replayed
+                //if it does not fit into the subscriber queue
+                let packets = self.key_frame_cache.lock().await.packets();
+                if packets.len() > sender.capacity() {
+                    log::warn!(
+                        "send_prior_data: the key frame of {} packets is larger than the queue",
+                        packets.len()
+                    );
+                    return Ok(());
+                }
+                for (timestamp, data) in packets {
+                    if !try_send_prior_data(&sender, PacketData::Video { timestamp, data })? {
+                        break;
+                    }
+                }
+                return Ok(());
+            }
+        };
+
+        let media_info = FrameData::MediaInfo {
+            media_info: MediaInfo {
+                audio_clock_rate: OPUS_CLOCK_RATE,
+                video_clock_rate: H264_CLOCK_RATE,
+                vcodec: VideoCodecType::H264,
+            },
+        };
+        if !try_send_prior_data(&sender, media_info)? {
+            return Ok(());
+        }
+
+        let (sps, pps) = (self.sps.lock().await, self.pps.lock().await);
+        if !sps.is_empty() && !pps.is_empty() {
+            let mut data = BytesMut::new();
+            for nalu in [&*sps, &*pps] {
+                data.extend_from_slice(&ANNEXB_START_CODE);
+                data.extend_from_slice(nalu);
+            }
+            let video = FrameData::Video {
+                timestamp: 0,
+                data: data.freeze(),
+            };
+            if !try_send_prior_data(&sender, video)? {
+                return Ok(());
+            }
+        }
+
+        //the AudioSpecificConfig of the transcoded aac frames
+        if let Ok(config) =
+            Mpeg4Aac::new(2, OPUS_CLOCK_RATE, 2).and_then(|aac| aac.gen_audio_specific_config())
+        {
+            let audio = FrameData::Audio {
+                timestamp: 0,
+                data: config.freeze(),
+            };
+            try_send_prior_data(&sender, audio)?;
+        }
         Ok(())
     }
     async fn get_statistic_data(&self) -> Option<StatisticsStream> {
@@ -43,4 +137,27 @@ impl TStreamHandler for WebRTCStreamHandler {
     }
 
     async fn send_information(&self, _sender: InformationSender) {}
+
+    async fn request_key_frame(&self) {
+        let mut requester = self.key_frame_requester.lock().await;
+        if requester
+            .last_request
+            .is_some_and(|last| last.elapsed() < KEY_FRAME_REQUEST_INTERVAL)
+        {
+            return;
+        }
+        //the streams remuxed from other protocols have no whip peer connection
+        let Some(peer_connection) = requester.peer_connection.upgrade() else {
+            return;
+        };
+        requester.last_request = Some(Instant::now());
+
+        let pli = PictureLossIndication {
+            sender_ssrc: 0,
+            media_ssrc: requester.media_ssrc,
+        };
+        if let Err(err) = peer_connection.write_rtcp(&[Box::new(pli)]).await {
+            log::warn!("send pli to the whip publisher error: {}", err);
+        }
+    }
 }
@@ -1,4 +1,4 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, net::SocketAddr, sync::Arc};
 
 use crate::{whep::handle_whep, whip::handle_whip};
 
@@ -14,8 +14,8 @@ use axum::{
 use http::{header, StatusCode};
 use streamhub::{
     define::{
-        NotifyInfo, PublishType, PublisherInfo, StreamHubEvent, StreamHubEventSender,
-        SubscribeType, SubscriberInfo,
+        NotifyInfo, PublishMode, PublishType, PublisherInfo, StatisticData, StreamHubEvent,
+        StreamHubEventSender, SubscribeType, SubscriberInfo,
     },
     stream::StreamIdentifier,
     utils::Uuid,
@@ -25,7 +25,6 @@ use webrtc::{
     peer_connection::{
         offer_answer_options::RTCAnswerOptions, peer_connection_state::RTCPeerConnectionState, sdp::session_description::RTCSessionDescription, RTCPeerConnection
     },
-    sdp::util::Codec,
 };
 
 pub type WebrtcSessionMapping = HashMap<Uuid, Arc<RwLock<WebRTCServerSession>>>;
@@ -40,7 +39,8 @@ pub struct WebRTCServerSession {
 
     pub session_id: Uuid,
     pub peer_connection: Option<Arc<RTCPeerConnection>>,
-    pub stream_codecs: Arc<RwLock<HashMap<String, Codec>>>,
+
+    notify_info: NotifyInfo,
 }
 
 impl WebRTCServerSession {
@@ -54,10 +54,20 @@ impl WebRTCServerSession {
             stream_name,
             session_id,
             peer_connection: None,
-            stream_codecs: Arc::new(RwLock::new(HashMap::new())),
+            notify_info: NotifyInfo {
+                request_url: String::from(""),
+                remote_addr: String::from(""),
+            },
         }
     }
 
+    pub fn set_notify_info(&mut self, request_url: String, remote_addr: SocketAddr) {
+        self.notify_info = NotifyInfo {
+            request_url,
+            remote_addr: remote_addr.to_string(),
+        };
+    }
+
     pub fn new(app_name: String, stream_name: String, event_sender: StreamHubEventSender) -> Self {
         Self::new_with_id(app_name, stream_name, event_sender, Uuid::new())
     }
@@ -83,12 +93,31 @@ impl WebRTCServerSession {
             });
         }
 
-        let (frame_sender, packet_sender) = match event_result_receiver.await?? {
-            (Some(a), Some(b), _c) => (a, b),
+        let (frame_sender, packet_sender, statistic_sender) = match event_result_receiver.await?? {
+            (Some(a), Some(b), c) => (a, b, c),
             _ => return Ok(StatusCode::SERVICE_UNAVAILABLE.into_response()),
         };
 
-        match handle_whip(offer, frame_sender, packet_sender, self.stream_codecs.clone()).await {
+        if let Some(sender) = &statistic_sender {
+            let statistic_publisher = StatisticData::Publisher {
+                id: self.session_id,
+                remote_addr: self.notify_info.remote_addr.clone(),
+                start_time: chrono::Local::now(),
+            };
+            if let Err(err) = sender.send(statistic_publisher) {
+                log::error!("send statistic_publisher err: {}", err);
+            }
+        }
+
+        match handle_whip(
+            offer,
+            frame_sender,
+            packet_sender,
+            self.stream_handler.clone(),
+            statistic_sender,
+        )
+        .await
+        {
             Ok((session_description, peer_connection)) => {
                 self.peer_connection = Some(peer_connection);
 
@@ -147,11 +176,38 @@ impl WebRTCServerSession {
             });
         }
 
-        let receiver = event_result_receiver.await??.0.packet_receiver.unwrap();
+        let (receiver, statistic_sender) = event_result_receiver.await??;
+        let receiver = receiver.packet_receiver.unwrap();
+
+        if let Some(sender) = &statistic_sender {
+            let statistic_subscriber = StatisticData::Subscriber {
+                id: self.session_id,
+                remote_addr: self.notify_info.remote_addr.clone(),
+                sub_type: SubscribeType::PlayerWebrtc,
+                start_time: chrono::Local::now(),
+            };
+            if let Err(err) = sender.send(statistic_subscriber) {
+                log::error!("send statistic_subscriber err: {}", err);
+            }
+        }
 
         let (pc_state_sender, mut pc_state_receiver) = broadcast::channel(1);
 
-        let response = match handle_whep(offer, receiver, pc_state_sender).await {
+        let identifier = StreamIdentifier::WebRTC {
+            app_name: self.app_name.clone(),
+            stream_name: self.stream_name.clone(),
+        };
+        let response = match handle_whep(
+            offer,
+            receiver,
+            pc_state_sender,
+            self.event_sender.clone(),
+            identifier,
+            self.session_id,
+            statistic_sender,
+        )
+        .await
+        {
             Ok((session_description, peer_connection)) => {
                 let pc_clone = peer_connection.clone();
 
@@ -161,17 +217,32 @@ impl WebRTCServerSession {
                 let sender_out = self.event_sender.clone();
 
                 tokio::spawn(async move {
+                    //a failure after the connection is established is not a handshake failure
+                    let mut connected = false;
                     loop {
                         if let Ok(state) = pc_state_receiver.recv().await {
                             log::info!("state: {}", state);
                             match state {
+                                RTCPeerConnectionState::Connected => connected = true,
                                 RTCPeerConnectionState::Disconnected
                                 | RTCPeerConnectionState::Failed => {
+                                    if state == RTCPeerConnectionState::Failed && !connected {
+                                        metrics::counter!(
+                                            crate::metrics::HANDSHAKE_FAILURES,
+                                            "kind" => "whep"
+                                        )
+                                        .increment(1);
+                                    }
                                     if let Err(err) = pc_clone.close().await {
                                         log::error!("peer connection close error: {}", err);
                                     }
                                 }
                                 RTCPeerConnectionState::Closed => {
+                                    metrics::gauge!(
+                                        crate::metrics::PEER_CONNECTIONS,
+                                        "kind" => "whep"
+                                    )
+                                    .decrement(1);
                                     if let Err(err) = Self::unsubscribe_whep(
                                         app_name_out,
                                         stream_name_out,
@@ -191,6 +262,7 @@ impl WebRTCServerSession {
                 });
 
                 self.peer_connection = Some(peer_connection);
+                metrics::gauge!(crate::metrics::PEER_CONNECTIONS, "kind" => "whep").increment(1);
 
                 Response::builder()
                     .header(header::CONTENT_TYPE, "application/sdp")
@@ -257,10 +329,7 @@ impl WebRTCServerSession {
             id,
             sub_type: SubscribeType::PlayerWebrtc,
             sub_data_type: streamhub::define::SubDataType::Packet,
-            notify_info: NotifyInfo {
-                request_url: String::from(""),
-                remote_addr: String::from(""),
-            },
+            notify_info: self.notify_info.clone(),
         }
     }
 
@@ -271,10 +340,8 @@ impl WebRTCServerSession {
             id,
             pub_type: PublishType::PushWebRTC,
             pub_data_type: streamhub::define::PubDataType::Both,
-            notify_info: NotifyInfo {
-                request_url: String::from(""),
-                remote_addr: String::from(""),
-            },
+            notify_info: self.notify_info.clone(),
+            mode: PublishMode::Live,
         }
     }
 }
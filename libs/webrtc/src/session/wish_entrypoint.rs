@@ -1,7 +1,7 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, net::SocketAddr, sync::Arc};
 
 use anyhow::anyhow;
-use auth::Auth;
+use auth::{Auth, AuthContext, AuthDirection, AuthProtocol};
 use axum::{
     body,
     extract::{self},
@@ -92,7 +92,11 @@ impl<Addr: ToSocketAddrs, A: Auth> WishEntrypointServer<Addr, A> {
              ));
 
         let listenser = tokio::net::TcpListener::bind(&self.addr).await?;
-        axum::serve(listenser, router).await?;
+        axum::serve(
+            listenser,
+            router.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await?;
         Ok(())
     }
 }
@@ -139,9 +143,30 @@ async fn cors_middleware(req: extract::Request, next: middleware::Next) -> Respo
     resp
 }
 
+fn auth_context(
+    direction: AuthDirection, par: &EntrypointParrams, remote_addr: SocketAddr,
+    req: &extract::Request,
+) -> AuthContext {
+    let mut ctx = AuthContext::new(
+        AuthProtocol::WebRTC,
+        direction,
+        par.app.clone().unwrap_or_default(),
+        par.stream.clone().unwrap_or_default(),
+    )
+    .with_query(req.uri().query())
+    .with_remote_addr(Some(remote_addr));
+    for (name, value) in req.headers() {
+        if let Ok(value) = value.to_str() {
+            ctx = ctx.with_header(name.as_str(), value);
+        }
+    }
+    ctx
+}
+
 async fn whip_auth_middleware<A: Auth>(
     extract::State(state): extract::State<State<A>>,
-    extract::Query(par): extract::Query<EntrypointParrams>, req: extract::Request,
+    extract::Query(par): extract::Query<EntrypointParrams>,
+    extract::ConnectInfo(remote_addr): extract::ConnectInfo<SocketAddr>, req: extract::Request,
     next: middleware::Next,
 ) -> Response {
     let app = par.app.as_deref();
@@ -152,8 +177,8 @@ async fn whip_auth_middleware<A: Auth>(
     }
 
     if let Some(auth) = state.auth {
-        let query = req.uri().query();
-        if let Err(err) = auth.auth(app, stream, query) {
+        let ctx = auth_context(AuthDirection::Publish, &par, remote_addr, &req);
+        if let Err(err) = auth.authenticate(&ctx).await {
             log::error!(
                 "whip auth error: app={} stream={}: {}",
                 app.unwrap_or(""),
@@ -168,7 +193,8 @@ async fn whip_auth_middleware<A: Auth>(
 
 async fn whep_auth_middleware<A: Auth>(
     extract::State(state): extract::State<State<A>>,
-    extract::Query(par): extract::Query<EntrypointParrams>, req: extract::Request,
+    extract::Query(par): extract::Query<EntrypointParrams>,
+    extract::ConnectInfo(remote_addr): extract::ConnectInfo<SocketAddr>, req: extract::Request,
     next: middleware::Next,
 ) -> Response {
     let app = par.app.as_deref();
@@ -179,8 +205,8 @@ async fn whep_auth_middleware<A: Auth>(
     }
 
     if let Some(auth) = state.auth {
-        let query = req.uri().query();
-        if let Err(err) = auth.auth_pull(app, stream, query) {
+        let ctx = auth_context(AuthDirection::Play, &par, remote_addr, &req);
+        if let Err(err) = auth.authenticate(&ctx).await {
             log::error!(
                 "whep auth error: app={} stream={}: {}",
                 app.unwrap_or(""),
@@ -196,7 +222,7 @@ async fn whep_auth_middleware<A: Auth>(
 async fn post_whip_handler<A: Auth>(
     extract::State(state): extract::State<State<A>>,
     extract::Query(par): extract::Query<EntrypointParrams>, uri: extract::OriginalUri,
-    sdp_data: String,
+    extract::ConnectInfo(remote_addr): extract::ConnectInfo<SocketAddr>, sdp_data: String,
 ) -> Response {
     let EntrypointParrams { app, stream, .. } = par;
     let app = app.unwrap_or_default();
@@ -224,6 +250,7 @@ async fn post_whip_handler<A: Auth>(
     );
     let mut session =
         WebRTCServerSession::new_with_id(app, stream, state.event_producer, session_id);
+    session.set_notify_info(uri.to_string(), remote_addr);
 
     match session.publish_whip(path, offer).await {
         Ok(resp) => {
@@ -279,7 +306,7 @@ async fn delete_whip_handler<A: Auth>(
 async fn post_whep_handler<A: Auth>(
     extract::State(state): extract::State<State<A>>,
     extract::Query(par): extract::Query<EntrypointParrams>, uri: extract::OriginalUri,
-    sdp_data: String,
+    extract::ConnectInfo(remote_addr): extract::ConnectInfo<SocketAddr>, sdp_data: String,
 ) -> Response {
     let EntrypointParrams { app, stream, .. } = par;
     let app = app.unwrap_or_default();
@@ -307,6 +334,7 @@ async fn post_whep_handler<A: Auth>(
     );
     let mut session =
         WebRTCServerSession::new_with_id(app, stream, state.event_producer, session_id);
+    session.set_notify_info(uri.to_string(), remote_addr);
 
     match session.subscribe_whep(path, offer).await {
         Ok(resp) => {
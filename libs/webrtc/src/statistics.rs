@@ -0,0 +1,259 @@
+use {
+    bytes::BytesMut,
+    bytesio::bytes_reader::BytesReader,
+    h264_decoder::sps::SpsParser,
+    std::{
+        sync::{
+            atomic::{AtomicU32, Ordering},
+            Arc, Weak,
+        },
+        time::{Duration, Instant},
+    },
+    streamhub::{
+        define::{StatisticData, StatisticDataSender},
+        statistics::RtpStatistics,
+        utils::Uuid,
+    },
+    webrtc::{
+        peer_connection::{peer_connection_state::RTCPeerConnectionState, RTCPeerConnection},
+        sdp::util::Codec,
+        stats::{StatsReport, StatsReportType},
+    },
+    xflv::define::{self, AacProfile, AvcCodecId, SoundFormat},
+};
+
+pub const STATISTICS_INTERVAL: Duration = Duration::from_secs(5);
+
+//The packet loss and the interarrival jitter of a received rtp stream, see RFC 3550 A.3 and A.8.
+pub struct RtpReceiveStatistics {
+    clock_rate: u32,
+    start: Instant,
+    base_sequence_number: Option<u16>,
+    max_sequence_number: u16,
+    cycles: u64,
+    received: u64,
+    expected_prior: u64,
+    received_prior: u64,
+    transit: Option<u32>,
+    //in rtp timestamp units
+    jitter: f64,
+}
+
+impl RtpReceiveStatistics {
+    pub fn new(clock_rate: u32) -> Self {
+        Self {
+            clock_rate,
+            start: Instant::now(),
+            base_sequence_number: None,
+            max_sequence_number: 0,
+            cycles: 0,
+            received: 0,
+            expected_prior: 0,
+            received_prior: 0,
+            transit: None,
+            jitter: 0.,
+        }
+    }
+
+    pub fn update(&mut self, sequence_number: u16, timestamp: u32, arrival: Instant) {
+        if self.base_sequence_number.is_none() {
+            self.base_sequence_number = Some(sequence_number);
+            self.max_sequence_number = sequence_number;
+        } else if sequence_number.wrapping_sub(self.max_sequence_number) < 0x8000 {
+            //a late or duplicated packet does not move the highest sequence number
+            if sequence_number < self.max_sequence_number {
+                self.cycles += 1 << 16;
+            }
+            self.max_sequence_number = sequence_number;
+        }
+        self.received += 1;
+
+        let arrival = (arrival.saturating_duration_since(self.start).as_secs_f64()
+            * self.clock_rate as f64) as u64 as u32;
+        let transit = arrival.wrapping_sub(timestamp);
+        if let Some(last_transit) = self.transit {
+            let d = (transit.wrapping_sub(last_transit) as i32).unsigned_abs() as f64;
+            self.jitter += (d - self.jitter) / 16.;
+        }
+        self.transit = Some(transit);
+    }
+
+    /// The fraction lost is counted since the previous report.
+    pub fn report(&mut self) -> RtpStatistics {
+        let Some(base_sequence_number) = self.base_sequence_number else {
+            return RtpStatistics::default();
+        };
+        let expected =
+            self.cycles + self.max_sequence_number as u64 - base_sequence_number as u64 + 1;
+
+        let expected_interval = expected - self.expected_prior;
+        let received_interval = self.received - self.received_prior;
+        self.expected_prior = expected;
+        self.received_prior = self.received;
+
+        let lost_interval = expected_interval as i64 - received_interval as i64;
+        let fraction_lost = if expected_interval == 0 || lost_interval <= 0 {
+            0.
+        } else {
+            lost_interval as f64 * 100. / expected_interval as f64
+        };
+
+        RtpStatistics {
+            packets_lost: expected as i64 - self.received as i64,
+            fraction_lost,
+            jitter: self.jitter * 1000. / self.clock_rate as f64,
+            rtt: None,
+        }
+    }
+}
+
+/// The round trip time of the nominated ice candidate pair in milliseconds.
+pub fn round_trip_time(report: &StatsReport) -> Option<f64> {
+    report.reports.values().find_map(|stats| match stats {
+        StatsReportType::CandidatePair(pair) if pair.nominated => {
+            Some(pair.current_round_trip_time * 1000.)
+        }
+        _ => None,
+    })
+}
+
+/// The video codec of a h264 stream from its sps nalu.
+pub fn video_codec_statistic(sps: &[u8]) -> Option<StatisticData> {
+    let mut sps_parser = SpsParser::new(BytesReader::new(BytesMut::from(sps.get(1..)?)));
+    let (width, height) = sps_parser.parse().ok()?;
+
+    Some(StatisticData::VideoCodec {
+        codec: AvcCodecId::H264,
+        profile: define::u8_2_avc_profile(sps_parser.sps.profile_idc),
+        level: define::u8_2_avc_level(sps_parser.sps.level_idc),
+        width,
+        height,
+    })
+}
+
+/// The audio codec negotiated for an opus track.
+pub fn audio_codec_statistic(codec: &Codec) -> StatisticData {
+    StatisticData::AudioCodec {
+        sound_format: SoundFormat::OPUS,
+        profile: AacProfile::UNKNOWN,
+        samplerate: codec.clock_rate,
+        channels: codec.encoding_parameters.parse().unwrap_or(2),
+    }
+}
+
+//Report the traffic of a whep player and the rtp statistics of the video it receives,
+//the jitter is taken from the rtcp receiver reports of the player.
+pub async fn report_player_statistics(
+    peer_connection: Weak<RTCPeerConnection>, id: Uuid, sender: StatisticDataSender,
+    jitter: Arc<AtomicU32>,
+) {
+    let mut interval = tokio::time::interval(STATISTICS_INTERVAL);
+    let mut last_bytes_sent = 0;
+
+    loop {
+        interval.tick().await;
+        let Some(pc) = peer_connection.upgrade() else {
+            break;
+        };
+        if pc.connection_state() == RTCPeerConnectionState::Closed {
+            break;
+        }
+        let report = pc.get_stats().await;
+
+        let mut bytes_sent = 0;
+        let mut statistics = RtpStatistics::default();
+        for stats in report.reports.values() {
+            match stats {
+                StatsReportType::OutboundRTP(outbound) => {
+                    bytes_sent += outbound.header_bytes_sent + outbound.bytes_sent;
+                }
+                StatsReportType::RemoteInboundRTP(remote) if remote.kind == "video" => {
+                    statistics.packets_lost = remote.packets_lost;
+                    statistics.fraction_lost = remote.fraction_lost * 100.;
+                    statistics.rtt = remote.round_trip_time.map(|rtt| rtt * 1000.);
+                }
+                _ => {}
+            }
+        }
+        statistics.rtt = statistics.rtt.or_else(|| round_trip_time(&report));
+        statistics.jitter = jitter.load(Ordering::Relaxed) as f64 * 1000.
+            / crate::rtp2frame::H264_CLOCK_RATE as f64;
+
+        let bytes = StatisticData::SubscriberBytes {
+            uuid: id,
+            data_size: bytes_sent.saturating_sub(last_bytes_sent),
+        };
+        last_bytes_sent = bytes_sent;
+        let rtp = StatisticData::Rtp {
+            uuid: Some(id),
+            statistics,
+        };
+        if sender.send(bytes).is_err() || sender.send(rtp).is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rtp_receive_statistics() {
+        let mut statistics = RtpReceiveStatistics::new(90000);
+        let start = Instant::now();
+        //the sequence numbers wrap around and one packet is lost
+        for idx in 0..200_u32 {
+            if idx == 50 {
+                continue;
+            }
+            let sequence_number = 65500_u16.wrapping_add(idx as u16);
+            let arrival = start + Duration::from_millis(idx as u64 * 10);
+            statistics.update(sequence_number, 1000 + idx * 900, arrival);
+        }
+
+        let report = statistics.report();
+        assert_eq!(report.packets_lost, 1);
+        assert_eq!(report.fraction_lost, 0.5);
+        assert!(report.jitter < 1., "{}", report.jitter);
+
+        //nothing is lost in the next interval
+        for idx in 200..300_u32 {
+            let arrival = start + Duration::from_millis(idx as u64 * 10);
+            statistics.update(
+                65500_u16.wrapping_add(idx as u16),
+                1000 + idx * 900,
+                arrival,
+            );
+        }
+        let report = statistics.report();
+        assert_eq!(report.packets_lost, 1);
+        assert_eq!(report.fraction_lost, 0.);
+    }
+
+    #[test]
+    fn test_rtp_receive_statistics_jitter() {
+        let mut statistics = RtpReceiveStatistics::new(90000);
+        let start = Instant::now();
+        //every second packet arrives 20ms late
+        for idx in 0..1000_u32 {
+            let delay = if idx % 2 == 0 { 0 } else { 20 };
+            let arrival = start + Duration::from_millis(idx as u64 * 40 + delay);
+            statistics.update(idx as u16, idx * 3600, arrival);
+        }
+
+        let jitter = statistics.report().jitter;
+        assert!((jitter - 20.).abs() < 1., "{}", jitter);
+    }
+
+    #[test]
+    fn test_video_codec_statistic() {
+        //a 1280x720 baseline sps
+        let sps = [0x67, 0x42, 0xc0, 0x1f, 0xda, 0x01, 0x40, 0x16, 0xe4];
+        let Some(StatisticData::VideoCodec { width, height, .. }) = video_codec_statistic(&sps)
+        else {
+            panic!("no video codec");
+        };
+        assert_eq!((width, height), (1280, 720));
+    }
+}
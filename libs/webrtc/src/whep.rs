@@ -1,9 +1,14 @@
 use super::errors::WebRTCError;
 use super::errors::WebRTCErrorValue;
 
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
 use streamhub::define::PacketData;
-use streamhub::define::PacketDataReceiver;
+use streamhub::define::{
+    StatisticDataSender, StreamHubEvent, StreamHubEventSender, SubPacketDataReceiver,
+};
+use streamhub::stream::StreamIdentifier;
+use streamhub::utils::Uuid;
 
 use webrtc::api::interceptor_registry::register_default_interceptors;
 use webrtc::api::media_engine::MIME_TYPE_AV1;
@@ -16,6 +21,9 @@ use webrtc::peer_connection::configuration::RTCConfiguration;
 
 use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
 use webrtc::peer_connection::RTCPeerConnection;
+use webrtc::rtcp::payload_feedbacks::full_intra_request::FullIntraRequest;
+use webrtc::rtcp::payload_feedbacks::picture_loss_indication::PictureLossIndication;
+use webrtc::rtcp::receiver_report::ReceiverReport;
 
 use tokio::sync::broadcast;
 use webrtc::rtp_transceiver::rtp_codec::RTCRtpCodecCapability;
@@ -30,8 +38,9 @@ use webrtc::peer_connection::peer_connection_state::RTCPeerConnectionState;
 use super::webrtc::ICE_SERVERS;
 
 pub async fn handle_whep(
-    offer: RTCSessionDescription, mut receiver: PacketDataReceiver,
-    state_sender: broadcast::Sender<RTCPeerConnectionState>,
+    offer: RTCSessionDescription, mut receiver: SubPacketDataReceiver,
+    state_sender: broadcast::Sender<RTCPeerConnectionState>, event_sender: StreamHubEventSender,
+    identifier: StreamIdentifier, session_id: Uuid, statistic_sender: Option<StatisticDataSender>,
 ) -> Result<(RTCSessionDescription, Arc<RTCPeerConnection>)> {
     // Everything below is the WebRTC-rs API! Thanks for using it ❤️.
 
@@ -99,12 +108,55 @@ pub async fn handle_whep(
         .add_track(Arc::clone(&audio_track) as Arc<dyn TrackLocal + Send + Sync>)
         .await?;
 
+    //the receiver reports of the audio track are ignored
+    let video_ssrc = rtp_sender
+        .get_parameters()
+        .await
+        .encodings
+        .first()
+        .map(|encoding| encoding.ssrc);
+
+    //the interarrival jitter of the video reported by the player
+    let jitter = Arc::new(AtomicU32::new(0));
+    if let Some(sender) = statistic_sender {
+        tokio::spawn(super::statistics::report_player_statistics(
+            Arc::downgrade(&peer_connection),
+            session_id,
+            sender,
+            jitter.clone(),
+        ));
+    }
+
     // Read incoming RTCP packets
     // Before these packets are returned they are processed by interceptors. For things
     // like NACK this needs to be called.
     tokio::spawn(async move {
         let mut rtcp_buf = vec![0u8; 1500];
-        while let Ok((_, _)) = rtp_sender.read(&mut rtcp_buf).await {}
+        while let Ok((packets, _)) = rtp_sender.read(&mut rtcp_buf).await {
+            for packet in &packets {
+                if let Some(receiver_report) = packet.as_any().downcast_ref::<ReceiverReport>() {
+                    for report in &receiver_report.reports {
+                        if Some(report.ssrc) == video_ssrc {
+                            jitter.store(report.jitter, Ordering::Relaxed);
+                        }
+                    }
+                }
+            }
+            //the key frame requests of the player are forwarded to the publisher,
+            //which limits the rate of the requests of all the players
+            let key_frame_requested = packets.iter().any(|packet| {
+                let packet = packet.as_any();
+                packet.is::<PictureLossIndication>() || packet.is::<FullIntraRequest>()
+            });
+            if key_frame_requested {
+                let event = StreamHubEvent::RequestKeyFrame {
+                    identifier: identifier.clone(),
+                };
+                if event_sender.send(event).is_err() {
+                    break;
+                }
+            }
+        }
         Result::<()>::Ok(())
     });
 
@@ -158,21 +210,29 @@ pub async fn handle_whep(
     let _ = gather_complete.recv().await;
 
     // Read RTP packets forever and send them to the WebRTC Client
+    let pc = Arc::downgrade(&peer_connection);
     tokio::spawn(async move {
         loop {
             tokio::select! {
                 av_data = receiver.recv() =>{
-                    if let Some(data) = av_data {
-                        match data {
-                            PacketData::Video { timestamp: _, data } => {
-                                if let Err(err) = video_track.write(&data[..]).await {
-                                    log::error!("send video data error: {}", err);
-                                }
+                    // the channel is closed when the stream is unpublished or the client is kicked
+                    let Some(data) = av_data else {
+                        if let Some(pc) = pc.upgrade() {
+                            if let Err(err) = pc.close().await {
+                                log::error!("peer connection close error: {}", err);
                             }
-                            PacketData::Audio { timestamp: _, data } => {
-                                if let Err(err) = audio_track.write(&data[..]).await {
-                                    log::error!("send audio data error: {}", err);
-                                }
+                        }
+                        break;
+                    };
+                    match data {
+                        PacketData::Video { timestamp: _, data } => {
+                            if let Err(err) = video_track.write(&data[..]).await {
+                                log::error!("send video data error: {}", err);
+                            }
+                        }
+                        PacketData::Audio { timestamp: _, data } => {
+                            if let Err(err) = audio_track.write(&data[..]).await {
+                                log::error!("send audio data error: {}", err);
                             }
                         }
                     }
@@ -1,18 +1,19 @@
-use crate::opus2aac::Opus2AacTranscoder;
+use crate::rtp2frame::{split_nalus, H264FrameAssembler, OpusFrameTranscoder, H264_CLOCK_RATE};
+use crate::session::WebRTCStreamHandler;
+use crate::statistics::{self, RtpReceiveStatistics, STATISTICS_INTERVAL};
 
 use super::errors::WebRTCError;
 use super::errors::WebRTCErrorValue;
-use bytes::BytesMut;
-use tokio::sync::RwLock;
+use bytes::Bytes;
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::ops::ControlFlow;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use streamhub::define::VideoCodecType;
-use streamhub::define::{FrameData, PacketData};
+use std::time::Instant;
+use streamhub::define::{FrameData, PacketData, StatisticData, StatisticDataSender};
 use tokio::sync::mpsc::UnboundedSender;
-use webrtc::rtp::codecs::opus::OpusPacket;
 
 use tokio::time::Duration;
 use webrtc::api::interceptor_registry::register_default_interceptors;
@@ -24,17 +25,14 @@ use webrtc::peer_connection::configuration::RTCConfiguration;
 use webrtc::peer_connection::peer_connection_state::RTCPeerConnectionState;
 use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
 use webrtc::peer_connection::RTCPeerConnection;
-use webrtc::rtcp::payload_feedbacks::picture_loss_indication::PictureLossIndication;
-use webrtc::rtp::codecs::h264::H264Packet;
 use webrtc::sdp::util::Codec;
 
-use super::rtp_queue::RtpQueue;
-use webrtc::rtp::packetizer::Depacketizer;
 use webrtc::rtp_transceiver::rtp_codec::RTPCodecType;
 use webrtc::rtp_transceiver::rtp_transceiver_direction::RTCRtpTransceiverDirection;
 use webrtc::rtp_transceiver::RTCRtpTransceiverInit;
 use webrtc::util::Marshal;
-use xflv::mpeg4_aac::Mpeg4Aac;
+use xflv::define::aac_packet_type::AAC_RAW;
+use xflv::define::h264_nal_type::{H264_NAL_IDR, H264_NAL_PPS, H264_NAL_SPS};
 
 use super::webrtc::ICE_SERVERS;
 
@@ -149,8 +147,8 @@ impl FromStr for MediaCodec {
 
 pub async fn handle_whip(
     offer: RTCSessionDescription, frame_sender: UnboundedSender<FrameData>,
-    packet_sender: UnboundedSender<PacketData>,
-    stream_codecs: Arc<RwLock<HashMap<String, Codec>>>,
+    packet_sender: UnboundedSender<PacketData>, stream_handler: Arc<WebRTCStreamHandler>,
+    statistic_sender: Option<StatisticDataSender>,
 ) -> Result<(RTCSessionDescription, Arc<RTCPeerConnection>)> {
     // Create a MediaEngine object to configure the supported codec
     let mut m = MediaEngine::default();
@@ -214,38 +212,36 @@ pub async fn handle_whip(
     // our UDP listeners.
     // In your application this is where you would handle/process audio/video
 
-    // let pc = Arc::downgrade(&peer_connection);
+    let pc = Arc::downgrade(&peer_connection);
     peer_connection.on_track(Box::new(move |track, _, _| {
-        // Send a PLI on an interval so that the publisher is pushing a keyframe every rtcpPLIInterval
-        // let media_ssrc = track.ssrc();
-        // let pc2 = pc.clone();
-        // tokio::spawn(async move {
-        //     let mut result = Result::<usize>::Ok(0);
-        //     while result.is_ok() {
-        //         let timeout = tokio::time::sleep(Duration::from_secs(3));
-        //         // tokio::pin!(timeout);
-
-        //         tokio::select! {
-        //             _ = timeout =>{
-        //                 if let Some(pc) = pc2.upgrade(){
-        //                     result = pc.write_rtcp(&[Box::new(PictureLossIndication{
-        //                         sender_ssrc: 0,
-        //                         media_ssrc,
-        //                     })]).await.map_err(Into::into);
-        //                 }else{
-        //                     break;
-        //                 }
-        //             }
-        //         };
-        //     }
-        // });
-
         let sdp = sdp.clone();
+        let pc = pc.clone();
         let packet_sender_clone = packet_sender.clone();
-        // let frame_sender_clone = frame_sender.clone();
+        let frame_sender_clone = frame_sender.clone();
+        let stream_handler = stream_handler.clone();
+        let statistic_sender = statistic_sender.clone();
         // let offer_clone = offer_in.clone();
         tokio::spawn(async move {
+            //the key frame requests of the whep subscribers are sent to the video track
+            if track.kind() == RTPCodecType::Video {
+                stream_handler
+                    .set_video_track(pc.clone(), track.ssrc())
+                    .await;
+            }
+
             let mut b = vec![0u8; 3000];
+            //the frames are remuxed to rtmp, the opus audio is transcoded to aac
+            let mut h264_assembler = H264FrameAssembler::default();
+            let mut opus_transcoder = match track.kind() {
+                RTPCodecType::Audio => match OpusFrameTranscoder::new() {
+                    Ok(transcoder) => Some(transcoder),
+                    Err(err) => {
+                        log::error!("create opus2aac transcoder error: {:?}", err);
+                        None
+                    }
+                },
+                _ => None,
+            };
             // let mut h264_packet = H264Packet::default();
             // let mut opus_packet = OpusPacket;
 
@@ -330,6 +326,11 @@ pub async fn handle_whip(
 
             let mut high_speed_codec_map: HashMap<u8, Codec> = HashMap::new();
 
+            //the loss and jitter of the video are reported with the rtt of the connection
+            let mut rtp_statistics = RtpReceiveStatistics::new(H264_CLOCK_RATE);
+            let mut last_report = Instant::now();
+            let mut last_sps = Vec::new();
+
             while let Ok((rtp_packet, _)) = track.read(&mut b).await {
                 let n = rtp_packet.marshal_to(&mut b)?;
 
@@ -339,7 +340,15 @@ pub async fn handle_whip(
                 let codec = match entry {
                     Entry::Occupied(ref e) => e.get(),
                     Entry::Vacant(e) => match sdp.get_codec_for_payload_type(payload_type) {
-                        Ok(c) => e.insert(c),
+                        Ok(c) => {
+                            if c.name.eq_ignore_ascii_case("opus") {
+                                send_statistic(
+                                    &statistic_sender,
+                                    statistics::audio_codec_statistic(&c),
+                                );
+                            }
+                            e.insert(c)
+                        }
                         Err(err) => {
                             log::error!("get codec error: {}, payload_type: {}", err, payload_type);
                             continue;
@@ -355,36 +364,133 @@ pub async fn handle_whip(
                     }
                 };
 
+                let data = Bytes::copy_from_slice(&b[..n]);
+                if let MediaCodec::H264 = media_codec {
+                    stream_handler.save_video_packet(&data).await;
+                }
+                if media_codec.is_video() {
+                    rtp_statistics.update(
+                        rtp_packet.header.sequence_number,
+                        rtp_packet.header.timestamp,
+                        Instant::now(),
+                    );
+                    if last_report.elapsed() >= STATISTICS_INTERVAL {
+                        last_report = Instant::now();
+                        let mut statistics = rtp_statistics.report();
+                        if let Some(pc) = pc.upgrade() {
+                            statistics.rtt = statistics::round_trip_time(&pc.get_stats().await);
+                        }
+                        send_statistic(
+                            &statistic_sender,
+                            StatisticData::Rtp {
+                                uuid: None,
+                                statistics,
+                            },
+                        );
+                    }
+                }
                 let packet = match media_codec.media_type() {
                     MediaType::Video => PacketData::Video {
                         timestamp: rtp_packet.header.timestamp,
-                        data: BytesMut::from(&b[..n]),
+                        data,
                     },
                     MediaType::Audio => PacketData::Audio {
                         timestamp: rtp_packet.header.timestamp,
-                        data: BytesMut::from(&b[..n]),
+                        data,
                     },
                 };
 
+                // the receiver is dropped when the stream is unpublished or the publisher is kicked
                 if let Err(err) = packet_sender_clone.send(packet) {
                     log::error!("send packet error: {}", err);
+                    if let Some(pc) = pc.upgrade() {
+                        if let Err(err) = pc.close().await {
+                            log::error!("peer connection close error: {}", err);
+                        }
+                    }
+                    break;
                 }
 
-                // TODO: depacketize and send video/audio packets to `frame_sender`
-                // ** Maybe `frame_sender` not used in WebRTC? **
-                // ** I wont implement it nearly. **
-
-                // match media_codec {
-                //     MediaCodec::H264 => {
-                //         // TODO
-                //     }
-                //     MediaCodec::Opus => {
-                //         // TODO
-                //     }
-                //     c => {
-                //         log::error!("codec {:?} not implemented yet", c);
-                //     }
-                // }
+                let frames: Vec<FrameData> = match media_codec {
+                    MediaCodec::H264 => {
+                        let frames = h264_assembler.push(rtp_packet);
+                        for (_, data) in &frames {
+                            let mut is_key_frame = false;
+                            for nalu in split_nalus(data) {
+                                match nalu.first().map(|header| header & 0x1F) {
+                                    Some(H264_NAL_SPS) => {
+                                        if nalu != last_sps.as_slice() {
+                                            last_sps = nalu.to_vec();
+                                            if let Some(codec) =
+                                                statistics::video_codec_statistic(nalu)
+                                            {
+                                                send_statistic(&statistic_sender, codec);
+                                            }
+                                        }
+                                        stream_handler.set_sps(nalu.to_vec()).await
+                                    }
+                                    Some(H264_NAL_PPS) => {
+                                        stream_handler.set_pps(nalu.to_vec()).await
+                                    }
+                                    Some(H264_NAL_IDR) => is_key_frame = true,
+                                    _ => {}
+                                }
+                            }
+                            send_statistic(
+                                &statistic_sender,
+                                StatisticData::Video {
+                                    uuid: None,
+                                    data_size: data.len() as u64,
+                                    frame_count: 1,
+                                    is_key_frame: Some(is_key_frame),
+                                    duration: 0,
+                                },
+                            );
+                        }
+                        frames
+                            .into_iter()
+                            .map(|(timestamp, data)| FrameData::Video {
+                                timestamp,
+                                data: data.freeze(),
+                            })
+                            .collect()
+                    }
+                    MediaCodec::Opus => {
+                        send_statistic(
+                            &statistic_sender,
+                            StatisticData::Audio {
+                                uuid: None,
+                                data_size: rtp_packet.payload.len() as u64,
+                                aac_packet_type: AAC_RAW,
+                                duration: 0,
+                            },
+                        );
+                        match opus_transcoder
+                            .as_mut()
+                            .map(|transcoder| transcoder.push(&rtp_packet))
+                        {
+                            Some(Ok(frames)) => frames
+                                .into_iter()
+                                .map(|(timestamp, data)| FrameData::Audio {
+                                    timestamp,
+                                    data: data.freeze(),
+                                })
+                                .collect(),
+                            Some(Err(err)) => {
+                                log::error!("opus2aac transcode error: {:?}", err);
+                                Vec::new()
+                            }
+                            None => Vec::new(),
+                        }
+                    }
+                    _ => Vec::new(),
+                };
+
+                for frame in frames {
+                    if let Err(err) = frame_sender_clone.send(frame) {
+                        log::error!("send frame error: {}", err);
+                    }
+                }
 
                 /*
                 match rtp_packet.header.payload_type {
@@ -513,22 +619,39 @@ pub async fn handle_whip(
     // Set the handler for Peer connection state
     // This will notify you when the peer has connected/disconnected
 
+    let pc_weak = Arc::downgrade(&peer_connection);
+    //a failure after the connection is established is not a handshake failure
+    let connected = Arc::new(AtomicBool::new(false));
     peer_connection.on_peer_connection_state_change(Box::new(move |s: RTCPeerConnectionState| {
         log::info!("Peer Connection State has changed: {s}");
 
-        match s {
-            RTCPeerConnectionState::Failed => {
-                // Wait until PeerConnection has had no network activity for 30 seconds or another failure. It may be reconnected using an ICE Restart.
-                // Use webrtc.PeerConnectionStateDisconnected if you are interested in detecting faster timeout.
-                // Note that the PeerConnection may come back from PeerConnectionStateDisconnected.
-                println!("Peer Connection has gone to failed exiting: Done forwarding");
-            }
-            RTCPeerConnectionState::Closed => {
-                // TODO: disconnect all clients
+        let pc = pc_weak.clone();
+        let connected = connected.clone();
+        Box::pin(async move {
+            match s {
+                RTCPeerConnectionState::Connected => connected.store(true, Ordering::Relaxed),
+                RTCPeerConnectionState::Failed => {
+                    // Wait until PeerConnection has had no network activity for 30 seconds or another failure. It may be reconnected using an ICE Restart.
+                    // Use webrtc.PeerConnectionStateDisconnected if you are interested in detecting faster timeout.
+                    // Note that the PeerConnection may come back from PeerConnectionStateDisconnected.
+                    log::info!("Peer Connection has gone to failed exiting: Done forwarding");
+                    if !connected.load(Ordering::Relaxed) {
+                        metrics::counter!(crate::metrics::HANDSHAKE_FAILURES, "kind" => "whip")
+                            .increment(1);
+                    }
+                    if let Some(pc) = pc.upgrade() {
+                        if let Err(err) = pc.close().await {
+                            log::error!("peer connection close error: {}", err);
+                        }
+                    }
+                }
+                RTCPeerConnectionState::Closed => {
+                    metrics::gauge!(crate::metrics::PEER_CONNECTIONS, "kind" => "whip")
+                        .decrement(1);
+                }
+                _ => {}
             }
-            _ => {}
-        }
-        Box::pin(async {})
+        })
     }));
 
     // Set the remote SessionDescription
@@ -550,6 +673,7 @@ pub async fn handle_whip(
 
     // Output the answer in base64 so we can paste it in browser
     if let Some(local_desc) = peer_connection.local_description().await {
+        metrics::gauge!(crate::metrics::PEER_CONNECTIONS, "kind" => "whip").increment(1);
         Ok((local_desc, peer_connection))
     } else {
         Err(WebRTCError {
@@ -557,3 +681,11 @@ pub async fn handle_whip(
         })
     }
 }
+
+fn send_statistic(sender: &Option<StatisticDataSender>, data: StatisticData) {
+    if let Some(sender) = sender {
+        if let Err(err) = sender.send(data) {
+            log::error!("send statistic data err: {}", err);
+        }
+    }
+}
@@ -1,14 +1,235 @@
-use crate::{config, services};
+use std::future::Future;
+
+use rtmp::relay::{restream::RestreamManager, status::RelayStatuses};
+use streamhub::StreamsHub;
+use tokio::task::JoinSet;
+use tracing::{error, info, warn};
+
+use crate::{
+    config,
+    services::{self, auth::AuthChain, state::live_room::LiveRooms},
+};
 
 pub async fn start_app(conf: &config::AppConfig) {
     let span = tracing::span!(tracing::Level::DEBUG, "live_stream_app");
     let _ = span.enter();
 
-    // services::stream::pull_stream::rtmp_server(&conf)
-    //     .await
-    //     .expect("TODO: panic message");
+    let notifier = match services::webhook::build_notifier(&conf.webhook) {
+        Ok(notifier) => notifier,
+        Err(err) => {
+            error!("failed to build the webhook notifier: {:?}", err);
+            return;
+        }
+    };
+
+    // every protocol listener publishes to / subscribes from the same hub,
+    // so a stream ingested over one protocol can be played over another.
+    let mut stream_hub = StreamsHub::new(notifier.clone());
+    stream_hub.set_subscriber_queue_config(services::stream::hub::subscriber_queue_config(
+        &conf.stream.subscriber_queue,
+    ));
+    let sender = stream_hub.get_hub_event_sender();
+
+    // the rooms are managed by the apiserver through the LiveRoomService,
+    // they are checked by the `live_room` auth backend.
+    let rooms = LiveRooms::new();
+    let authenticator = match AuthChain::from_conf(&conf.auth, &rooms) {
+        Ok(authenticator) => authenticator,
+        Err(err) => {
+            error!("failed to build the auth chain: {:?}", err);
+            return;
+        }
+    };
+
+    // the recorder is installed before any listener, no metric is dropped
+    let metrics = if conf.metrics.enabled {
+        services::metrics::install_recorder()
+    } else {
+        None
+    };
+
+    let mut listeners = JoinSet::new();
+
+    if conf.stream.rtmp.enabled {
+        spawn_listener(
+            &mut listeners,
+            "rtmp",
+            services::stream::pull_stream::rtmp_server(
+                conf.stream.rtmp.clone(),
+                sender.clone(),
+                authenticator.clone(),
+            ),
+        );
+    }
+
+    if conf.stream.webrtc.enabled {
+        spawn_listener(
+            &mut listeners,
+            "webrtc",
+            services::stream::webrtc::start_server(
+                conf.stream.webrtc.clone(),
+                sender.clone(),
+                authenticator.clone(),
+            ),
+        );
+
+        if conf.stream.webrtc.remux_rtmp {
+            stream_hub.set_webrtc_remuxer_enabled(true);
+            spawn_listener(
+                &mut listeners,
+                "webrtc_remuxer",
+                services::stream::webrtc::start_remuxer(
+                    sender.clone(),
+                    stream_hub.get_client_event_consumer(),
+                ),
+            );
+        }
+    }
+
+    // the whip and rtsp streams are remuxed to rtmp by the same remuxer
+    let remux_whip = conf.stream.webrtc.enabled && conf.stream.webrtc.remux_whip;
+    let remux_rtsp = conf.stream.rtsp.remux_rtmp;
+    if remux_whip || remux_rtsp {
+        stream_hub.set_whip_remuxer_enabled(remux_whip);
+        stream_hub.set_rtsp_remuxer_enabled(remux_rtsp);
+        spawn_listener(
+            &mut listeners,
+            "rtmp_remuxer",
+            services::stream::webrtc::start_rtmp_remuxer(
+                sender.clone(),
+                stream_hub.get_client_event_consumer(),
+            ),
+        );
+    }
+
+    if conf.stream.hls.enabled {
+        stream_hub.set_hls_enabled(true);
+        spawn_listener(
+            &mut listeners,
+            "hls",
+            services::stream::hls::start_server(
+                conf.stream.clone(),
+                sender.clone(),
+                stream_hub.get_client_event_consumer(),
+            ),
+        );
+    }
+
+    if conf.record.enabled {
+        stream_hub.set_record_enabled(true);
+        spawn_listener(
+            &mut listeners,
+            "record",
+            services::stream::record::start_recorder(
+                conf.record.clone(),
+                sender.clone(),
+                stream_hub.get_client_event_consumer(),
+                notifier,
+            ),
+        );
+    }
+
+    // the relay status is reported by the admin api
+    let relays = RelayStatuses::new();
+    if !conf.relay.push.is_empty() {
+        stream_hub.set_rtmp_push_enabled(true);
+        spawn_listener(
+            &mut listeners,
+            "relay_push",
+            services::stream::relay::start_push_relay(
+                conf.relay.clone(),
+                sender.clone(),
+                stream_hub.get_client_event_consumer(),
+                relays.clone(),
+            ),
+        );
+    }
+
+    if !conf.relay.pull.is_empty() {
+        spawn_listener(
+            &mut listeners,
+            "relay_pull",
+            services::stream::relay::start_pull_relay(
+                conf.relay.clone(),
+                sender.clone(),
+                relays.clone(),
+            ),
+        );
+    }
+
+    if conf.stream.httpflv.enabled {
+        spawn_listener(
+            &mut listeners,
+            "httpflv",
+            services::stream::httpflv::start_server(
+                conf.stream.httpflv.clone(),
+                sender.clone(),
+                authenticator.clone(),
+            ),
+        );
+    }
+
+    if conf.stream.rtsp.enabled {
+        spawn_listener(
+            &mut listeners,
+            "rtsp",
+            services::stream::rtsp::start_server(
+                conf.stream.rtsp.clone(),
+                sender.clone(),
+                authenticator.clone(),
+            ),
+        );
+    }
+
+    if listeners.is_empty() {
+        warn!("no stream listener is enabled");
+    }
+
+    if conf.admin.enabled {
+        spawn_listener(
+            &mut listeners,
+            "admin",
+            services::admin::start_server(
+                conf.admin.clone(),
+                sender.clone(),
+                relays.clone(),
+                RestreamManager::new(
+                    sender.clone(),
+                    services::stream::relay::reconnect_policy(&conf.relay),
+                    relays,
+                ),
+                rooms.clone(),
+                metrics,
+            ),
+        );
+    }
+
+    spawn_listener(
+        &mut listeners,
+        "keepalive",
+        services::state::streamserver::keep_alive_loop(conf.server.clone(), sender.clone()),
+    );
+
+    spawn_listener(
+        &mut listeners,
+        "grpc",
+        services::server::start_server(conf.server.clone(), rooms, sender.clone()),
+    );
+
+    tokio::spawn(async move { stream_hub.run().await });
+
+    while let Some(res) = listeners.join_next().await {
+        match res {
+            Ok((name, Ok(()))) => info!("{} listener stopped", name),
+            Ok((name, Err(err))) => error!("{} listener failed: {:?}", name, err),
+            Err(err) => error!("stream listener task aborted: {}", err),
+        }
+    }
+}
 
-    services::stream::webrtc::start_server(conf)
-        .await
-        .expect("TODO: panic message");
+fn spawn_listener<F>(listeners: &mut JoinSet<(&'static str, anyhow::Result<()>)>, name: &'static str, fut: F)
+where
+    F: Future<Output = anyhow::Result<()>> + Send + 'static,
+{
+    listeners.spawn(async move { (name, fut.await) });
 }
@@ -1,6 +1,25 @@
+/// `get_listen_addr` of the listener configs with a `host` and a `port`.
+macro_rules! impl_listen_addr {
+    ($($conf:ty),+) => {
+        $(
+            impl $conf {
+                pub fn get_listen_addr(&self) -> String {
+                    format!("{}:{}", self.host, self.port)
+                }
+            }
+        )+
+    };
+}
+
+pub mod admin;
+pub mod auth;
 pub mod log;
+pub mod metrics;
+pub mod record;
+pub mod relay;
 pub mod server;
 pub mod stream;
+pub mod webhook;
 
 use anyhow::Context;
 use serde::Deserialize;
@@ -17,13 +36,27 @@ pub struct AppConfig {
     pub server: server::ServerConf,
     #[serde(default)]
     pub stream: stream::StreamConf,
+    #[serde(default)]
+    pub admin: admin::AdminConf,
+    #[serde(default)]
+    pub metrics: metrics::MetricsConf,
+    #[serde(default)]
+    pub auth: auth::AuthConf,
+    #[serde(default)]
+    pub webhook: webhook::WebhookConf,
+    #[serde(default)]
+    pub record: record::RecordConf,
+    #[serde(default)]
+    pub relay: relay::RelayConf,
 }
 
 impl AppConfig {
     pub fn load_from_config(config: config::Config) -> anyhow::Result<Self> {
-        config
+        let conf: Self = config
             .try_deserialize()
-            .with_context(|| "Failed to deserialize config")
+            .with_context(|| "Failed to deserialize config")?;
+        conf.stream.check()?;
+        Ok(conf)
     }
 
     pub fn load_from_file(file_path: Option<&str>, env_prefix: &str) -> anyhow::Result<Self> {
@@ -57,3 +90,23 @@ impl<T, O> With<T> for O {
         f(self)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_removed_stream_listener() {
+        let load = |key: &str, value: &str| {
+            let config = config::Config::builder()
+                .set_override(key, value)
+                .and_then(|builder| builder.build())
+                .unwrap();
+            AppConfig::load_from_config(config)
+        };
+
+        assert!(load("stream.webrtc.port", "8081").is_ok());
+        assert!(load("stream.port", "8081").is_err());
+        assert!(load("stream.host", "0.0.0.0").is_err());
+    }
+}
@@ -0,0 +1,27 @@
+use serde::Deserialize;
+
+/// Admin http listener, serving the statistics / kick api, the health probes and the metrics.
+///
+/// The api is protected by `token`, passed as `Authorization: Bearer <token>`;
+/// it is disabled while the token is empty. The probes and the metrics are not protected.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case", default)]
+pub struct AdminConf {
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+    pub token: String,
+}
+
+impl Default for AdminConf {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            host: "127.0.0.1".to_string(),
+            port: 8090,
+            token: "".to_string(),
+        }
+    }
+}
+
+impl_listen_addr!(AdminConf);
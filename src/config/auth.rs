@@ -0,0 +1,90 @@
+use serde::Deserialize;
+
+/// Authentication of the publish and play sessions, each direction has its own policy.
+///
+/// ```toml
+/// [auth.publish]
+/// mode = "any"
+/// backends = [
+///     { type = "live_room" },
+///     { type = "static_token", token = "123456" },
+/// ]
+///
+/// [auth.play]
+/// backends = [{ type = "signed_url", secret = "..." }]
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case", default)]
+pub struct AuthConf {
+    pub publish: AuthPolicyConf,
+    pub play: AuthPolicyConf,
+}
+
+impl Default for AuthConf {
+    fn default() -> Self {
+        Self {
+            publish: AuthPolicyConf::live_room(),
+            play: AuthPolicyConf::live_room(),
+        }
+    }
+}
+
+/// A chain of auth backends, an empty chain allows every session.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "snake_case", default)]
+pub struct AuthPolicyConf {
+    pub mode: AuthChainMode,
+    pub backends: Vec<AuthBackendConf>,
+}
+
+impl AuthPolicyConf {
+    fn live_room() -> Self {
+        Self {
+            mode: AuthChainMode::Any,
+            backends: vec![AuthBackendConf::LiveRoom],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthChainMode {
+    /// the session is allowed by the first backend allowing it
+    #[default]
+    Any,
+    /// the session has to be allowed by every backend
+    All,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum AuthBackendConf {
+    /// allow every session
+    None,
+    /// a `token` query param shared by every stream
+    StaticToken { token: String },
+    /// a `token` query param per app/stream, the `*` stream matches every stream of the app
+    Table { entries: Vec<AuthTableEntry> },
+    /// HMAC-SHA256 signed urls with an expiration: `?expires=..&sign=..`,
+    /// a url is signed for either publishing or playing
+    SignedUrl { secret: String },
+    /// ask an http service, a 2xx response allows the session
+    HttpCallback {
+        url: String,
+        #[serde(default = "default_callback_timeout_ms")]
+        timeout_ms: u64,
+    },
+    /// the rooms managed by the apiserver through the LiveRoomService
+    LiveRoom,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuthTableEntry {
+    pub app: String,
+    pub stream: String,
+    pub token: String,
+}
+
+fn default_callback_timeout_ms() -> u64 {
+    3000
+}
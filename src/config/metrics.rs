@@ -0,0 +1,17 @@
+use serde::Deserialize;
+
+/// Prometheus metrics of the process, e.g. the rtmp connections and the auth rejections.
+///
+/// The recorder is installed at startup whether or not the admin listener is
+/// enabled, the metrics are rendered by its `/metrics` route.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case", default)]
+pub struct MetricsConf {
+    pub enabled: bool,
+}
+
+impl Default for MetricsConf {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
@@ -0,0 +1,55 @@
+use serde::Deserialize;
+
+/// Recording of the published rtmp streams into flv files.
+///
+/// A stream is recorded when it is published with the `record`/`append` type,
+/// or its app is configured with `always = true`. When `apps` is not empty,
+/// only the listed apps are recorded.
+///
+/// ```toml
+/// [record]
+/// enabled = true
+/// path = "/data/record/{app}/{stream}-{time}.flv"
+/// max_duration_seconds = 3600
+/// apps = [
+///     { app = "live" },
+///     { app = "class", always = true, path = "/data/class/{stream}-{timestamp}.flv" },
+/// ]
+/// ```
+///
+/// The path placeholders are `{app}`, `{stream}`, `{time}` (`%Y%m%d%H%M%S`) and `{timestamp}`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case", default)]
+pub struct RecordConf {
+    pub enabled: bool,
+    pub path: String,
+    /// split the file at the next key frame over this size, 0 disables it
+    pub max_file_size: u64,
+    /// split the file at the next key frame over this duration, 0 disables it
+    pub max_duration_seconds: u32,
+    pub apps: Vec<RecordAppConf>,
+}
+
+impl Default for RecordConf {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: "./record/{app}/{stream}-{time}.flv".to_string(),
+            max_file_size: 0,
+            max_duration_seconds: 0,
+            apps: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "snake_case", default)]
+pub struct RecordAppConf {
+    pub app: String,
+    /// record the live published streams too
+    pub always: bool,
+    /// overrides the default path
+    pub path: Option<String>,
+    /// only record these streams if it is not empty
+    pub streams: Vec<String>,
+}
@@ -7,6 +7,11 @@ pub struct ServerConf {
     pub port: u16,
     pub api_addr: String,
     pub api_secret: String,
+
+    /// Seconds between two keepalive requests to the apiserver.
+    pub keep_alive_interval: u64,
+    /// Upper bound in seconds of the backoff between register retries.
+    pub register_max_backoff: u64,
 }
 
 impl Default for ServerConf {
@@ -16,6 +21,8 @@ impl Default for ServerConf {
             port: 8080,
             api_addr: "http://[::1]:9082".to_string(),
             api_secret: "secret".to_string(),
+            keep_alive_interval: 10,
+            register_max_backoff: 60,
         }
     }
 }
@@ -5,19 +5,31 @@ use serde::Deserialize;
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "snake_case", default)]
 pub struct StreamConf {
-    pub host: String,
-    pub port: u16,
+    pub rtmp: RtmpConf,
+    pub webrtc: WebRTCConf,
+    pub hls: HlsConf,
+    pub httpflv: HttpFlvConf,
+    pub rtsp: RtspConf,
+    pub subscriber_queue: SubscriberQueueConf,
 
     pub temp_hls_path: String,
     pub hls_fragment_seconds: i32,
     pub hls_fragment_max_count: i32,
+
+    // the removed single listener, it is rejected instead of being ignored
+    host: Option<String>,
+    port: Option<u16>,
 }
 
 impl Default for StreamConf {
     fn default() -> Self {
         Self {
-            host: "127.0.0.1".to_string(),
-            port: 8081,
+            rtmp: RtmpConf::default(),
+            webrtc: WebRTCConf::default(),
+            hls: HlsConf::default(),
+            httpflv: HttpFlvConf::default(),
+            rtsp: RtspConf::default(),
+            subscriber_queue: SubscriberQueueConf::default(),
             temp_hls_path: dirs::template_dir()
                 .unwrap_or(path::PathBuf::from("."))
                 .join("stream-hls")
@@ -26,6 +38,151 @@ impl Default for StreamConf {
                 .to_string(),
             hls_fragment_seconds: 1,
             hls_fragment_max_count: 10,
+            host: None,
+            port: None,
+        }
+    }
+}
+
+impl StreamConf {
+    pub fn check(&self) -> anyhow::Result<()> {
+        if self.host.is_some() || self.port.is_some() {
+            anyhow::bail!(
+                "`stream.host` and `stream.port` are removed, every protocol has its own \
+                 listener, e.g. `stream.webrtc.host` and `stream.webrtc.port`"
+            );
+        }
+        Ok(())
+    }
+}
+
+/// RTMP ingest / playback listener.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case", default)]
+pub struct RtmpConf {
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+}
+
+impl Default for RtmpConf {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            host: "127.0.0.1".to_string(),
+            port: 1935,
+        }
+    }
+}
+
+/// WHIP / WHEP http listener, `remux_rtmp` lets whep players play the rtmp
+/// streams, `remux_whip` lets rtmp, http-flv and hls play the whip streams.
+/// The remuxers are started when a stream is first played over the
+/// other protocol and stopped when its last player leaves.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case", default)]
+pub struct WebRTCConf {
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+    pub remux_rtmp: bool,
+    pub remux_whip: bool,
+}
+
+impl Default for WebRTCConf {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            host: "127.0.0.1".to_string(),
+            port: 8081,
+            remux_rtmp: true,
+            remux_whip: true,
+        }
+    }
+}
+
+/// HLS playlist / segment http listener, the segments are written under `temp_hls_path`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case", default)]
+pub struct HlsConf {
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+}
+
+impl Default for HlsConf {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            host: "127.0.0.1".to_string(),
+            port: 8082,
+        }
+    }
+}
+
+/// HTTP-FLV / WebSocket-FLV listener.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case", default)]
+pub struct HttpFlvConf {
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+}
+
+impl Default for HttpFlvConf {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            host: "127.0.0.1".to_string(),
+            port: 8083,
         }
     }
 }
+
+/// RTSP listener, publish with ANNOUNCE/RECORD and play with DESCRIBE/PLAY over
+/// tcp interleaved or udp transports. `remux_rtmp` lets rtmp, http-flv and hls
+/// play the rtsp streams, including the pulled rtsp cameras when the listener
+/// is disabled, and whep play them too if `webrtc.remux_rtmp` is set.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case", default)]
+pub struct RtspConf {
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+    pub remux_rtmp: bool,
+}
+
+impl Default for RtspConf {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            host: "127.0.0.1".to_string(),
+            port: 8554,
+            remux_rtmp: true,
+        }
+    }
+}
+
+/// The per-player queues of the hub. Above `drop_threshold` queued frames the
+/// non-reference video frames are dropped, a full queue drops the video until the
+/// next key frame, and a player staying above the threshold for `max_lag_ms` is
+/// disconnected.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case", default)]
+pub struct SubscriberQueueConf {
+    pub capacity: usize,
+    pub drop_threshold: usize,
+    pub max_lag_ms: u64,
+}
+
+impl Default for SubscriberQueueConf {
+    fn default() -> Self {
+        Self {
+            capacity: 1024,
+            drop_threshold: 512,
+            max_lag_ms: 10000,
+        }
+    }
+}
+
+impl_listen_addr!(RtmpConf, WebRTCConf, HlsConf, HttpFlvConf, RtspConf);
@@ -0,0 +1,73 @@
+use serde::Deserialize;
+
+/// Http hooks called on the stream hub events, an empty url disables its event.
+///
+/// ```toml
+/// [webhook]
+/// on_publish = "http://127.0.0.1:8000/hooks/publish"
+/// on_unpublish = "http://127.0.0.1:8000/hooks/unpublish"
+/// secret = "..."
+/// blocking_publish = true
+/// ```
+///
+/// With a `secret` the body is signed in the `X-Hub-Signature-256: sha256=<hex>` header.
+/// In the blocking mode the publish/play is denied unless the hook responds with 2xx.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case", default)]
+pub struct WebhookConf {
+    pub on_publish: String,
+    pub on_unpublish: String,
+    pub on_play: String,
+    pub on_stop: String,
+    pub on_stream_stalled: String,
+    pub on_record_done: String,
+    pub on_kick: String,
+
+    pub secret: String,
+    pub timeout_ms: u64,
+    pub max_retries: u32,
+    pub retry_backoff_ms: u64,
+    pub queue_size: usize,
+    pub blocking_publish: bool,
+    pub blocking_play: bool,
+    /// no data received in this duration makes a stream stalled, 0 disables it
+    pub stall_timeout_ms: u64,
+}
+
+impl Default for WebhookConf {
+    fn default() -> Self {
+        Self {
+            on_publish: "".to_string(),
+            on_unpublish: "".to_string(),
+            on_play: "".to_string(),
+            on_stop: "".to_string(),
+            on_stream_stalled: "".to_string(),
+            on_record_done: "".to_string(),
+            on_kick: "".to_string(),
+            secret: "".to_string(),
+            timeout_ms: 3000,
+            max_retries: 3,
+            retry_backoff_ms: 500,
+            queue_size: 1024,
+            blocking_publish: false,
+            blocking_play: false,
+            stall_timeout_ms: 10000,
+        }
+    }
+}
+
+impl WebhookConf {
+    pub fn is_enabled(&self) -> bool {
+        [
+            &self.on_publish,
+            &self.on_unpublish,
+            &self.on_play,
+            &self.on_stop,
+            &self.on_stream_stalled,
+            &self.on_record_done,
+            &self.on_kick,
+        ]
+        .iter()
+        .any(|url| !url.is_empty())
+    }
+}
@@ -92,11 +92,8 @@ async fn main() {
 
     tracing::debug!("bootstrap: {:?}", app_config);
 
-    // register api server
+    // the registration to the apiserver is kept alive in the background by the app
     let api_cfg = app_config.server.clone();
-    streamserver::services::state::streamserver::register_to_apiserver(&api_cfg)
-            .await
-            .expect("can't connect to apiserver");
 
     tokio::spawn(async move {
         app::start_app(&app_config).await;
@@ -106,8 +103,8 @@ async fn main() {
     tokio::signal::ctrl_c().await.expect("failed to install signal handler");
     info!("shutting down");
 
-    streamserver::services::state::streamserver::unregister_to_apiserver(&api_cfg)
-        .await
-        .expect("can't connect to apiserver");
+    if let Err(err) = streamserver::services::state::streamserver::unregister_to_apiserver(&api_cfg).await {
+        tracing::error!("unregister to apiserver failed: {}", err);
+    }
 
 }
@@ -425,6 +425,16 @@ pub struct StreamServerKeepAliveRequest {
     pub host: ::prost::alloc::string::String,
     #[prost(string, tag = "2")]
     pub secret: ::prost::alloc::string::String,
+    /// load of the stream server
+    #[prost(uint32, tag = "3")]
+    pub publisher_count: u32,
+    #[prost(uint32, tag = "4")]
+    pub subscriber_count: u32,
+    /// kbits/s
+    #[prost(uint64, tag = "5")]
+    pub ingress_bitrate: u64,
+    #[prost(uint64, tag = "6")]
+    pub egress_bitrate: u64,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct StreamServerKeepAliveResponse {
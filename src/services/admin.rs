@@ -0,0 +1,324 @@
+use auth::constant_time_eq;
+use axum::{
+    extract::{Path, Query, Request, State},
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{delete, get},
+    Json, Router,
+};
+use metrics_exporter_prometheus::PrometheusHandle;
+use rtmp::relay::{restream::RestreamManager, status::RelayStatuses};
+use serde::Deserialize;
+use serde_json::json;
+use streamhub::{
+    define::StreamHubEventSender,
+    stream::{StreamIdentifier, StreamKey},
+    utils::Uuid,
+};
+use tracing::{error, info, warn};
+
+use crate::{
+    config::admin::AdminConf,
+    services::{control, metrics, state::live_room::LiveRooms},
+};
+
+#[derive(Clone)]
+struct AdminState {
+    token: String,
+    sender: StreamHubEventSender,
+    relays: RelayStatuses,
+    restreams: RestreamManager,
+    rooms: LiveRooms,
+    metrics: Option<PrometheusHandle>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamsQuery {
+    top_n: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AttachRestream {
+    url: String,
+}
+
+/// Serve the admin api:
+///   GET    /healthz                                           liveness probe
+///   GET    /readyz                                            readiness probe, the stream hub is responsive
+///   GET    /metrics                                           prometheus metrics
+///   GET    /api/v1/streams?top_n=                             statistics of all the streams
+///   GET    /api/v1/streams/{protocol}/{app}/{stream}          statistics of a stream
+///   GET    /api/v1/streams/{protocol}/{app}/{stream}/{uuid}   statistics of a session of a stream
+///   DELETE /api/v1/sessions/{uuid}                            kick off a session
+///   GET    /api/v1/relays                                     status of the push/pull relays
+///   GET    /api/v1/restreams/{app}/{stream}                   restreams of a stream
+///   POST   /api/v1/restreams/{app}/{stream}                   push a stream over rtmp to {"url": ...}
+///   DELETE /api/v1/restreams/{app}/{stream}/{id}              stop a restream
+///   GET    /api/v1/rooms/{room_id}/restreams                  restreams of the stream of a room
+///   POST   /api/v1/rooms/{room_id}/restreams                  push the stream of a room to {"url": ...}
+///   DELETE /api/v1/rooms/{room_id}/restreams/{id}             stop a restream of a room
+/// The {protocol} is one of rtmp, httpflv, hls, rtsp and webrtc.
+pub async fn start_server(
+    conf: AdminConf, sender: StreamHubEventSender, relays: RelayStatuses,
+    restreams: RestreamManager, rooms: LiveRooms, metrics: Option<PrometheusHandle>,
+) -> anyhow::Result<()> {
+    if conf.token.is_empty() {
+        warn!("admin token is empty, the admin api is disabled");
+    }
+
+    let state = AdminState {
+        token: conf.token.clone(),
+        sender,
+        relays,
+        restreams,
+        rooms,
+        metrics,
+    };
+
+    let api = Router::new()
+        .route("/streams", get(list_streams))
+        .route("/streams/:protocol/:app/:stream", get(get_stream))
+        .route("/streams/:protocol/:app/:stream/:uuid", get(get_session))
+        .route("/sessions/:uuid", delete(kick_session))
+        .route("/relays", get(list_relays))
+        .route(
+            "/restreams/:app/:stream",
+            get(list_restreams).post(attach_restream),
+        )
+        .route("/restreams/:app/:stream/:id", delete(detach_restream))
+        .route(
+            "/rooms/:room_id/restreams",
+            get(list_room_restreams).post(attach_room_restream),
+        )
+        .route(
+            "/rooms/:room_id/restreams/:id",
+            delete(detach_room_restream),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth_middleware,
+        ));
+
+    let app = Router::new()
+        .route("/healthz", get(|| async { "ok" }))
+        .route("/readyz", get(readiness))
+        .route("/metrics", get(prometheus_metrics))
+        .nest("/api/v1", api)
+        .with_state(state);
+
+    let listener = tokio::net::TcpListener::bind(conf.get_listen_addr()).await?;
+    info!(
+        "admin server listening on http://{}",
+        conf.get_listen_addr()
+    );
+
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+async fn auth_middleware(State(state): State<AdminState>, req: Request, next: Next) -> Response {
+    let token = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+
+    match token {
+        Some(token) if !state.token.is_empty() && constant_time_eq(token, &state.token) => {
+            next.run(req).await
+        }
+        _ => StatusCode::UNAUTHORIZED.into_response(),
+    }
+}
+
+async fn readiness(State(state): State<AdminState>) -> Response {
+    match control::query_statistics(&state.sender, Some(0), None, None).await {
+        Ok(_) => "ok".into_response(),
+        Err(err) => {
+            error!("admin readiness check failed: {}", err);
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "stream hub is not responsive",
+            )
+                .into_response()
+        }
+    }
+}
+
+async fn prometheus_metrics(State(state): State<AdminState>) -> Response {
+    let statistics = match control::query_statistics(&state.sender, None, None, None).await {
+        Ok(statistics) => statistics,
+        Err(err) => {
+            error!("admin query statistics error: {}", err);
+            return StatusCode::SERVICE_UNAVAILABLE.into_response();
+        }
+    };
+
+    let mut body = metrics::render_stream_metrics(&statistics);
+    if let Some(handle) = &state.metrics {
+        body.push_str(&handle.render());
+    }
+
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body).into_response()
+}
+
+// a protocol which the stream is not published over is answered by the origin,
+// http-flv and hls are served from the rtmp representation
+fn parse_identifier(protocol: &str, app: &str, stream: &str) -> Option<StreamIdentifier> {
+    let key = StreamKey::new(app, stream);
+    match protocol {
+        "rtmp" | "httpflv" | "hls" => Some(key.rtmp_identifier()),
+        "rtsp" => Some(key.rtsp_identifier()),
+        "webrtc" => Some(key.webrtc_identifier()),
+        _ => None,
+    }
+}
+
+async fn statistics_response(
+    state: &AdminState, top_n: Option<usize>, identifier: Option<StreamIdentifier>,
+    uuid: Option<Uuid>,
+) -> Response {
+    let single = identifier.is_some();
+    match control::query_statistics(&state.sender, top_n, identifier, uuid).await {
+        // an empty list is returned for a stream that is not published
+        Ok(value) if single && value.as_array().is_some_and(|v| v.is_empty()) => {
+            StatusCode::NOT_FOUND.into_response()
+        }
+        Ok(value) => Json(value).into_response(),
+        Err(err) => {
+            error!("admin query statistics error: {}", err);
+            StatusCode::SERVICE_UNAVAILABLE.into_response()
+        }
+    }
+}
+
+async fn list_streams(
+    State(state): State<AdminState>, Query(query): Query<StreamsQuery>,
+) -> Response {
+    statistics_response(&state, query.top_n, None, None).await
+}
+
+async fn get_stream(
+    State(state): State<AdminState>, Path((protocol, app, stream)): Path<(String, String, String)>,
+) -> Response {
+    let Some(identifier) = parse_identifier(&protocol, &app, &stream) else {
+        return (StatusCode::BAD_REQUEST, "unknown protocol").into_response();
+    };
+    statistics_response(&state, None, Some(identifier), None).await
+}
+
+async fn get_session(
+    State(state): State<AdminState>,
+    Path((protocol, app, stream, uuid)): Path<(String, String, String, String)>,
+) -> Response {
+    let Some(identifier) = parse_identifier(&protocol, &app, &stream) else {
+        return (StatusCode::BAD_REQUEST, "unknown protocol").into_response();
+    };
+    let Some(uuid) = Uuid::from_str2(&uuid) else {
+        return (StatusCode::BAD_REQUEST, "invalid uuid").into_response();
+    };
+    statistics_response(&state, None, Some(identifier), Some(uuid)).await
+}
+
+async fn kick_session(State(state): State<AdminState>, Path(uuid): Path<String>) -> Response {
+    let Some(uuid) = Uuid::from_str2(&uuid) else {
+        return (StatusCode::BAD_REQUEST, "invalid uuid").into_response();
+    };
+
+    match control::kick_client(&state.sender, uuid) {
+        // the session is kicked off asynchronously by the stream hub
+        Ok(()) => StatusCode::ACCEPTED.into_response(),
+        Err(err) => {
+            error!("admin kick session error: {}", err);
+            StatusCode::SERVICE_UNAVAILABLE.into_response()
+        }
+    }
+}
+
+async fn list_relays(State(state): State<AdminState>) -> Response {
+    Json(state.relays.list()).into_response()
+}
+
+fn room_stream_key(state: &AdminState, room_id: &str) -> Option<StreamKey> {
+    let (app, stream) = state.rooms.get(room_id)?.stream_key()?;
+    Some(StreamKey::new(&app, &stream))
+}
+
+fn restreams_response(state: &AdminState, key: &StreamKey) -> Response {
+    Json(state.restreams.list(key)).into_response()
+}
+
+async fn attach_restream_response(state: &AdminState, key: StreamKey, url: String) -> Response {
+    // the restream is bound to the current publishing of the stream, which is
+    // answered by the origin if it is not published over rtmp
+    match control::query_statistics(&state.sender, None, Some(key.rtmp_identifier()), None).await {
+        Ok(value) if value.as_array().is_some_and(|v| v.is_empty()) => {
+            return (StatusCode::NOT_FOUND, "stream is not published").into_response();
+        }
+        Ok(_) => {}
+        Err(err) => {
+            error!("admin query statistics error: {}", err);
+            return StatusCode::SERVICE_UNAVAILABLE.into_response();
+        }
+    }
+
+    match state.restreams.attach(key, url) {
+        Ok(id) => (StatusCode::CREATED, Json(json!({ "id": id }))).into_response(),
+        Err(err) => (StatusCode::BAD_REQUEST, err.to_string()).into_response(),
+    }
+}
+
+fn detach_restream_response(state: &AdminState, key: &StreamKey, id: &str) -> Response {
+    if state.restreams.detach(key, id) {
+        StatusCode::NO_CONTENT.into_response()
+    } else {
+        StatusCode::NOT_FOUND.into_response()
+    }
+}
+
+async fn list_restreams(
+    State(state): State<AdminState>, Path((app, stream)): Path<(String, String)>,
+) -> Response {
+    restreams_response(&state, &StreamKey::new(&app, &stream))
+}
+
+async fn attach_restream(
+    State(state): State<AdminState>, Path((app, stream)): Path<(String, String)>,
+    Json(body): Json<AttachRestream>,
+) -> Response {
+    attach_restream_response(&state, StreamKey::new(&app, &stream), body.url).await
+}
+
+async fn detach_restream(
+    State(state): State<AdminState>, Path((app, stream, id)): Path<(String, String, String)>,
+) -> Response {
+    detach_restream_response(&state, &StreamKey::new(&app, &stream), &id)
+}
+
+async fn list_room_restreams(
+    State(state): State<AdminState>, Path(room_id): Path<String>,
+) -> Response {
+    let Some(key) = room_stream_key(&state, &room_id) else {
+        return (StatusCode::NOT_FOUND, "room not found").into_response();
+    };
+    restreams_response(&state, &key)
+}
+
+async fn attach_room_restream(
+    State(state): State<AdminState>, Path(room_id): Path<String>, Json(body): Json<AttachRestream>,
+) -> Response {
+    let Some(key) = room_stream_key(&state, &room_id) else {
+        return (StatusCode::NOT_FOUND, "room not found").into_response();
+    };
+    attach_restream_response(&state, key, body.url).await
+}
+
+async fn detach_room_restream(
+    State(state): State<AdminState>, Path((room_id, id)): Path<(String, String)>,
+) -> Response {
+    let Some(key) = room_stream_key(&state, &room_id) else {
+        return (StatusCode::NOT_FOUND, "room not found").into_response();
+    };
+    detach_restream_response(&state, &key, &id)
+}
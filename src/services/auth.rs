@@ -0,0 +1,251 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use auth::{
+    Auth, AuthContext, AuthDirection, AuthError, HttpCallbackAuthenticator, SignedUrlAuthenticator,
+    SimpleTokenAuthenticator, TokenTableAuthenticator,
+};
+
+use crate::{
+    config::auth::{AuthBackendConf, AuthChainMode, AuthConf, AuthPolicyConf},
+    services::state::live_room::{LiveRoomAuthenticator, LiveRooms},
+};
+
+#[derive(Debug, Clone)]
+pub enum AuthBackend {
+    None,
+    StaticToken(SimpleTokenAuthenticator),
+    Table(TokenTableAuthenticator),
+    SignedUrl(SignedUrlAuthenticator),
+    HttpCallback(HttpCallbackAuthenticator),
+    LiveRoom(LiveRoomAuthenticator),
+}
+
+impl AuthBackend {
+    fn from_conf(conf: &AuthBackendConf, rooms: &LiveRooms) -> anyhow::Result<Self> {
+        let backend = match conf {
+            AuthBackendConf::None => Self::None,
+            AuthBackendConf::StaticToken { token } => {
+                Self::StaticToken(SimpleTokenAuthenticator::new(token.clone()))
+            }
+            AuthBackendConf::Table { entries } => {
+                let mut table = TokenTableAuthenticator::new();
+                for entry in entries {
+                    table.insert(entry.app.clone(), entry.stream.clone(), entry.token.clone());
+                }
+                Self::Table(table)
+            }
+            AuthBackendConf::SignedUrl { secret } => {
+                Self::SignedUrl(SignedUrlAuthenticator::new(secret.clone()))
+            }
+            AuthBackendConf::HttpCallback { url, timeout_ms } => Self::HttpCallback(
+                HttpCallbackAuthenticator::new(url.clone(), Duration::from_millis(*timeout_ms))?,
+            ),
+            AuthBackendConf::LiveRoom => Self::LiveRoom(LiveRoomAuthenticator::new(rooms.clone())),
+        };
+        Ok(backend)
+    }
+
+    fn auth(
+        &self, app: Option<&str>, stream: Option<&str>, query: Option<&str>,
+    ) -> Result<(), AuthError> {
+        match self {
+            Self::None => Ok(()),
+            Self::StaticToken(a) => a.auth(app, stream, query),
+            Self::Table(a) => a.auth(app, stream, query),
+            Self::SignedUrl(a) => a.auth(app, stream, query),
+            Self::HttpCallback(a) => a.auth(app, stream, query),
+            Self::LiveRoom(a) => a.auth(app, stream, query),
+        }
+    }
+
+    fn auth_pull(
+        &self, app: Option<&str>, stream: Option<&str>, query: Option<&str>,
+    ) -> Result<(), AuthError> {
+        match self {
+            Self::None => Ok(()),
+            Self::StaticToken(a) => a.auth_pull(app, stream, query),
+            Self::Table(a) => a.auth_pull(app, stream, query),
+            Self::SignedUrl(a) => a.auth_pull(app, stream, query),
+            Self::HttpCallback(a) => a.auth_pull(app, stream, query),
+            Self::LiveRoom(a) => a.auth_pull(app, stream, query),
+        }
+    }
+
+    async fn authenticate(&self, ctx: &AuthContext) -> Result<(), AuthError> {
+        match self {
+            Self::None => Ok(()),
+            Self::StaticToken(a) => a.authenticate(ctx).await,
+            Self::Table(a) => a.authenticate(ctx).await,
+            Self::SignedUrl(a) => a.authenticate(ctx).await,
+            Self::HttpCallback(a) => a.authenticate(ctx).await,
+            Self::LiveRoom(a) => a.authenticate(ctx).await,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AuthPolicy {
+    mode: AuthChainMode,
+    backends: Vec<AuthBackend>,
+}
+
+impl AuthPolicy {
+    fn from_conf(conf: &AuthPolicyConf, rooms: &LiveRooms) -> anyhow::Result<Self> {
+        let backends = conf
+            .backends
+            .iter()
+            .map(|backend| AuthBackend::from_conf(backend, rooms))
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self {
+            mode: conf.mode,
+            backends,
+        })
+    }
+
+    fn check<F>(&self, f: F) -> Result<(), AuthError>
+    where
+        F: Fn(&AuthBackend) -> Result<(), AuthError>,
+    {
+        let mut results = self.backends.iter().map(f);
+        match self.mode {
+            AuthChainMode::All => results.try_for_each(|result| result),
+            AuthChainMode::Any => {
+                let mut last = Ok(());
+                for result in results {
+                    if result.is_ok() {
+                        return result;
+                    }
+                    last = result;
+                }
+                last
+            }
+        }
+    }
+
+    async fn check_context(&self, ctx: &AuthContext) -> Result<(), AuthError> {
+        let mut last = Ok(());
+        for backend in &self.backends {
+            let result = backend.authenticate(ctx).await;
+            match self.mode {
+                AuthChainMode::All if result.is_err() => return result,
+                AuthChainMode::Any if result.is_ok() => return result,
+                _ => last = result,
+            }
+        }
+        last
+    }
+}
+
+/// The publish and play auth policies built from the `auth` config section.
+#[derive(Debug, Clone)]
+pub struct AuthChain {
+    publish: AuthPolicy,
+    play: AuthPolicy,
+}
+
+impl AuthChain {
+    pub fn from_conf(conf: &AuthConf, rooms: &LiveRooms) -> anyhow::Result<Self> {
+        Ok(Self {
+            publish: AuthPolicy::from_conf(&conf.publish, rooms)?,
+            play: AuthPolicy::from_conf(&conf.play, rooms)?,
+        })
+    }
+}
+
+#[async_trait]
+impl Auth for AuthChain {
+    fn auth(
+        &self, app: Option<&str>, stream: Option<&str>, query: Option<&str>,
+    ) -> Result<(), AuthError> {
+        self.publish
+            .check(|backend| backend.auth(app, stream, query))
+    }
+
+    fn auth_pull(
+        &self, app: Option<&str>, stream: Option<&str>, query: Option<&str>,
+    ) -> Result<(), AuthError> {
+        self.play
+            .check(|backend| backend.auth_pull(app, stream, query))
+    }
+
+    async fn authenticate(&self, ctx: &AuthContext) -> Result<(), AuthError> {
+        match ctx.direction {
+            AuthDirection::Publish => self.publish.check_context(ctx).await,
+            AuthDirection::Play => self.play.check_context(ctx).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(mode: AuthChainMode, tokens: &[&str]) -> AuthPolicyConf {
+        AuthPolicyConf {
+            mode,
+            backends: tokens
+                .iter()
+                .map(|token| AuthBackendConf::StaticToken {
+                    token: token.to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_auth_chain() {
+        let conf = AuthConf {
+            publish: policy(AuthChainMode::Any, &["abc", "xyz"]),
+            play: policy(AuthChainMode::All, &["abc", "xyz"]),
+        };
+        let chain = AuthChain::from_conf(&conf, &LiveRooms::new()).unwrap();
+
+        assert!(chain
+            .auth(Some("live"), Some("s"), Some("token=xyz"))
+            .is_ok());
+        assert!(chain
+            .auth(Some("live"), Some("s"), Some("token=123"))
+            .is_err());
+        assert!(chain
+            .auth_pull(Some("live"), Some("s"), Some("token=xyz"))
+            .is_err());
+
+        // an empty chain allows every session
+        let chain = AuthChain::from_conf(
+            &AuthConf {
+                publish: AuthPolicyConf::default(),
+                play: AuthPolicyConf::default(),
+            },
+            &LiveRooms::new(),
+        )
+        .unwrap();
+        assert!(chain.auth(Some("live"), Some("s"), None).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_auth_chain_context() {
+        use auth::AuthProtocol;
+
+        let conf = AuthConf {
+            publish: policy(AuthChainMode::Any, &["abc"]),
+            play: policy(AuthChainMode::Any, &["abc"]),
+        };
+        let chain = AuthChain::from_conf(&conf, &LiveRooms::new()).unwrap();
+
+        let ctx = AuthContext::new(AuthProtocol::WebRTC, AuthDirection::Publish, "live", "s");
+        assert!(matches!(
+            chain.authenticate(&ctx).await,
+            Err(AuthError::NoTokenFound)
+        ));
+        let ctx = ctx.with_header("Authorization", "Bearer abc");
+        assert!(chain.authenticate(&ctx).await.is_ok());
+
+        let ctx = AuthContext::new(AuthProtocol::Rtmp, AuthDirection::Play, "live", "s")
+            .with_query(Some("token=123"));
+        assert!(matches!(
+            chain.authenticate(&ctx).await,
+            Err(AuthError::TokenIsNotCorrect)
+        ));
+    }
+}
@@ -0,0 +1,47 @@
+use std::time::Duration;
+
+use serde_json::Value;
+use streamhub::{
+    define::{StreamHubEvent, StreamHubEventSender},
+    stream::{StreamIdentifier, StreamKey},
+    utils::Uuid,
+};
+use tokio::sync::oneshot;
+
+const STATISTIC_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Kick off the publisher and the viewers of a stream, over every protocol.
+pub fn kick_stream(
+    sender: &StreamHubEventSender, app_name: &str, stream_name: &str,
+) -> anyhow::Result<()> {
+    let key = StreamKey::new(app_name, stream_name);
+    sender
+        .send(StreamHubEvent::ApiKickStream { key })
+        .map_err(|_| anyhow::anyhow!("failed to send kick stream event to stream hub"))
+}
+
+/// Kick off a publisher or a subscriber session.
+pub fn kick_client(sender: &StreamHubEventSender, id: Uuid) -> anyhow::Result<()> {
+    sender
+        .send(StreamHubEvent::ApiKickClient { id })
+        .map_err(|_| anyhow::anyhow!("failed to send kick client event to stream hub"))
+}
+
+/// Query the `StatisticsStream` list of the hub, optionally narrowed to one
+/// stream and to one session of it.
+pub async fn query_statistics(
+    sender: &StreamHubEventSender, top_n: Option<usize>, identifier: Option<StreamIdentifier>,
+    uuid: Option<Uuid>,
+) -> anyhow::Result<Value> {
+    let (result_sender, result_receiver) = oneshot::channel();
+    sender
+        .send(StreamHubEvent::ApiStatistic {
+            top_n,
+            identifier,
+            uuid,
+            result_sender,
+        })
+        .map_err(|_| anyhow::anyhow!("failed to send statistic event to stream hub"))?;
+
+    Ok(tokio::time::timeout(STATISTIC_TIMEOUT, result_receiver).await??)
+}
@@ -0,0 +1,208 @@
+use std::fmt::Write;
+
+use async_trait::async_trait;
+use auth::{Auth, AuthContext, AuthError};
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use serde_json::Value;
+use tracing::warn;
+
+/// gauge, the rtmp connections being served
+pub const RTMP_CONNECTIONS: &str = "rtmp_connections";
+/// counter
+pub const RTMP_HANDSHAKE_FAILURES: &str = "rtmp_handshake_failures_total";
+/// counter, labels: protocol, direction = publish | play
+pub const AUTH_REJECTIONS: &str = "auth_rejections_total";
+
+/// Install the global recorder of the process level metrics, they are
+/// rendered by the returned handle.
+pub fn install_recorder() -> Option<PrometheusHandle> {
+    match PrometheusBuilder::new().install_recorder() {
+        Ok(handle) => Some(handle),
+        Err(err) => {
+            warn!("install metrics recorder failed: {}", err);
+            None
+        }
+    }
+}
+
+/// Count the sessions rejected by the wrapped authenticator.
+#[derive(Debug, Clone)]
+pub struct MeteredAuth<A: Auth> {
+    protocol: &'static str,
+    inner: A,
+}
+
+impl<A: Auth> MeteredAuth<A> {
+    pub fn new(protocol: &'static str, inner: A) -> Self {
+        Self { protocol, inner }
+    }
+
+    fn record(
+        &self, direction: &'static str, result: Result<(), AuthError>,
+    ) -> Result<(), AuthError> {
+        if result.is_err() {
+            metrics::counter!(AUTH_REJECTIONS, "protocol" => self.protocol, "direction" => direction)
+                .increment(1);
+        }
+        result
+    }
+}
+
+#[async_trait]
+impl<A: Auth> Auth for MeteredAuth<A> {
+    fn auth(
+        &self, app: Option<&str>, stream: Option<&str>, query: Option<&str>,
+    ) -> Result<(), AuthError> {
+        self.record("publish", self.inner.auth(app, stream, query))
+    }
+
+    fn auth_pull(
+        &self, app: Option<&str>, stream: Option<&str>, query: Option<&str>,
+    ) -> Result<(), AuthError> {
+        self.record("play", self.inner.auth_pull(app, stream, query))
+    }
+
+    async fn authenticate(&self, ctx: &AuthContext) -> Result<(), AuthError> {
+        self.record(ctx.direction.as_str(), self.inner.authenticate(ctx).await)
+    }
+}
+
+struct StreamMetric {
+    name: &'static str,
+    kind: &'static str,
+    help: &'static str,
+    value: fn(&Value) -> Option<u64>,
+}
+
+const STREAM_METRICS: &[StreamMetric] = &[
+    StreamMetric {
+        name: "stream_subscribers",
+        kind: "gauge",
+        help: "Clients subscribing to the stream.",
+        value: |s| s["subscriber_count"].as_u64(),
+    },
+    StreamMetric {
+        name: "stream_recv_bitrate_kbps",
+        kind: "gauge",
+        help: "Bitrate at which the stream is received.",
+        value: |s| s["publisher"]["recv_bitrate(kbits/s)"].as_u64(),
+    },
+    StreamMetric {
+        name: "stream_video_bitrate_kbps",
+        kind: "gauge",
+        help: "Video bitrate of the stream.",
+        value: |s| s["publisher"]["video"]["bitrate(kbits/s)"].as_u64(),
+    },
+    StreamMetric {
+        name: "stream_audio_bitrate_kbps",
+        kind: "gauge",
+        help: "Audio bitrate of the stream.",
+        value: |s| s["publisher"]["audio"]["bitrate(kbits/s)"].as_u64(),
+    },
+    StreamMetric {
+        name: "stream_video_frame_rate",
+        kind: "gauge",
+        help: "Video frame rate of the stream.",
+        value: |s| s["publisher"]["video"]["frame_rate"].as_u64(),
+    },
+    StreamMetric {
+        name: "stream_video_gop",
+        kind: "gauge",
+        help: "Video frames of a GOP of the stream.",
+        value: |s| s["publisher"]["video"]["gop"].as_u64(),
+    },
+    StreamMetric {
+        name: "stream_recv_bytes_total",
+        kind: "counter",
+        help: "Bytes received from the publisher.",
+        value: |s| s["total_recv_bytes"].as_u64(),
+    },
+    StreamMetric {
+        name: "stream_send_bytes_total",
+        kind: "counter",
+        help: "Bytes sent to the subscribers.",
+        value: |s| s["total_send_bytes"].as_u64(),
+    },
+];
+
+/// `(protocol, app, stream)` of a serialized `StreamIdentifier`
+fn stream_labels(identifier: &Value) -> Option<(&str, &str, &str)> {
+    let (protocol, names) = identifier.as_object()?.iter().next()?;
+    // rtsp streams are identified by a path only
+    let app = names["app_name"].as_str().unwrap_or_default();
+    let stream = names["stream_name"]
+        .as_str()
+        .or_else(|| names["stream_path"].as_str())?;
+    Some((protocol, app, stream))
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Render the `StatisticsStream` list returned by the `ApiStatistic` hub event
+/// in the prometheus text format.
+pub fn render_stream_metrics(statistics: &Value) -> String {
+    let streams: Vec<(String, &Value)> = statistics
+        .as_array()
+        .map(|streams| {
+            streams
+                .iter()
+                .filter_map(|stream| {
+                    let (protocol, app, name) = stream_labels(&stream["publisher"]["identifier"])?;
+                    let labels = format!(
+                        "protocol=\"{}\",app=\"{}\",stream=\"{}\"",
+                        escape_label(protocol),
+                        escape_label(app),
+                        escape_label(name)
+                    );
+                    Some((labels, stream))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut out = String::new();
+    for metric in STREAM_METRICS {
+        let _ = writeln!(out, "# HELP {} {}", metric.name, metric.help);
+        let _ = writeln!(out, "# TYPE {} {}", metric.name, metric.kind);
+        for (labels, stream) in &streams {
+            let value = (metric.value)(stream).unwrap_or_default();
+            let _ = writeln!(out, "{}{{{}}} {}", metric.name, labels, value);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_stream_metrics() {
+        let statistics = serde_json::json!([{
+            "publisher": {
+                "identifier": { "rtmp": { "app_name": "live", "stream_name": "room\"1" } },
+                "video": { "bitrate(kbits/s)": 1800, "frame_rate": 30, "gop": 60 },
+                "audio": { "bitrate(kbits/s)": 128 },
+                "recv_bitrate(kbits/s)": 1928
+            },
+            "subscribers": {},
+            "subscriber_count": 3,
+            "total_recv_bytes": 1024,
+            "total_send_bytes": 3072
+        }]);
+
+        let text = render_stream_metrics(&statistics);
+        assert!(text.contains("# TYPE stream_subscribers gauge\n"));
+        assert!(text.contains(
+            "stream_subscribers{protocol=\"rtmp\",app=\"live\",stream=\"room\\\"1\"} 3\n"
+        ));
+        assert!(text.contains(
+            "stream_send_bytes_total{protocol=\"rtmp\",app=\"live\",stream=\"room\\\"1\"} 3072\n"
+        ));
+    }
+}
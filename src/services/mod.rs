@@ -1,4 +1,8 @@
+pub mod admin;
+pub mod auth;
 pub mod control;
+pub mod metrics;
 pub mod server;
 pub mod stream;
-pub mod state;
\ No newline at end of file
+pub mod state;
+pub mod webhook;
@@ -0,0 +1,143 @@
+use std::net::SocketAddr;
+
+use streamhub::{define::StreamHubEventSender, utils::Uuid};
+use tonic::{Request, Response, Status};
+use tracing::{info, warn};
+
+use crate::{
+    config::server::ServerConf,
+    pb::live_room::{
+        live_room_service_server::{LiveRoomService, LiveRoomServiceServer},
+        CreateLiveRoomRequest, LiveRoomResponse, UpdateLiveRoomRequest,
+    },
+    services::{
+        control,
+        state::live_room::{parse_stream_url, LiveRoom, LiveRooms},
+    },
+};
+
+impl From<LiveRoom> for LiveRoomResponse {
+    fn from(room: LiveRoom) -> Self {
+        Self {
+            room_id: room.room_id,
+            is_open: room.is_open,
+            stream_url: room.stream_url,
+            stream_auth_token: room.stream_auth_token,
+        }
+    }
+}
+
+pub struct LiveRoomServer {
+    rooms: LiveRooms,
+    sender: StreamHubEventSender,
+}
+
+impl LiveRoomServer {
+    pub fn new(rooms: LiveRooms, sender: StreamHubEventSender) -> Self {
+        Self { rooms, sender }
+    }
+}
+
+#[tonic::async_trait]
+impl LiveRoomService for LiveRoomServer {
+    async fn create_live_room(
+        &self, request: Request<CreateLiveRoomRequest>,
+    ) -> Result<Response<LiveRoomResponse>, Status> {
+        let CreateLiveRoomRequest { room_id } = request.into_inner();
+        if room_id.is_empty() {
+            return Err(Status::invalid_argument("room_id cannot be empty"));
+        }
+
+        let token = Uuid::new().to_string().replace('-', "");
+        let room = self
+            .rooms
+            .create(LiveRoom::new(room_id.clone(), token))
+            .ok_or_else(|| Status::already_exists(format!("room {} already exists", room_id)))?;
+        info!(room_id, stream_url = room.stream_url, "live room created");
+
+        Ok(Response::new(room.into()))
+    }
+
+    async fn update_live_room(
+        &self, request: Request<UpdateLiveRoomRequest>,
+    ) -> Result<Response<LiveRoomResponse>, Status> {
+        let req = request.into_inner();
+        let mut room = self
+            .rooms
+            .get(&req.room_id)
+            .ok_or_else(|| Status::not_found(format!("room {} not found", req.room_id)))?;
+
+        room.is_open = req.is_open;
+        // empty fields keep the current values
+        if !req.stream_url.is_empty() {
+            let Some((app, stream)) = parse_stream_url(&req.stream_url) else {
+                return Err(Status::invalid_argument(format!(
+                    "invalid stream_url: {}",
+                    req.stream_url
+                )));
+            };
+            if self
+                .rooms
+                .find_by_stream(&app, &stream)
+                .is_some_and(|other| other.room_id != room.room_id)
+            {
+                return Err(Status::already_exists(format!(
+                    "stream {}/{} is bound to another room",
+                    app, stream
+                )));
+            }
+            room.stream_url = req.stream_url;
+        }
+        if !req.stream_auth_token.is_empty() {
+            room.stream_auth_token = req.stream_auth_token;
+        }
+
+        let previous = self
+            .rooms
+            .update(room.clone())
+            .ok_or_else(|| Status::not_found(format!("room {} not found", room.room_id)))?;
+        info!(
+            room_id = room.room_id,
+            is_open = room.is_open,
+            stream_url = room.stream_url,
+            "live room updated"
+        );
+
+        // the sessions of a closed room, or of the stream the room is no longer bound to,
+        // are disconnected immediately.
+        let previous_key = previous.stream_key();
+        if previous.is_open && (!room.is_open || previous_key != room.stream_key()) {
+            if let Some((app, stream)) = previous_key {
+                info!(
+                    room_id = room.room_id,
+                    app, stream, "kick off live room sessions"
+                );
+                if let Err(err) = control::kick_stream(&self.sender, &app, &stream) {
+                    warn!(
+                        "kick off live room {} sessions error: {}",
+                        room.room_id, err
+                    );
+                }
+            }
+        }
+
+        Ok(Response::new(room.into()))
+    }
+}
+
+/// Serve the grpc services called by the apiserver, on the address registered to it.
+pub async fn start_server(
+    conf: ServerConf, rooms: LiveRooms, sender: StreamHubEventSender,
+) -> anyhow::Result<()> {
+    let addr: SocketAddr = conf.get_self_addr().parse()?;
+    info!("grpc server listening on {}", addr);
+
+    tonic::transport::Server::builder()
+        .add_service(LiveRoomServiceServer::new(LiveRoomServer::new(
+            rooms, sender,
+        )))
+        .serve(addr)
+        .await?;
+
+    Ok(())
+}
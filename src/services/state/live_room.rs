@@ -0,0 +1,223 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use async_trait::async_trait;
+use auth::{constant_time_eq, Auth, AuthContext, AuthDirection, AuthError};
+
+const DEFAULT_ROOM_APP: &str = "live";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LiveRoom {
+    pub room_id: String,
+    pub is_open: bool,
+    pub stream_url: String,
+    pub stream_auth_token: String,
+}
+
+impl LiveRoom {
+    pub fn new(room_id: String, stream_auth_token: String) -> Self {
+        Self {
+            stream_url: format!("{}/{}", DEFAULT_ROOM_APP, room_id),
+            room_id,
+            is_open: false,
+            stream_auth_token,
+        }
+    }
+
+    /// The `(app, stream)` key the room is published under.
+    pub fn stream_key(&self) -> Option<(String, String)> {
+        parse_stream_url(&self.stream_url)
+    }
+}
+
+/// Extract `(app, stream)` from a room's stream url, the url can be either a
+/// full url (`rtmp://host:1935/live/room1?token=...`) or a bare `live/room1` path.
+pub fn parse_stream_url(stream_url: &str) -> Option<(String, String)> {
+    let path = match stream_url.split_once("://") {
+        Some((_, rest)) => rest
+            .split_once('/')
+            .map(|(_, path)| path)
+            .unwrap_or_default(),
+        None => stream_url,
+    };
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+
+    let mut segments = path.rsplit('/').filter(|s| !s.is_empty());
+    let stream = segments.next()?;
+    let app = segments.next()?;
+    Some((app.to_string(), stream.to_string()))
+}
+
+/// In-memory registry of the live rooms managed by the apiserver.
+///
+/// A std lock is used since the lookups never hold it across an await.
+#[derive(Debug, Clone, Default)]
+pub struct LiveRooms {
+    rooms: Arc<RwLock<HashMap<String, LiveRoom>>>,
+}
+
+impl LiveRooms {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, room_id: &str) -> Option<LiveRoom> {
+        self.rooms.read().ok()?.get(room_id).cloned()
+    }
+
+    /// Insert a new room, returns `None` if the room id already exists.
+    pub fn create(&self, room: LiveRoom) -> Option<LiveRoom> {
+        let mut rooms = self.rooms.write().ok()?;
+        if rooms.contains_key(&room.room_id) {
+            return None;
+        }
+        rooms.insert(room.room_id.clone(), room.clone());
+        Some(room)
+    }
+
+    /// Replace an existing room, returns the previous state of the room.
+    pub fn update(&self, room: LiveRoom) -> Option<LiveRoom> {
+        let mut rooms = self.rooms.write().ok()?;
+        let previous = rooms.get_mut(&room.room_id)?;
+        Some(std::mem::replace(previous, room))
+    }
+
+    pub fn find_by_stream(&self, app: &str, stream: &str) -> Option<LiveRoom> {
+        self.rooms
+            .read()
+            .ok()?
+            .values()
+            .find(|room| {
+                room.stream_key()
+                    .is_some_and(|(room_app, room_stream)| room_app == app && room_stream == stream)
+            })
+            .cloned()
+    }
+}
+
+/// Authenticate the sessions against the room the stream is bound to:
+/// publishing requires an open room and its `stream_auth_token` passed as the
+/// `token` query param, playing only requires an open room.
+#[derive(Debug, Clone)]
+pub struct LiveRoomAuthenticator {
+    rooms: LiveRooms,
+}
+
+impl LiveRoomAuthenticator {
+    pub fn new(rooms: LiveRooms) -> Self {
+        Self { rooms }
+    }
+
+    fn find_open_room(
+        &self, app: Option<&str>, stream: Option<&str>,
+    ) -> Result<LiveRoom, AuthError> {
+        let (Some(app), Some(stream)) = (app, stream) else {
+            return Err(AuthError::StreamNotFound);
+        };
+        let room = self
+            .rooms
+            .find_by_stream(app, stream)
+            .ok_or(AuthError::StreamNotFound)?;
+        if !room.is_open {
+            return Err(AuthError::StreamIsClosed);
+        }
+        Ok(room)
+    }
+
+    fn verify_publish(
+        &self, app: Option<&str>, stream: Option<&str>, token: Option<String>,
+    ) -> Result<(), AuthError> {
+        let room = self.find_open_room(app, stream)?;
+        tracing::debug!(
+            room_id = room.room_id,
+            app,
+            stream,
+            "live room publish auth"
+        );
+
+        let token = token.ok_or(AuthError::NoTokenFound)?;
+        if !constant_time_eq(&token, &room.stream_auth_token) {
+            return Err(AuthError::TokenIsNotCorrect);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Auth for LiveRoomAuthenticator {
+    fn auth(
+        &self, app: Option<&str>, stream: Option<&str>, query: Option<&str>,
+    ) -> Result<(), AuthError> {
+        let token = url::form_urlencoded::parse(query.unwrap_or_default().as_bytes())
+            .find(|(k, _)| k == "token")
+            .map(|(_, v)| v.into_owned());
+        self.verify_publish(app, stream, token)
+    }
+
+    fn auth_pull(
+        &self, app: Option<&str>, stream: Option<&str>, _query: Option<&str>,
+    ) -> Result<(), AuthError> {
+        self.find_open_room(app, stream).map(|_| ())
+    }
+
+    /// the publish token can also be sent as an `Authorization: Bearer` header
+    async fn authenticate(&self, ctx: &AuthContext) -> Result<(), AuthError> {
+        let (app, stream) = (Some(ctx.app.as_str()), Some(ctx.stream.as_str()));
+        match ctx.direction {
+            AuthDirection::Publish => self.verify_publish(app, stream, ctx.token()),
+            AuthDirection::Play => self.find_open_room(app, stream).map(|_| ()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_stream_url() {
+        assert_eq!(
+            parse_stream_url("rtmp://127.0.0.1:1935/live/room1?token=abc"),
+            Some(("live".to_string(), "room1".to_string()))
+        );
+        assert_eq!(
+            parse_stream_url("live/room1"),
+            Some(("live".to_string(), "room1".to_string()))
+        );
+        assert_eq!(parse_stream_url("rtmp://127.0.0.1:1935/room1"), None);
+        assert_eq!(parse_stream_url(""), None);
+    }
+
+    #[test]
+    fn test_live_room_auth() {
+        let rooms = LiveRooms::new();
+        let authenticator = LiveRoomAuthenticator::new(rooms.clone());
+        let mut room = LiveRoom::new("room1".to_string(), "abc".to_string());
+        rooms.create(room.clone());
+
+        assert!(matches!(
+            authenticator.auth(Some("live"), Some("room1"), Some("token=abc")),
+            Err(AuthError::StreamIsClosed)
+        ));
+
+        room.is_open = true;
+        rooms.update(room);
+
+        assert!(authenticator
+            .auth(Some("live"), Some("room1"), Some("token=abc"))
+            .is_ok());
+        assert!(matches!(
+            authenticator.auth(Some("live"), Some("room1"), Some("token=123")),
+            Err(AuthError::TokenIsNotCorrect)
+        ));
+        assert!(matches!(
+            authenticator.auth(Some("live"), Some("room2"), Some("token=abc")),
+            Err(AuthError::StreamNotFound)
+        ));
+        assert!(authenticator
+            .auth_pull(Some("live"), Some("room1"), None)
+            .is_ok());
+    }
+}
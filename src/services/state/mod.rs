@@ -1 +1,2 @@
-pub mod streamserver;
\ No newline at end of file
+pub mod live_room;
+pub mod streamserver;
@@ -1,34 +1,277 @@
-use crate::{config::server::ServerConf, pb::streamserver::stream_server_client::StreamServerClient};
-use crate::pb::streamserver::StreamServerRegisterRequest;
-use tracing::{info};
+use std::time::Duration;
+
+use serde_json::Value;
+use streamhub::define::StreamHubEventSender;
+use tonic::transport::{Channel, Endpoint};
+use tracing::{debug, info, warn};
+
+use crate::pb::streamserver::{StreamServerKeepAliveRequest, StreamServerRegisterRequest};
+use crate::{
+    config::server::ServerConf, pb::streamserver::stream_server_client::StreamServerClient,
+    services::control,
+};
+
+const API_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+fn new_client(cfg: &ServerConf) -> anyhow::Result<StreamServerClient<Channel>> {
+    let endpoint = Endpoint::from_shared(cfg.api_addr.clone())?
+        .connect_timeout(API_REQUEST_TIMEOUT)
+        .timeout(API_REQUEST_TIMEOUT);
+    // the channel (re)connects on demand, so it survives the restarts of the apiserver.
+    Ok(StreamServerClient::new(endpoint.connect_lazy()))
+}
 
 pub async fn register_to_apiserver(cfg: &ServerConf) -> anyhow::Result<()> {
     info!("register to apiserver");
-    let mut client = StreamServerClient::connect(cfg.api_addr.clone()).await?;
+    let mut client = new_client(cfg)?;
+    register(&mut client, cfg).await
+}
+
+pub async fn unregister_to_apiserver(cfg: &ServerConf) -> anyhow::Result<()> {
+    info!("unregister to apiserver");
+    let mut client = new_client(cfg)?;
     let request = tonic::Request::new(StreamServerRegisterRequest {
         host: cfg.get_self_addr(),
         secret: cfg.api_secret.clone(),
     });
 
-    let response = client.register(request).await?;
-    info!("register success: {:?}", response.get_ref().success);
+    let response = client.unregister(request).await?;
+    info!("unregister success: {:?}", response.get_ref().success);
 
     Ok(())
 }
 
-
-pub async fn unregister_to_apiserver(cfg: &ServerConf) -> anyhow::Result<()> {
-    info!("unregister to apiserver");
-    let mut client = StreamServerClient::connect(cfg.api_addr.clone()).await?;
+async fn register(
+    client: &mut StreamServerClient<Channel>, cfg: &ServerConf,
+) -> anyhow::Result<()> {
     let request = tonic::Request::new(StreamServerRegisterRequest {
         host: cfg.get_self_addr(),
         secret: cfg.api_secret.clone(),
     });
 
-    let response = client.unregister(request).await?;
-    info!("unregister success: {:?}", response.get_ref().success);
+    let response = client.register(request).await?.into_inner();
+    if !response.success {
+        anyhow::bail!("register rejected: {}", response.message);
+    }
+    info!("register success");
 
     Ok(())
 }
 
+/// Load of the stream server reported with every keepalive.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ServerLoad {
+    pub publisher_count: u32,
+    pub subscriber_count: u32,
+    /// kbits/s
+    pub ingress_bitrate: u64,
+    /// kbits/s
+    pub egress_bitrate: u64,
+}
+
+impl ServerLoad {
+    /// Sum up the `StatisticsStream` list returned by the `ApiStatistic` hub event.
+    ///
+    /// The representations published by the remuxers and the subscribers inside
+    /// this server (remuxers, hls, recorder, push clients) are not counted.
+    fn from_statistics(statistics: &Value) -> Self {
+        let mut load = Self::default();
+        let Some(streams) = statistics.as_array() else {
+            return load;
+        };
+
+        for stream in streams {
+            if !stream["remuxed"].as_bool().unwrap_or_default() {
+                load.publisher_count += 1;
+                load.ingress_bitrate += stream["publisher"]["recv_bitrate(kbits/s)"]
+                    .as_u64()
+                    .unwrap_or_default();
+            }
+
+            if let Some(subscribers) = stream["subscribers"].as_object() {
+                for subscriber in subscribers.values() {
+                    if !subscriber["player"].as_bool().unwrap_or_default() {
+                        continue;
+                    }
+                    load.subscriber_count += 1;
+                    load.egress_bitrate += subscriber["send_bitrate(kbits/s)"]
+                        .as_u64()
+                        .unwrap_or_default();
+                }
+            }
+        }
 
+        load
+    }
+}
+
+async fn collect_load(sender: &StreamHubEventSender) -> anyhow::Result<ServerLoad> {
+    let statistics = control::query_statistics(sender, None, None, None).await?;
+    Ok(ServerLoad::from_statistics(&statistics))
+}
+
+/// Register to the apiserver and keep the registration alive, the load of the
+/// server is reported with each heartbeat.
+///
+/// The server is registered again, with an exponential backoff, whenever the
+/// apiserver is unreachable or rejects the keepalive (e.g. it was restarted);
+/// the streams are served as usual in the meantime.
+pub async fn keep_alive_loop(cfg: ServerConf, sender: StreamHubEventSender) -> anyhow::Result<()> {
+    let mut client = new_client(&cfg)?;
+
+    let interval = Duration::from_secs(cfg.keep_alive_interval.max(1));
+    let max_backoff = Duration::from_secs(cfg.register_max_backoff.max(1));
+    let mut backoff = Duration::from_secs(1);
+    let mut registered = false;
+
+    loop {
+        if !registered {
+            if let Err(err) = register(&mut client, &cfg).await {
+                warn!(
+                    "register to apiserver failed, retry in {:?}: {}",
+                    backoff, err
+                );
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(max_backoff);
+                continue;
+            }
+            registered = true;
+            backoff = Duration::from_secs(1);
+        }
+
+        tokio::time::sleep(interval).await;
+
+        let load = collect_load(&sender).await.unwrap_or_else(|err| {
+            warn!("collect server load failed: {}", err);
+            ServerLoad::default()
+        });
+        debug!(?load, "keepalive to apiserver");
+
+        let request = tonic::Request::new(StreamServerKeepAliveRequest {
+            host: cfg.get_self_addr(),
+            secret: cfg.api_secret.clone(),
+            publisher_count: load.publisher_count,
+            subscriber_count: load.subscriber_count,
+            ingress_bitrate: load.ingress_bitrate,
+            egress_bitrate: load.egress_bitrate,
+        });
+
+        match client.keep_alive(request).await {
+            Ok(response) if response.get_ref().success => {}
+            Ok(response) => {
+                warn!(
+                    "keepalive rejected, register again: {}",
+                    response.get_ref().message
+                );
+                registered = false;
+            }
+            Err(err) => {
+                warn!("keepalive failed, register again: {}", err);
+                registered = false;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_load_from_statistics() {
+        let statistics = serde_json::json!([
+            {
+                "publisher": { "recv_bitrate(kbits/s)": 2000 },
+                "remuxed": false,
+                "subscribers": {
+                    "a": { "player": true, "send_bitrate(kbits/s)": 1000 },
+                    "b": { "player": true, "send_bitrate(kbits/s)": 1500 }
+                },
+                "subscriber_count": 2
+            },
+            {
+                "publisher": { "recv_bitrate(kbits/s)": 500 },
+                "remuxed": false,
+                "subscribers": {},
+                "subscriber_count": 0
+            }
+        ]);
+
+        assert_eq!(
+            ServerLoad::from_statistics(&statistics),
+            ServerLoad {
+                publisher_count: 2,
+                subscriber_count: 2,
+                ingress_bitrate: 2500,
+                egress_bitrate: 2500,
+            }
+        );
+        // no stream is published
+        assert_eq!(
+            ServerLoad::from_statistics(&serde_json::json!([])),
+            ServerLoad::default()
+        );
+    }
+
+    #[test]
+    fn test_server_load_skip_internal_subscribers() {
+        // hls, the recorder and a push relay are not viewers of the stream
+        let statistics = serde_json::json!([
+            {
+                "publisher": { "recv_bitrate(kbits/s)": 1000 },
+                "remuxed": false,
+                "subscribers": {
+                    "hls": { "player": false, "send_bitrate(kbits/s)": 1000 },
+                    "record": { "player": false, "send_bitrate(kbits/s)": 1000 },
+                    "push": { "player": false, "send_bitrate(kbits/s)": 1000 },
+                    "flv": { "player": true, "send_bitrate(kbits/s)": 1000 }
+                },
+                "subscriber_count": 4
+            }
+        ]);
+
+        assert_eq!(
+            ServerLoad::from_statistics(&statistics),
+            ServerLoad {
+                publisher_count: 1,
+                subscriber_count: 1,
+                ingress_bitrate: 1000,
+                egress_bitrate: 1000,
+            }
+        );
+    }
+
+    #[test]
+    fn test_server_load_skip_remuxed_stream() {
+        // a rtmp stream is played by webrtc, the whep player subscribes the
+        // webrtc representation published by the remuxer
+        let statistics = serde_json::json!([
+            {
+                "publisher": { "recv_bitrate(kbits/s)": 2000 },
+                "remuxed": false,
+                "subscribers": {
+                    "remuxer": { "player": false, "send_bitrate(kbits/s)": 2000 }
+                },
+                "subscriber_count": 1
+            },
+            {
+                "publisher": { "recv_bitrate(kbits/s)": 2000 },
+                "remuxed": true,
+                "subscribers": {
+                    "whep": { "player": true, "send_bitrate(kbits/s)": 2000 }
+                },
+                "subscriber_count": 1
+            }
+        ]);
+
+        assert_eq!(
+            ServerLoad::from_statistics(&statistics),
+            ServerLoad {
+                publisher_count: 1,
+                subscriber_count: 1,
+                ingress_bitrate: 2000,
+                egress_bitrate: 2000,
+            }
+        );
+    }
+}
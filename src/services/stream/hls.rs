@@ -0,0 +1,34 @@
+use hls::{flv_data_receiver::HlsConfig, remuxer::HlsRemuxer};
+use streamhub::define::{BroadcastEventReceiver, StreamHubEventSender};
+use tracing::info;
+
+use crate::config::stream::StreamConf;
+
+/// Remux every stream published to the hub into HLS segments under `temp_hls_path`
+/// and serve the playlists over http.
+pub async fn start_server(
+    conf: StreamConf, sender: StreamHubEventSender, client_event_consumer: BroadcastEventReceiver,
+) -> anyhow::Result<()> {
+    let hls_config = HlsConfig {
+        hls_path: conf.temp_hls_path.clone(),
+        fragment_seconds: conf.hls_fragment_seconds as i64,
+        fragment_max_count: conf.hls_fragment_max_count.max(1) as usize,
+    };
+    info!("hls segments are written to {}", hls_config.hls_path);
+
+    let mut remuxer = HlsRemuxer::new(client_event_consumer, sender, hls_config);
+    let remuxer_task = async move {
+        remuxer
+            .run()
+            .await
+            .map_err(|err| anyhow::anyhow!("hls remuxer error: {}", err))
+    };
+    let server_task = async move {
+        hls::server::run(conf.hls.get_listen_addr(), conf.temp_hls_path)
+            .await
+            .map_err(anyhow::Error::new)
+    };
+
+    tokio::try_join!(remuxer_task, server_task)?;
+    Ok(())
+}
@@ -0,0 +1,19 @@
+use httpflv::server::HttpFlvServer;
+use streamhub::define::StreamHubEventSender;
+
+use crate::{
+    config::stream::HttpFlvConf,
+    services::{auth::AuthChain, metrics::MeteredAuth},
+};
+
+pub async fn start_server(
+    conf: HttpFlvConf, sender: StreamHubEventSender, auth: AuthChain,
+) -> anyhow::Result<()> {
+    let mut httpflv_server = HttpFlvServer::new(
+        conf.get_listen_addr(),
+        sender,
+        Some(MeteredAuth::new("httpflv", auth)),
+    );
+
+    httpflv_server.run().await.map_err(anyhow::Error::new)
+}
@@ -0,0 +1,15 @@
+use std::time::Duration;
+
+use streamhub::queue::SubscriberQueueConfig;
+
+use crate::config::stream::SubscriberQueueConf;
+
+pub fn subscriber_queue_config(conf: &SubscriberQueueConf) -> SubscriberQueueConfig {
+    // a zero sized channel panics, and the threshold can not exceed the queue.
+    let capacity = conf.capacity.max(1);
+    SubscriberQueueConfig {
+        capacity,
+        drop_threshold: conf.drop_threshold.min(capacity),
+        max_lag: Duration::from_millis(conf.max_lag_ms),
+    }
+}
@@ -1,2 +1,8 @@
+pub mod hls;
+pub mod httpflv;
+pub mod hub;
 pub mod pull_stream;
-pub mod webrtc;
\ No newline at end of file
+pub mod record;
+pub mod relay;
+pub mod rtsp;
+pub mod webrtc;
@@ -1,37 +1,33 @@
-
+use rtmp::session::errors::SessionError;
 use streamhub::define::StreamHubEventSender;
-use streamhub::StreamsHub;
 use tracing::{error, info};
-use auth::auth::SimpleTokenAuthenticator;
+
+use crate::{
+    config::stream::RtmpConf,
+    services::{auth::AuthChain, metrics},
+};
 
 #[derive(Debug)]
 struct RtmpSessionContext {
     pub stream: tokio::net::TcpStream,
     pub sender: StreamHubEventSender,
+    pub auth: metrics::MeteredAuth<AuthChain>,
 }
 
-pub async fn rtmp_server(conf: &crate::config::AppConfig) -> anyhow::Result<()> {
-    let listen_port = conf.stream.port;
-    let listen_host = conf.stream.host.clone();
-
-    /* let mut rtmp_server = RtmpServer::new(
-        listen_host + ":" + &listen_port.to_string(),
-        sender,
-        2,
-    ); */
-
-    start_rtmp_server_loop(listen_host, listen_port).await
+pub async fn rtmp_server(
+    conf: RtmpConf, sender: StreamHubEventSender, auth: AuthChain,
+) -> anyhow::Result<()> {
+    start_rtmp_server_loop(conf.host, conf.port, sender, auth).await
 }
 
-async fn start_rtmp_server_loop(host: String, port: u16) -> anyhow::Result<()>
-{
-    let mut stream_hub = StreamsHub::new(None);
-    let sender = stream_hub.get_hub_event_sender();
-    tokio::spawn(async move { stream_hub.run().await });
-
+async fn start_rtmp_server_loop(
+    host: String, port: u16, sender: StreamHubEventSender, auth: AuthChain,
+) -> anyhow::Result<()> {
     let listener = tokio::net::TcpListener::bind((host.as_str(), port)).await?;
     info!("rtmp server listening on rtmp://{}:{}", &host, port);
 
+    let auth = metrics::MeteredAuth::new("rtmp", auth);
+
     loop {
         match listener.accept().await {
             Ok((stream, client_addr)) => {
@@ -40,9 +36,15 @@ async fn start_rtmp_server_loop(host: String, port: u16) -> anyhow::Result<()>
                 let ctx = RtmpSessionContext {
                     stream,
                     sender: sender.clone(),
+                    auth: auth.clone(),
                 };
                 tokio::spawn(async move {
-                    handle_rtmp_session(ctx).await
+                    let connections = ::metrics::gauge!(metrics::RTMP_CONNECTIONS);
+                    connections.increment(1);
+                    if let Err(err) = handle_rtmp_session(ctx).await {
+                        error!("rtmp session {} error: {}", client_addr, err);
+                    }
+                    connections.decrement(1);
                 });
             }
             Err(err) => {
@@ -50,15 +52,19 @@ async fn start_rtmp_server_loop(host: String, port: u16) -> anyhow::Result<()>
             }
         }
     }
-
 }
 
 async fn handle_rtmp_session(ctx: RtmpSessionContext) -> anyhow::Result<()> {
     let stream = ctx.stream;
     let sender = ctx.sender;
 
-    let authenticator = SimpleTokenAuthenticator::new("123456".to_string());
-    let mut rtmp_session = rtmp::session::server_session::ServerSession::new(stream, sender, 2, Some(authenticator));
+    let mut rtmp_session =
+        rtmp::session::server_session::ServerSession::new(stream, sender, 2, Some(ctx.auth));
 
-    rtmp_session.run().await.map_err(anyhow::Error::new)
+    rtmp_session.run().await.map_err(|err| {
+        if let SessionError::HandshakeError(_) = err {
+            ::metrics::counter!(metrics::RTMP_HANDSHAKE_FAILURES).increment(1);
+        }
+        anyhow::Error::new(err)
+    })
 }
@@ -0,0 +1,41 @@
+use record::{
+    config::{RecordAppConfig, RecordConfig},
+    manager::RecordManager,
+};
+use streamhub::{
+    define::{BroadcastEventReceiver, StreamHubEventSender},
+    notify::Notifier,
+};
+use tracing::info;
+
+use crate::config::record::RecordConf;
+
+/// Record the rtmp streams published to the hub into flv files,
+/// a `on_record_done` hook is sent when a file is closed.
+pub async fn start_recorder(
+    conf: RecordConf, sender: StreamHubEventSender, client_event_consumer: BroadcastEventReceiver,
+    notifier: Option<Notifier>,
+) -> anyhow::Result<()> {
+    let record_config = RecordConfig {
+        path: conf.path,
+        max_file_size: conf.max_file_size,
+        max_duration: conf.max_duration_seconds.saturating_mul(1000),
+        apps: conf
+            .apps
+            .into_iter()
+            .map(|app| RecordAppConfig {
+                app_name: app.app,
+                always: app.always,
+                path: app.path,
+                streams: app.streams,
+            })
+            .collect(),
+    };
+    info!("flv files are recorded to {}", record_config.path);
+
+    let mut manager = RecordManager::new(client_event_consumer, sender, record_config, notifier);
+    manager
+        .run()
+        .await
+        .map_err(|err| anyhow::anyhow!("record manager error: {}", err))
+}
@@ -0,0 +1,146 @@
+use std::{
+    sync::{atomic::AtomicU64, Arc},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use rtmp::relay::{
+    client::{Disconnect, RelayClient, RelayConnector},
+    define::{redact_url, PullSource, PushTarget, ReconnectPolicy},
+    pull_client::PullClient,
+    push_client::PushClient,
+    status::{RelayDirection, RelayStatuses},
+};
+use streamhub::{
+    define::{BroadcastEventReceiver, StreamHubEventSender},
+    utils::Uuid,
+};
+use tokio::{sync::oneshot, task::JoinSet};
+use tracing::info;
+use xrtsp::session::{client_session::ClientTransport, RtspClientSession};
+
+use crate::config::relay::{PullSourceConf, RelayConf, RtspTransportConf};
+
+/// The backoff of the static relays, it is used by the runtime restreams too.
+pub fn reconnect_policy(conf: &RelayConf) -> ReconnectPolicy {
+    ReconnectPolicy {
+        initial_delay: Duration::from_millis(conf.initial_backoff_ms),
+        max_delay: Duration::from_millis(conf.max_backoff_ms.max(conf.initial_backoff_ms)),
+    }
+}
+
+/// Push the published rtmp streams to the configured targets while they are published.
+pub async fn start_push_relay(
+    conf: RelayConf, sender: StreamHubEventSender, client_event_consumer: BroadcastEventReceiver,
+    statuses: RelayStatuses,
+) -> anyhow::Result<()> {
+    let policy = reconnect_policy(&conf);
+    let targets = conf
+        .push
+        .into_iter()
+        .map(|target| PushTarget {
+            app_name: target.app,
+            stream_name: target.stream,
+            url: target.url,
+            query: target.query.into_iter().collect(),
+        })
+        .collect();
+
+    let mut client = PushClient::new(targets, policy, statuses, client_event_consumer, sender);
+    client
+        .run()
+        .await
+        .map_err(|err| anyhow::anyhow!("push relay error: {}", err))
+}
+
+/// Keep pulling the configured remote rtmp/rtsp streams as local streams.
+pub async fn start_pull_relay(
+    conf: RelayConf, sender: StreamHubEventSender, statuses: RelayStatuses,
+) -> anyhow::Result<()> {
+    let policy = reconnect_policy(&conf);
+    let (rtsp_sources, rtmp_sources): (Vec<_>, Vec<_>) = conf
+        .pull
+        .into_iter()
+        .partition(|source| source.url.starts_with("rtsp://"));
+
+    let mut rtsp_relays = JoinSet::new();
+    for source in rtsp_sources {
+        rtsp_relays.spawn(pull_rtsp(source, policy, statuses.clone(), sender.clone()));
+    }
+
+    let rtmp_sources = rtmp_sources
+        .into_iter()
+        .map(|source| PullSource {
+            app_name: source.app,
+            stream_name: source.stream,
+            url: source.url,
+        })
+        .collect();
+    let mut rtmp_client = PullClient::new(rtmp_sources, policy, statuses, sender);
+
+    tokio::join!(rtmp_client.run(), async {
+        while rtsp_relays.join_next().await.is_some() {}
+    });
+    Ok(())
+}
+
+//Pull a rtsp source, e.g. an ip camera, the connection is retried by the same
+//loop as the rtmp relays and the status is reported with them.
+async fn pull_rtsp(
+    source: PullSourceConf, policy: ReconnectPolicy, statuses: RelayStatuses,
+    sender: StreamHubEventSender,
+) {
+    info!(
+        app = source.app,
+        stream = source.stream,
+        url = redact_url(&source.url),
+        "start rtsp pull relay"
+    );
+    let transport = match source.transport {
+        RtspTransportConf::Tcp => ClientTransport::Tcp,
+        RtspTransportConf::Udp => ClientTransport::Udp,
+    };
+    let connector = RtspConnector {
+        url: source.url.clone(),
+        transport,
+        app_name: source.app.clone(),
+        stream_name: source.stream.clone(),
+        sender,
+    };
+
+    let mut client = RelayClient::with_connector(
+        RelayDirection::Pull,
+        source.app,
+        source.stream,
+        &source.url,
+        Box::new(connector),
+        policy,
+        statuses,
+    );
+    client.run().await;
+}
+
+struct RtspConnector {
+    url: String,
+    transport: ClientTransport,
+    app_name: String,
+    stream_name: String,
+    sender: StreamHubEventSender,
+}
+
+#[async_trait]
+impl RelayConnector for RtspConnector {
+    async fn connect(
+        &mut self, _bytes_sent: Arc<AtomicU64>, relaying: oneshot::Sender<Uuid>,
+    ) -> Disconnect {
+        let mut session = RtspClientSession::new(
+            self.url.clone(),
+            self.transport,
+            self.app_name.clone(),
+            self.stream_name.clone(),
+            self.sender.clone(),
+        );
+        session.notify_playing(relaying);
+        Disconnect::Closed(session.run().await.err().map(|err| err.to_string()))
+    }
+}
@@ -0,0 +1,19 @@
+use streamhub::define::StreamHubEventSender;
+use xrtsp::server::RtspServer;
+
+use crate::{
+    config::stream::RtspConf,
+    services::{auth::AuthChain, metrics::MeteredAuth},
+};
+
+pub async fn start_server(
+    conf: RtspConf, sender: StreamHubEventSender, auth: AuthChain,
+) -> anyhow::Result<()> {
+    let mut rtsp_server = RtspServer::new(
+        conf.get_listen_addr(),
+        sender,
+        Some(MeteredAuth::new("rtsp", auth)),
+    );
+
+    rtsp_server.run().await.map_err(anyhow::Error::new)
+}
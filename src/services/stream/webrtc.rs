@@ -1,26 +1,41 @@
-use auth::{self, SimpleTokenAuthenticator};
-use streamhub::StreamsHub;
-use tracing::instrument::WithSubscriber;
-use xwebrtc::webrtc::WebRTCServer;
+use rtmp::remuxer::RtmpRemuxer;
+use streamhub::define::{BroadcastEventReceiver, StreamHubEventSender};
+use xwebrtc::{remuxer::WebRTCRemuxer, webrtc::WebRTCServer};
 
-pub async fn start_server(conf: &crate::config::AppConfig) -> anyhow::Result<()> {
-    let listen_port = conf.stream.port;
-    let listen_host = conf.stream.host.clone();
+use crate::{
+    config::stream::WebRTCConf,
+    services::{auth::AuthChain, metrics::MeteredAuth},
+};
 
-    let mut stream_hub = StreamsHub::new(None);
-    let sender = stream_hub.get_hub_event_sender();
-
-    let new_rt = tokio::runtime::Builder::new_multi_thread()
-        .enable_all()
-        .build()?;
-    new_rt.spawn(async move { stream_hub.run().await }.with_current_subscriber());
-
-    let authenticator = SimpleTokenAuthenticator::new("123456".to_string());
+pub async fn start_server(
+    conf: WebRTCConf, sender: StreamHubEventSender, auth: AuthChain,
+) -> anyhow::Result<()> {
     let mut webrtc_server = WebRTCServer::new(
-        format!("{}:{}", listen_host, listen_port),
+        conf.get_listen_addr(),
         sender,
-        Some(authenticator),
+        Some(MeteredAuth::new("webrtc", auth)),
     );
 
     webrtc_server.run().await
 }
+
+/// Republish the rtmp streams requested by whep players as rtp packets.
+pub async fn start_remuxer(
+    sender: StreamHubEventSender, client_event_consumer: BroadcastEventReceiver,
+) -> anyhow::Result<()> {
+    WebRTCRemuxer::new(client_event_consumer, sender)
+        .run()
+        .await
+        .map_err(|err| anyhow::anyhow!("webrtc remuxer error: {}", err))
+}
+
+/// Republish the whip and rtsp streams requested over rtmp, http-flv
+/// or hls as rtmp.
+pub async fn start_rtmp_remuxer(
+    sender: StreamHubEventSender, client_event_consumer: BroadcastEventReceiver,
+) -> anyhow::Result<()> {
+    RtmpRemuxer::new(client_event_consumer, sender)
+        .run()
+        .await
+        .map_err(|err| anyhow::anyhow!("rtmp remuxer error: {}", err))
+}
@@ -0,0 +1,38 @@
+use std::time::Duration;
+
+use streamhub::notify::{Notifier, NotifierConfig};
+
+use crate::config::webhook::WebhookConf;
+
+fn non_empty(value: &str) -> Option<String> {
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+pub fn notifier_config(conf: &WebhookConf) -> NotifierConfig {
+    NotifierConfig {
+        on_publish_url: non_empty(&conf.on_publish),
+        on_unpublish_url: non_empty(&conf.on_unpublish),
+        on_play_url: non_empty(&conf.on_play),
+        on_stop_url: non_empty(&conf.on_stop),
+        on_stream_stalled_url: non_empty(&conf.on_stream_stalled),
+        on_record_done_url: non_empty(&conf.on_record_done),
+        on_kick_url: non_empty(&conf.on_kick),
+        secret: non_empty(&conf.secret),
+        timeout: Duration::from_millis(conf.timeout_ms),
+        max_retries: conf.max_retries,
+        retry_backoff: Duration::from_millis(conf.retry_backoff_ms),
+        queue_size: conf.queue_size,
+        blocking_publish: conf.blocking_publish,
+        blocking_play: conf.blocking_play,
+        stall_timeout: (conf.stall_timeout_ms > 0)
+            .then(|| Duration::from_millis(conf.stall_timeout_ms)),
+    }
+}
+
+/// The hub notifier, none if no hook url is configured.
+pub fn build_notifier(conf: &WebhookConf) -> anyhow::Result<Option<Notifier>> {
+    if !conf.is_enabled() {
+        return Ok(None);
+    }
+    Ok(Some(Notifier::new(notifier_config(conf))?))
+}